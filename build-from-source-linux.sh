@@ -1,20 +1,45 @@
 #!/bin/bash
+# Pass --static to produce fully static binaries (musl for Rust, no cgo for Go)
+STATIC=0
+if [ "$1" == "--static" ]; then
+    STATIC=1
+fi
+CARGO_FLAGS="--release"
+CFLAGS=""
+if [ $STATIC -eq 1 ]; then
+    CARGO_FLAGS="--release --target x86_64-unknown-linux-musl"
+    CFLAGS="-static"
+    export CGO_ENABLED=0
+fi
 cd source
 cd compiler
-cargo build --release
+cargo build $CARGO_FLAGS
 cd ..
+cd vira-rt
+cargo build $CARGO_FLAGS
+cd ..
+# plsa checks the old syntax. The CLI no longer runs it, but it is still built until it is retired
 cd plsa
-g++ main.cpp -o plsa
+g++ $CFLAGS main.cpp -o plsa
 cd ..
 cd updater
 go get updater
 go build
 cd ..
 cd diagnostic
-cargo build --release
+cargo build $CARGO_FLAGS
+cd ..
+cd formatter
+cargo build $CARGO_FLAGS
+cd ..
+cd lsp
+cargo build $CARGO_FLAGS
+cd ..
+cd dump
+cargo build $CARGO_FLAGS
 cd ..
 cd preprocessor
-gcc main.c -o preprocessor 
+gcc $CFLAGS main.c -o preprocessor 
 cd ..
 cd ..
 cd cli
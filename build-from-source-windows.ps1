@@ -4,7 +4,12 @@
 Set-Location source/compiler
 cargo build --release
 
-# source/plsa
+# source/vira-rt
+Set-Location ..
+Set-Location vira-rt
+cargo build --release
+
+# source/plsa, the checker for the old syntax. The CLI no longer runs it, but it is still built until it is retired
 Set-Location ..
 Set-Location plsa
 g++ main.cpp -o plsa
@@ -20,6 +25,21 @@ Set-Location ..
 Set-Location diagnostic
 cargo build --release
 
+# source/formatter
+Set-Location ..
+Set-Location formatter
+cargo build --release
+
+# source/lsp
+Set-Location ..
+Set-Location lsp
+cargo build --release
+
+# source/dump
+Set-Location ..
+Set-Location dump
+cargo build --release
+
 # source/preprocessor
 Set-Location ..
 Set-Location preprocessor
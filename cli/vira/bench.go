@@ -0,0 +1,70 @@
+package main
+
+import (
+	"errors"
+	"fmt"
+	"math"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"runtime"
+	"sort"
+	"time"
+
+	"github.com/pterm/pterm"
+)
+
+// bench builds inputFiles once, then runs the program iterations times with its output discarded
+// and prints statistics of the wall time per run.
+func bench(inputFiles []string, opts buildOptions, iterations int) error {
+	if iterations < 1 {
+		return errors.New("--iterations must be at least 1")
+	}
+	dir := filepath.Join(cacheDir, "bench")
+	if err := os.MkdirAll(dir, 0o755); err != nil {
+		return err
+	}
+	opts.output = filepath.Join(dir, programName(inputFiles[0]))
+	if runtime.GOOS == "windows" {
+		opts.output += ".exe"
+	}
+	if err := build(inputFiles, opts, false); err != nil {
+		return err
+	}
+	times := make([]time.Duration, 0, iterations)
+	pterm.Info.Printf("Running %s %d times\n", inputFiles[0], iterations)
+	for i := 0; i < iterations; i++ {
+		cmd := exec.Command(opts.output)
+		started := time.Now()
+		err := cmd.Run()
+		elapsed := time.Since(started)
+		// A non-zero exit code is the program's choice, not a failure to benchmark it
+		var exitErr *exec.ExitError
+		if err != nil && !errors.As(err, &exitErr) {
+			return err
+		}
+		times = append(times, elapsed)
+	}
+
+	sort.Slice(times, func(i, j int) bool { return times[i] < times[j] })
+	var total time.Duration
+	for _, t := range times {
+		total += t
+	}
+	mean := total / time.Duration(len(times))
+	median := times[len(times)/2]
+	if len(times)%2 == 0 {
+		median = (times[len(times)/2-1] + times[len(times)/2]) / 2
+	}
+	var variance float64
+	for _, t := range times {
+		diff := float64(t - mean)
+		variance += diff * diff
+	}
+	stddev := time.Duration(math.Sqrt(variance / float64(len(times))))
+
+	round := func(d time.Duration) string { return d.Round(time.Microsecond).String() }
+	fmt.Printf("mean    %s\nmedian  %s\nstddev  %s\nmin     %s\nmax     %s\n",
+		round(mean), round(median), round(stddev), round(times[0]), round(times[len(times)-1]))
+	return nil
+}
@@ -0,0 +1,40 @@
+package main
+
+import (
+	"errors"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"runtime"
+)
+
+// checkArgs are the compiler arguments that check inputFiles, applying the fixes when fix is set.
+func checkArgs(inputFiles []string, fix bool) []string {
+	args := []string{"check"}
+	if fix {
+		args = append(args, "--fix")
+	}
+	return append(args, inputFiles...)
+}
+
+// check reports the errors and warnings in inputFiles without compiling them. The files go to the
+// compiler as they are, without the preprocessor, so that fixes land where they belong in them.
+func check(inputFiles []string, fix bool) error {
+	compiler := filepath.Join(binPath, "compiler")
+	if runtime.GOOS == "windows" {
+		compiler += ".exe"
+	}
+	cmdCheck := exec.Command(compiler, checkArgs(inputFiles, fix)...)
+	cmdCheck.Stdin = os.Stdin
+	cmdCheck.Stdout = os.Stdout
+	cmdCheck.Stderr = os.Stderr
+	if err := cmdCheck.Run(); err != nil {
+		var exitErr *exec.ExitError
+		if errors.As(err, &exitErr) {
+			// The compiler has printed what it found
+			return &toolError{status: exitErr.ExitCode()}
+		}
+		return err
+	}
+	return nil
+}
@@ -0,0 +1,16 @@
+package main
+
+import (
+	"reflect"
+	"testing"
+)
+
+func TestCheckArgs(t *testing.T) {
+	files := []string{"main.vira", "lib.vira"}
+	if got, want := checkArgs(files, false), []string{"check", "main.vira", "lib.vira"}; !reflect.DeepEqual(got, want) {
+		t.Errorf("args = %q, want %q", got, want)
+	}
+	if got, want := checkArgs(files, true), []string{"check", "--fix", "main.vira", "lib.vira"}; !reflect.DeepEqual(got, want) {
+		t.Errorf("args with --fix = %q, want %q", got, want)
+	}
+}
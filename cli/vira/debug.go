@@ -0,0 +1,83 @@
+package main
+
+import (
+	"errors"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"runtime"
+	"strings"
+)
+
+// debuggers are tried in order; the first one on PATH wins.
+var debuggers = []string{"gdb", "lldb"}
+
+// findDebugger returns the path of a debugger that reads the DWARF the compiler emits with -g.
+func findDebugger() (string, error) {
+	for _, name := range debuggers {
+		if path, err := exec.LookPath(name); err == nil {
+			return path, nil
+		}
+	}
+	return "", errors.New("no debugger found; install gdb or lldb")
+}
+
+// debug builds inputFiles with debug info and starts the program under gdb or lldb, stopped at
+// the first line of the entry file. The debugger's own commands apply: `b 12` for a breakpoint in
+// the entry file, `s` and `n` to step, `c` to continue and `bt` for a backtrace. Other files go by
+// the name of their preprocessed copy, as in `b lib.vira.pre:3`.
+func debug(inputFiles []string, opts buildOptions) error {
+	debugger, err := findDebugger()
+	if err != nil {
+		return err
+	}
+	dir := filepath.Join(cacheDir, "debug")
+	if err := os.MkdirAll(dir, 0o755); err != nil {
+		return err
+	}
+	opts.debugInfo = true
+	opts.optLevel = "0"
+	opts.output = filepath.Join(dir, programName(inputFiles[0]))
+	if runtime.GOOS == "windows" {
+		opts.output += ".exe"
+	}
+	if err := build(inputFiles, opts, false); err != nil {
+		return err
+	}
+	var args []string
+	if strings.HasPrefix(filepath.Base(debugger), "lldb") {
+		args = []string{"-o", "breakpoint set --name main", "-o", "run", "--", opts.output}
+	} else {
+		args = []string{"-q", "-ex", "break main", "-ex", "run", "--args", opts.output}
+	}
+	cmd := exec.Command(debugger, args...)
+	cmd.Stdin = os.Stdin
+	cmd.Stdout = os.Stdout
+	cmd.Stderr = os.Stderr
+	return cmd.Run()
+}
+
+// dap serves the Debug Adapter Protocol on standard input and output by handing over to a debugger
+// that speaks it: lldb-dap (lldb-vscode before LLVM 18) or gdb 14 and later. Editors launch a
+// program built with `vira build -g` through it like any native program.
+func dap() error {
+	var cmd *exec.Cmd
+	for _, name := range []string{"lldb-dap", "lldb-vscode"} {
+		if path, err := exec.LookPath(name); err == nil {
+			cmd = exec.Command(path)
+			break
+		}
+	}
+	if cmd == nil {
+		path, err := exec.LookPath("gdb")
+		if err != nil {
+			return errors.New("no debug adapter found; install lldb-dap, or gdb 14 or later")
+		}
+		cmd = exec.Command(path, "--interpreter=dap")
+	}
+	// The protocol runs over stdin and stdout, so nothing else may write to them
+	cmd.Stdin = os.Stdin
+	cmd.Stdout = os.Stdout
+	cmd.Stderr = os.Stderr
+	return cmd.Run()
+}
@@ -0,0 +1,39 @@
+package main
+
+import (
+	"fmt"
+	"os"
+
+	"github.com/spf13/cobra"
+	"github.com/spf13/cobra/doc"
+)
+
+// completionShells are the shells completions can be generated for, in the order help lists them.
+var completionShells = []string{"bash", "zsh", "fish", "powershell"}
+
+// completions writes the completion script for shell to stdout. The script is generated from the
+// command tree, so it always matches the commands and flags this build has.
+func completions(root *cobra.Command, shell string) error {
+	switch shell {
+	case "bash":
+		return root.GenBashCompletionV2(os.Stdout, true)
+	case "zsh":
+		return root.GenZshCompletion(os.Stdout)
+	case "fish":
+		return root.GenFishCompletion(os.Stdout, true)
+	case "powershell":
+		return root.GenPowerShellCompletionWithDesc(os.Stdout)
+	}
+	return fmt.Errorf("unsupported shell %q, expected one of %v", shell, completionShells)
+}
+
+// manPages writes a page for vira and one for each of its commands, such as vira-compile.1, to dir.
+func manPages(root *cobra.Command, dir string) error {
+	if err := os.MkdirAll(dir, 0o755); err != nil {
+		return err
+	}
+	// The generation date would change the pages on every run
+	root.DisableAutoGenTag = true
+	header := &doc.GenManHeader{Title: "VIRA", Section: "1", Source: "Vira", Manual: "Vira Manual"}
+	return doc.GenManTree(root, header, dir)
+}
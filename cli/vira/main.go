@@ -1,10 +1,21 @@
 package main
 
 import (
+	"bytes"
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"net/http"
 	"os"
 	"os/exec"
 	"path/filepath"
 	"runtime"
+	"strconv"
+	"strings"
+	"time"
 
 	"github.com/pterm/pterm"
 	"github.com/spf13/cobra"
@@ -12,6 +23,16 @@ import (
 
 var binPath string
 
+var colorMode string
+
+// cacheDir holds compiled objects of earlier builds, relative to where vira is run.
+const cacheDir = ".vira-cache"
+
+const (
+	releaseURL       = "https://github.com/vira-language/vira/releases/download"
+	remoteVersionURL = "https://raw.githubusercontent.com/vira-language/vira/main/repository/vira-version.json"
+)
+
 func init() {
 	osName := runtime.GOOS
 	if osName == "linux" {
@@ -32,14 +53,187 @@ func main() {
 	var rootCmd = &cobra.Command{
 		Use:   "vira",
 		Short: "Vira general CLI tool",
+		PersistentPreRun: func(cmd *cobra.Command, args []string) {
+			configureColor()
+		},
 	}
+	rootCmd.PersistentFlags().StringVar(&colorMode, "color", "auto", "When to use colors: auto, always or never (auto respects NO_COLOR)")
 
+	var opts buildOptions
+	var noCache, watchFiles bool
 	var compileCmd = &cobra.Command{
-		Use:   "compile [input.vira]",
-		Short: "Compile a .vira file",
+		Use:     "compile [main.vira | -] [files.vira...]",
+		Aliases: []string{"build"},
+		Short:   "Compile .vira files into one program; the first file is the entry point",
+		Long:    "Compile .vira files into one program; the first file is the entry point.\nWithout files, builds the project whose " + manifestName + " is in this directory or a parent.",
+		Run: func(cmd *cobra.Command, args []string) {
+			opts.useCache = !noCache
+			args = projectInputs(args, &opts)
+			if watchFiles {
+				watch(args, func() error { return build(args, opts, false) })
+			} else {
+				compile(args, opts)
+			}
+		},
+	}
+	compileCmd.Flags().StringVarP(&opts.output, "output", "o", "", "Path of the executable (defaults to a.out, or a.exe on Windows)")
+	compileCmd.Flags().BoolVarP(&opts.debugInfo, "debug", "g", false, "Include debug info so gdb and lldb can step through the Vira source")
+	compileCmd.Flags().BoolVar(&opts.sandbox, "sandbox", false, "Reject programs that use environment variables, run commands, make HTTP requests or call exit")
+	compileCmd.Flags().BoolVar(&noCache, "no-cache", false, "Compile every file from scratch without reading or writing "+cacheDir)
+	compileCmd.Flags().BoolVarP(&watchFiles, "watch", "w", false, "Rebuild whenever an input file or one it includes changes")
+
+	var runCmd = &cobra.Command{
+		Use:   "run [main.vira | -] [files.vira...]",
+		Short: "Compile .vira files and run the program",
+		Long:  "Compile .vira files and run the program.\nWithout files, runs the project whose " + manifestName + " is in this directory or a parent.",
+		Run: func(cmd *cobra.Command, args []string) {
+			opts.useCache = !noCache
+			if opts.profile != "" {
+				// Relative to where vira was started, even once in a project's root
+				if abs, err := filepath.Abs(opts.profile); err == nil {
+					opts.profile = abs
+				}
+			}
+			if _, err := strconv.ParseFloat(opts.seed, 64); opts.seed != "" && err != nil {
+				pterm.Error.Printf("--seed takes a number, not %q\n", opts.seed)
+				os.Exit(2)
+			}
+			args = projectInputs(args, &opts)
+			if watchFiles {
+				watch(args, func() error {
+					code, err := run(args, opts, false)
+					if err == nil && code != 0 {
+						err = fmt.Errorf("program exited with status %d", code)
+					}
+					return err
+				})
+			} else {
+				code, err := run(args, opts, true)
+				if err != nil {
+					pterm.Error.Println(err)
+					os.Exit(exitStatus(err))
+				}
+				os.Exit(code)
+			}
+		},
+	}
+	runCmd.Flags().BoolVarP(&opts.debugInfo, "debug", "g", false, "Include debug info so gdb and lldb can step through the Vira source")
+	runCmd.Flags().StringVar(&opts.profile, "profile", "", "Time every function call and write the profile in collapsed-stack format to this file")
+	runCmd.Flags().BoolVar(&opts.sandbox, "sandbox", false, "Reject programs that use environment variables, run commands, make HTTP requests or call exit")
+	runCmd.Flags().BoolVar(&noCache, "no-cache", false, "Compile every file from scratch without reading or writing "+cacheDir)
+	runCmd.Flags().BoolVarP(&watchFiles, "watch", "w", false, "Rebuild and rerun whenever an input file or one it includes changes")
+	runCmd.Flags().StringVar(&opts.seed, "seed", "", "Seed the program's random numbers with this number, so every run gets the same ones")
+
+	var benchOpts buildOptions
+	var iterations int
+	var benchCmd = &cobra.Command{
+		Use:   "bench [main.vira] [files.vira...]",
+		Short: "Compile a program, run it repeatedly and report how long a run takes",
+		Run: func(cmd *cobra.Command, args []string) {
+			benchOpts.useCache = !noCache
+			args = projectInputs(args, &benchOpts)
+			if err := bench(args, benchOpts, iterations); err != nil {
+				pterm.Error.Println(err)
+				os.Exit(1)
+			}
+		},
+	}
+	benchCmd.Flags().IntVarP(&iterations, "iterations", "n", 10, "How many times to run the program")
+	benchCmd.Flags().BoolVar(&noCache, "no-cache", false, "Compile every file from scratch without reading or writing "+cacheDir)
+
+	var debugOpts buildOptions
+	var debugCmd = &cobra.Command{
+		Use:   "debug [main.vira] [files.vira...]",
+		Short: "Compile a program with debug info and start it under gdb or lldb",
+		Run: func(cmd *cobra.Command, args []string) {
+			debugOpts.useCache = !noCache
+			args = projectInputs(args, &debugOpts)
+			if err := debug(args, debugOpts); err != nil {
+				pterm.Error.Println(err)
+				os.Exit(1)
+			}
+		},
+	}
+	debugCmd.Flags().BoolVar(&noCache, "no-cache", false, "Compile every file from scratch without reading or writing "+cacheDir)
+
+	var dapCmd = &cobra.Command{
+		Use:   "dap",
+		Short: "Serve the Debug Adapter Protocol over stdio for editors, debugging programs built with -g",
+		Args:  cobra.NoArgs,
+		Run: func(cmd *cobra.Command, args []string) {
+			if err := dap(); err != nil {
+				// Standard output belongs to the protocol
+				fmt.Fprintln(os.Stderr, err)
+				os.Exit(1)
+			}
+		},
+	}
+
+	var testOpts buildOptions
+	var testCmd = &cobra.Command{
+		Use:   "test [files.vira...]",
+		Short: "Build and run tests, by default those in tests/*.vira",
+		Long: "Build and run tests, by default those in tests/*.vira.\n" +
+			"Each file is a test, unless it defines functions named test_*: then it may only define functions, and each of those is a test.\n" +
+			"A test fails when it doesn't build, an assert fails or it exits with a non-zero status.",
+		Run: func(cmd *cobra.Command, args []string) {
+			testOpts.useCache = !noCache
+			failed, err := runTests(args, testOpts)
+			if err != nil {
+				pterm.Error.Println(err)
+				os.Exit(1)
+			}
+			if failed > 0 {
+				os.Exit(1)
+			}
+		},
+	}
+	testCmd.Flags().BoolVarP(&testOpts.debugInfo, "debug", "g", false, "Include debug info so gdb and lldb can step through the Vira source")
+	testCmd.Flags().BoolVar(&noCache, "no-cache", false, "Compile every file from scratch without reading or writing "+cacheDir)
+
+	var checkFix bool
+	var checkCmd = &cobra.Command{
+		Use:   "check [main.vira | -] [files.vira...]",
+		Short: "Report errors and warnings in .vira files without compiling them",
+		Long: "Report errors and warnings in .vira files without compiling them.\n" +
+			"With --fix, apply the fixes the compiler is certain of, such as inserting a missing `;`, to the files in place.\n" +
+			"Without files, checks the project whose " + manifestName + " is in this directory or a parent.",
+		Run: func(cmd *cobra.Command, args []string) {
+			var checkOpts buildOptions
+			args = projectInputs(args, &checkOpts)
+			if err := check(args, checkFix); err != nil {
+				var tool *toolError
+				if !errors.As(err, &tool) {
+					pterm.Error.Println(err)
+				}
+				os.Exit(exitStatus(err))
+			}
+		},
+	}
+	checkCmd.Flags().BoolVar(&checkFix, "fix", false, "Apply the fixes the compiler is certain of to the files in place")
+
+	var newCmd = &cobra.Command{
+		Use:   "new [name]",
+		Short: "Create a project directory with a " + manifestName + " and src/main.vira",
 		Args:  cobra.ExactArgs(1),
 		Run: func(cmd *cobra.Command, args []string) {
-			compile(args[0])
+			if err := newProject(args[0]); err != nil {
+				pterm.Error.Println(err)
+				os.Exit(1)
+			}
+		},
+	}
+
+	var cleanCmd = &cobra.Command{
+		Use:   "clean",
+		Short: "Delete the build cache in " + cacheDir,
+		Args:  cobra.NoArgs,
+		Run: func(cmd *cobra.Command, args []string) {
+			if err := os.RemoveAll(cacheDir); err != nil {
+				pterm.Error.Println(err)
+				os.Exit(1)
+			}
+			pterm.Success.Println("Removed " + cacheDir)
 		},
 	}
 
@@ -51,7 +245,84 @@ func main() {
 		},
 	}
 
-	rootCmd.AddCommand(compileCmd, updateCmd)
+	var selfUpdateCmd = &cobra.Command{
+		Use:   "self-update",
+		Short: "Replace this vira executable with the latest release",
+		Run: func(cmd *cobra.Command, args []string) {
+			if err := selfUpdate(); err != nil {
+				pterm.Error.Println(err)
+				os.Exit(1)
+			}
+		},
+	}
+
+	var explainCmd = &cobra.Command{
+		Use:   "explain [code]",
+		Short: "Explain a diagnostic code such as V0102",
+		Args:  cobra.ExactArgs(1),
+		Run: func(cmd *cobra.Command, args []string) {
+			explain(args[0])
+		},
+	}
+
+	var fmtCheck bool
+	var fmtCmd = &cobra.Command{
+		Use:   "fmt [paths...]",
+		Short: "Format .vira files in place, or list unformatted files with --check",
+		Args:  cobra.MinimumNArgs(1),
+		Run: func(cmd *cobra.Command, args []string) {
+			format(args, fmtCheck)
+		},
+	}
+	fmtCmd.Flags().BoolVar(&fmtCheck, "check", false, "Exit with 1 if any file is not formatted, without rewriting it")
+
+	var dumpTokens, dumpAST, dumpTrivia bool
+	var dumpCmd = &cobra.Command{
+		Use:   "dump [input.vira]",
+		Short: "Print the token stream or parsed program of a .vira file as JSON",
+		Args:  cobra.ExactArgs(1),
+		Run: func(cmd *cobra.Command, args []string) {
+			dump(args[0], dumpTokens, dumpAST, dumpTrivia)
+		},
+	}
+	dumpCmd.Flags().BoolVar(&dumpTokens, "tokens", false, "Dump the token stream")
+	dumpCmd.Flags().BoolVar(&dumpAST, "ast", false, "Dump the parsed program")
+	dumpCmd.Flags().BoolVar(&dumpTrivia, "trivia", false, "Dump tokens with their whitespace and comments")
+	dumpCmd.MarkFlagsMutuallyExclusive("tokens", "ast", "trivia")
+	dumpCmd.MarkFlagsOneRequired("tokens", "ast", "trivia")
+
+	var completionsCmd = &cobra.Command{
+		Use:   "completions [shell]",
+		Short: "Print a completion script for bash, zsh, fish or powershell",
+		Long: "Print a completion script for bash, zsh, fish or powershell.\n" +
+			"For example, add `source <(vira completions bash)` to ~/.bashrc, or save the output of\n" +
+			"`vira completions fish` as ~/.config/fish/completions/vira.fish.",
+		Args:      cobra.ExactArgs(1),
+		ValidArgs: completionShells,
+		Run: func(cmd *cobra.Command, args []string) {
+			if err := completions(rootCmd, args[0]); err != nil {
+				pterm.Error.Println(err)
+				os.Exit(1)
+			}
+		},
+	}
+
+	var manCmd = &cobra.Command{
+		Use:   "man [dir]",
+		Short: "Write man pages for vira and each of its commands to a directory",
+		Args:  cobra.ExactArgs(1),
+		Run: func(cmd *cobra.Command, args []string) {
+			if err := manPages(rootCmd, args[0]); err != nil {
+				pterm.Error.Println(err)
+				os.Exit(1)
+			}
+			pterm.Success.Println("Wrote man pages to " + args[0])
+		},
+	}
+
+	rootCmd.AddCommand(compileCmd, runCmd, checkCmd, testCmd, benchCmd, debugCmd, dapCmd, newCmd, cleanCmd, updateCmd, selfUpdateCmd, explainCmd, fmtCmd, dumpCmd, completionsCmd, manCmd)
+	// completions replaces cobra's own completion command
+	rootCmd.CompletionOptions.DisableDefaultCmd = true
 
 	if err := rootCmd.Execute(); err != nil {
 		pterm.Error.Println(err)
@@ -59,32 +330,99 @@ func main() {
 	}
 }
 
-func compile(inputFile string) {
-	outputPre := inputFile + ".pre"
+func configureColor() {
+	if colorMode == "never" || (colorMode == "auto" && os.Getenv("NO_COLOR") != "") {
+		pterm.DisableColor()
+		// Child tools read NO_COLOR themselves
+		os.Setenv("NO_COLOR", "1")
+	} else if colorMode == "always" {
+		pterm.EnableColor()
+	}
+}
+
+// buildOptions are the compiler settings shared by build and run.
+type buildOptions struct {
+	output    string
+	useCache  bool
+	debugInfo bool
+	// optLevel and target are passed to the compiler when set; only a project manifest sets them.
+	optLevel string
+	target   string
+	// profile is where run writes the program's profile; empty means no profiling.
+	profile string
+	// seed is what run seeds the program's random numbers with; empty means a new seed every run.
+	seed string
+	// sandbox makes the compiler reject the builtins that reach outside the program.
+	sandbox bool
+}
 
-	pterm.DefaultSection.Println("Preprocessing")
-	preprocessor := filepath.Join(binPath, "preprocessor")
-	if runtime.GOOS == "windows" {
-		preprocessor += ".exe"
+func compile(inputFiles []string, opts buildOptions) {
+	if err := build(inputFiles, opts, true); err != nil {
+		pterm.Error.Println(err)
+		os.Exit(exitStatus(err))
 	}
-	cmdPre := exec.Command(preprocessor, inputFile, outputPre)
-	if out, err := cmdPre.CombinedOutput(); err != nil {
-		pterm.Error.Println(string(out))
-		os.Exit(1)
+}
+
+// toolError is a failed run of one of the Vira tools, which keeps its exit status so vira can
+// exit with it: 2 for errors in the program, 3 for I/O and 101 for a bug in the tool.
+type toolError struct {
+	output string
+	status int
+}
+
+func (e *toolError) Error() string {
+	return e.output
+}
+
+// exitStatus is what vira exits with after err: the failing tool's status when there is one.
+func exitStatus(err error) int {
+	var tool *toolError
+	if errors.As(err, &tool) {
+		return tool.status
 	}
-	pterm.Success.Println("Preprocessing done")
+	return 1
+}
 
-	pterm.DefaultSection.Println("Parsing and Checking")
-	plsa := filepath.Join(binPath, "plsa")
+// build preprocesses and compiles inputFiles into one executable. On failure the error holds the
+// failing tool's output. verbose prints a section per stage.
+func build(inputFiles []string, opts buildOptions, verbose bool) error {
+	if verbose {
+		pterm.DefaultSection.Println("Preprocessing")
+	}
+	preprocessor := filepath.Join(binPath, "preprocessor")
 	if runtime.GOOS == "windows" {
-		plsa += ".exe"
+		preprocessor += ".exe"
 	}
-	cmdPlsa := exec.Command(plsa, outputPre)
-	if out, err := cmdPlsa.CombinedOutput(); err != nil {
-		pterm.Error.Println(string(out))
-		os.Exit(1)
+	var preFiles []string
+	// Standard input is preprocessed in memory and piped to the compiler, which names it <stdin>
+	var stdinSource []byte
+	for _, inputFile := range inputFiles {
+		if inputFile == "-" {
+			if stdinSource != nil {
+				return errors.New("standard input (-) can only be one of the inputs")
+			}
+			cmdPre := exec.Command(preprocessor, "-", "-")
+			cmdPre.Stdin = os.Stdin
+			var stderr bytes.Buffer
+			cmdPre.Stderr = &stderr
+			out, err := cmdPre.Output()
+			if err != nil {
+				return errors.New(strings.TrimSpace(stderr.String()))
+			}
+			stdinSource = out
+			preFiles = append(preFiles, "-")
+			continue
+		}
+		outputPre := inputFile + ".pre"
+		cmdPre := exec.Command(preprocessor, inputFile, outputPre)
+		if out, err := cmdPre.CombinedOutput(); err != nil {
+			return errors.New(strings.TrimSpace(string(out)))
+		}
+		preFiles = append(preFiles, outputPre)
+	}
+	if verbose {
+		pterm.Success.Println("Preprocessing done")
 	}
-	pterm.Success.Println("PLSA done")
 
 	// Assume diagnostic needs error simulation, but for now skip or mock
 	// diagnostic := filepath.Join(binPath, "diagnostic")
@@ -95,18 +433,230 @@ func compile(inputFile string) {
 	// }
 	// pterm.Success.Println("Diagnostic done")
 
-	pterm.DefaultSection.Println("Compiling")
+	// The compiler parses and checks the program itself
+	if verbose {
+		pterm.DefaultSection.Println("Compiling")
+	}
 	compiler := filepath.Join(binPath, "compiler")
 	if runtime.GOOS == "windows" {
 		compiler += ".exe"
 	}
-	outputObj := inputFile + ".o"
-	cmdComp := exec.Command(compiler, outputPre, outputObj)
+	// Every file goes to one compiler run, which resolves calls between them
+	compileArgs := append([]string{"compile"}, preFiles...)
+	if opts.output != "" {
+		compileArgs = append(compileArgs, "-o", opts.output)
+	}
+	if opts.debugInfo {
+		compileArgs = append(compileArgs, "-g")
+	}
+	if opts.profile != "" {
+		compileArgs = append(compileArgs, "--profile")
+	}
+	if opts.optLevel != "" {
+		compileArgs = append(compileArgs, "-O", opts.optLevel)
+	}
+	if opts.target != "" {
+		compileArgs = append(compileArgs, "--target", opts.target)
+	}
+	if opts.sandbox {
+		compileArgs = append(compileArgs, "--sandbox")
+	}
+	if opts.useCache {
+		// Files whose preprocessed source hasn't changed reuse their object from the last build
+		compileArgs = append(compileArgs, "--cache-dir", cacheDir)
+	}
+	cmdComp := exec.Command(compiler, compileArgs...)
+	if stdinSource != nil {
+		cmdComp.Stdin = bytes.NewReader(stdinSource)
+	}
 	if out, err := cmdComp.CombinedOutput(); err != nil {
+		var exitErr *exec.ExitError
+		if errors.As(err, &exitErr) {
+			return &toolError{output: strings.TrimSpace(string(out)), status: exitErr.ExitCode()}
+		}
+		return errors.New(strings.TrimSpace(string(out)))
+	}
+	if verbose {
+		pterm.Success.Println("Compilation done")
+	}
+	return nil
+}
+
+// run builds inputFiles and executes the result with the terminal attached, returning its exit code.
+func run(inputFiles []string, opts buildOptions, verbose bool) (int, error) {
+	dir := filepath.Join(cacheDir, "run")
+	if err := os.MkdirAll(dir, 0o755); err != nil {
+		return 0, err
+	}
+	opts.output = filepath.Join(dir, programName(inputFiles[0]))
+	if runtime.GOOS == "windows" {
+		opts.output += ".exe"
+	}
+	if err := build(inputFiles, opts, verbose); err != nil {
+		return 0, err
+	}
+	cmdRun := exec.Command(opts.output)
+	cmdRun.Stdin = os.Stdin
+	cmdRun.Stdout = os.Stdout
+	cmdRun.Stderr = os.Stderr
+	cmdRun.Env = os.Environ()
+	if opts.profile != "" {
+		// The runtime writes the profile when the program exits
+		cmdRun.Env = append(cmdRun.Env, "VIRA_PROFILE="+opts.profile)
+		defer pterm.Info.Printf("Wrote the profile to %s; inferno-flamegraph or flamegraph.pl turn it into a flame graph\n", opts.profile)
+	}
+	if opts.seed != "" {
+		// The runtime seeds its random numbers from VIRA_SEED
+		cmdRun.Env = append(cmdRun.Env, "VIRA_SEED="+opts.seed)
+	}
+	err := cmdRun.Run()
+	var exitErr *exec.ExitError
+	if errors.As(err, &exitErr) {
+		// Vira programs choose their exit code with a top-level return
+		return exitErr.ExitCode(), nil
+	}
+	return 0, err
+}
+
+// programName names the executable built from entry, a path or - for standard input.
+func programName(entry string) string {
+	if entry == "-" {
+		return "stdin"
+	}
+	return strings.TrimSuffix(filepath.Base(entry), filepath.Ext(entry))
+}
+
+// watch runs action, then again every time one of inputFiles or a file they include changes,
+// clearing the screen before each run and ending it with a one-line summary.
+func watch(inputFiles []string, action func() error) {
+	for _, inputFile := range inputFiles {
+		if inputFile == "-" {
+			pterm.Error.Println("--watch can't rebuild a program read from standard input")
+			os.Exit(1)
+		}
+	}
+	for {
+		fmt.Print("\033[H\033[2J")
+		started := time.Now()
+		if err := action(); err != nil {
+			pterm.Error.Println(err)
+		} else {
+			pterm.Success.Printf("Done in %v\n", time.Since(started).Round(time.Millisecond))
+		}
+		files := watchedFiles(inputFiles)
+		pterm.Info.Printf("Watching %s for changes, press Ctrl+C to stop\n", strings.Join(files, ", "))
+		waitForChange(files)
+	}
+}
+
+// watchedFiles lists inputFiles and every file they pull in with a quoted #include, which the
+// preprocessor opens relative to the working directory.
+func watchedFiles(inputFiles []string) []string {
+	seen := map[string]bool{}
+	var files []string
+	pending := append([]string(nil), inputFiles...)
+	for len(pending) > 0 {
+		file := pending[0]
+		pending = pending[1:]
+		if seen[file] {
+			continue
+		}
+		seen[file] = true
+		files = append(files, file)
+		data, err := os.ReadFile(file)
+		if err != nil {
+			continue
+		}
+		for _, line := range strings.Split(string(data), "\n") {
+			line = strings.TrimSpace(line)
+			if !strings.HasPrefix(line, "#") {
+				continue
+			}
+			directive := strings.TrimSpace(strings.TrimPrefix(line, "#"))
+			if !strings.HasPrefix(directive, "include") {
+				continue
+			}
+			name := strings.TrimSpace(strings.TrimPrefix(directive, "include"))
+			if strings.HasPrefix(name, "\"") {
+				if end := strings.Index(name[1:], "\""); end >= 0 {
+					pending = append(pending, name[1:end+1])
+				}
+			}
+		}
+	}
+	return files
+}
+
+// waitForChange blocks until one of files is modified, created or removed. Polling keeps the CLI
+// free of platform-specific file watching, and a quarter of a second is quick enough to feel instant.
+func waitForChange(files []string) {
+	snapshot := func() string {
+		var state strings.Builder
+		for _, file := range files {
+			if info, err := os.Stat(file); err == nil {
+				fmt.Fprintf(&state, "%s %d %d\n", file, info.Size(), info.ModTime().UnixNano())
+			} else {
+				fmt.Fprintf(&state, "%s missing\n", file)
+			}
+		}
+		return state.String()
+	}
+	before := snapshot()
+	for snapshot() == before {
+		time.Sleep(250 * time.Millisecond)
+	}
+	// Editors often save in several writes, so let them finish
+	time.Sleep(100 * time.Millisecond)
+}
+
+func explain(code string) {
+	diagnostic := filepath.Join(binPath, "diagnostic")
+	if runtime.GOOS == "windows" {
+		diagnostic += ".exe"
+	}
+	cmdExplain := exec.Command(diagnostic, "--explain", code)
+	out, err := cmdExplain.CombinedOutput()
+	if err != nil {
 		pterm.Error.Println(string(out))
 		os.Exit(1)
 	}
-	pterm.Success.Println("Compilation done")
+	fmt.Print(string(out))
+}
+
+func format(paths []string, check bool) {
+	formatter := filepath.Join(binPath, "formatter")
+	if runtime.GOOS == "windows" {
+		formatter += ".exe"
+	}
+	args := paths
+	if check {
+		args = append([]string{"--check"}, paths...)
+	}
+	cmdFmt := exec.Command(formatter, args...)
+	out, err := cmdFmt.CombinedOutput()
+	fmt.Print(string(out))
+	if err != nil {
+		os.Exit(1)
+	}
+}
+
+func dump(inputFile string, tokens, ast, trivia bool) {
+	dumper := filepath.Join(binPath, "dump")
+	if runtime.GOOS == "windows" {
+		dumper += ".exe"
+	}
+	mode := "--trivia"
+	if tokens {
+		mode = "--tokens"
+	} else if ast {
+		mode = "--ast"
+	}
+	cmdDump := exec.Command(dumper, mode, inputFile)
+	cmdDump.Stdout = os.Stdout
+	cmdDump.Stderr = os.Stderr
+	if err := cmdDump.Run(); err != nil {
+		os.Exit(1)
+	}
 }
 
 func update() {
@@ -122,3 +672,94 @@ func update() {
 	}
 	pterm.Success.Println("Update done")
 }
+
+func selfUpdate() error {
+	pterm.DefaultSection.Println("Updating vira")
+	client := &http.Client{Timeout: 30 * time.Second}
+
+	versionData, err := fetch(client, remoteVersionURL)
+	if err != nil {
+		// Nothing has been touched yet, so being offline just means staying on the current build
+		return fmt.Errorf("could not reach the release server, vira was left unchanged: %v", err)
+	}
+	var versions []string
+	if err := json.Unmarshal(versionData, &versions); err != nil || len(versions) == 0 {
+		return fmt.Errorf("invalid remote version JSON: %v", err)
+	}
+	version := versions[0]
+
+	artifact := fmt.Sprintf("vira-%s-%s", runtime.GOOS, runtime.GOARCH)
+	if runtime.GOOS == "windows" {
+		artifact += ".exe"
+	}
+	artifactURL := fmt.Sprintf("%s/v%s/%s", releaseURL, version, artifact)
+	binary, err := fetch(client, artifactURL)
+	if err != nil {
+		return fmt.Errorf("failed to download %s: %v", artifact, err)
+	}
+	checksumData, err := fetch(client, artifactURL+".sha256")
+	if err != nil {
+		return fmt.Errorf("failed to download checksum for %s: %v", artifact, err)
+	}
+	fields := strings.Fields(string(checksumData))
+	if len(fields) == 0 {
+		return fmt.Errorf("empty checksum file for %s", artifact)
+	}
+	sum := sha256.Sum256(binary)
+	if !strings.EqualFold(hex.EncodeToString(sum[:]), fields[0]) {
+		return fmt.Errorf("checksum mismatch for %s, refusing to install it", artifact)
+	}
+	pterm.Success.Println("Downloaded and verified " + artifact + " " + version)
+
+	if err := replaceExecutable(binary); err != nil {
+		return fmt.Errorf("failed to replace executable: %v", err)
+	}
+	pterm.Success.Println("vira updated to " + version)
+	return nil
+}
+
+func fetch(client *http.Client, url string) ([]byte, error) {
+	resp, err := client.Get(url)
+	if err != nil {
+		return nil, err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return nil, fmt.Errorf("bad status: %s", resp.Status)
+	}
+	return io.ReadAll(resp.Body)
+}
+
+// replaceExecutable writes the new binary next to the running one and swaps it in. Windows cannot
+// overwrite a running executable, so the old one is renamed out of the way first.
+func replaceExecutable(binary []byte) error {
+	current, err := os.Executable()
+	if err != nil {
+		return err
+	}
+	current, err = filepath.EvalSymlinks(current)
+	if err != nil {
+		return err
+	}
+	info, err := os.Stat(current)
+	if err != nil {
+		return err
+	}
+	staged := current + ".new"
+	if err := os.WriteFile(staged, binary, info.Mode()); err != nil {
+		return err
+	}
+	if runtime.GOOS == "windows" {
+		old := current + ".old"
+		os.Remove(old)
+		if err := os.Rename(current, old); err != nil {
+			os.Remove(staged)
+			return err
+		}
+	}
+	if err := os.Rename(staged, current); err != nil {
+		os.Remove(staged)
+		return err
+	}
+	return nil
+}
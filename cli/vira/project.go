@@ -0,0 +1,169 @@
+package main
+
+import (
+	"errors"
+	"fmt"
+	"os"
+	"path/filepath"
+	"runtime"
+	"sort"
+	"strings"
+
+	"github.com/BurntSushi/toml"
+	"github.com/pterm/pterm"
+)
+
+const manifestName = "vira.toml"
+
+// manifest is the vira.toml at the root of a project.
+type manifest struct {
+	Package struct {
+		Name    string `toml:"name"`
+		Version string `toml:"version"`
+		// Entry is the file whose top-level code runs, relative to the project root.
+		Entry string `toml:"entry"`
+	} `toml:"package"`
+	Build struct {
+		// Target is a target triple; empty means the host.
+		Target   string `toml:"target"`
+		OptLevel string `toml:"opt-level"`
+		Debug    bool   `toml:"debug"`
+	} `toml:"build"`
+	// Imports maps a name to another source file that is compiled into the program with the entry.
+	Imports map[string]string `toml:"imports"`
+}
+
+// project is a loaded manifest and the directory it was found in.
+type project struct {
+	root     string
+	manifest manifest
+}
+
+// findProject looks for vira.toml in the working directory and its parents. It returns nil
+// without an error when there is none.
+func findProject() (*project, error) {
+	dir, err := os.Getwd()
+	if err != nil {
+		return nil, err
+	}
+	for {
+		path := filepath.Join(dir, manifestName)
+		if _, err := os.Stat(path); err == nil {
+			var m manifest
+			if _, err := toml.DecodeFile(path, &m); err != nil {
+				return nil, fmt.Errorf("%s: %v", path, err)
+			}
+			if m.Package.Entry == "" {
+				m.Package.Entry = filepath.Join("src", "main.vira")
+			}
+			if m.Package.Name == "" {
+				m.Package.Name = filepath.Base(dir)
+			}
+			return &project{root: dir, manifest: m}, nil
+		}
+		parent := filepath.Dir(dir)
+		if parent == dir {
+			return nil, nil
+		}
+		dir = parent
+	}
+}
+
+// inputs lists the entry point first, then the imported files in name order so builds are reproducible.
+func (p *project) inputs() []string {
+	files := []string{p.manifest.Package.Entry}
+	names := make([]string, 0, len(p.manifest.Imports))
+	for name := range p.manifest.Imports {
+		names = append(names, name)
+	}
+	sort.Strings(names)
+	for _, name := range names {
+		files = append(files, p.manifest.Imports[name])
+	}
+	return files
+}
+
+// executable is where build puts the program: named after the package, in the project root.
+func (p *project) executable() string {
+	if runtime.GOOS == "windows" {
+		return p.manifest.Package.Name + ".exe"
+	}
+	return p.manifest.Package.Name
+}
+
+// applyDefaults fills the settings the command line left unset from the manifest.
+func (p *project) applyDefaults(opts *buildOptions) {
+	if opts.output == "" {
+		opts.output = p.executable()
+	}
+	if opts.target == "" {
+		opts.target = p.manifest.Build.Target
+	}
+	if opts.optLevel == "" {
+		opts.optLevel = p.manifest.Build.OptLevel
+	}
+	opts.debugInfo = opts.debugInfo || p.manifest.Build.Debug
+}
+
+// projectInputs returns the files to compile: args when given, otherwise those of the project
+// around the working directory. Inside a project, it also moves to the project root, since
+// includes and the build cache are relative to it.
+func projectInputs(args []string, opts *buildOptions) []string {
+	if len(args) > 0 {
+		return args
+	}
+	p, err := findProject()
+	if err == nil && p == nil {
+		err = errors.New("no input files given and no " + manifestName + " found; pass files or run `vira new` to start a project")
+	}
+	if err == nil {
+		err = os.Chdir(p.root)
+	}
+	if err != nil {
+		pterm.Error.Println(err)
+		os.Exit(1)
+	}
+	p.applyDefaults(opts)
+	return p.inputs()
+}
+
+// newProject creates a directory called name with a manifest and a hello world program.
+func newProject(name string) error {
+	if name == "" || strings.ContainsAny(name, `/\`) || name == "." || name == ".." {
+		return fmt.Errorf("invalid project name %q", name)
+	}
+	if entries, err := os.ReadDir(name); err == nil && len(entries) > 0 {
+		return fmt.Errorf("%s already exists and is not empty", name)
+	}
+	if err := os.MkdirAll(filepath.Join(name, "src"), 0o755); err != nil {
+		return err
+	}
+	manifestText := fmt.Sprintf(`[package]
+name = %q
+version = "0.1.0"
+entry = "src/main.vira"
+
+[build]
+# Target triple to compile for; empty means this machine
+target = ""
+# 0 for none, 1 or 2 for speed, s for speed and size
+opt-level = "0"
+debug = false
+
+# Other source files compiled into the program, by name
+[imports]
+# utils = "src/utils.vira"
+`, name)
+	files := map[string]string{
+		manifestName:                      manifestText,
+		filepath.Join("src", "main.vira"): "write \"Hello, world!\";\n",
+		".gitignore":                      cacheDir + "/\n/" + name + "\n/" + name + ".exe\n",
+	}
+	for file, content := range files {
+		if err := os.WriteFile(filepath.Join(name, file), []byte(content), 0o644); err != nil {
+			return err
+		}
+	}
+	pterm.Success.Printf("Created project %s; run it with `cd %s && vira run`\n", name, name)
+	return nil
+}
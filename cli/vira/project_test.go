@@ -0,0 +1,168 @@
+package main
+
+import (
+	"os"
+	"path/filepath"
+	"reflect"
+	"testing"
+)
+
+// chdir moves the test into dir and back out when it ends.
+func chdir(t *testing.T, dir string) {
+	t.Helper()
+	old, err := os.Getwd()
+	if err != nil {
+		t.Fatal(err)
+	}
+	if err := os.Chdir(dir); err != nil {
+		t.Fatal(err)
+	}
+	t.Cleanup(func() { os.Chdir(old) })
+}
+
+// newTestProject writes a manifest into a fresh directory and returns the directory, with symlinks
+// resolved so it compares equal to what os.Getwd reports.
+func newTestProject(t *testing.T, manifestText string) string {
+	t.Helper()
+	root, err := filepath.EvalSymlinks(t.TempDir())
+	if err != nil {
+		t.Fatal(err)
+	}
+	if err := os.WriteFile(filepath.Join(root, manifestName), []byte(manifestText), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	return root
+}
+
+func TestFindProjectInParent(t *testing.T) {
+	root := newTestProject(t, `
+[package]
+name = "demo"
+
+[imports]
+strings = "src/strings.vira"
+math = "src/math.vira"
+`)
+	nested := filepath.Join(root, "src", "deep")
+	if err := os.MkdirAll(nested, 0o755); err != nil {
+		t.Fatal(err)
+	}
+	chdir(t, nested)
+
+	p, err := findProject()
+	if err != nil {
+		t.Fatal(err)
+	}
+	if p == nil {
+		t.Fatal("no project found")
+	}
+	if p.root != root {
+		t.Errorf("root = %q, want %q", p.root, root)
+	}
+	want := []string{filepath.Join("src", "main.vira"), "src/math.vira", "src/strings.vira"}
+	if got := p.inputs(); !reflect.DeepEqual(got, want) {
+		t.Errorf("inputs = %q, want %q", got, want)
+	}
+}
+
+func TestFindProjectNameDefaultsToDirectory(t *testing.T) {
+	root := newTestProject(t, "[package]\nentry = \"app.vira\"\n")
+	chdir(t, root)
+
+	p, err := findProject()
+	if err != nil || p == nil {
+		t.Fatalf("findProject = %v, %v", p, err)
+	}
+	if want := filepath.Base(root); p.manifest.Package.Name != want {
+		t.Errorf("name = %q, want %q", p.manifest.Package.Name, want)
+	}
+	if got := p.inputs(); !reflect.DeepEqual(got, []string{"app.vira"}) {
+		t.Errorf("inputs = %q, want the entry alone", got)
+	}
+}
+
+func TestFindProjectWithoutManifest(t *testing.T) {
+	chdir(t, t.TempDir())
+
+	p, err := findProject()
+	if p != nil || err != nil {
+		t.Errorf("findProject = %v, %v, want no project and no error", p, err)
+	}
+}
+
+func TestFindProjectInvalidManifest(t *testing.T) {
+	chdir(t, newTestProject(t, "[package\nname = "))
+
+	if _, err := findProject(); err == nil {
+		t.Error("an invalid manifest was accepted")
+	}
+}
+
+func TestProjectInputsPrefersArguments(t *testing.T) {
+	chdir(t, newTestProject(t, "[build]\ntarget = \"wasm32-unknown-unknown\"\n"))
+
+	var opts buildOptions
+	args := []string{"a.vira", "b.vira"}
+	if got := projectInputs(args, &opts); !reflect.DeepEqual(got, args) {
+		t.Errorf("inputs = %q, want %q", got, args)
+	}
+	if opts.target != "" {
+		t.Errorf("target = %q, want the manifest ignored", opts.target)
+	}
+}
+
+func TestProjectInputsFromManifest(t *testing.T) {
+	root := newTestProject(t, `
+[package]
+name = "demo"
+entry = "main.vira"
+
+[build]
+target = "x86_64-unknown-linux-gnu"
+opt-level = "2"
+debug = true
+
+[imports]
+util = "lib/util.vira"
+`)
+	nested := filepath.Join(root, "lib")
+	if err := os.MkdirAll(nested, 0o755); err != nil {
+		t.Fatal(err)
+	}
+	chdir(t, nested)
+
+	var opts buildOptions
+	want := []string{"main.vira", "lib/util.vira"}
+	if got := projectInputs(nil, &opts); !reflect.DeepEqual(got, want) {
+		t.Errorf("inputs = %q, want %q", got, want)
+	}
+	// The inputs are relative to the root, so that is where the build runs
+	if dir, _ := os.Getwd(); dir != root {
+		t.Errorf("working directory = %q, want the project root %q", dir, root)
+	}
+	var named project
+	named.manifest.Package.Name = "demo"
+	wantOpts := buildOptions{
+		output:    named.executable(),
+		target:    "x86_64-unknown-linux-gnu",
+		optLevel:  "2",
+		debugInfo: true,
+	}
+	if opts != wantOpts {
+		t.Errorf("options = %+v, want %+v", opts, wantOpts)
+	}
+}
+
+func TestApplyDefaultsKeepsFlags(t *testing.T) {
+	var p project
+	p.manifest.Package.Name = "demo"
+	p.manifest.Build.Target = "x86_64-unknown-linux-gnu"
+	p.manifest.Build.OptLevel = "2"
+
+	opts := buildOptions{output: "out", target: "aarch64-apple-darwin", optLevel: "s"}
+	want := opts
+	p.applyDefaults(&opts)
+	if opts != want {
+		t.Errorf("options = %+v, want the flags kept as %+v", opts, want)
+	}
+}
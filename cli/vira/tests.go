@@ -0,0 +1,132 @@
+package main
+
+import (
+	"errors"
+	"fmt"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"regexp"
+	"runtime"
+	"sort"
+	"strings"
+
+	"github.com/pterm/pterm"
+)
+
+// testFunction matches the definition of a test function, which takes no parameters.
+var testFunction = regexp.MustCompile(`(?m)^\s*def\s+(test_\w*)\s*\(\s*\)`)
+
+// testCase is one program vira test builds and runs.
+type testCase struct {
+	name string
+	// inputs are the files to compile, entry point first.
+	inputs []string
+}
+
+// runTests builds and runs every test in files, or in tests/*.vira when files is empty, each as a
+// program of its own so that one test can't affect another. It returns the number of failures.
+func runTests(files []string, opts buildOptions) (int, error) {
+	// Inside a project, tests can call the functions of its imported files
+	var support []string
+	p, err := findProject()
+	if err != nil {
+		return 0, err
+	}
+	if p != nil {
+		if len(files) == 0 {
+			if err := os.Chdir(p.root); err != nil {
+				return 0, err
+			}
+		}
+		p.applyDefaults(&opts)
+		for _, file := range p.inputs()[1:] {
+			support = append(support, filepath.Join(p.root, file))
+		}
+	}
+	if len(files) == 0 {
+		files, _ = filepath.Glob(filepath.Join("tests", "*.vira"))
+		sort.Strings(files)
+		if len(files) == 0 {
+			return 0, errors.New("no tests found; put them in tests/*.vira or pass test files")
+		}
+	}
+	dir := filepath.Join(cacheDir, "test")
+	if err := os.MkdirAll(dir, 0o755); err != nil {
+		return 0, err
+	}
+	var cases []testCase
+	for _, file := range files {
+		fileCases, err := discoverTests(file, support, dir)
+		if err != nil {
+			return 0, err
+		}
+		cases = append(cases, fileCases...)
+	}
+
+	failed := 0
+	for i, test := range cases {
+		opts.output = filepath.Join(dir, fmt.Sprintf("test%d", i))
+		if runtime.GOOS == "windows" {
+			opts.output += ".exe"
+		}
+		output, err := runTest(test, opts)
+		if err != nil {
+			failed++
+			pterm.Error.Printf("%s: %v\n", test.name, err)
+			if output = strings.TrimRight(output, "\n"); output != "" {
+				fmt.Println(output)
+			}
+		} else {
+			pterm.Success.Println(test.name)
+		}
+	}
+	summary := fmt.Sprintf("%d passed, %d failed", len(cases)-failed, failed)
+	if failed > 0 {
+		pterm.Error.Println(summary)
+	} else {
+		pterm.Success.Println(summary)
+	}
+	return failed, nil
+}
+
+// discoverTests turns a test file into test cases. A file that defines test_ functions only holds
+// definitions, and each of those functions is a test, called from a generated entry point in dir.
+// Any other file is a single test: its top-level code.
+func discoverTests(file string, support []string, dir string) ([]testCase, error) {
+	src, err := os.ReadFile(file)
+	if err != nil {
+		return nil, err
+	}
+	matches := testFunction.FindAllSubmatch(src, -1)
+	if len(matches) == 0 {
+		return []testCase{{name: file, inputs: append([]string{file}, support...)}}, nil
+	}
+	var cases []testCase
+	for _, match := range matches {
+		function := string(match[1])
+		entry := filepath.Join(dir, strings.TrimSuffix(filepath.Base(file), ".vira")+"."+function+".vira")
+		if err := os.WriteFile(entry, []byte(function+"();\n"), 0o644); err != nil {
+			return nil, err
+		}
+		cases = append(cases, testCase{
+			name:   file + ": " + function,
+			inputs: append([]string{entry, file}, support...),
+		})
+	}
+	return cases, nil
+}
+
+// runTest builds and runs one test, returning what it printed. The error says why it failed: the
+// build failed, an assertion failed or the program exited with a non-zero status.
+func runTest(test testCase, opts buildOptions) (string, error) {
+	if err := build(test.inputs, opts, false); err != nil {
+		return err.Error(), errors.New("build failed")
+	}
+	out, err := exec.Command(opts.output).CombinedOutput()
+	var exitErr *exec.ExitError
+	if errors.As(err, &exitErr) {
+		return string(out), fmt.Errorf("exited with status %d", exitErr.ExitCode())
+	}
+	return string(out), err
+}
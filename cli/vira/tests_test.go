@@ -0,0 +1,99 @@
+package main
+
+import (
+	"os"
+	"path/filepath"
+	"reflect"
+	"testing"
+)
+
+func TestTestFunctionPattern(t *testing.T) {
+	cases := []struct {
+		line string
+		want string
+	}{
+		{"def test_add() {", "test_add"},
+		{"    def test_nested ( ) {", "test_nested"},
+		{"def test_() {", "test_"},
+		// Tests take no parameters, so a function with some is a helper
+		{"def test_with(x) {", ""},
+		{"def testing() {", ""},
+		{"def helper() {", ""},
+		{"write test_add();", ""},
+	}
+	for _, c := range cases {
+		got := ""
+		if match := testFunction.FindStringSubmatch(c.line); match != nil {
+			got = match[1]
+		}
+		if got != c.want {
+			t.Errorf("%q: found %q, want %q", c.line, got, c.want)
+		}
+	}
+}
+
+// writeFile writes content to name in dir and returns its path.
+func writeFile(t *testing.T, dir, name, content string) string {
+	t.Helper()
+	path := filepath.Join(dir, name)
+	if err := os.WriteFile(path, []byte(content), 0o644); err != nil {
+		t.Fatal(err)
+	}
+	return path
+}
+
+func TestDiscoverTestFunctions(t *testing.T) {
+	dir := t.TempDir()
+	file := writeFile(t, dir, "math.vira", `def double(x) { return x * 2; }
+def test_double() { assert_eq(double(2), 4); }
+def test_zero() {
+    assert_eq(double(0), 0);
+}
+`)
+	entries := t.TempDir()
+
+	cases, err := discoverTests(file, []string{"lib.vira"}, entries)
+	if err != nil {
+		t.Fatal(err)
+	}
+	if len(cases) != 2 {
+		t.Fatalf("found %d tests, want 2: %+v", len(cases), cases)
+	}
+	for i, function := range []string{"test_double", "test_zero"} {
+		test := cases[i]
+		if want := file + ": " + function; test.name != want {
+			t.Errorf("name = %q, want %q", test.name, want)
+		}
+		entry := filepath.Join(entries, "math."+function+".vira")
+		if want := []string{entry, file, "lib.vira"}; !reflect.DeepEqual(test.inputs, want) {
+			t.Errorf("inputs = %q, want %q", test.inputs, want)
+		}
+		src, err := os.ReadFile(entry)
+		if err != nil {
+			t.Fatal(err)
+		}
+		if want := function + "();\n"; string(src) != want {
+			t.Errorf("entry point = %q, want %q", src, want)
+		}
+	}
+}
+
+func TestDiscoverTopLevelTest(t *testing.T) {
+	dir := t.TempDir()
+	file := writeFile(t, dir, "smoke.vira", "def test_with(x) { return x; }\nassert_eq(test_with(1), 1);\n")
+
+	cases, err := discoverTests(file, []string{"lib.vira"}, t.TempDir())
+	if err != nil {
+		t.Fatal(err)
+	}
+	want := []testCase{{name: file, inputs: []string{file, "lib.vira"}}}
+	if !reflect.DeepEqual(cases, want) {
+		t.Errorf("tests = %+v, want %+v", cases, want)
+	}
+}
+
+func TestDiscoverTestsMissingFile(t *testing.T) {
+	if _, err := discoverTests(filepath.Join(t.TempDir(), "missing.vira"), nil, t.TempDir()); err == nil {
+		t.Error("a missing test file was accepted")
+	}
+}
@@ -12,6 +12,11 @@ import (
 
 var binPath string
 
+var (
+	colorMode  string
+	renderMode string
+)
+
 func init() {
 	osName := runtime.GOOS
 	if osName == "linux" {
@@ -34,9 +39,12 @@ func main() {
 		Short: "Vira compilation tool",
 		Args:  cobra.ExactArgs(1),
 		Run: func(cmd *cobra.Command, args []string) {
+			configureColor()
 			compile(args[0])
 		},
 	}
+	rootCmd.Flags().StringVar(&colorMode, "color", "auto", "When to use colors: auto, always or never (auto respects NO_COLOR)")
+	rootCmd.Flags().StringVar(&renderMode, "render", "graphical", "Error layout: graphical, narratable or short")
 
 	if err := rootCmd.Execute(); err != nil {
 		pterm.Error.Println(err)
@@ -44,6 +52,14 @@ func main() {
 	}
 }
 
+func configureColor() {
+	if colorMode == "never" || (colorMode == "auto" && os.Getenv("NO_COLOR") != "") {
+		pterm.DisableColor()
+	} else if colorMode == "always" {
+		pterm.EnableColor()
+	}
+}
+
 func compile(inputFile string) {
 	outputPre := inputFile + ".pre"
 	outputObj := inputFile + ".o"
@@ -60,26 +76,16 @@ func compile(inputFile string) {
 	}
 	pterm.Success.Println("Preprocessing done")
 
-	pterm.DefaultSection.Println("Parsing and Checking")
-	plsa := filepath.Join(binPath, "plsa")
-	if runtime.GOOS == "windows" {
-		plsa += ".exe"
-	}
-	cmdPlsa := exec.Command(plsa, outputPre)
-	if out, err := cmdPlsa.CombinedOutput(); err != nil {
-		handleError(outputPre, string(out))
-		os.Exit(1)
-	}
-	pterm.Success.Println("PLSA done")
-
+	// The compiler parses and checks the program itself
 	pterm.DefaultSection.Println("Compiling")
 	compiler := filepath.Join(binPath, "compiler")
 	if runtime.GOOS == "windows" {
 		compiler += ".exe"
 	}
-	cmdComp := exec.Command(compiler, outputPre, outputObj)
+	cmdComp := exec.Command(compiler, "compile", outputPre, "--emit", "obj", "-o", outputObj)
 	if out, err := cmdComp.CombinedOutput(); err != nil {
-		handleError(outputPre, string(out))
+		// The compiler renders its own diagnostics with source context
+		pterm.Error.Println(string(out))
 		os.Exit(1)
 	}
 	pterm.Success.Println("Compilation done")
@@ -90,14 +96,15 @@ func compile(inputFile string) {
 	if runtime.GOOS == "windows" {
 		linker = "link.exe" // Adjust as needed
 		outputExe := inputFile + ".exe"
-		cmdLink := exec.Command(linker, "/OUT:"+outputExe, outputObj) // Simplified
+		cmdLink := exec.Command(linker, "/OUT:"+outputExe, outputObj, filepath.Join(binPath, "vira_rt.lib")) // Simplified
 		if out, err := cmdLink.CombinedOutput(); err != nil {
 			pterm.Error.Println(string(out))
 			os.Exit(1)
 		}
 	} else {
 		outputExe := "a.out" // Or input without ext
-		cmdLink := exec.Command(linker, outputObj, "-o", outputExe)
+		// The runtime library provides printing and strings to the generated code
+		cmdLink := exec.Command(linker, outputObj, "-o", outputExe, "-lm", filepath.Join(binPath, "libvira_rt.a"))
 		if out, err := cmdLink.CombinedOutput(); err != nil {
 			pterm.Error.Println(string(out))
 			os.Exit(1)
@@ -125,6 +132,8 @@ func handleError(sourceFile, errorMsg string) {
 		"--message", message,
 		"--line", string(line + '0'), // Convert to string
 		"--column", string(column + '0'),
+		"--color", colorMode,
+		"--render", renderMode,
 	)
 	if out, err := cmdDiag.CombinedOutput(); err != nil {
 		pterm.Error.Println(string(out))
@@ -0,0 +1,111 @@
+//! How much faster `-O1` and `-O2` make compiled programs than `-O0`, on arithmetic-heavy fixtures.
+//!
+//! Run with `cargo bench`; each fixture is compiled once per level, and only running the
+//! executable is timed. Criterion compares each run against the previous one.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+/// Programs that spend their time in number crunching rather than in the runtime, each writing a
+/// single result so that printing doesn't count.
+const FIXTURES: &[(&str, &str)] = &[
+    (
+        "sum-of-squares",
+        "\
+let total = 0;
+let i = 0;
+while i < 3000000 {
+    total = total + i * i % 7;
+    i = i + 1;
+}
+write total;
+",
+    ),
+    (
+        "nested-loops",
+        "\
+let count = 0;
+let a = 0;
+while a < 1500 {
+    let b = 0;
+    while b < 1500 {
+        if (a * b) % 3 == 1 && a > b {
+            count = count + 1;
+        }
+        b = b + 1;
+    }
+    a = a + 1;
+}
+write count;
+",
+    ),
+    (
+        "fibonacci",
+        "\
+def fib(n) {
+    if n < 2 { return n; }
+    return fib(n - 1) + fib(n - 2);
+}
+write fib(25);
+",
+    ),
+];
+
+/// The runtime archive, built in release mode from source/vira-rt.
+fn runtime() -> PathBuf {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../vira-rt");
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--release", "--quiet"])
+        .current_dir(&dir)
+        .status()
+        .expect("cargo should run");
+    assert!(status.success(), "building vira-rt failed");
+    let name = if cfg!(target_env = "msvc") { "vira_rt.lib" } else { "libvira_rt.a" };
+    dir.join("target/release").join(name)
+}
+
+/// Compiles `source` at `level` into `dir`, returning the executable.
+fn compile(dir: &Path, runtime: &Path, name: &str, source: &str, level: &str) -> PathBuf {
+    let input = dir.join(format!("{}.vira", name));
+    fs::write(&input, source).unwrap();
+    let program = dir.join(format!("{}-O{}", name, level));
+    let output = Command::new(env!("CARGO_BIN_EXE_compiler"))
+        .arg("compile")
+        .arg(&input)
+        .arg("-o")
+        .arg(&program)
+        .args(["-O", level, "--runtime"])
+        .arg(runtime)
+        .output()
+        .expect("the compiler should run");
+    assert!(output.status.success(), "compiling {} failed:\n{}", name, String::from_utf8_lossy(&output.stderr));
+    program
+}
+
+fn opt_levels(c: &mut Criterion) {
+    let runtime = runtime();
+    let dir = std::env::temp_dir().join(format!("vira-bench-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut group = c.benchmark_group("opt-level");
+    group.sample_size(10);
+    for (name, source) in FIXTURES {
+        let mut expected = None;
+        for level in ["0", "1", "2"] {
+            let program = compile(&dir, &runtime, name, source, level);
+            // Every level has to compute the same thing for the comparison to mean anything
+            let output = Command::new(&program).output().unwrap().stdout;
+            assert_eq!(expected.get_or_insert_with(|| output.clone()), &output, "{} at -O{}", name, level);
+            group.bench_with_input(BenchmarkId::new(*name, format!("O{}", level)), &program, |b, program| {
+                b.iter(|| Command::new(program).output().unwrap())
+            });
+        }
+    }
+    group.finish();
+    let _ = fs::remove_dir_all(&dir);
+}
+
+criterion_group!(benches, opt_levels);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::link::Requirements;
+
+/// Object files from earlier builds, one per source file. Each is named after a hash of everything
+/// that went into it, so an entry is never stale: a changed input simply maps to a new name.
+pub struct Cache {
+    dir: PathBuf,
+    extension: &'static str,
+}
+
+impl Cache {
+    /// Opens the cache in `dir`, creating the directory if needed.
+    pub fn open(dir: &Path, extension: &'static str) -> io::Result<Cache> {
+        fs::create_dir_all(dir)?;
+        Ok(Cache { dir: dir.to_path_buf(), extension })
+    }
+
+    /// Hex SHA-256 of `parts`. Each part is prefixed with its length so that moving bytes from one
+    /// part to the next changes the key.
+    pub fn key(parts: &[&[u8]]) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Path of the cached object for `key`, if there is one.
+    pub fn get(&self, key: &str) -> Option<(PathBuf, Requirements)> {
+        let object = self.object_path(key);
+        let requirements = fs::read_to_string(self.dir.join(format!("{}.req", key))).ok()?;
+        object.is_file().then(|| {
+            let requirements = Requirements {
+                math: requirements.lines().any(|line| line == "math"),
+                runtime: requirements.lines().any(|line| line == "runtime"),
+            };
+            (object, requirements)
+        })
+    }
+
+    /// Stores an object and what linking it requires, returning the object's path.
+    pub fn put(&self, key: &str, object: &[u8], requirements: &Requirements) -> io::Result<PathBuf> {
+        let mut lines = String::new();
+        if requirements.math {
+            lines.push_str("math\n");
+        }
+        if requirements.runtime {
+            lines.push_str("runtime\n");
+        }
+        fs::write(self.dir.join(format!("{}.req", key)), lines)?;
+        // The object goes last and appears atomically, so an interrupted build never leaves a
+        // truncated object behind that a later build would link
+        let path = self.object_path(key);
+        let partial = path.with_extension("tmp");
+        fs::write(&partial, object)?;
+        fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+
+    fn object_path(&self, key: &str) -> PathBuf {
+        self.dir.join(key).with_extension(self.extension)
+    }
+}
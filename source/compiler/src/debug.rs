@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::env;
+
+use cranelift_codegen::gimli::write::{self, Address, AttributeValue, DwarfUnit, EndianVec, LineProgram, LineString, Range, RangeList, Sections, Writer};
+use cranelift_codegen::gimli::{constants, Encoding, Format, LineEncoding, RunTimeEndian, SectionId};
+use cranelift_codegen::ir::Endianness;
+use cranelift_codegen::isa::TargetIsa;
+use cranelift_module::FuncId;
+use cranelift_object::object::write::{Relocation, StandardSegment};
+use cranelift_object::object::{BinaryFormat, RelocationEncoding, RelocationFlags, RelocationKind, SectionKind};
+use cranelift_object::ObjectProduct;
+use diagnostic::span::{ColumnUnit, SourceMap};
+
+use crate::error::CompileError;
+
+/// Where the machine code of one compiled function came from.
+pub struct FunctionLines {
+    pub func_id: FuncId,
+    /// Name in Vira, which the debugger shows.
+    pub name: String,
+    pub symbol: String,
+    pub file: String,
+    /// Byte offset of the definition, or of the first statement for `main`.
+    pub start: usize,
+    pub size: u64,
+    /// Code offset and source byte offset where each statement's instructions begin.
+    pub rows: Vec<(u64, usize)>,
+}
+
+/// DWARF for `-g`: a line table mapping machine code back to `.vira` lines and an entry per
+/// function, enough for gdb and lldb to set breakpoints, step by statement and show the source.
+pub struct DebugInfo {
+    sources: Vec<(String, String)>,
+    functions: Vec<FunctionLines>,
+    endian: RunTimeEndian,
+    address_size: u8,
+}
+
+impl DebugInfo {
+    pub fn new(sources: &[(String, String)], isa: &dyn TargetIsa) -> Self {
+        DebugInfo {
+            sources: sources.to_vec(),
+            functions: Vec::new(),
+            endian: match isa.endianness() {
+                Endianness::Little => RunTimeEndian::Little,
+                Endianness::Big => RunTimeEndian::Big,
+            },
+            address_size: isa.pointer_bytes(),
+        }
+    }
+
+    pub fn add(&mut self, function: FunctionLines) {
+        self.functions.push(function);
+    }
+
+    /// Adds the `.debug_*` sections describing the recorded functions to the object.
+    pub fn write(&self, product: &mut ObjectProduct) -> Result<(), CompileError> {
+        let Some((entry, _)) = self.sources.first() else {
+            return Ok(());
+        };
+        // Version 4 is the newest that every supported gdb, lldb and linker reads
+        let encoding = Encoding {
+            format: Format::Dwarf32,
+            version: 4,
+            address_size: self.address_size,
+        };
+        let mut dwarf = DwarfUnit::new(encoding);
+        let working_dir = env::current_dir().map(|dir| dir.display().to_string()).unwrap_or_else(|_| ".".to_string());
+        let comp_dir = LineString::new(working_dir.as_bytes(), encoding, &mut dwarf.line_strings);
+        let comp_file = LineString::new(entry.as_bytes(), encoding, &mut dwarf.line_strings);
+        dwarf.unit.line_program = LineProgram::new(encoding, LineEncoding::default(), comp_dir, None, comp_file, None);
+        let directory = dwarf.unit.line_program.default_directory();
+        let mut files = HashMap::new();
+        for (name, src) in &self.sources {
+            let file = LineString::new(name.as_bytes(), encoding, &mut dwarf.line_strings);
+            let file_id = dwarf.unit.line_program.add_file(file, directory, None);
+            files.insert(name.as_str(), (file_id, SourceMap::new(src).with_unit(ColumnUnit::Byte)));
+        }
+
+        // Functions are referred to by their index here and by their symbol in the object
+        let function_address = |index: usize| Address::Symbol { symbol: index, addend: 0 };
+        let ranges = RangeList(
+            self.functions
+                .iter()
+                .enumerate()
+                .map(|(index, function)| Range::StartLength {
+                    begin: function_address(index),
+                    length: function.size,
+                })
+                .collect(),
+        );
+        let ranges = dwarf.unit.ranges.add(ranges);
+        let root = dwarf.unit.root();
+        let unit = dwarf.unit.get_mut(root);
+        let producer = format!("vira compiler {}", env!("CARGO_PKG_VERSION"));
+        unit.set(constants::DW_AT_producer, AttributeValue::String(producer.into_bytes()));
+        unit.set(constants::DW_AT_name, AttributeValue::String(entry.as_bytes().to_vec()));
+        unit.set(constants::DW_AT_comp_dir, AttributeValue::String(working_dir.into_bytes()));
+        unit.set(constants::DW_AT_stmt_list, AttributeValue::LineProgramRef);
+        unit.set(constants::DW_AT_low_pc, AttributeValue::Address(Address::Constant(0)));
+        unit.set(constants::DW_AT_ranges, AttributeValue::RangeListRef(ranges));
+
+        for (index, function) in self.functions.iter().enumerate() {
+            let Some((file_id, map)) = files.get(function.file.as_str()) else {
+                continue;
+            };
+            let id = dwarf.unit.add(root, constants::DW_TAG_subprogram);
+            let entry = dwarf.unit.get_mut(id);
+            entry.set(constants::DW_AT_name, AttributeValue::String(function.name.as_bytes().to_vec()));
+            if function.symbol != function.name {
+                entry.set(constants::DW_AT_linkage_name, AttributeValue::String(function.symbol.as_bytes().to_vec()));
+            }
+            entry.set(constants::DW_AT_external, AttributeValue::Flag(true));
+            entry.set(constants::DW_AT_low_pc, AttributeValue::Address(function_address(index)));
+            entry.set(constants::DW_AT_high_pc, AttributeValue::Udata(function.size));
+            entry.set(constants::DW_AT_decl_file, AttributeValue::FileIndex(Some(*file_id)));
+            entry.set(constants::DW_AT_decl_line, AttributeValue::Udata(map.line_col(function.start).0 as u64));
+
+            let program = &mut dwarf.unit.line_program;
+            program.begin_sequence(Some(function_address(index)));
+            // The prologue belongs to the definition, so a breakpoint on the function stops there
+            let first = (function.rows.first().map(|&(offset, _)| offset) != Some(0)).then_some((0, function.start));
+            for (offset, source) in first.into_iter().chain(function.rows.iter().copied()) {
+                let (line, column) = map.line_col(source);
+                let row = program.row();
+                row.address_offset = offset;
+                row.file = *file_id;
+                row.line = line as u64;
+                row.column = column as u64;
+                program.generate_row();
+            }
+            program.end_sequence(function.size);
+        }
+
+        let mut sections = Sections::new(RelocatingWriter::new(self.endian));
+        dwarf.write(&mut sections).map_err(CompileError::internal)?;
+        let symbols: Vec<_> = self.functions.iter().map(|function| product.function_symbol(function.func_id)).collect();
+        let object = &mut product.object;
+        // Every section has to exist before relocations can refer to it
+        let mut section_ids = HashMap::new();
+        sections
+            .for_each(|id, writer| {
+                if !writer.data.slice().is_empty() {
+                    let name = match object.format() {
+                        BinaryFormat::MachO => id.name().replacen('.', "__", 1),
+                        _ => id.name().to_string(),
+                    };
+                    let segment = object.segment_name(StandardSegment::Debug).to_vec();
+                    let section = object.add_section(segment, name.into_bytes(), SectionKind::Debug);
+                    object.set_section_data(section, writer.data.slice().to_vec(), 1);
+                    section_ids.insert(id, section);
+                }
+                Ok::<_, CompileError>(())
+            })?;
+        sections.for_each(|id, writer| {
+            let Some(&section) = section_ids.get(&id) else {
+                return Ok(());
+            };
+            for reloc in &writer.relocations {
+                let (symbol, kind) = match reloc.target {
+                    RelocTarget::Function(index) => (symbols[index], RelocationKind::Absolute),
+                    // Mach-O debuggers read the debug sections of each object separately, so the
+                    // offset already written is final; elsewhere the linker concatenates them
+                    RelocTarget::Section(_) if object.format() == BinaryFormat::MachO => continue,
+                    RelocTarget::Section(target) => {
+                        let Some(&target) = section_ids.get(&target) else {
+                            continue;
+                        };
+                        let kind = match object.format() {
+                            BinaryFormat::Coff => RelocationKind::SectionOffset,
+                            _ => RelocationKind::Absolute,
+                        };
+                        (object.section_symbol(target), kind)
+                    }
+                };
+                let relocation = Relocation {
+                    offset: reloc.offset,
+                    symbol,
+                    addend: reloc.addend,
+                    flags: RelocationFlags::Generic {
+                        kind,
+                        encoding: RelocationEncoding::Generic,
+                        size: reloc.size * 8,
+                    },
+                };
+                object.add_relocation(section, relocation).map_err(CompileError::internal)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum RelocTarget {
+    Function(usize),
+    Section(SectionId),
+}
+
+#[derive(Clone)]
+struct DebugReloc {
+    offset: u64,
+    size: u8,
+    target: RelocTarget,
+    addend: i64,
+}
+
+/// A section writer that records where function addresses and offsets into other sections go,
+/// since only the linker knows their final values.
+#[derive(Clone)]
+struct RelocatingWriter {
+    data: EndianVec<RunTimeEndian>,
+    relocations: Vec<DebugReloc>,
+}
+
+impl RelocatingWriter {
+    fn new(endian: RunTimeEndian) -> Self {
+        RelocatingWriter {
+            data: EndianVec::new(endian),
+            relocations: Vec::new(),
+        }
+    }
+}
+
+impl Writer for RelocatingWriter {
+    type Endian = RunTimeEndian;
+
+    fn endian(&self) -> RunTimeEndian {
+        self.data.endian()
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> write::Result<()> {
+        self.data.write(bytes)
+    }
+
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) -> write::Result<()> {
+        self.data.write_at(offset, bytes)
+    }
+
+    fn write_address(&mut self, address: Address, size: u8) -> write::Result<()> {
+        match address {
+            Address::Constant(value) => self.write_udata(value, size),
+            Address::Symbol { symbol, addend } => {
+                self.relocations.push(DebugReloc {
+                    offset: self.len() as u64,
+                    size,
+                    target: RelocTarget::Function(symbol),
+                    addend,
+                });
+                self.write_udata(0, size)
+            }
+        }
+    }
+
+    fn write_offset(&mut self, val: usize, section: SectionId, size: u8) -> write::Result<()> {
+        self.relocations.push(DebugReloc {
+            offset: self.len() as u64,
+            size,
+            target: RelocTarget::Section(section),
+            addend: val as i64,
+        });
+        self.write_udata(val as u64, size)
+    }
+
+    fn write_offset_at(&mut self, offset: usize, val: usize, section: SectionId, size: u8) -> write::Result<()> {
+        self.relocations.push(DebugReloc {
+            offset: offset as u64,
+            size,
+            target: RelocTarget::Section(section),
+            addend: val as i64,
+        });
+        self.write_udata_at(offset, val as u64, size)
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt::Display;
+
+use diagnostic::exit;
+use diagnostic::format::ErrorFormat;
+use diagnostic::{Applicability, Severity, ViraDiagnostic};
+use vira_core::Span;
+
+/// Anything that stops or warns about a compilation. Rendered against the source file through the
+/// shared diagnostic renderer, so the compiler reports problems the way the other tools do.
+#[derive(Debug)]
+pub struct CompileError {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub message: String,
+    /// Input file the span points into.
+    pub file: Option<String>,
+    pub span: Option<Span>,
+    /// Other places in the same file the error relates to, with what each is.
+    pub labels: Vec<(Span, String)>,
+    /// Edits in the same file certain to fix the error, as the text to put in place of each span.
+    pub fixes: Vec<(Span, String)>,
+    pub help: Option<String>,
+    pub notes: Vec<String>,
+    /// What the compiler exits with when this error stops it; see `diagnostic::exit`.
+    pub status: i32,
+}
+
+impl CompileError {
+    pub fn new(code: &'static str, message: impl Into<String>) -> Self {
+        CompileError {
+            severity: Severity::Error,
+            code: Some(code),
+            message: message.into(),
+            file: None,
+            span: None,
+            labels: Vec::new(),
+            fixes: Vec::new(),
+            help: None,
+            notes: Vec::new(),
+            status: exit::COMPILE,
+        }
+    }
+
+    /// Valid Vira the native backend can't lower yet.
+    pub fn unsupported(what: &str, span: Span) -> Self {
+        CompileError::new("V0201", format!("{} is not supported by the native compiler yet", what)).with_span(span)
+    }
+
+    /// A problem with the invocation rather than the program, such as a bad flag.
+    pub fn usage(message: impl Into<String>) -> Self {
+        CompileError {
+            code: None,
+            ..CompileError::new("", message)
+        }
+    }
+
+    /// A failure inside Cranelift or the object writer, which means the compiler has a bug.
+    pub fn internal(err: impl Display) -> Self {
+        CompileError {
+            status: exit::INTERNAL,
+            ..CompileError::usage(format!("internal compiler error: {}", err))
+        }
+        .with_help("please report this at https://github.com/vira-language/vira/issues")
+    }
+
+    /// Information about what the compiler did, which never fails a build.
+    pub fn note(message: impl Into<String>) -> Self {
+        CompileError {
+            severity: Severity::Note,
+            ..CompileError::usage(message)
+        }
+    }
+
+    pub fn warning(mut self) -> Self {
+        self.severity = Severity::Warning;
+        self
+    }
+
+    /// Blames the environment rather than the program, such as an unreadable file or a missing linker.
+    pub fn io(mut self) -> Self {
+        self.status = exit::IO;
+        self
+    }
+
+    /// Attributes the error to `file` unless it already names one.
+    pub fn in_file(mut self, file: &str) -> Self {
+        self.file.get_or_insert_with(|| file.to_string());
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// The span is only shown when the error's file is among `files`, given as name and source.
+    pub fn to_diagnostic(&self, files: &[(String, String)]) -> ViraDiagnostic {
+        let mut diag = ViraDiagnostic::new(self.severity, self.message.clone());
+        if let Some(code) = self.code {
+            diag = diag.with_code(code);
+        }
+        let source = files.iter().find(|(name, _)| Some(name) == self.file.as_ref());
+        if let (Some(span), Some((name, src))) = (self.span, source) {
+            diag = diag.with_label((span.start, span.len()), "here").with_source(name, src.as_str());
+            for (span, text) in &self.labels {
+                diag = diag.with_secondary_label((span.start, span.len()), text.clone());
+            }
+            for (span, replacement) in &self.fixes {
+                diag = diag.with_suggestion((span.start, span.len()), replacement.clone(), "", Applicability::MachineApplicable);
+            }
+        }
+        if let Some(help) = &self.help {
+            diag = diag.with_help(help.clone());
+        }
+        for note in &self.notes {
+            diag = diag.with_note(note.clone());
+        }
+        diag
+    }
+}
+
+impl From<vira_core::Error> for CompileError {
+    fn from(err: vira_core::Error) -> Self {
+        CompileError {
+            help: err.help,
+            span: Some(err.span),
+            labels: err.labels,
+            fixes: err.fix.into_iter().map(|fix| *fix).collect(),
+            ..CompileError::new(err.code, err.message)
+        }
+    }
+}
+
+/// Prints a diagnostic to stderr in `format`.
+pub fn report(err: &CompileError, files: &[(String, String)], format: ErrorFormat) {
+    format.eprint(&err.to_diagnostic(files));
+}
@@ -0,0 +1,123 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How a linker expects its arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flavor {
+    /// A C compiler driver such as `cc`, `clang` or `gcc`, which adds the C runtime itself.
+    Driver,
+    /// `link.exe` or `lld-link`, which take `/FLAG` arguments.
+    Msvc,
+}
+
+/// A linker to run and the ld it should use, if not its default.
+#[derive(Debug, Clone)]
+pub struct Linker {
+    pub program: PathBuf,
+    pub flavor: Flavor,
+    /// Passed as `-fuse-ld=` to a driver, e.g. `lld`.
+    pub fuse_ld: Option<String>,
+}
+
+/// What the program needs from the platform beyond the C library.
+#[derive(Debug, Default)]
+pub struct Requirements {
+    /// libm, for `fmod` and the builtins it implements.
+    pub math: bool,
+    /// The Vira runtime library, for printing and strings.
+    pub runtime: bool,
+}
+
+impl Requirements {
+    /// Adds what another object of the same program needs.
+    pub fn merge(&mut self, other: &Requirements) {
+        self.math |= other.math;
+        self.runtime |= other.runtime;
+    }
+}
+
+/// File name of the runtime archive as Cargo builds it for the host.
+pub const RUNTIME: &str = if cfg!(target_env = "msvc") { "vira_rt.lib" } else { "libvira_rt.a" };
+
+/// Where the runtime archive is installed: next to the compiler executable.
+pub fn bundled_runtime() -> Option<PathBuf> {
+    Some(env::current_exe().ok()?.parent()?.join(RUNTIME))
+}
+
+const DRIVERS: [&str; 3] = ["cc", "clang", "gcc"];
+
+impl Linker {
+    /// First linker found on `PATH` for the host, preferring the platform's native one.
+    pub fn detect() -> Option<Linker> {
+        if cfg!(windows) {
+            for name in ["link", "lld-link"] {
+                if let Some(program) = find_program(name) {
+                    return Some(Linker { program, flavor: Flavor::Msvc, fuse_ld: None });
+                }
+            }
+        }
+        DRIVERS.iter().find_map(|name| find_program(name)).map(|program| Linker {
+            program,
+            flavor: Flavor::Driver,
+            fuse_ld: None,
+        })
+    }
+
+    /// The linker named by `--linker`. `lld` means a detected C driver running with `-fuse-ld=lld`;
+    /// anything else is a path or program name whose flavor is guessed from its file name.
+    pub fn from_name(name: &str) -> Option<Linker> {
+        if name == "lld" {
+            if cfg!(windows) {
+                return find_program("lld-link").map(|program| Linker { program, flavor: Flavor::Msvc, fuse_ld: None });
+            }
+            let program = DRIVERS.iter().find_map(|name| find_program(name))?;
+            return Some(Linker {
+                program,
+                flavor: Flavor::Driver,
+                fuse_ld: Some("lld".to_string()),
+            });
+        }
+        let path = Path::new(name);
+        let program = if path.components().count() > 1 { path.to_path_buf() } else { find_program(name)? };
+        let stem = program.file_stem().map(|stem| stem.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let flavor = if stem == "link" || stem == "lld-link" { Flavor::Msvc } else { Flavor::Driver };
+        Some(Linker { program, flavor, fuse_ld: None })
+    }
+
+    /// The command linking `objects` into the executable `output` for the host.
+    pub fn command(&self, objects: &[PathBuf], output: &Path, requirements: &Requirements) -> Command {
+        let mut cmd = Command::new(&self.program);
+        match self.flavor {
+            Flavor::Driver => {
+                cmd.args(objects).arg("-o").arg(output);
+                if let Some(ld) = &self.fuse_ld {
+                    cmd.arg(format!("-fuse-ld={}", ld));
+                }
+                // Functions are in their own sections, so unreferenced ones can be dropped
+                cmd.arg(if cfg!(target_os = "macos") { "-Wl,-dead_strip" } else { "-Wl,--gc-sections" });
+                // libm is part of libSystem on macOS and of the CRT on Windows
+                if requirements.math && !cfg!(target_os = "macos") && !cfg!(windows) {
+                    cmd.arg("-lm");
+                }
+            }
+            Flavor::Msvc => {
+                cmd.arg("/NOLOGO").args(objects);
+                let mut out = OsString::from("/OUT:");
+                out.push(output);
+                cmd.arg(out);
+                cmd.arg("/OPT:REF").arg("/SUBSYSTEM:CONSOLE");
+                // The static CRT starts the process and calls `main`; oldnames maps `gcvt` to `_gcvt`
+                cmd.arg("/DEFAULTLIB:libcmt").arg("/DEFAULTLIB:oldnames");
+            }
+        }
+        cmd
+    }
+}
+
+/// Full path of an executable on `PATH`.
+fn find_program(name: &str) -> Option<PathBuf> {
+    let file = if cfg!(windows) && Path::new(name).extension().is_none() { format!("{}.exe", name) } else { name.to_string() };
+    env::split_paths(&env::var_os("PATH")?).map(|dir| dir.join(&file)).find(|path| path.is_file())
+}
@@ -0,0 +1,301 @@
+use std::collections::{HashMap, HashSet};
+
+use vira_core::ast::{Else, Expr, Pattern, Program, Stmt, Variant};
+use vira_core::check::BUILTINS;
+use vira_core::resolve::SymbolKind;
+use vira_core::{ensure_stack, Resolution, Span, Symbol};
+use vira_ir::optimize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedFunction,
+    UnreachableCode,
+    ShadowedVariable,
+    NonExhaustiveMatch,
+    UnusedVariable,
+    ConstantCondition,
+    ShadowedBuiltin,
+}
+
+impl Lint {
+    const ALL: [Lint; 7] = [
+        Lint::UnusedFunction,
+        Lint::UnreachableCode,
+        Lint::ShadowedVariable,
+        Lint::NonExhaustiveMatch,
+        Lint::UnusedVariable,
+        Lint::ConstantCondition,
+        Lint::ShadowedBuiltin,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedFunction => "unused-function",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::ShadowedVariable => "shadowed-variable",
+            Lint::NonExhaustiveMatch => "non-exhaustive-match",
+            Lint::UnusedVariable => "unused-variable",
+            Lint::ConstantCondition => "constant-condition",
+            Lint::ShadowedBuiltin => "shadowed-builtin",
+        }
+    }
+
+    /// Stable diagnostic code, see `diagnostic --explain`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::UnusedFunction => "V0301",
+            Lint::UnreachableCode => "V0302",
+            Lint::ShadowedVariable => "V0303",
+            Lint::NonExhaustiveMatch => "V0304",
+            Lint::UnusedVariable => "V0305",
+            Lint::ConstantCondition => "V0306",
+            Lint::ShadowedBuiltin => "V0307",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Lint> {
+        Lint::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+pub struct Warning {
+    pub lint: Lint,
+    pub message: String,
+    /// What to point at.
+    pub span: Span,
+}
+
+/// Per-lint levels set from `--allow`/`--deny`; everything warns by default.
+#[derive(Default)]
+pub struct LintConfig {
+    allowed: HashSet<Lint>,
+    denied: HashSet<Lint>,
+}
+
+impl LintConfig {
+    pub fn allow(&mut self, lint: Lint) {
+        self.denied.remove(&lint);
+        self.allowed.insert(lint);
+    }
+
+    pub fn deny(&mut self, lint: Lint) {
+        self.allowed.remove(&lint);
+        self.denied.insert(lint);
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        if self.allowed.contains(&lint) {
+            Level::Allow
+        } else if self.denied.contains(&lint) {
+            Level::Deny
+        } else {
+            Level::Warn
+        }
+    }
+}
+
+/// Reports suspicious but valid code. Never fails on its own; the caller decides based on `LintConfig`.
+/// `resolution` is the program's, from `vira_core::resolve`, and `enums` holds the variants of every
+/// enum across all files.
+pub fn check(program: &Program, resolution: &Resolution, reachable: &HashSet<Symbol>, enums: &HashMap<Symbol, &[Variant]>) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for stmt in &program.statements {
+        if let Stmt::FuncDef { name, name_span, body, .. } = stmt {
+            if !reachable.contains(name) {
+                warnings.push(Warning {
+                    lint: Lint::UnusedFunction,
+                    message: format!("function '{}' is never called", name),
+                    span: *name_span,
+                });
+            }
+            if BUILTINS.iter().any(|&(builtin, _, _)| name == builtin) {
+                warnings.push(Warning {
+                    lint: Lint::ShadowedBuiltin,
+                    message: format!("function '{}' replaces the builtin of the same name", name),
+                    span: *name_span,
+                });
+            }
+            check_unreachable(&format!("'{}'", name), body.statements.iter(), &mut warnings);
+        }
+    }
+    let top_level = program.statements.iter().filter(|stmt| !matches!(stmt, Stmt::FuncDef { .. } | Stmt::Enum { .. }));
+    check_unreachable("the top-level program", top_level, &mut warnings);
+    check_matches(&program.statements, enums, &mut warnings);
+    check_conditions(&program.statements, &mut warnings);
+    // A leading underscore marks a variable kept on purpose
+    let unread = resolution.declarations.iter().filter(|declaration| {
+        declaration.kind == SymbolKind::Variable && declaration.reads == 0 && !declaration.name.as_str().starts_with('_')
+    });
+    for declaration in unread {
+        warnings.push(Warning {
+            lint: Lint::UnusedVariable,
+            message: format!("variable '{}' is never read", declaration.name),
+            span: declaration.span,
+        });
+    }
+    for shadowing in &resolution.shadowings {
+        let declaration = resolution.declaration(shadowing.symbol);
+        let shadowed = match resolution.declaration(shadowing.shadowed).kind {
+            SymbolKind::Parameter => "parameter",
+            SymbolKind::Variable => "variable",
+        };
+        warnings.push(Warning {
+            lint: Lint::ShadowedVariable,
+            message: format!("variable '{}' shadows an earlier {} of the same name", declaration.name, shadowed),
+            span: declaration.span,
+        });
+    }
+    warnings.sort_by_key(|warning| warning.span.start);
+    warnings
+}
+
+fn check_unreachable<'a>(function: &str, statements: impl Iterator<Item = &'a Stmt> + Clone, warnings: &mut Vec<Warning>) {
+    if let Some(index) = statements.clone().position(|stmt| matches!(stmt, Stmt::Return(..))) {
+        let dead = statements.clone().count() - index - 1;
+        if let Some(first) = statements.clone().nth(index + 1) {
+            warnings.push(Warning {
+                lint: Lint::UnreachableCode,
+                message: format!("{} unreachable statement(s) after return in {}", dead, function),
+                span: first.span(),
+            });
+        }
+    }
+    for stmt in statements {
+        match stmt {
+            Stmt::If {
+                then_block,
+                else_branch,
+                ..
+            } => {
+                check_unreachable(function, then_block.statements.iter(), warnings);
+                match else_branch {
+                    Some(Else::If(nested)) => check_unreachable(function, std::iter::once(nested.as_ref()), warnings),
+                    Some(Else::Block(block)) => check_unreachable(function, block.statements.iter(), warnings),
+                    None => {}
+                }
+            }
+            Stmt::While { body, .. } => check_unreachable(function, body.statements.iter(), warnings),
+            Stmt::Match { arms, .. } => {
+                for arm in arms {
+                    check_unreachable(function, arm.body.statements.iter(), warnings);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Warns about every match without a `_` arm that leaves out variants of its enum, since nothing
+/// runs for those.
+fn check_matches(statements: &[Stmt], enums: &HashMap<Symbol, &[Variant]>, warnings: &mut Vec<Warning>) {
+    for stmt in statements {
+        match stmt {
+            Stmt::FuncDef { body, .. } | Stmt::While { body, .. } => check_matches(&body.statements, enums, warnings),
+            Stmt::If {
+                then_block,
+                else_branch,
+                ..
+            } => {
+                check_matches(&then_block.statements, enums, warnings);
+                match else_branch {
+                    Some(Else::If(nested)) => check_matches(std::slice::from_ref(nested.as_ref()), enums, warnings),
+                    Some(Else::Block(block)) => check_matches(&block.statements, enums, warnings),
+                    None => {}
+                }
+            }
+            Stmt::Match { value, arms, span } => {
+                let patterns = || arms.iter().flat_map(|arm| &arm.patterns);
+                let wildcard = patterns().any(|pattern| matches!(pattern, Pattern::Wildcard(_)));
+                // Lints run alongside the checker, which reports patterns of unknown or mixed enums
+                let enum_name = patterns().find_map(|pattern| match pattern {
+                    Pattern::Variant(enum_name, _, _) => Some(*enum_name),
+                    Pattern::Wildcard(_) => None,
+                });
+                let variants = enum_name.and_then(|enum_name| Some((enum_name, *enums.get(&enum_name)?)));
+                if let (false, Some((enum_name, variants))) = (wildcard, variants) {
+                    let missing: Vec<&str> = variants
+                        .iter()
+                        .filter(|variant| !patterns().any(|pattern| matches!(pattern, Pattern::Variant(_, name, _) if *name == variant.name)))
+                        .map(|variant| variant.name.as_str())
+                        .collect();
+                    if !missing.is_empty() {
+                        warnings.push(Warning {
+                            lint: Lint::NonExhaustiveMatch,
+                            message: format!("match on '{}' doesn't handle {}", enum_name, missing.join(", ")),
+                            // Just `match value`, not every arm
+                            span: Span::new(span.start, value.span().end),
+                        });
+                    }
+                }
+                for arm in arms {
+                    check_matches(&arm.body.statements, enums, warnings);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Warns about every `if` whose condition is the same each time, and every `while` whose body never
+/// runs. A `while` on a constant that is true is left alone: it is how a loop that ends with
+/// `return` is written.
+fn check_conditions(statements: &[Stmt], warnings: &mut Vec<Warning>) {
+    for stmt in statements {
+        match stmt {
+            Stmt::FuncDef { body, .. } => check_conditions(&body.statements, warnings),
+            Stmt::While { condition, body, .. } => {
+                if constant(condition) == Some(0.0) {
+                    warnings.push(Warning {
+                        lint: Lint::ConstantCondition,
+                        message: "loop condition is always false, so the body never runs".to_string(),
+                        span: condition.span(),
+                    });
+                }
+                check_conditions(&body.statements, warnings);
+            }
+            Stmt::If {
+                condition,
+                then_block,
+                else_branch,
+                ..
+            } => {
+                if let Some(value) = constant(condition) {
+                    warnings.push(Warning {
+                        lint: Lint::ConstantCondition,
+                        message: format!("condition is always {}", if value != 0.0 { "true" } else { "false" }),
+                        span: condition.span(),
+                    });
+                }
+                check_conditions(&then_block.statements, warnings);
+                match else_branch {
+                    Some(Else::If(nested)) => check_conditions(std::slice::from_ref(nested.as_ref()), warnings),
+                    Some(Else::Block(block)) => check_conditions(&block.statements, warnings),
+                    None => {}
+                }
+            }
+            Stmt::Match { arms, .. } => {
+                for arm in arms {
+                    check_conditions(&arm.body.statements, warnings);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The value of an expression made of number literals and operators alone.
+fn constant(expr: &Expr) -> Option<f64> {
+    ensure_stack(|| match expr {
+        Expr::Number(value, _) => Some(*value),
+        Expr::Unary(op, operand, _) => Some(optimize::unary(*op, constant(operand)?)),
+        Expr::Binary(op, left, right, _) => Some(optimize::binary(*op, constant(left)?, constant(right)?)),
+        _ => None,
+    })
+}
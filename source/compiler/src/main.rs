@@ -1,345 +1,1097 @@
-use std::collections::HashMap;
+// Errors are created once and end compilation, so their size on the error path does not matter
+#![allow(clippy::result_large_err)]
+
+mod cache;
+mod debug;
+mod error;
+mod link;
+mod lint;
+
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::fs::{self, File};
-use std::io::{self, Write};
-use std::process::Command;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+use clap::{Parser, Subcommand, ValueEnum};
+use rayon::prelude::*;
+use tracing::{debug, info, info_span};
 use cranelift::prelude::*;
-use cranelift_codegen::ir::{AbiParam, InstBuilder, UserFuncName};
+use cranelift_codegen::ir::{AbiParam, InstBuilder, SourceLoc, UserFuncName};
 use cranelift_codegen::isa::{self};
 use cranelift_codegen::settings::{self, Configurable};
 use cranelift_codegen::Context;
 use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
-use cranelift_module::{Linkage, Module};
+use cranelift_module::{DataDescription, DataId, FuncId, Linkage, Module};
 use cranelift_object::{ObjectBuilder, ObjectModule};
-use target_lexicon::Triple;
-
-#[derive(Debug, PartialEq, Clone)]
-enum Token {
-    Identifier(String),
-    Keyword(String),
-    Number(i64),
-    StringLiteral(String),
-    Punctuator(char),
-    EOF,
+use target_lexicon::{Architecture, OperatingSystem, Triple};
+use vira_core::ast::{BinOp, Expr, Param, Program, Stmt, UnOp, Variant};
+use vira_core::{Span, Symbol};
+use vira_ir as ir;
+
+use diagnostic::fix;
+use diagnostic::format::ErrorFormat;
+use diagnostic::input;
+use diagnostic::log::{self, LogArgs};
+use diagnostic::span::SourceMap;
+use diagnostic::Severity;
+use error::CompileError;
+
+/// A parameter as the cache key needs it: its name and its default, without spans, which move
+/// whenever the file is edited above the definition.
+fn describe_param(param: &Param) -> String {
+    fn literal(expr: &Expr) -> String {
+        match expr {
+            Expr::Number(value, _) => value.to_string(),
+            Expr::String(value, _) => format!("{:?}", value),
+            Expr::Unary(op, operand, _) => format!("{}{}", op.symbol(), literal(operand)),
+            // The parser only takes the literals above
+            other => format!("{:?}", other),
+        }
+    }
+    match &param.default {
+        Some(default) => format!("{}={}", param.name, literal(default)),
+        None => param.name.to_string(),
+    }
 }
 
-struct Lexer {
-    input: String,
-    position: usize,
+/// Symbol for a Vira function: `_V`, then the name prefixed with its length, so `add` becomes `_V3add`.
+/// Names starting with an underscore and a capital letter are reserved in C, so user functions can't
+/// collide with `main`, libc or C code linked alongside, and the length prefix leaves room for
+/// module paths later.
+fn symbol_name(name: &str) -> String {
+    format!("_V{}{}", name.len(), name)
 }
 
-impl Lexer {
-    fn new(input: String) -> Self {
-        Lexer { input, position: 0 }
-    }
+struct CodeGenerator {
+    module: ObjectModule,
+    functions: HashMap<Symbol, FuncId>,
+    /// C library functions, kept apart from `functions` so user code can't shadow them.
+    imports: HashMap<&'static str, FuncId>,
+    /// One data object per distinct string literal.
+    strings: HashMap<String, DataId>,
+    /// What `listing` collects: Cranelift IR for `Emit::Clif`, machine code for `Emit::Asm`.
+    emit: Emit,
+    listing: String,
+    /// Line tables being collected for `-g`.
+    debug: Option<debug::DebugInfo>,
+    /// Name and source of every file, in the order they were lowered, for the locations `assert`
+    /// reports.
+    sources: Vec<(String, String)>,
+    /// Whether functions report calls to the runtime's profiler.
+    profile: bool,
+}
 
-    fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
-        if self.position >= self.input.len() {
-            return Token::EOF;
-        }
-        let ch = self.current_char();
-        if ch.is_alphabetic() || ch == '_' {
-            return self.lex_identifier_or_keyword();
-        } else if ch.is_digit(10) {
-            return self.lex_number();
-        } else if ch == '"' {
-            return self.lex_string();
-        } else if "+-*/=();{}[]<>,&|!".contains(ch) {
-            self.advance();
-            return Token::Punctuator(ch);
-        } else {
-            panic!("Unexpected character: {}", ch);
+/// Everything one compilation produces.
+struct Generated {
+    object: Vec<u8>,
+    listing: String,
+    requirements: link::Requirements,
+}
+
+impl CodeGenerator {
+    /// Fails when Cranelift has no backend for the target's architecture.
+    /// `opt_level` is a Cranelift `opt_level` setting: `none`, `speed` or `speed_and_size`.
+    fn new(triple: Triple, opt_level: &str, emit: Emit, sources: &[(String, String)]) -> Result<Self, CompileError> {
+        let mut flag_builder = settings::builder();
+        flag_builder.set("opt_level", opt_level).map_err(CompileError::internal)?;
+        // Verifying the IR catches code generator bugs early but slows down release builds
+        let verify = if cfg!(debug_assertions) { "true" } else { "false" };
+        flag_builder.set("enable_verifier", verify).map_err(CompileError::internal)?;
+        flag_builder.set("use_colocated_libcalls", "false").map_err(CompileError::internal)?;
+        // Position-independent code so the default PIE link needs no text relocations for imports and data
+        flag_builder.set("is_pic", "true").map_err(CompileError::internal)?;
+        if matches!(triple.architecture, Architecture::Wasm32 | Architecture::Wasm64) {
+            return Err(CompileError::usage(format!(
+                "unsupported target '{}': Cranelift generates native code only and has no WebAssembly backend",
+                triple
+            )));
         }
+        // The object format and default calling convention both follow from the triple
+        let isa_builder = isa::lookup(triple.clone()).map_err(|err| CompileError::usage(format!("unsupported target '{}': {}", triple, err)))?;
+        let isa = isa_builder.finish(settings::Flags::new(flag_builder)).map_err(CompileError::internal)?;
+        let mut builder = ObjectBuilder::new(isa, "vira_module".to_owned(), cranelift_module::default_libcall_names()).map_err(CompileError::internal)?;
+        // One section per function lets the linker drop anything left unreferenced
+        builder.per_function_section(true);
+        let module = ObjectModule::new(builder);
+        Ok(CodeGenerator {
+            module,
+            functions: HashMap::new(),
+            imports: HashMap::new(),
+            strings: HashMap::new(),
+            emit,
+            listing: String::new(),
+            debug: None,
+            sources: sources.to_vec(),
+            profile: false,
+        })
     }
 
-    fn current_char(&self) -> char {
-        self.input.as_bytes()[self.position] as char
+    /// Makes every function report when it is entered and left, and `main` start the profiler.
+    fn with_profiling(mut self) -> Self {
+        self.profile = true;
+        self
     }
 
-    fn advance(&mut self) {
-        self.position += 1;
+    /// Records where each function's code came from, adds DWARF to the object and tracks calls for backtraces.
+    fn with_debug_info(mut self) -> Self {
+        self.debug = Some(debug::DebugInfo::new(&self.sources, self.module.isa()));
+        self
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.position < self.input.len() && self.current_char().is_whitespace() {
-            self.advance();
+    /// Compiles the functions of all files into one object, or with `only`, just the functions of
+    /// that one file. The objects of every file then link together, each importing what the others
+    /// define. `main` is exported and belongs to the first file.
+    fn generate(mut self, program: &ir::Module, only: Option<usize>) -> Result<Generated, CompileError> {
+        // Declare everything up front so calls can refer to functions defined later or in other files
+        for function in program.functions.iter().filter(|function| !function.is_main) {
+            let linkage = match only {
+                None => Linkage::Local,
+                Some(file) if file == function.file => Linkage::Hidden,
+                Some(_) => Linkage::Import,
+            };
+            let func_id = self.declare_function(&symbol_name(function.name.as_str()), function.params, types::F64, linkage)?;
+            self.functions.insert(function.name, func_id);
         }
+        for function in program.functions.iter().filter(|function| only.is_none_or(|only| only == function.file)) {
+            let func_id = if function.is_main {
+                // `main` alone returns an int, the process exit code
+                self.declare_function("main", 0, types::I32, Linkage::Export)?
+            } else {
+                self.functions[&function.name]
+            };
+            let file = self.sources[function.file].0.clone();
+            self.generate_function(func_id, function).map_err(|err| err.in_file(&file))?;
+        }
+        let mut product = self.module.finish();
+        if let Some(debug) = &self.debug {
+            debug.write(&mut product)?;
+        }
+        Ok(Generated {
+            object: product.object.write().map_err(CompileError::internal)?,
+            listing: self.listing,
+            requirements: link::Requirements {
+                // Everything called outside the runtime, like `fmod`, comes from libm
+                math: self.imports.keys().any(|name| !name.starts_with("vira_")),
+                runtime: self.imports.keys().any(|name| name.starts_with("vira_")),
+            },
+        })
+    }
+
+    fn declare_function(&mut self, symbol: &str, arity: usize, returns: Type, linkage: Linkage) -> Result<FuncId, CompileError> {
+        let mut sig = self.module.make_signature();
+        sig.params.extend((0..arity).map(|_| AbiParam::new(types::F64)));
+        sig.returns.push(AbiParam::new(returns));
+        self.module.declare_function(symbol, linkage, &sig).map_err(CompileError::internal)
     }
 
-    fn lex_identifier_or_keyword(&mut self) -> Token {
-        let mut id = String::new();
-        while self.position < self.input.len() && (self.current_char().is_alphanumeric() || self.current_char() == '_') {
-            id.push(self.current_char());
-            self.advance();
+    /// Compiles one function. Every IR local becomes a Cranelift variable and every block that can
+    /// run becomes a Cranelift block; Cranelift's SSA builder does the rest.
+    fn generate_function(&mut self, func_id: FuncId, function: &ir::Function) -> Result<(), CompileError> {
+        let sig = self.module.declarations().get_function_decl(func_id).signature.clone();
+        let mut func = cranelift_codegen::ir::Function::with_name_signature(UserFuncName::user(0, func_id.as_u32()), sig);
+        let mut builder_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut func, &mut builder_ctx);
+        let variables: Vec<Variable> = function.locals.iter().map(|local| builder.declare_var(self.value_type(local.ty))).collect();
+        let blocks: Vec<Option<Block>> = function
+            .reachable_blocks()
+            .into_iter()
+            .map(|reachable| reachable.then(|| builder.create_block()))
+            .collect();
+        let block = |id: ir::BlockId| blocks[id.0 as usize].ok_or_else(|| CompileError::internal(format!("jump to unreachable block b{}", id.0)));
+
+        // The IR's entry block is never a jump target, so it can take the function's parameters
+        let entry_block = block(ir::BlockId(0))?;
+        builder.append_block_params_for_function_params(entry_block);
+        builder.switch_to_block(entry_block);
+        if self.debug.is_some() {
+            builder.set_srcloc(SourceLoc::new(function.span.start as u32));
         }
-        if ["int", "return", "if", "else", "while", "for"].contains(&id.as_str()) {
-            Token::Keyword(id)
-        } else {
-            Token::Identifier(id)
+        let args = builder.block_params(entry_block).to_vec();
+        for (var, arg) in variables.iter().zip(args) {
+            builder.def_var(*var, arg);
+        }
+        if self.profile {
+            if function.is_main {
+                self.call_import("vira_profile_start", &[], None, &mut builder)?;
+            } else {
+                let name = self.string(function.name.as_str(), &mut builder)?;
+                self.call_import("vira_profile_enter", &[name], None, &mut builder)?;
+            }
+        }
+
+        for (index, ir_block) in function.blocks.iter().enumerate() {
+            let Some(current) = blocks[index] else {
+                continue;
+            };
+            if index > 0 {
+                builder.switch_to_block(current);
+            }
+            for inst in &ir_block.insts {
+                if self.debug.is_some() && inst.span != Span::default() {
+                    // Source locations are byte offsets into the file the function comes from
+                    builder.set_srcloc(SourceLoc::new(inst.span.start as u32));
+                }
+                self.generate_inst(function, inst, &variables, &mut builder)?;
+            }
+            if self.debug.is_some() && ir_block.terminator_span != Span::default() {
+                builder.set_srcloc(SourceLoc::new(ir_block.terminator_span.start as u32));
+            }
+            match &ir_block.terminator {
+                ir::Terminator::Jump(target) => {
+                    builder.ins().jump(block(*target)?, &[]);
+                }
+                ir::Terminator::Branch { condition, then, otherwise } => {
+                    let condition = builder.use_var(variables[condition.0 as usize]);
+                    let condition = Self::is_truthy(condition, &mut builder);
+                    builder.ins().brif(condition, block(*then)?, &[], block(*otherwise)?, &[]);
+                }
+                ir::Terminator::Return(value) => {
+                    let value = builder.use_var(variables[value.0 as usize]);
+                    self.return_number(value, function.is_main, &mut builder)?;
+                }
+            }
         }
+        // Every jump is in place, so every block has all its predecessors
+        builder.seal_all_blocks();
+        builder.finalize();
+        let symbol = self.module.declarations().get_function_decl(func_id).linkage_name(func_id).into_owned();
+        let mut ctx = Context::for_function(func);
+        if self.emit == Emit::Clif {
+            // Printed before Cranelift optimizes it, as the code generator produced it
+            self.listing.push_str(&format!("; {}\n{}\n", symbol, ctx.func.display()));
+        }
+        ctx.set_disasm(self.emit == Emit::Asm);
+        self.module.define_function(func_id, &mut ctx).map_err(CompileError::internal)?;
+        if self.emit == Emit::Asm {
+            let code = ctx.compiled_code().and_then(|code| code.vcode.as_deref()).unwrap_or_default();
+            self.listing.push_str(&format!("{}:\n{}\n", symbol, code));
+        }
+        if let (Some(debug), Some(code)) = (&mut self.debug, ctx.compiled_code()) {
+            let mut rows: Vec<(u64, usize)> = code
+                .buffer
+                .get_srclocs_sorted()
+                .iter()
+                .filter(|srcloc| !srcloc.loc.is_default())
+                .map(|srcloc| (u64::from(srcloc.start), srcloc.loc.bits() as usize))
+                .collect();
+            rows.dedup_by_key(|&mut (_, source)| source);
+            debug.add(debug::FunctionLines {
+                func_id,
+                name: function.name.to_string(),
+                symbol,
+                file: self.sources[function.file].0.clone(),
+                start: function.span.start,
+                size: code.code_buffer().len() as u64,
+                rows,
+            });
+        }
+        Ok(())
     }
 
-    fn lex_number(&mut self) -> Token {
-        let mut num = 0i64;
-        while self.position < self.input.len() && self.current_char().is_digit(10) {
-            num = num * 10 + self.current_char().to_digit(10).unwrap() as i64;
-            self.advance();
+    fn generate_inst(&mut self, function: &ir::Function, inst: &ir::Inst, variables: &[Variable], builder: &mut FunctionBuilder) -> Result<(), CompileError> {
+        let local = |builder: &mut FunctionBuilder, id: &ir::LocalId| builder.use_var(variables[id.0 as usize]);
+        let value = match &inst.op {
+            ir::Op::Number(n) => builder.ins().f64const(*n),
+            ir::Op::String(text) => self.string(text, builder)?,
+            ir::Op::Copy(source) => local(builder, source),
+            ir::Op::Unary(op, operand) => {
+                let val = local(builder, operand);
+                match op {
+                    UnOp::Neg => builder.ins().fneg(val),
+                    UnOp::Not => {
+                        let zero = builder.ins().f64const(0.0);
+                        let is_zero = builder.ins().fcmp(FloatCC::Equal, val, zero);
+                        Self::from_flag(is_zero, builder)
+                    }
+                }
+            }
+            ir::Op::Binary(op, left, right) => {
+                let lhs = local(builder, left);
+                let rhs = local(builder, right);
+                self.generate_binary(*op, lhs, rhs, builder)?
+            }
+            ir::Op::Concat(left, right) => {
+                let lhs = local(builder, left);
+                let rhs = local(builder, right);
+                let pointer_type = self.module.target_config().pointer_type();
+                let joined = self.call_import("vira_concat", &[lhs, rhs], Some(pointer_type), builder)?;
+                joined.ok_or_else(|| CompileError::internal("vira_concat returned nothing"))?
+            }
+            ir::Op::Append(left, right) => {
+                let lhs = local(builder, left);
+                let rhs = local(builder, right);
+                let pointer_type = self.module.target_config().pointer_type();
+                let joined = self.call_import("vira_append", &[lhs, rhs], Some(pointer_type), builder)?;
+                joined.ok_or_else(|| CompileError::internal("vira_append returned nothing"))?
+            }
+            ir::Op::Call(name, args) => {
+                let Some(&func_id) = self.functions.get(name) else {
+                    return Err(CompileError::internal(format!("no function for '{}'", name)).with_span(inst.span));
+                };
+                let func_ref = self.module.declare_func_in_func(func_id, builder.func);
+                let args: Vec<Value> = args.iter().map(|arg| local(builder, arg)).collect();
+                // Debug builds keep the call stack, which failures print
+                if self.debug.is_some() {
+                    let callee = self.string(name.as_str(), builder)?;
+                    let location = self.location(function.file, inst.span);
+                    let location = self.string(&location, builder)?;
+                    self.call_import("vira_frame_push", &[callee, location], None, builder)?;
+                }
+                let call = builder.ins().call(func_ref, &args);
+                let result = builder.inst_results(call)[0];
+                if self.debug.is_some() {
+                    self.call_import("vira_frame_pop", &[], None, builder)?;
+                }
+                result
+            }
+            ir::Op::Builtin { builtin, args, at } => {
+                let mut args: Vec<Value> = args.iter().map(|arg| local(builder, arg)).collect();
+                if builtin.can_fail() {
+                    let location = self.location(function.file, *at);
+                    args.push(self.string(&location, builder)?);
+                }
+                let returns = self.value_type(builtin.returns());
+                let result = self.call_import(builtin.symbol(), &args, Some(returns), builder)?;
+                result.ok_or_else(|| CompileError::internal(format!("{} returned nothing", builtin.symbol())))?
+            }
+            ir::Op::Write(value) => {
+                // Each `write` prints its value on a line of its own
+                let print = match function.local(*value).ty {
+                    ir::Type::Str => "vira_print_str",
+                    ir::Type::Number => "vira_print_num",
+                };
+                let val = local(builder, value);
+                self.call_import(print, &[val], None, builder)?;
+                return Ok(());
+            }
+            ir::Op::AssertFailed { message, values, at } => {
+                // The runtime reports the message with the call's location, then the values noted
+                // before, and exits with status 1
+                for value in values {
+                    let note = match function.local(*value).ty {
+                        ir::Type::Str => "vira_assert_value_str",
+                        ir::Type::Number => "vira_assert_value_num",
+                    };
+                    let val = local(builder, value);
+                    self.call_import(note, &[val], None, builder)?;
+                }
+                let message = match message {
+                    Some(message) => local(builder, message),
+                    // Without a message of its own, the report shows the call as written
+                    None => {
+                        let call = self.sources[function.file].1.get(at.start..at.end).unwrap_or("assert").to_string();
+                        self.string(&call, builder)?
+                    }
+                };
+                let location = self.location(function.file, *at);
+                let location = self.string(&location, builder)?;
+                self.call_import("vira_assert_failed", &[message, location], None, builder)?;
+                return Ok(());
+            }
+        };
+        if let Some(dest) = inst.dest {
+            builder.def_var(variables[dest.0 as usize], value);
         }
-        Token::Number(num)
+        Ok(())
+    }
+
+    fn generate_binary(&mut self, op: BinOp, lhs: Value, rhs: Value, builder: &mut FunctionBuilder) -> Result<Value, CompileError> {
+        let cc = match op {
+            BinOp::Add => return Ok(builder.ins().fadd(lhs, rhs)),
+            BinOp::Sub => return Ok(builder.ins().fsub(lhs, rhs)),
+            BinOp::Mul => return Ok(builder.ins().fmul(lhs, rhs)),
+            BinOp::Div => return Ok(builder.ins().fdiv(lhs, rhs)),
+            // Cranelift has no float remainder; libm's fmod truncates like `%` on f64 in Rust
+            BinOp::Mod => {
+                let remainder = self.call_import("fmod", &[lhs, rhs], Some(types::F64), builder)?;
+                return remainder.ok_or_else(|| CompileError::internal("fmod returned nothing"));
+            }
+            BinOp::Eq => FloatCC::Equal,
+            BinOp::Ne => FloatCC::NotEqual,
+            BinOp::Lt => FloatCC::LessThan,
+            BinOp::Le => FloatCC::LessThanOrEqual,
+            BinOp::Gt => FloatCC::GreaterThan,
+            BinOp::Ge => FloatCC::GreaterThanOrEqual,
+            BinOp::And | BinOp::Or => return Err(CompileError::internal("&& and || should have been lowered to branches")),
+        };
+        let flag = builder.ins().fcmp(cc, lhs, rhs);
+        Ok(Self::from_flag(flag, builder))
     }
 
-    fn lex_string(&mut self) -> Token {
-        self.advance(); // skip opening "
-        let mut s = String::new();
-        while self.position < self.input.len() && self.current_char() != '"' {
-            s.push(self.current_char());
-            self.advance();
+    /// Returns a number, converting it to the exit code when the function is `main`.
+    fn return_number(&mut self, val: Value, is_main: bool, builder: &mut FunctionBuilder) -> Result<(), CompileError> {
+        let val = if is_main {
+            builder.ins().fcvt_to_sint_sat(types::I32, val)
+        } else {
+            // The profiler closes `main` itself when the program exits, however that happens
+            if self.profile {
+                self.call_import("vira_profile_exit", &[], None, builder)?;
+            }
+            val
+        };
+        builder.ins().return_(&[val]);
+        Ok(())
+    }
+
+    /// Integer flag that is set when a number is truthy, i.e. not zero.
+    fn is_truthy(val: Value, builder: &mut FunctionBuilder) -> Value {
+        let zero = builder.ins().f64const(0.0);
+        builder.ins().fcmp(FloatCC::NotEqual, val, zero)
+    }
+
+    /// The number 1 for a set flag and 0 otherwise.
+    fn from_flag(flag: Value, builder: &mut FunctionBuilder) -> Value {
+        let flag = builder.ins().uextend(types::I32, flag);
+        builder.ins().fcvt_from_uint(types::F64, flag)
+    }
+
+    /// Strings are pointers to NUL-terminated read-only data.
+    fn value_type(&self, ty: ir::Type) -> Type {
+        match ty {
+            ir::Type::Number => types::F64,
+            ir::Type::Str => self.module.target_config().pointer_type(),
         }
-        self.advance(); // skip closing "
-        Token::StringLiteral(s)
+    }
+
+    /// `file:line:column` of `span` in the file with index `file`, for the errors the runtime reports.
+    fn location(&self, file: usize, span: Span) -> String {
+        let (name, src) = &self.sources[file];
+        let (line, column) = SourceMap::new(src).line_col(span.start);
+        format!("{}:{}:{}", name, line, column)
+    }
+
+    /// Address of a NUL-terminated copy of `text` in read-only data.
+    fn string(&mut self, text: &str, builder: &mut FunctionBuilder) -> Result<Value, CompileError> {
+        let data_id = match self.strings.get(text) {
+            Some(id) => *id,
+            None => {
+                let id = self.module.declare_anonymous_data(false, false).map_err(CompileError::internal)?;
+                let mut desc = DataDescription::new();
+                let mut bytes = text.as_bytes().to_vec();
+                bytes.push(0);
+                desc.define(bytes.into_boxed_slice());
+                self.module.define_data(id, &desc).map_err(CompileError::internal)?;
+                self.strings.insert(text.to_string(), id);
+                id
+            }
+        };
+        let global = self.module.declare_data_in_func(data_id, builder.func);
+        let pointer_type = self.module.target_config().pointer_type();
+        Ok(builder.ins().symbol_value(pointer_type, global))
+    }
+
+    /// Calls a function of the Vira runtime or the C library, declaring it as an import on first use.
+    /// Returns the result, if the function has one.
+    fn call_import(
+        &mut self,
+        name: &'static str,
+        args: &[Value],
+        returns: Option<Type>,
+        builder: &mut FunctionBuilder,
+    ) -> Result<Option<Value>, CompileError> {
+        let func_id = match self.imports.get(name) {
+            Some(id) => *id,
+            None => {
+                let mut sig = self.module.make_signature();
+                for arg in args {
+                    sig.params.push(AbiParam::new(builder.func.dfg.value_type(*arg)));
+                }
+                sig.returns.extend(returns.map(AbiParam::new));
+                let id = self.module.declare_function(name, Linkage::Import, &sig).map_err(CompileError::internal)?;
+                self.imports.insert(name, id);
+                id
+            }
+        };
+        let func_ref = self.module.declare_func_in_func(func_id, builder.func);
+        let call = builder.ins().call(func_ref, args);
+        Ok(builder.inst_results(call).first().copied())
     }
 }
 
-#[derive(Debug)]
-enum ASTNode {
-    Program(Vec<ASTNode>),
-    Function(String, Vec<ASTNode>),
-    Return(Box<ASTNode>),
-    BinaryOp(char, Box<ASTNode>, Box<ASTNode>),
-    Number(i64),
-    Identifier(String),
-    // Add more as needed for full C-like support
+#[derive(Parser, Debug)]
+#[command(version, about = "Vira Compiler")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
 }
 
-struct Parser {
-    lexer: Lexer,
-    current_token: Token,
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Compile a program to an executable, an object file or a listing
+    Compile(CompileArgs),
+    /// Parse and check a program without compiling it
+    Check(CheckArgs),
 }
 
-impl Parser {
-    fn new(input: String) -> Self {
-        let mut lexer = Lexer::new(input);
-        let current_token = lexer.next_token();
-        Parser { lexer, current_token }
-    }
+#[derive(clap::Args, Debug)]
+struct CheckArgs {
+    /// Source files of one program, as for compile
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Apply the fixes that are certain, such as inserting a missing `;`, to the files in place
+    #[arg(long)]
+    fix: bool,
+    /// How to print errors and warnings
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+    #[command(flatten)]
+    log: LogArgs,
+}
 
-    fn eat(&mut self, expected: Token) {
-        if self.current_token == expected {
-            self.current_token = self.lexer.next_token();
-        } else {
-            panic!("Expected {:?}, got {:?}", expected, self.current_token);
-        }
-    }
+#[derive(clap::Args, Debug)]
+struct CompileArgs {
+    /// Source files to compile into one program; the first is the entry point, and the others may
+    /// only define functions. `-` reads one of them from standard input
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Output file; defaults to a.out (a.exe on Windows) for executables and to the input name otherwise
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// What to produce
+    #[arg(long, value_enum, default_value_t = Emit::Exe)]
+    emit: Emit,
+    /// Keep the object file next to the executable instead of deleting it after linking
+    #[arg(long)]
+    keep_object: bool,
+    /// Compile each file to its own object and keep the objects in DIR, reusing them for files that
+    /// have not changed since an earlier build; only applies when linking an executable
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+    /// Include DWARF line tables so gdb and lldb can step through the Vira source, and print a
+    /// backtrace when an assertion, panic or runtime error stops the program
+    #[arg(short = 'g')]
+    debug_info: bool,
+    /// Time every function call; the program writes its profile in collapsed-stack format to the
+    /// file named by VIRA_PROFILE, or profile.folded, when it exits
+    #[arg(long)]
+    profile: bool,
+    /// Optimization level: 0 for none, 1 or 2 for speed, s for speed and size
+    #[arg(short = 'O', value_name = "LEVEL", default_value = "0", value_parser = ["0", "1", "2", "s"])]
+    opt_level: String,
+    /// Generate code for the program as written, without folding constants and removing dead code first
+    #[arg(long)]
+    no_opt: bool,
+    /// Reject programs that read or set environment variables, run commands, make HTTP requests or
+    /// choose their exit status, for running code that isn't trusted
+    #[arg(long)]
+    sandbox: bool,
+    /// Linker to use instead of the first of cc, clang and gcc on PATH: a path, a program name, or lld
+    #[arg(long, value_name = "PATH")]
+    linker: Option<String>,
+    /// Vira runtime library to link instead of the one installed next to the compiler
+    #[arg(long, value_name = "PATH")]
+    runtime: Option<PathBuf>,
+    /// Target triple to compile for; defaults to the host
+    #[arg(long)]
+    target: Option<String>,
+    /// List functions left out because nothing calls them
+    #[arg(long)]
+    print_removed: bool,
+    /// Silence a lint, such as unused-function
+    #[arg(long, value_name = "LINT")]
+    allow: Vec<String>,
+    /// Turn a lint into an error
+    #[arg(long, value_name = "LINT")]
+    deny: Vec<String>,
+    /// How to print errors and warnings
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+    // -v also notes what the optimizer removed
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// A linked executable
+    Exe,
+    /// An object file, to be linked with the Vira runtime library
+    Obj,
+    /// The generated machine code of each function
+    Asm,
+    /// The Cranelift IR of each function, before optimization
+    Clif,
+    /// The Vira IR of the whole program, after the optimizations that --no-opt turns off
+    Ir,
+}
 
-    fn parse(&mut self) -> ASTNode {
-        let mut functions = Vec::new();
-        while self.current_token != Token::EOF {
-            functions.push(self.parse_function());
+fn main() {
+    let command = Cli::parse().command;
+    let (inputs, error_format, log_args) = match &command {
+        Commands::Compile(args) => (&args.inputs, args.error_format, &args.log),
+        Commands::Check(args) => (&args.inputs, args.error_format, &args.log),
+    };
+    let timings = log::init(log_args);
+    let mut files = Vec::new();
+    let mut diagnostics = Vec::new();
+    let read = info_span!("read").entered();
+    if inputs.iter().filter(|path| input::is_stdin(path)).count() > 1 {
+        diagnostics.push(CompileError::usage("standard input (`-`) can only be one of the inputs"));
+    }
+    for path in inputs {
+        let name = input::name(path);
+        match input::read(path) {
+            Ok(src) => files.push((name, src)),
+            Err(err) => diagnostics.push(CompileError::usage(format!("could not read {}: {}", name, err)).io()),
         }
-        ASTNode::Program(functions)
-    }
-
-    fn parse_function(&mut self) -> ASTNode {
-        self.eat(Token::Keyword("int".to_string()));
-        if let Token::Identifier(name) = self.current_token.clone() {
-            self.eat(Token::Identifier(name.clone()));
-            self.eat(Token::Punctuator('('));
-            self.eat(Token::Punctuator(')'));
-            self.eat(Token::Punctuator('{'));
-            let mut statements = Vec::new();
-            while self.current_token != Token::Punctuator('}') {
-                statements.push(self.parse_statement());
-            }
-            self.eat(Token::Punctuator('}'));
-            ASTNode::Function(name, statements)
-        } else {
-            panic!("Expected identifier");
+    }
+    drop(read);
+    if diagnostics.is_empty() {
+        let result = match &command {
+            Commands::Compile(args) => run(args, &files, &mut diagnostics),
+            Commands::Check(args) => check(args, &files, &mut diagnostics),
+        };
+        if let Err(err) = result {
+            diagnostics.push(err);
         }
     }
+    for diag in &diagnostics {
+        error::report(diag, &files, error_format);
+    }
+    // The most serious failure decides: a compiler bug, then the environment, then the program
+    let status = diagnostics.iter().filter(|diag| diag.severity == Severity::Error).map(|diag| diag.status).max();
+    if let Some(status) = status {
+        // Exiting skips destructors
+        drop(timings);
+        process::exit(status);
+    }
+}
 
-    fn parse_statement(&mut self) -> ASTNode {
-        if self.current_token == Token::Keyword("return".to_string()) {
-            self.eat(Token::Keyword("return".to_string()));
-            let expr = self.parse_expr();
-            self.eat(Token::Punctuator(';'));
-            ASTNode::Return(Box::new(expr))
-        } else {
-            panic!("Unsupported statement");
+/// Parses `files`, given as name and source, reporting every syntax error in each. Only the files
+/// without errors are returned.
+fn parse_files(files: &[(String, String)], diagnostics: &mut Vec<CompileError>) -> Vec<(String, Program)> {
+    let _parse = info_span!("parse").entered();
+    let parsed: Vec<_> = files.par_iter().map(|(name, src)| (name, vira_core::parse_all(src))).collect();
+    let mut programs = Vec::new();
+    for (name, (program, _, errors)) in parsed {
+        if errors.is_empty() {
+            programs.push((name.clone(), program));
         }
+        diagnostics.extend(errors.into_iter().map(|err| CompileError::from(err).in_file(name)));
     }
+    programs
+}
 
-    fn parse_expr(&mut self) -> ASTNode {
-        let mut node = self.parse_primary();
-        while let Token::Punctuator(op) = self.current_token {
-            if op == '+' || op == '-' || op == '*' || op == '/' {
-                self.eat(Token::Punctuator(op));
-                let right = self.parse_primary();
-                node = ASTNode::BinaryOp(op, Box::new(node), Box::new(right));
-            } else {
-                break;
-            }
+/// Parses and checks `files` as `compile` would, without lowering or generating code. With
+/// `--fix`, each file that has fixes is rewritten with them applied.
+fn check(args: &CheckArgs, files: &[(String, String)], diagnostics: &mut Vec<CompileError>) -> Result<(), CompileError> {
+    let programs = parse_files(files, diagnostics);
+    if !has_errors(diagnostics) {
+        let _check = info_span!("check").entered();
+        check_files(&programs, files, diagnostics);
+    }
+    if !args.fix {
+        return Ok(());
+    }
+    let _fix = info_span!("fix").entered();
+    let mut notes = Vec::new();
+    for (path, (name, src)) in args.inputs.iter().zip(files) {
+        let suggestions: Vec<_> = diagnostics
+            .iter()
+            .filter(|diag| diag.file.as_ref() == Some(name))
+            .flat_map(|diag| diag.to_diagnostic(files).suggestions)
+            .collect();
+        if suggestions.is_empty() {
+            continue;
+        }
+        // Nothing to write back to
+        if input::is_stdin(path) {
+            notes.push(CompileError::note(format!("can't fix {} in place", name)));
+            continue;
         }
-        node
+        let (fixed, applied) = fix::apply_suggestions(src, &suggestions);
+        fs::write(path, fixed).map_err(|err| CompileError::usage(format!("could not write {}: {}", name, err)).io())?;
+        notes.push(CompileError::note(format!("fixed {} issue(s) in {}", applied, name)));
     }
+    diagnostics.extend(notes);
+    Ok(())
+}
 
-    fn parse_primary(&mut self) -> ASTNode {
-        match self.current_token.clone() {
-            Token::Number(n) => {
-                self.eat(Token::Number(n));
-                ASTNode::Number(n)
-            }
-            Token::Identifier(id) => {
-                self.eat(Token::Identifier(id.clone()));
-                ASTNode::Identifier(id)
+/// Whether compilation has failed so far; warnings alone don't stop it.
+fn has_errors(diagnostics: &[CompileError]) -> bool {
+    diagnostics.iter().any(|diag| diag.severity == Severity::Error)
+}
+
+/// Compiles `files`, given as name and source, as `args` asks. Warnings and errors that don't stop
+/// the current stage are collected in `diagnostics`; the returned error is the one that stopped compilation.
+fn run(args: &CompileArgs, files: &[(String, String)], diagnostics: &mut Vec<CompileError>) -> Result<(), CompileError> {
+    let mut lints = lint::LintConfig::default();
+    for (names, deny) in [(&args.allow, false), (&args.deny, true)] {
+        for name in names {
+            let Some(found) = lint::Lint::from_name(name) else {
+                return Err(CompileError::usage(format!("unknown lint '{}'", name)));
+            };
+            if deny {
+                lints.deny(found);
+            } else {
+                lints.allow(found);
             }
-            _ => panic!("Unexpected token in primary: {:?}", self.current_token),
         }
     }
-}
+    let target = match &args.target {
+        Some(name) => Triple::from_str(name).map_err(|err| CompileError::usage(format!("invalid target '{}': {}", name, err)))?,
+        None => Triple::host(),
+    };
+    // The system linker only produces host executables
+    if args.emit == Emit::Exe && target != Triple::host() {
+        return Err(CompileError::usage(format!("cannot link an executable for {}", target))
+            .with_help("use --emit obj and link with a toolchain for that target"));
+    }
+    let windows = target.operating_system == OperatingSystem::Windows;
+    let object_extension = if windows { "obj" } else { "o" };
+    // Listings of standard input default to stdin.s and the like
+    let entry = if input::is_stdin(&args.inputs[0]) { Path::new("stdin") } else { &args.inputs[0] };
+    let output = args.output.clone().unwrap_or_else(|| match args.emit {
+        Emit::Exe => PathBuf::from(if windows { "a.exe" } else { "a.out" }),
+        Emit::Obj => entry.with_extension(object_extension),
+        Emit::Asm => entry.with_extension("s"),
+        Emit::Clif => entry.with_extension("clif"),
+        Emit::Ir => entry.with_extension("ir"),
+    });
 
-struct CodeGenerator {
-    module: ObjectModule,
-    variables: HashMap<String, Variable>,
-    var_index: usize,
-}
+    // Files are parsed, checked and linted each on its own thread; results are gathered in input
+    // order, so diagnostics come out the same however the work was split
+    let programs = parse_files(files, diagnostics);
+    if has_errors(diagnostics) {
+        return Ok(());
+    }
+    let check = info_span!("check").entered();
+    check_files(&programs, files, diagnostics);
+    let resolutions: Vec<vira_core::Resolution> = programs.par_iter().map(|(_, program)| vira_core::resolve(program)).collect();
+    drop(check);
+    if has_errors(diagnostics) {
+        return Ok(());
+    }
 
-impl CodeGenerator {
-    fn new() -> Self {
-        let mut flag_builder = settings::builder();
-        flag_builder.set("use_colocated_libcalls", "false").unwrap();
-        flag_builder.set("is_pic", "false").unwrap();
-        let isa_builder = isa::lookup(Triple::host()).unwrap();
-        let isa = isa_builder.finish(settings::Flags::new(flag_builder)).unwrap();
-        let builder = ObjectBuilder::new(isa, "vira_module".to_owned(), cranelift_module::default_libcall_names()).unwrap();
-        let module = ObjectModule::new(builder);
-        CodeGenerator {
-            module,
-            variables: HashMap::new(),
-            var_index: 0,
+    let lower = info_span!("lower").entered();
+    let mut module = ir::lower(programs.iter().map(|(_, program)| program).zip(&resolutions)).map_err(|err| {
+        let file = &programs[err.file].0;
+        if err.internal {
+            CompileError::internal(err.what).with_span(err.span).in_file(file)
+        } else {
+            CompileError::unsupported(&err.what, err.span).in_file(file)
+        }
+    })?;
+    debug!(functions = module.functions.len(), "lowered to IR");
+    drop(lower);
+    let linting = info_span!("lint").entered();
+    // What the IR calls, before optimizing drops calls that never run: a function called only
+    // from such a branch is still used as far as the source goes
+    let reachable = ir::called_functions(&module);
+    let enums = enums(&programs);
+    let warnings: Vec<Vec<lint::Warning>> = programs
+        .par_iter()
+        .zip(&resolutions)
+        .map(|((_, program), resolution)| lint::check(program, resolution, &reachable, &enums))
+        .collect();
+    for ((file, _), warnings) in programs.iter().zip(warnings) {
+        for warning in warnings {
+            let name = warning.lint.name();
+            let diag = CompileError::new(warning.lint.code(), warning.message).with_span(warning.span).in_file(file);
+            match lints.level(warning.lint) {
+                lint::Level::Allow => {}
+                lint::Level::Warn => diagnostics.push(diag.warning().with_note(format!("silence this with `--allow {}`", name))),
+                lint::Level::Deny => diagnostics.push(diag.with_note(format!("`--deny {}` turns this lint into an error", name))),
+            }
         }
     }
+    drop(linting);
+    if has_errors(diagnostics) {
+        return Ok(());
+    }
 
-    fn generate(mut self, ast: &ASTNode) -> Vec<u8> {
-        match ast {
-            ASTNode::Program(functions) => {
-                for func in functions {
-                    self.generate_function(func);
+    if args.sandbox {
+        // Before optimizing, so a call is rejected even where it would never run
+        for function in &module.functions {
+            for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+                if let ir::Op::Builtin { builtin, at, .. } = &inst.op {
+                    if builtin.is_system() {
+                        let file = &programs[function.file].0;
+                        diagnostics.push(
+                            CompileError::new("V0202", format!("'{}' is not allowed in a sandboxed build", builtin)).with_span(*at).in_file(file),
+                        );
+                    }
                 }
             }
-            _ => panic!("Expected Program"),
         }
-        let product = self.module.finish();
-        product.object.write().unwrap()
-    }
-
-    fn generate_function(&mut self, func: &ASTNode) {
-        if let ASTNode::Function(name, statements) = func {
-            let mut sig = self.module.make_signature();
-            sig.returns.push(AbiParam::new(types::I32)); // int return
-            let func_id = self.module.declare_function(name, Linkage::Export, &sig).unwrap();
-            let mut func = cranelift_codegen::ir::Function::with_name_signature(
-                UserFuncName::user(0, func_id.as_u32()),
-                                                                                sig,
-            );
-            let mut builder_ctx = FunctionBuilderContext::new();
-            let mut builder = FunctionBuilder::new(&mut func, &mut builder_ctx);
-            let entry_block = builder.create_block();
-            builder.append_block_params_for_function_params(entry_block);
-            builder.switch_to_block(entry_block);
-            builder.seal_block(entry_block);
-            for stmt in statements {
-                self.generate_statement(stmt, &mut builder);
-            }
-            // Default return 0 if no return
-            let zero = builder.ins().iconst(types::I32, 0);
-            builder.ins().return_(&[zero]);
-            builder.finalize();
-            let mut ctx = Context::for_function(func);
-            self.module.define_function(func_id, &mut ctx).unwrap();
-        } else {
-            panic!("Expected Function");
+        if has_errors(diagnostics) {
+            return Ok(());
         }
     }
-
-    fn generate_statement(&mut self, stmt: &ASTNode, builder: &mut FunctionBuilder) {
-        match stmt {
-            ASTNode::Return(expr) => {
-                let val = self.generate_expr(expr, builder);
-                builder.ins().return_(&[val]);
+    if !args.no_opt {
+        let _optimize = info_span!("optimize").entered();
+        for removed in ir::optimize(&mut module) {
+            if args.log.verbose > 0 {
+                let file = &programs[removed.file].0;
+                diagnostics.push(CompileError::note(format!("removed {}", removed.what)).with_span(removed.span).in_file(file));
             }
-            _ => panic!("Unsupported statement"),
         }
     }
-
-    fn generate_expr(&mut self, expr: &ASTNode, builder: &mut FunctionBuilder) -> Value {
-        match expr {
-            ASTNode::Number(n) => builder.ins().iconst(types::I32, *n),
-            ASTNode::Identifier(id) => {
-                if let Some(var) = self.variables.get(id) {
-                    builder.use_var(*var)
-                } else {
-                    panic!("Undefined variable: {}", id);
+    let opt_level = match args.opt_level.as_str() {
+        "0" => "none",
+        "s" => "speed_and_size",
+        _ => "speed",
+    };
+    if args.print_removed {
+        // With the other diagnostics on stderr, so the list doesn't mix with output on stdout
+        let kept: HashSet<Symbol> = module.functions.iter().filter(|function| !function.is_main).map(|function| function.name).collect();
+        for (file, program) in &programs {
+            for stmt in &program.statements {
+                if let Stmt::FuncDef { name, name_span, .. } = stmt {
+                    if !kept.contains(name) {
+                        diagnostics.push(
+                            CompileError::note(format!("removed unreachable function: {}", name)).with_span(*name_span).in_file(file),
+                        );
+                    }
                 }
             }
-            ASTNode::BinaryOp(op, left, right) => {
-                let lhs = self.generate_expr(left, builder);
-                let rhs = self.generate_expr(right, builder);
-                match op {
-                    '+' => builder.ins().iadd(lhs, rhs),
-                    '-' => builder.ins().isub(lhs, rhs),
-                    '*' => builder.ins().imul(lhs, rhs),
-                    '/' => builder.ins().sdiv(lhs, rhs),
-                    _ => panic!("Unsupported op: {}", op),
-                }
+        }
+    }
+    let write = |path: &PathBuf, bytes: &[u8]| {
+        fs::write(path, bytes).map_err(|err| CompileError::usage(format!("could not write {}: {}", path.display(), err)).io())
+    };
+    if args.emit == Emit::Ir {
+        return write(&output, module.to_string().as_bytes());
+    }
+    let generator = |target: Triple| {
+        let generator = CodeGenerator::new(target, opt_level, args.emit, files)?;
+        let generator = if args.profile { generator.with_profiling() } else { generator };
+        Ok::<_, CompileError>(if args.debug_info { generator.with_debug_info() } else { generator })
+    };
+    let codegen = info_span!("codegen").entered();
+    let mut objects = Vec::new();
+    let mut requirements = link::Requirements::default();
+    // Set when the object is only needed for this one link
+    let mut temporary = None;
+    match &args.cache_dir {
+        Some(dir) if args.emit == Emit::Exe => {
+            let cache = cache::Cache::open(dir, object_extension)
+                .map_err(|err| CompileError::usage(format!("could not open cache directory {}: {}", dir.display(), err)).io())?;
+            // Which functions are kept and how many parameters they take decides what each
+            // object defines and imports, and calls to them pass arguments by the parameters'
+            // names and fill in their defaults, so all of that is part of every key. So are the
+            // enums, since a variant compiles to its place in its enum
+            let params: HashMap<Symbol, &[Param]> = programs
+                .iter()
+                .flat_map(|(_, program)| &program.statements)
+                .filter_map(|stmt| match stmt {
+                    Stmt::FuncDef { name, params, .. } => Some((*name, params.as_slice())),
+                    _ => None,
+                })
+                .collect();
+            let mut signatures: Vec<String> = module
+                .functions
+                .iter()
+                .filter(|function| !function.is_main)
+                .map(|function| {
+                    let params = params.get(&function.name).map_or(Vec::new(), |params| params.iter().map(describe_param).collect());
+                    format!("{}({})", function.name, params.join(","))
+                })
+                .collect();
+            signatures.extend(enums.iter().map(|(name, variants)| {
+                let variants: Vec<&str> = variants.iter().map(|variant| variant.name.as_str()).collect();
+                format!("enum {}{{{}}}", name, variants.join(","))
+            }));
+            signatures.sort();
+            let compiler = compiler_identity();
+            let target_name = target.to_string();
+            for (index, (name, src)) in files.iter().enumerate() {
+                let key = cache::Cache::key(&[
+                    compiler.as_bytes(),
+                    target_name.as_bytes(),
+                    opt_level.as_bytes(),
+                    &[u8::from(index == 0), u8::from(args.debug_info), u8::from(args.profile), u8::from(args.no_opt)],
+                    signatures.join(",").as_bytes(),
+                    // Debug info and assertion failures name the file
+                    name.as_bytes(),
+                    src.as_bytes(),
+                ]);
+                let (path, needs) = match cache.get(&key) {
+                    Some(hit) => {
+                        info!(file = %name, "reusing cached object");
+                        hit
+                    }
+                    None => {
+                        debug!(file = %name, "cache miss");
+                        let generated = generator(target.clone())?.generate(&module, Some(index))?;
+                        let path = cache
+                            .put(&key, &generated.object, &generated.requirements)
+                            .map_err(|err| CompileError::usage(format!("could not write to cache directory {}: {}", dir.display(), err)).io())?;
+                        (path, generated.requirements)
+                    }
+                };
+                requirements.merge(&needs);
+                objects.push(path);
             }
-            _ => panic!("Unsupported expr"),
+        }
+        _ => {
+            let generated = generator(target)?.generate(&module, None)?;
+            match args.emit {
+                Emit::Asm | Emit::Clif | Emit::Ir => return write(&output, generated.listing.as_bytes()),
+                Emit::Obj => return write(&output, &generated.object),
+                Emit::Exe => {}
+            }
+            let object_path = output.with_extension(object_extension);
+            write(&object_path, &generated.object)?;
+            if !args.keep_object {
+                temporary = Some(object_path.clone());
+            }
+            requirements = generated.requirements;
+            objects.push(object_path);
         }
     }
-}
+    drop(codegen);
 
-fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 3 {
-        println!("Usage: compiler <input.vira> <output.o>");
-        return Ok(());
+    let _link = info_span!("link").entered();
+    if requirements.runtime {
+        // After the objects, since a driver only takes what earlier inputs need from an archive
+        let runtime = args.runtime.clone().or_else(link::bundled_runtime).filter(|path| path.is_file());
+        let runtime = runtime.ok_or_else(|| {
+            let looked = args.runtime.clone().or_else(link::bundled_runtime).unwrap_or_else(|| PathBuf::from(link::RUNTIME));
+            CompileError::new("V0402", format!("the Vira runtime library was not found at {}", looked.display()))
+                .with_help(format!("build source/vira-rt and put {} next to the compiler, or pass --runtime <path>", link::RUNTIME))
+                .io()
+        })?;
+        objects.push(runtime);
     }
-    let input_path = &args[1];
-    let mut output_path = args[2].clone();
-    let input = fs::read_to_string(input_path)?;
-    let mut parser = Parser::new(input);
-    let ast = parser.parse();
-    let generator = CodeGenerator::new();
-    let obj_bytes = generator.generate(&ast);
-    let os = env::consts::OS;
-    if os == "windows" {
-        output_path = output_path.replace(".o", ".obj");
-    }
-    let mut file = File::create(&output_path)?;
-    file.write_all(&obj_bytes)?;
-    let output_exe = if os == "windows" { "a.exe" } else { "a.out" };
-    let mut cmd = if os == "linux" {
-        Command::new("gcc")
-    } else if os == "macos" {
-        Command::new("clang")
-    } else if os == "windows" {
-        Command::new("link.exe")
-    } else {
-        panic!("Unsupported OS");
+    let linker = match &args.linker {
+        Some(name) => link::Linker::from_name(name).ok_or_else(|| CompileError::new("V0401", format!("linker '{}' not found", name)).io())?,
+        None => link::Linker::detect().ok_or_else(|| {
+            CompileError::new("V0401", "no linker found; looked for cc, clang and gcc on PATH")
+                .with_help("install a C toolchain or pass --linker <path>")
+                .io()
+        })?,
     };
-    if os == "linux" || os == "macos" {
-        cmd.arg(&output_path);
-        cmd.arg("-o");
-        cmd.arg(output_exe);
-    } else if os == "windows" {
-        cmd.arg(&output_path);
-        cmd.arg(format!("/out:{}", output_exe));
-        cmd.arg("/entry:main");
-        cmd.arg("/subsystem:console");
-    }
-    let status = cmd.status()?;
-    if !status.success() {
-        panic!("Linking failed");
+    let mut cmd = linker.command(&objects, &output, &requirements);
+    info!("linking: {:?}", cmd);
+    let result = cmd.output();
+    if let Some(path) = temporary {
+        // A leftover object file is harmless, so failing to delete it is not an error
+        let _ = fs::remove_file(path);
+    }
+    let result = result.map_err(|err| CompileError::new("V0401", format!("could not run linker '{}': {}", linker.program.display(), err)).io())?;
+    if !result.status.success() {
+        let mut err = CompileError::new("V0401", format!("linking with '{}' failed ({})", linker.program.display(), result.status)).io();
+        // MSVC's link.exe reports on stdout
+        for line in String::from_utf8_lossy(&result.stderr).lines().chain(String::from_utf8_lossy(&result.stdout).lines()) {
+            err = err.with_note(line);
+        }
+        return Err(err);
     }
     Ok(())
 }
+
+/// Identifies this build of the compiler for cache keys, so objects from an older compiler are never
+/// reused. The version alone isn't enough while developing, so the executable's modification time
+/// is part of it when available.
+fn compiler_identity() -> String {
+    let modified = env::current_exe()
+        .and_then(fs::metadata)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_nanos())
+        .unwrap_or_default();
+    format!("{} {}", env!("CARGO_PKG_VERSION"), modified)
+}
+
+/// The variants of every enum in the program, from its first definition.
+fn enums(programs: &[(String, Program)]) -> HashMap<Symbol, &[Variant]> {
+    let mut enums = HashMap::new();
+    for stmt in programs.iter().flat_map(|(_, program)| &program.statements) {
+        if let Stmt::Enum { name, variants, .. } = stmt {
+            enums.entry(*name).or_insert(variants.as_slice());
+        }
+    }
+    enums
+}
+
+/// Name resolution across files. Every file sees the functions and enums of all the others; a
+/// function or enum defined in two files is an error, as is top-level code outside the entry file.
+fn check_files(programs: &[(String, Program)], files: &[(String, String)], diagnostics: &mut Vec<CompileError>) {
+    // First definition of each function: file index, name span and parameters
+    let mut defined: HashMap<Symbol, (usize, Span, &[Param])> = HashMap::new();
+    // And of each enum, with its variants
+    let mut defined_enums: HashMap<Symbol, (usize, Span, &[Variant])> = HashMap::new();
+    for (index, (file, program)) in programs.iter().enumerate() {
+        for stmt in &program.statements {
+            match stmt {
+                Stmt::FuncDef { name, name_span, params, .. } => match defined.get(name) {
+                    // Duplicates within one file are reported by the checker
+                    Some(&(first, span, _)) if first != index => {
+                        let (line, column) = SourceMap::new(&files[first].1).line_col(span.start);
+                        diagnostics.push(
+                            CompileError::new("V0103", format!("Function '{}' is defined more than once", name))
+                                .in_file(file)
+                                .with_span(*name_span)
+                                .with_note(format!("first defined at {}:{}:{}", programs[first].0, line, column))
+                                .with_help("rename or remove one of the definitions"),
+                        );
+                    }
+                    Some(_) => {}
+                    None => {
+                        defined.insert(*name, (index, *name_span, params.as_slice()));
+                    }
+                },
+                Stmt::Enum { name, name_span, variants, .. } => match defined_enums.get(name) {
+                    Some(&(first, span, _)) if first != index => {
+                        let (line, column) = SourceMap::new(&files[first].1).line_col(span.start);
+                        diagnostics.push(
+                            CompileError::new("V0109", format!("Enum '{}' is defined more than once", name))
+                                .in_file(file)
+                                .with_span(*name_span)
+                                .with_note(format!("first defined at {}:{}:{}", programs[first].0, line, column))
+                                .with_help("rename or remove one of the definitions"),
+                        );
+                    }
+                    Some(_) => {}
+                    None => {
+                        defined_enums.insert(*name, (index, *name_span, variants.as_slice()));
+                    }
+                },
+                _ if index > 0 => diagnostics.push(
+                    CompileError::new("V0011", "Top-level statements are only allowed in the entry file")
+                        .in_file(file)
+                        .with_span(stmt.span())
+                        .with_help(format!("move this into a function, or into {}", programs[0].0)),
+                ),
+                _ => {}
+            }
+        }
+    }
+    let checked: Vec<Vec<CompileError>> = programs
+        .par_iter()
+        .map(|(file, program)| {
+            let local: HashSet<Symbol> = program
+                .statements
+                .iter()
+                .filter_map(|stmt| match stmt {
+                    Stmt::FuncDef { name, .. } => Some(*name),
+                    _ => None,
+                })
+                .collect();
+            let local_enums: HashSet<Symbol> = program
+                .statements
+                .iter()
+                .filter_map(|stmt| match stmt {
+                    Stmt::Enum { name, .. } => Some(*name),
+                    _ => None,
+                })
+                .collect();
+            let external: Vec<(Symbol, &[Param])> = defined
+                .iter()
+                .filter(|(name, _)| !local.contains(*name))
+                .map(|(name, (_, _, params))| (*name, *params))
+                .collect();
+            let external_enums: Vec<(Symbol, &[Variant])> = defined_enums
+                .iter()
+                .filter(|(name, _)| !local_enums.contains(*name))
+                .map(|(name, (_, _, variants))| (*name, *variants))
+                .collect();
+            vira_core::check_with(program, &external, &external_enums).into_iter().map(|err| CompileError::from(err).in_file(file)).collect()
+        })
+        .collect();
+    diagnostics.extend(checked.into_iter().flatten());
+}
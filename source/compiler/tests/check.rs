@@ -0,0 +1,35 @@
+mod common;
+
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn fix_inserts_missing_semicolons() {
+    let dir = common::scratch("check-fix");
+    let source = "def f(x) {\n    let a = x + 1\n    return a\n}\nwrite f(1)\n";
+    fs::write(dir.join("main.vira"), source).unwrap();
+    let check = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_compiler"))
+            .arg("check")
+            .args(args)
+            .arg("main.vira")
+            .current_dir(&dir)
+            .output()
+            .expect("the compiler should run")
+    };
+
+    // Without --fix the file is left alone
+    let output = check(&[]);
+    assert!(!output.status.success());
+    assert_eq!(fs::read_to_string(dir.join("main.vira")).unwrap(), source);
+
+    let output = check(&["--fix"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("fixed 3 issue(s) in main.vira"), "{}", stderr);
+    assert_eq!(
+        fs::read_to_string(dir.join("main.vira")).unwrap(),
+        "def f(x) {\n    let a = x + 1;\n    return a;\n}\nwrite f(1);\n"
+    );
+    let output = check(&[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}
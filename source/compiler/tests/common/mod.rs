@@ -0,0 +1,56 @@
+//! Compiles Vira programs with the compiler under test and runs what it builds.
+
+// Each test file uses only part of this module
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::OnceLock;
+
+/// The runtime archive, built from source/vira-rt once per test binary.
+fn runtime() -> &'static Path {
+    static RUNTIME: OnceLock<PathBuf> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../vira-rt");
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--quiet"])
+            .current_dir(&dir)
+            .status()
+            .expect("cargo should run");
+        assert!(status.success(), "building vira-rt failed");
+        let name = if cfg!(target_env = "msvc") { "vira_rt.lib" } else { "libvira_rt.a" };
+        dir.join("target/debug").join(name)
+    })
+}
+
+/// A directory of its own for the test called `name`, emptied first.
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vira-test-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs `compiler compile` on `source` with `args`, returning the compiler's output. The executable,
+/// if there is one, is `program` in the same directory as the source.
+pub fn compile(name: &str, source: &str, args: &[&str]) -> (Output, PathBuf) {
+    let dir = scratch(name);
+    fs::write(dir.join("main.vira"), source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_compiler"))
+        .args(["compile", "main.vira", "-o", "program", "--runtime"])
+        .arg(runtime())
+        .args(args)
+        .current_dir(&dir)
+        .output()
+        .expect("the compiler should run");
+    (output, dir.join("program"))
+}
+
+/// Compiles `source` with `args`, runs it and returns what it wrote to standard output.
+pub fn run(name: &str, source: &str, args: &[&str]) -> String {
+    let (output, program) = compile(name, source, args);
+    assert!(output.status.success(), "compiling failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    let output = Command::new(program).output().expect("the program should run");
+    String::from_utf8(output.stdout).unwrap()
+}
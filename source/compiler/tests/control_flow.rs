@@ -0,0 +1,146 @@
+mod common;
+
+use std::process::Command;
+
+/// Runs `source` both optimized and not, checking the two agree, and returns what it printed.
+fn output(name: &str, source: &str) -> String {
+    let optimized = common::run(&format!("{}-opt", name), source, &[]);
+    let unoptimized = common::run(&format!("{}-no-opt", name), source, &["--no-opt"]);
+    assert_eq!(optimized, unoptimized);
+    optimized
+}
+
+#[test]
+fn while_loops_run_until_the_condition_fails() {
+    let source = "\
+let i = 0;
+while i < 3 {
+    write i;
+    i = i + 1;
+}
+while 0 {
+    write \"never\";
+}
+write \"done\";
+";
+    assert_eq!(output("while", source), "0\n1\n2\ndone\n");
+}
+
+#[test]
+fn nested_loops_keep_their_own_counters() {
+    let source = "\
+let row = 1;
+while row <= 3 {
+    let line = \"\";
+    let column = 1;
+    while column <= row {
+        line = line + \"*\";
+        column = column + 1;
+    }
+    write line;
+    row = row + 1;
+}
+";
+    assert_eq!(output("nested", source), "*\n**\n***\n");
+}
+
+// Vira has no `break` or `continue`: a loop is left early by returning from the function around it,
+// and an iteration is skipped with `if`
+#[test]
+fn returning_leaves_a_loop_early() {
+    let source = "\
+def first_multiple(of, from) {
+    let n = from;
+    while 1 {
+        if n % of == 0 {
+            return n;
+        }
+        n = n + 1;
+    }
+}
+write first_multiple(7, 20);
+def sum_of_odd(to) {
+    let total = 0;
+    let n = 0;
+    while n < to {
+        n = n + 1;
+        if n % 2 == 0 {
+        } else {
+            total = total + n;
+        }
+    }
+    return total;
+}
+write sum_of_odd(10);
+";
+    assert_eq!(output("early-return", source), "21\n25\n");
+}
+
+#[test]
+fn if_else_chains_take_one_branch() {
+    let source = "\
+def sign(x) {
+    if x < 0 { write \"negative\"; } else if x == 0 { write \"zero\"; } else { write \"positive\"; }
+}
+sign(-5);
+sign(0);
+sign(3);
+";
+    assert_eq!(output("if-else", source), "negative\nzero\npositive\n");
+}
+
+#[test]
+fn and_and_or_skip_the_right_side() {
+    let source = "\
+def loud(x) {
+    write x;
+    return x;
+}
+if loud(0) && loud(1) { write \"both\"; }
+if loud(1) || loud(2) { write \"either\"; }
+";
+    assert_eq!(output("short-circuit", source), "0\n1\neither\n");
+}
+
+#[test]
+fn a_loop_decides_the_exit_status() {
+    let source = "\
+let steps = 0;
+let n = 6;
+while n != 1 {
+    if n % 2 == 0 { n = n / 2; } else { n = 3 * n + 1; }
+    steps = steps + 1;
+}
+exit(steps);
+";
+    let (output, program) = common::compile("exit-status", source, &[]);
+    assert!(output.status.success(), "compiling failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    let status = Command::new(program).status().expect("the program should run");
+    assert_eq!(status.code(), Some(8));
+}
+
+#[test]
+fn match_runs_the_arm_of_each_variant() {
+    let source = "\
+enum Color { Red, Green, Blue, Gray }
+def describe(c) {
+    match c {
+        Color.Red => { write \"red\"; }
+        Color.Green, Color.Blue => { write \"cool\"; }
+        _ => { write \"other\"; }
+    }
+    return c;
+}
+let i = 0;
+while i < 4 {
+    write describe(i);
+    i = i + 1;
+}
+write describe(Color.Blue) == Color.Blue;
+match Color.Gray {
+    Color.Red => { write \"no\"; }
+}
+write \"done\";
+";
+    assert_eq!(output("match", source), "red\n0\ncool\n1\ncool\n2\nother\n3\ncool\n1\ndone\n");
+}
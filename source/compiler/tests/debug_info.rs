@@ -0,0 +1,72 @@
+mod common;
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use object::{Object, ObjectSection};
+
+/// The lines of `file` that rows of the `.debug_line` table in `executable` point at. The runtime
+/// library's own rows, for its Rust sources, are left out.
+fn lines(executable: &Path, file: &str) -> BTreeSet<u64> {
+    let data = fs::read(executable).unwrap();
+    let object = object::File::parse(&*data).unwrap();
+    let endian = if object.is_little_endian() { gimli::RunTimeEndian::Little } else { gimli::RunTimeEndian::Big };
+    let sections = gimli::DwarfSections::load(|id| -> Result<Cow<[u8]>, gimli::Error> {
+        Ok(object
+            .section_by_name(id.name())
+            .and_then(|section| section.uncompressed_data().ok())
+            .unwrap_or(Cow::Borrowed(&[])))
+    })
+    .unwrap();
+    let dwarf = sections.borrow(|section| gimli::EndianSlice::new(section, endian));
+
+    let mut lines = BTreeSet::new();
+    let mut units = dwarf.units();
+    while let Some(header) = units.next().unwrap() {
+        let unit = dwarf.unit(header).unwrap();
+        let Some(program) = unit.line_program.clone() else {
+            continue;
+        };
+        let mut rows = program.rows();
+        while let Some((header, row)) = rows.next_row().unwrap() {
+            let Some(entry) = row.file(header) else {
+                continue;
+            };
+            let name = dwarf.attr_string(&unit, entry.path_name()).unwrap();
+            if name.to_string_lossy().ends_with(file) {
+                lines.extend(row.line().map(|line| line.get()));
+            }
+        }
+    }
+    lines
+}
+
+#[test]
+fn line_table_rows_point_at_the_statements() {
+    let source = "\
+def double(x) {
+    let y = x * 2;
+    return y;
+}
+
+let a = 1;
+write double(a);
+";
+    let (output, program) = common::compile("debug-line", source, &["-g"]);
+    assert!(output.status.success(), "compiling failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    let lines = lines(&program, "main.vira");
+    for statement in [2, 3, 6, 7] {
+        assert!(lines.contains(&statement), "no row for line {} in {:?}", statement, lines);
+    }
+    // Nothing points past the end of the file or at the blank line
+    assert!(lines.iter().all(|&line| (1..=7).contains(&line) && line != 5), "{:?}", lines);
+}
+
+#[test]
+fn no_line_table_without_g() {
+    let (output, program) = common::compile("no-debug-line", "write 1;\n", &[]);
+    assert!(output.status.success(), "compiling failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(lines(&program, "main.vira"), BTreeSet::new());
+}
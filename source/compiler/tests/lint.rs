@@ -0,0 +1,102 @@
+mod common;
+
+/// The warnings for `source`, one `line:column: warning[code]: message` each.
+fn warnings(name: &str, source: &str) -> Vec<String> {
+    let (output, _) = common::compile(name, source, &["--error-format", "short"]);
+    assert!(output.status.success(), "compiling failed:\n{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stderr)
+        .unwrap()
+        .lines()
+        .map(|line| line.strip_prefix("main.vira:").unwrap_or(line).to_string())
+        .collect()
+}
+
+#[test]
+fn warnings_point_at_what_they_are_about() {
+    let source = "\
+def unused(a) { return a; }
+def f(x) {
+    return x;
+    write 1;
+    write 2;
+}
+let total = 0;
+total = 5;
+let _kept = 1;
+if 1 < 2 {
+    write \"yes\";
+}
+while 0 {
+    write \"never\";
+}
+while 1 {
+    write f(3);
+    return 0;
+}
+";
+    assert_eq!(
+        warnings("lint-spans", source),
+        [
+            "1:5: warning[V0301]: function 'unused' is never called",
+            "4:5: warning[V0302]: 2 unreachable statement(s) after return in 'f'",
+            "7:5: warning[V0305]: variable 'total' is never read",
+            "10:4: warning[V0306]: condition is always true",
+            "13:7: warning[V0306]: loop condition is always false, so the body never runs",
+        ]
+    );
+}
+
+#[test]
+fn read_variables_and_changing_conditions_are_fine() {
+    let source = "def f(x) { let y = x * 2; if y > 3 { return y; } return 0; }\nlet n = f(2);\nwhile n > 0 { n = n - 1; }\n";
+    assert_eq!(warnings("lint-clean", source), Vec::<String>::new());
+}
+
+#[test]
+fn user_functions_replace_builtins_with_a_warning() {
+    let source = "def assert(condition) { write \"checked\"; return condition; }\nassert(0);\n";
+    assert_eq!(warnings("lint-builtin", source), ["1:5: warning[V0307]: function 'assert' replaces the builtin of the same name"]);
+    // The program's own function runs, so the false condition doesn't stop it
+    assert_eq!(common::run("lint-builtin-run", source, &["--allow", "shadowed-builtin"]), "checked\n");
+}
+
+#[test]
+fn shadowing_a_variable_warns_at_the_new_declaration() {
+    let source = "let x = 1;\nif x {\n    let x = 2;\n    write x;\n}\nwrite x;\n";
+    let found = warnings("lint-shadow", source);
+    assert_eq!(found.len(), 1, "{:?}", found);
+    assert!(found[0].starts_with("3:9: warning[V0303]: "), "{}", found[0]);
+    // Sibling blocks each have their own variable
+    let source = "def f(c) {\n    if c { let x = 1; write x; }\n    if c { let x = 2; write x; }\n    return 0;\n}\nf(1);\n";
+    assert_eq!(warnings("lint-no-shadow", source), Vec::<String>::new());
+}
+
+#[test]
+fn using_a_variable_before_its_declaration_is_an_error() {
+    let (output, _) = common::compile("use-before-let", "write total;\nlet total = 1;\n", &["--error-format", "short"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("main.vira:1:7: error[V0106]: Variable 'total' is used before its declaration"), "{}", stderr);
+}
+
+#[test]
+fn a_match_leaving_out_variants_warns() {
+    let source = "\
+enum Color { Red, Green, Blue }
+def name(c) {
+    match c {
+        Color.Green => { write \"green\"; }
+    }
+    match c {
+        Color.Red, Color.Green, Color.Blue => { write \"any\"; }
+    }
+    match c {
+        Color.Red => { write \"red\"; }
+        _ => { write \"other\"; }
+    }
+    return 0;
+}
+name(Color.Red);
+";
+    assert_eq!(warnings("lint-match", source), ["3:5: warning[V0304]: match on 'Color' doesn't handle Red, Blue"]);
+}
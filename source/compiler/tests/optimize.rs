@@ -0,0 +1,23 @@
+mod common;
+
+/// Optimizing must not change what a program prints.
+fn same_with_and_without_optimizing(name: &str, source: &str) {
+    let optimized = common::run(&format!("{}-opt", name), source, &[]);
+    let unoptimized = common::run(&format!("{}-no-opt", name), source, &["--no-opt"]);
+    assert_eq!(optimized, unoptimized);
+}
+
+#[test]
+fn adding_zero_keeps_the_sign_of_zero() {
+    let source = "def f(a) { write a + 0; write 0 + a; write a - -0; write a - 0; write a + -0; write a * 1; write a / 1; }\nf(-0);\n";
+    same_with_and_without_optimizing("signed-zero", source);
+    assert_eq!(common::run("signed-zero-output", source, &[]), "0\n0\n0\n-0\n-0\n-0\n-0\n");
+}
+
+#[test]
+fn folded_constants_match_run_time_results() {
+    same_with_and_without_optimizing(
+        "constants",
+        "write 2 + 3 * 4;\nwrite 7 % 3;\nwrite 1 / 0;\nwrite \"a\" + \"b\";\nwrite \"a\" == \"b\";\nif 1 < 2 { write \"yes\"; }\n",
+    );
+}
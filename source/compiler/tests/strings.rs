@@ -0,0 +1,85 @@
+//! Building strings with `s = s + x`, which the compiler turns into appends that may change the
+//! string in place wherever nothing else can see it.
+
+mod common;
+
+/// Runs `source` both optimized and not, checking the two agree, and returns what it printed.
+fn output(name: &str, source: &str) -> String {
+    let optimized = common::run(&format!("{}-opt", name), source, &[]);
+    let unoptimized = common::run(&format!("{}-no-opt", name), source, &["--no-opt"]);
+    assert_eq!(optimized, unoptimized);
+    optimized
+}
+
+#[test]
+fn appending_in_a_loop_grows_the_string() {
+    let source = "\
+let s = \"\";
+let i = 0;
+while i < 25 {
+    s = s + str(i);
+    i = i + 1;
+}
+write s;
+write len(s);
+";
+    assert_eq!(output("append-loop", source), "0123456789101112131415161718192021222324\n40\n");
+}
+
+#[test]
+fn appending_a_string_to_itself_doubles_it() {
+    let source = "\
+let s = \"ab\";
+let i = 0;
+while i < 6 {
+    s = s + s;
+    i = i + 1;
+}
+write len(s);
+write substr(s, 120, 8);
+";
+    assert_eq!(output("append-self", source), "128\nabababab\n");
+}
+
+#[test]
+fn a_copy_keeps_the_string_it_was_given() {
+    let source = "\
+let s = \"ab\";
+let t = s;
+s = s + \"c\";
+let snapshot = \"\";
+let i = 0;
+while i < 40 {
+    s = s + \"x\";
+    if i == 30 {
+        snapshot = s;
+    }
+    i = i + 1;
+}
+write t;
+write len(snapshot);
+write len(s);
+";
+    assert_eq!(output("append-copy", source), "ab\n34\n43\n");
+}
+
+#[test]
+fn many_strings_can_be_built_at_once() {
+    // More strings than the runtime keeps room after, appended to in turn
+    let names: Vec<String> = (0..20).map(|i| format!("s{}", i)).collect();
+    let mut source = String::new();
+    for name in &names {
+        source += &format!("let {} = \"{}:\";\n", name, name);
+    }
+    source += "let round = 0;\nwhile round < 3 {\n";
+    for name in &names {
+        source += &format!("    {} = {} + str(round);\n", name, name);
+    }
+    source += "    round = round + 1;\n}\n";
+    for name in &names {
+        source += &format!("write {};\n", name);
+    }
+
+    let expected: String = names.iter().map(|name| format!("{}:012\n", name)).collect();
+    assert_eq!(output("append-many", &source), expected);
+}
@@ -0,0 +1,89 @@
+use serde::Deserialize;
+
+use crate::report::{Applicability, Severity, ViraDiagnostic};
+use crate::span::{ColumnUnit, SourceMap};
+
+fn default_length() -> usize {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LabelInput {
+    pub line: usize,
+    pub column: usize,
+    #[serde(default = "default_length")]
+    pub length: usize,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+fn default_applicability() -> Applicability {
+    Applicability::MachineApplicable
+}
+
+/// Replacement text for `length` characters at a position; a zero length inserts.
+#[derive(Debug, Deserialize)]
+pub struct SuggestionInput {
+    pub line: usize,
+    pub column: usize,
+    #[serde(default)]
+    pub length: usize,
+    pub replacement: String,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default = "default_applicability")]
+    pub applicability: Applicability,
+}
+
+/// One line of batch input. Positions are 1-based and columns count characters.
+///
+/// `{"file": "main.vira", "message": "undefined variable", "line": 3, "column": 7}`
+#[derive(Debug, Deserialize)]
+pub struct DiagnosticInput {
+    pub file: String,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    #[serde(default = "default_length")]
+    pub length: usize,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub severity: Option<Severity>,
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<LabelInput>,
+    #[serde(default)]
+    pub help: Option<String>,
+    #[serde(default)]
+    pub notes: Vec<String>,
+    #[serde(default)]
+    pub suggestions: Vec<SuggestionInput>,
+}
+
+impl DiagnosticInput {
+    /// Resolves positions against `src`, the contents of `self.file`, reading columns as `unit`.
+    pub fn into_diagnostic(self, src: &str, unit: ColumnUnit) -> ViraDiagnostic {
+        let map = SourceMap::new(src).with_unit(unit);
+        let mut diag = ViraDiagnostic::new(self.severity.unwrap_or(Severity::Error), self.message)
+            .with_label(map.span(self.line, self.column, self.length), self.label.unwrap_or_else(|| "here".to_string()));
+        for label in self.labels {
+            diag = diag.with_secondary_label(map.span(label.line, label.column, label.length), label.text.unwrap_or_default());
+        }
+        if let Some(code) = self.code {
+            diag = diag.with_code(code);
+        }
+        if let Some(help) = self.help {
+            diag = diag.with_help(help);
+        }
+        for note in self.notes {
+            diag = diag.with_note(note);
+        }
+        for suggestion in self.suggestions {
+            let span = map.span(suggestion.line, suggestion.column, suggestion.length);
+            diag = diag.with_suggestion(span, suggestion.replacement, suggestion.message, suggestion.applicability);
+        }
+        diag.with_source(&self.file, src)
+    }
+}
@@ -0,0 +1,268 @@
+/// A stable diagnostic code with the long-form text shown by `--explain`.
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub title: &'static str,
+    pub description: &'static str,
+    pub example: &'static str,
+    pub fix: &'static str,
+}
+
+/// Every code the toolchain can emit. Codes are never reused once assigned:
+/// V00xx lexing and parsing, V01xx name resolution, V02xx code generation, V03xx lints, V04xx linking,
+/// V05xx preprocessing.
+pub const CODES: &[ErrorCode] = &[
+    ErrorCode {
+        code: "V0001",
+        title: "unexpected character",
+        description: "The lexer found a character that cannot start any token, such as `@` or `$` outside a string literal.",
+        example: "let x = 1 @ 2;",
+        fix: "Remove the character or replace it with a supported operator.",
+    },
+    ErrorCode {
+        code: "V0002",
+        title: "unterminated string",
+        description: "A string literal was opened with `\"`, `r\"` or `\"\"\"` but the file ended before the closing quote. A `\"\"\"` string only ends at the next `\"\"\"`, so a single `\"` inside it does not close it.",
+        example: "write \"hello;",
+        fix: "Add the closing `\"` at the end of the string.",
+    },
+    ErrorCode {
+        code: "V0003",
+        title: "number literal too large",
+        description: "A number literal is too large to be represented as a 64-bit float.",
+        example: "let x = 1000000000...000; // more than 308 digits",
+        fix: "Use a smaller value.",
+    },
+    ErrorCode {
+        code: "V0004",
+        title: "unknown escape sequence",
+        description: "A backslash in a string literal is followed by a character that does not form an escape.",
+        example: "write \"C:\\temp\";",
+        fix: "Use one of \\n, \\t, \\r, \\0, \\\" or \\\\, for example `\"C:\\\\temp\"`, or a raw string, `r\"C:\\temp\"`, which keeps backslashes as written.",
+    },
+    ErrorCode {
+        code: "V0010",
+        title: "unexpected token",
+        description: "The parser found a token that cannot come next. The message lists what could (for example `;` at the end of a statement), and a missing closing bracket also points at where the bracket opened. Parsing resumes at the next statement, so one run reports every syntax error in the file.",
+        example: "let x = 1\nwrite x;",
+        fix: "Insert the expected token, here the `;` ending the `let` statement.",
+    },
+    ErrorCode {
+        code: "V0011",
+        title: "unsupported statement",
+        description: "A statement appears where it is not allowed, such as a `def` inside a block. Functions and enums can only be defined at the top level, and when a program is built from several files, only the first one may contain top-level code; the others define functions and enums.",
+        example: "def outer() {\n    def inner() { return 1; }\n}",
+        fix: "Move the function definition to the top level, or move top-level code into the entry file.",
+    },
+    ErrorCode {
+        code: "V0101",
+        title: "undefined function",
+        description: "A call names a function that is not defined anywhere in the program.",
+        example: "def helper() { return 1; }\nwrite helpr();",
+        fix: "Correct the spelling or define the function.",
+    },
+    ErrorCode {
+        code: "V0102",
+        title: "undefined variable",
+        description: "An expression or assignment refers to a name that is not declared in any enclosing scope. Function bodies only see their parameters and their own `let` variables.",
+        example: "let counter = 0;\nwrite countr;",
+        fix: "Correct the spelling or declare the variable with `let` before using it.",
+    },
+    ErrorCode {
+        code: "V0103",
+        title: "duplicate function",
+        description: "Two functions in the same program have the same name, so calls to it would be ambiguous. This applies across files too: all files of a program share one function namespace.",
+        example: "def helper() { return 1; }\ndef helper() { return 2; }",
+        fix: "Rename or remove one of the definitions.",
+    },
+    ErrorCode {
+        code: "V0104",
+        title: "wrong number of arguments",
+        description: "A call passes more arguments than the function has parameters, or leaves out a parameter that has no default value. Builtins take a fixed count or, like `assert` with its optional message, a range.",
+        example: "def add(a, b) { return a + b; }\nwrite add(1);",
+        fix: "Pass one argument for every parameter without a default, or give the parameter a default: `def add(a, b = 0)`.",
+    },
+    ErrorCode {
+        code: "V0105",
+        title: "duplicate parameter",
+        description: "A function lists the same parameter name twice, so the body could only ever see one of the arguments.",
+        example: "def add(a, a) { return a + a; }",
+        fix: "Give every parameter its own name.",
+    },
+    ErrorCode {
+        code: "V0106",
+        title: "variable used before its declaration",
+        description: "A variable is read or assigned before the `let` that declares it. Variables are visible from their `let` to the end of the enclosing block, never earlier.",
+        example: "write total;\nlet total = 3;",
+        fix: "Move the `let` above the first use.",
+    },
+    ErrorCode {
+        code: "V0107",
+        title: "unknown named argument",
+        description: "A call passes an argument by a name that none of the function's parameters has. Builtins take arguments by position only.",
+        example: "def area(width, height) { return width * height; }\nwrite area(3, hieght = 4);",
+        fix: "Use the name of one of the function's parameters, or pass the argument by position.",
+    },
+    ErrorCode {
+        code: "V0108",
+        title: "argument given twice",
+        description: "A call passes two arguments for the same parameter, by position and by name, or by the same name twice.",
+        example: "def double(x) { return x * 2; }\nwrite double(3, x = 4);",
+        fix: "Remove one of the arguments.",
+    },
+    ErrorCode {
+        code: "V0109",
+        title: "duplicate enum or variant",
+        description: "Two enums have the same name, in one file or in different files of the same program, or one enum lists the same variant twice.",
+        example: "enum Color { Red, Green, Red }",
+        fix: "Rename or remove one of them.",
+    },
+    ErrorCode {
+        code: "V0110",
+        title: "undefined enum or variant",
+        description: "`Enum.Variant` names an enum that no file of the program defines, or a variant its enum doesn't list. The same goes for the patterns of a `match`.",
+        example: "enum Color { Red, Green }
+write Color.Blue;",
+        fix: "Check the spelling, or add the variant to the enum.",
+    },
+    ErrorCode {
+        code: "V0111",
+        title: "match mixes enums",
+        description: "The patterns of a `match` name variants of more than one enum. Variants are numbers at runtime, so `Color.Red` and `Size.Small` would both match 0; a match only handles one enum.",
+        example: "enum Color { Red }
+enum Size { Small }
+match 0 {
+    Color.Red => { write 1; }
+    Size.Small => { write 2; }
+}",
+        fix: "Match on variants of one enum only, and handle the other in a separate match.",
+    },
+    ErrorCode {
+        code: "V0112",
+        title: "string passed as a parameter",
+        description: "Function parameters hold numbers, but a parameter's default or an argument in a call is a string literal or strings joined with `+`.",
+        example: "def greet(name, greeting = \"hi\") { return 0; }\ngreet(name = \"bob\");",
+        fix: "Pass a number instead. Strings can't go into a function, so build and write text where it is used.",
+    },
+    ErrorCode {
+        code: "V0201",
+        title: "not supported by the native compiler",
+        description: "The program is valid Vira, but the native compiler cannot generate code for this construct yet.",
+        example: "write \"count: \" + 3;",
+        fix: "Rewrite the program without the construct, or check the release notes for when the compiler supports it.",
+    },
+    ErrorCode {
+        code: "V0202",
+        title: "not allowed in a sandboxed build",
+        description: "The program was compiled with `--sandbox`, which rejects the builtins that reach outside the program: `getenv`, `setenv`, `exec`, `exec_output`, `exit`, `http_get`, `http_post` and `http_status`. Calls are rejected wherever they are, even in a function that is never called.",
+        example: "write getenv(\"HOME\");",
+        fix: "Remove the call, or compile without `--sandbox` if the program is trusted.",
+    },
+    ErrorCode {
+        code: "V0301",
+        title: "unused function",
+        description: "A function is never called from the top-level program, directly or indirectly, so it is left out of the binary. This is a warning.",
+        example: "def unused() { return 1; }\nwrite 0;",
+        fix: "Call the function, delete it, or silence the lint with `--allow unused-function`.",
+    },
+    ErrorCode {
+        code: "V0302",
+        title: "unreachable code",
+        description: "Statements follow a `return` in the same block and can never run. This is a warning.",
+        example: "return 0;\nwrite 1;",
+        fix: "Delete the statements after the return, or silence the lint with `--allow unreachable-code`.",
+    },
+    ErrorCode {
+        code: "V0303",
+        title: "shadowed variable",
+        description: "A `let` declares a variable with the same name as one that is already visible, a parameter or a variable of an enclosing block or of the same block. Until the end of its block the new variable hides the old one, which is easy to miss when reading or assigning it. This is a warning.",
+        example: "let count = 0;\nwhile count < 3 {\n    let count = count + 1;\n}",
+        fix: "Rename one of the variables, assign to the existing one instead of declaring a new one, or silence the lint with `--allow shadowed-variable`.",
+    },
+    ErrorCode {
+        code: "V0304",
+        title: "non-exhaustive match",
+        description: "A `match` without a `_` arm leaves out some variants of its enum, so for those values none of its arms runs. This is a warning.",
+        example: "enum Color { Red, Green, Blue }
+match Color.Blue {
+    Color.Red => { write \"red\"; }
+    Color.Green => { write \"green\"; }
+}",
+        fix: "Add arms for the missing variants, or a `_ => { }` arm if doing nothing is intended, or silence the lint with `--allow non-exhaustive-match`.",
+    },
+    ErrorCode {
+        code: "V0305",
+        title: "unused variable",
+        description: "A `let` declares a variable that is never read. Assigning to it doesn't count, and names starting with `_` are left alone. This is a warning.",
+        example: "let total = 0;\ntotal = 5;",
+        fix: "Use the variable, delete the `let`, start its name with `_`, or silence the lint with `--allow unused-variable`.",
+    },
+    ErrorCode {
+        code: "V0306",
+        title: "constant condition",
+        description: "The condition of an `if` is made of number literals and operators alone, so the same branch runs every time, or that of a `while` is always false, so its body never runs. A `while` that is always true is how a loop that ends with `return` is written, and is left alone. This is a warning.",
+        example: "if 1 < 2 {\n    write \"always\";\n}",
+        fix: "Use a condition that can change, remove the branch that never runs, or silence the lint with `--allow constant-condition`.",
+    },
+    ErrorCode {
+        code: "V0307",
+        title: "shadowed builtin",
+        description: "A function has the name of a builtin such as `assert` or `len`. Every call by that name, in any file of the program, runs the program's own function instead of the builtin. This is a warning.",
+        example: "def panic(code) {\n    write code;\n    return code;\n}\npanic(3);",
+        fix: "Rename the function if the builtin was meant, or silence the lint with `--allow shadowed-builtin`.",
+    },
+    ErrorCode {
+        code: "V0401",
+        title: "linking failed",
+        description: "No linker was found, or the linker rejected the generated object file. The linker's own output is shown as notes.",
+        example: "write 1; // compiled on a machine without cc, clang or gcc",
+        fix: "Install a C toolchain (cc, clang, gcc, or link.exe on Windows) or point the compiler at one with `--linker <path>`.",
+    },
+    ErrorCode {
+        code: "V0402",
+        title: "runtime library not found",
+        description: "Compiled programs print and join strings through the Vira runtime library, libvira_rt.a (vira_rt.lib with MSVC), which the compiler looks for next to its own executable.",
+        example: "write \"hello\"; // with the compiler copied somewhere without the runtime",
+        fix: "Build source/vira-rt and place the library next to the compiler, or pass `--runtime <path>`.",
+    },
+    ErrorCode {
+        code: "V0501",
+        title: "invalid include",
+        description: "An `#include` directive is not followed by a `\"file\"` or `<file>` name.",
+        example: "#include utils.vira",
+        fix: "Quote the file name: `#include \"utils.vira\"`.",
+    },
+    ErrorCode {
+        code: "V0502",
+        title: "include not found",
+        description: "The file named by an `#include` could not be opened from the current directory or the include paths.",
+        example: "#include \"missing.vira\"",
+        fix: "Check the file name and that the file exists relative to where the preprocessor runs.",
+    },
+    ErrorCode {
+        code: "V0503",
+        title: "include depth exceeded",
+        description: "Includes are nested more than 16 levels deep, which usually means two files include each other.",
+        example: "// a.vira\n#include \"b.vira\"\n// b.vira\n#include \"a.vira\"",
+        fix: "Break the include cycle.",
+    },
+    ErrorCode {
+        code: "V0504",
+        title: "too many defines",
+        description: "More than 1024 `#define` macros are active at once.",
+        example: "#define A 1\n#define B 2\n...",
+        fix: "Remove unused macros or `#undef` them when they are no longer needed.",
+    },
+];
+
+pub fn lookup(code: &str) -> Option<&'static ErrorCode> {
+    CODES.iter().find(|entry| entry.code.eq_ignore_ascii_case(code))
+}
+
+/// Long-form text for `--explain`, laid out like `rustc --explain`.
+pub fn explain(entry: &ErrorCode) -> String {
+    let example: String = entry.example.lines().map(|line| format!("    {}\n", line)).collect();
+    format!(
+        "{}: {}\n\n{}\n\nErroneous code example:\n\n{}\n{}\n",
+        entry.code, entry.title, entry.description, example, entry.fix
+    )
+}
@@ -0,0 +1,17 @@
+//! Exit statuses shared by the Vira tools, so scripts and editors can tell a broken program from a
+//! broken environment or a bug in the tool.
+
+/// The tool did what was asked.
+pub const SUCCESS: i32 = 0;
+/// A compiled program failed while running, such as a failed `assert`. The tools themselves don't
+/// use it.
+pub const RUNTIME: i32 = 1;
+/// The formatter's `--check` found files that are not formatted. It shares its number with
+/// [`RUNTIME`], as the two never come from the same tool, and matches what `rustfmt --check` returns.
+pub const UNFORMATTED: i32 = 1;
+/// The source has errors, or the command line is invalid; clap uses the same status.
+pub const COMPILE: i32 = 2;
+/// A file could not be read or written, or a tool such as the linker could not be found or run.
+pub const IO: i32 = 3;
+/// The tool hit a bug in itself; Rust uses the same status for a panic.
+pub const INTERNAL: i32 = 101;
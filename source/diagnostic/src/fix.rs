@@ -0,0 +1,29 @@
+use crate::report::{Applicability, Suggestion};
+
+/// Applies the machine-applicable suggestions to `src`, returning the new text and how many were applied.
+/// Suggestions that overlap one already applied are skipped rather than guessed at.
+pub fn apply_suggestions<'a>(src: &str, suggestions: impl IntoIterator<Item = &'a Suggestion>) -> (String, usize) {
+    let mut edits: Vec<&Suggestion> = suggestions
+        .into_iter()
+        .filter(|suggestion| suggestion.applicability == Applicability::MachineApplicable)
+        .filter(|suggestion| suggestion.span.offset() + suggestion.span.len() <= src.len())
+        .collect();
+    edits.sort_by_key(|suggestion| (suggestion.span.offset(), suggestion.span.len()));
+
+    let mut out = String::with_capacity(src.len());
+    let mut cursor = 0;
+    let mut applied = 0;
+    for edit in edits {
+        let start = edit.span.offset();
+        let end = start + edit.span.len();
+        if start < cursor || !src.is_char_boundary(start) || !src.is_char_boundary(end) {
+            continue;
+        }
+        out.push_str(&src[cursor..start]);
+        out.push_str(&edit.replacement);
+        cursor = end;
+        applied += 1;
+    }
+    out.push_str(&src[cursor..]);
+    (out, applied)
+}
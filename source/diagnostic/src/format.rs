@@ -0,0 +1,195 @@
+use clap::ValueEnum;
+use miette::SourceSpan;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::render::{ColorChoice, RenderMode, Renderer};
+use crate::report::{Applicability, Severity, ViraDiagnostic};
+use crate::span::SourceMap;
+
+#[derive(Debug, Serialize)]
+pub struct LabelRecord {
+    pub offset: usize,
+    pub length: usize,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub text: Option<String>,
+    pub primary: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SuggestionRecord {
+    pub offset: usize,
+    pub length: usize,
+    pub line: usize,
+    pub column: usize,
+    pub replacement: String,
+    pub message: String,
+    pub applicability: Applicability,
+}
+
+/// Flattened, serializable view of a `ViraDiagnostic` with spans resolved to line/column.
+#[derive(Debug, Serialize)]
+pub struct DiagnosticRecord {
+    pub file: Option<String>,
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<LabelRecord>,
+    pub help: Option<String>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<SuggestionRecord>,
+    pub rendered: String,
+}
+
+impl DiagnosticRecord {
+    pub fn new(diag: &ViraDiagnostic) -> Self {
+        let src = diag.source.as_ref().map_or("", |named| named.inner().as_str());
+        let map = SourceMap::new(src);
+        let labels = diag
+            .labels
+            .iter()
+            .map(|label| label_record(&map, label.span, label.text.clone(), label.primary))
+            .collect();
+        let suggestions = diag
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                let (line, column) = map.line_col(suggestion.span.offset());
+                SuggestionRecord {
+                    offset: suggestion.span.offset(),
+                    length: suggestion.span.len(),
+                    line,
+                    column,
+                    replacement: suggestion.replacement.clone(),
+                    message: suggestion.message.clone(),
+                    applicability: suggestion.applicability,
+                }
+            })
+            .collect();
+        DiagnosticRecord {
+            file: diag.source.as_ref().map(|named| named.name().to_string()),
+            severity: diag.severity,
+            code: diag.code.clone(),
+            message: diag.message.clone(),
+            labels,
+            help: diag.help.clone(),
+            notes: diag.notes.clone(),
+            suggestions,
+            rendered: Renderer::new(RenderMode::Graphical, ColorChoice::Never).render(diag).unwrap_or_default(),
+        }
+    }
+
+    pub fn primary(&self) -> Option<&LabelRecord> {
+        self.labels.iter().find(|label| label.primary).or(self.labels.first())
+    }
+}
+
+fn label_record(map: &SourceMap, span: SourceSpan, text: Option<String>, primary: bool) -> LabelRecord {
+    let (line, column) = map.line_col(span.offset());
+    let (end_line, end_column) = map.line_col(span.offset() + span.len());
+    LabelRecord {
+        offset: span.offset(),
+        length: span.len(),
+        line,
+        column,
+        end_line,
+        end_column,
+        text,
+        primary,
+    }
+}
+
+/// How the command-line tools print diagnostics, chosen with `--error-format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// Source snippets with underlined labels
+    Human,
+    /// One `file:line:col: error[code]: message` line per diagnostic
+    Short,
+    /// One JSON object per line
+    Json,
+}
+
+impl ErrorFormat {
+    /// Prints `diag` to stderr in this format.
+    pub fn eprint(self, diag: &ViraDiagnostic) {
+        match self {
+            ErrorFormat::Human => match Renderer::new(RenderMode::Graphical, ColorChoice::Auto).render(diag) {
+                Ok(rendered) => eprint!("{}", rendered),
+                Err(_) => ErrorFormat::Short.eprint(diag),
+            },
+            ErrorFormat::Short => match Renderer::new(RenderMode::Short, ColorChoice::Never).render(diag) {
+                Ok(rendered) => eprintln!("{}", rendered),
+                Err(_) => eprintln!("{}: {}", diag.severity, diag.message),
+            },
+            ErrorFormat::Json => eprintln!("{}", to_json(diag)),
+        }
+    }
+}
+
+/// One JSON object per diagnostic.
+pub fn to_json(diag: &ViraDiagnostic) -> Value {
+    serde_json::to_value(DiagnosticRecord::new(diag)).unwrap_or(Value::Null)
+}
+
+/// A SARIF 2.1.0 log with a single run covering all `diags`.
+pub fn to_sarif(diags: &[ViraDiagnostic]) -> Value {
+    let results: Vec<Value> = diags
+        .iter()
+        .map(|diag| {
+            let record = DiagnosticRecord::new(diag);
+            let locations: Vec<Value> = record
+                .primary()
+                .map(|label| {
+                    json!({
+                        "physicalLocation": {
+                            "artifactLocation": { "uri": record.file },
+                            "region": {
+                                "startLine": label.line,
+                                "startColumn": label.column,
+                                "endLine": label.end_line,
+                                "endColumn": label.end_column,
+                                "byteOffset": label.offset,
+                                "byteLength": label.length,
+                            }
+                        }
+                    })
+                })
+                .into_iter()
+                .collect();
+            let mut result = json!({
+                "level": sarif_level(record.severity),
+                "message": { "text": record.message },
+                "locations": locations,
+            });
+            if let Some(code) = &record.code {
+                result["ruleId"] = json!(code);
+            }
+            result
+        })
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "vira",
+                    "informationUri": "https://github.com/vira-language/vira",
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+    }
+}
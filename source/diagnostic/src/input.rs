@@ -0,0 +1,35 @@
+//! Reading source files for the command-line tools, where the path `-` means standard input.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// The path that stands for standard input.
+pub const STDIN: &str = "-";
+
+/// What diagnostics call standard input.
+pub const STDIN_NAME: &str = "<stdin>";
+
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new(STDIN)
+}
+
+/// The name diagnostics use for `path`.
+pub fn name(path: &Path) -> String {
+    if is_stdin(path) {
+        STDIN_NAME.to_string()
+    } else {
+        path.display().to_string()
+    }
+}
+
+/// Reads the source at `path`, or all of standard input for `-`.
+pub fn read(path: &Path) -> io::Result<String> {
+    if is_stdin(path) {
+        let mut src = String::new();
+        io::stdin().read_to_string(&mut src)?;
+        Ok(src)
+    } else {
+        fs::read_to_string(path)
+    }
+}
@@ -0,0 +1,12 @@
+pub mod batch;
+pub mod codes;
+pub mod exit;
+pub mod fix;
+pub mod format;
+pub mod input;
+pub mod log;
+pub mod render;
+pub mod report;
+pub mod span;
+
+pub use report::{Applicability, Label, Severity, Suggestion, ViraDiagnostic};
@@ -0,0 +1,143 @@
+use std::fmt::Write as _;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use clap::{ArgAction, ValueEnum};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+use crate::render::ColorChoice;
+
+/// Logging flags shared by the command-line tools. Logs go to stderr, so they never mix with
+/// a tool's output.
+#[derive(clap::Args, Debug, Clone)]
+pub struct LogArgs {
+    /// Log what the tool is doing; -vv adds detail and how long each phase took, -vvv everything
+    #[arg(short, long, action = ArgAction::Count)]
+    pub verbose: u8,
+    /// How log lines are written
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+    /// Print how long each phase took when the tool finishes
+    #[arg(long)]
+    pub timings: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// One line per event
+    Text,
+    /// One JSON object per event
+    Json,
+}
+
+/// Installs the logger `args` asks for. Phases are `info` spans; the returned guard prints their
+/// timings, if asked to, when it is dropped.
+pub fn init(args: &LogArgs) -> Timings {
+    let level = match args.verbose {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        2 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    let span_events = if args.verbose >= 2 { FmtSpan::CLOSE } else { FmtSpan::NONE };
+    let output = match args.log_format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_writer(io::stderr)
+            .with_ansi(ColorChoice::Auto.enabled())
+            .with_target(false)
+            .with_timer(())
+            .with_span_events(span_events)
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_writer(io::stderr)
+            .with_span_events(span_events)
+            .boxed(),
+    };
+    let timings = Timings {
+        phases: args.timings.then(Default::default),
+        format: args.log_format,
+    };
+    let recorder = timings.phases.clone().map(|phases| Recorder { phases }.with_filter(LevelFilter::INFO));
+    // Fails only if a logger is already installed, which then keeps logging
+    let _ = tracing_subscriber::registry().with(output.with_filter(level)).with(recorder).try_init();
+    timings
+}
+
+type Phases = Arc<Mutex<Vec<(&'static str, Duration)>>>;
+
+/// Total time spent in each phase, in the order the phases first started.
+pub struct Timings {
+    phases: Option<Phases>,
+    format: LogFormat,
+}
+
+impl Timings {
+    fn summary(&self) -> Option<String> {
+        let phases = self.phases.as_ref()?.lock().unwrap_or_else(|e| e.into_inner());
+        let total: Duration = phases.iter().map(|(_, time)| *time).sum();
+        let mut out = String::new();
+        match self.format {
+            LogFormat::Text => {
+                let width = phases.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max("total".len());
+                for (name, time) in phases.iter().chain([&("total", total)]) {
+                    let _ = writeln!(out, "{:width$}  {:>10.3}ms", name, time.as_secs_f64() * 1000.0);
+                }
+            }
+            LogFormat::Json => {
+                let phases: Vec<_> = phases
+                    .iter()
+                    .map(|(name, time)| serde_json::json!({ "phase": name, "ms": time.as_secs_f64() * 1000.0 }))
+                    .collect();
+                let _ = writeln!(out, "{}", serde_json::json!({ "timings": phases, "total_ms": total.as_secs_f64() * 1000.0 }));
+            }
+        }
+        Some(out)
+    }
+}
+
+impl Drop for Timings {
+    fn drop(&mut self) {
+        if let Some(summary) = self.summary() {
+            eprint!("{}", summary);
+        }
+    }
+}
+
+/// When a span started, kept in the span itself.
+struct Started(Instant);
+
+struct Recorder {
+    phases: Phases,
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Recorder {
+    fn on_new_span(&self, _: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Started(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        // Only the outermost spans are phases; the ones inside them are already counted
+        if span.parent().is_some() {
+            return;
+        }
+        let Some(&Started(start)) = span.extensions().get::<Started>() else { return };
+        let mut phases = self.phases.lock().unwrap_or_else(|e| e.into_inner());
+        let name = span.name();
+        match phases.iter_mut().find(|(phase, _)| *phase == name) {
+            Some((_, time)) => *time += start.elapsed(),
+            None => phases.push((name, start.elapsed())),
+        }
+    }
+}
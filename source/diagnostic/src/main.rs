@@ -1,94 +1,321 @@
-use clap::Parser;
-use miette::{Diagnostic, GraphicalReportHandler, SourceSpan};
+use clap::{Parser, ValueEnum};
+use diagnostic::batch::DiagnosticInput;
+use diagnostic::codes;
+use diagnostic::exit;
+use diagnostic::fix;
+use diagnostic::format::{self, ErrorFormat};
+use diagnostic::input;
+use diagnostic::log::{self, LogArgs};
+use diagnostic::render::{ColorChoice, RenderMode, Renderer};
+use diagnostic::span::{ColumnUnit, SourceMap, DEFAULT_TAB_WIDTH};
+use diagnostic::{Severity, ViraDiagnostic};
+use std::collections::HashMap;
 use std::fs;
-use std::fmt;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::process;
+use tracing::info_span;
 
-#[derive(Debug, Diagnostic)]
-struct ViraError {
-    message: String,
-    #[source_code]
-    src: String,
-    #[label("here")]
-    span: SourceSpan,
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SeverityArg {
+    Error,
+    Warning,
+    Note,
 }
 
-impl fmt::Display for ViraError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.message)
+impl From<SeverityArg> for Severity {
+    fn from(arg: SeverityArg) -> Self {
+        match arg {
+            SeverityArg::Error => Severity::Error,
+            SeverityArg::Warning => Severity::Warning,
+            SeverityArg::Note => Severity::Note,
+        }
     }
 }
 
-impl std::error::Error for ViraError {}
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ColumnUnitArg {
+    /// User-perceived characters
+    Grapheme,
+    /// UTF-8 bytes
+    Byte,
+    /// Terminal cells, with tabs expanded to --tab-width
+    Display,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum RenderArg {
+    Graphical,
+    Narratable,
+    Short,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ColorArg {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    Human,
+    Json,
+    Sarif,
+}
 
 #[derive(Parser, Debug)]
 #[command(version, about = "Vira Diagnostic Tool")]
 struct Args {
-    /// Path to the source file
-    #[arg(short, long)]
-    source: String,
+    /// Path to the source file, or - for stdin
+    #[arg(short, long, required_unless_present_any = ["batch", "explain"])]
+    source: Option<String>,
     /// Error message
-    #[arg(short, long)]
-    message: String,
+    #[arg(short, long, required_unless_present_any = ["batch", "explain"])]
+    message: Option<String>,
     /// Line number (1-based)
-    #[arg(short, long)]
-    line: usize,
+    #[arg(short, long, required_unless_present_any = ["batch", "explain"])]
+    line: Option<usize>,
     /// Column number (1-based)
-    #[arg(short, long)]
-    column: usize,
+    #[arg(short, long, required_unless_present_any = ["batch", "explain"])]
+    column: Option<usize>,
     /// Length of the span
-    #[arg(short, long, default_value_t = 1)]
+    #[arg(long, default_value_t = 1)]
     length: usize,
+    /// Text shown under the primary span
+    #[arg(long, default_value = "here")]
+    label: String,
+    /// Additional label as LINE:COLUMN:LENGTH:TEXT (repeatable)
+    #[arg(long = "secondary")]
+    secondary: Vec<String>,
+    /// Severity of the diagnostic
+    #[arg(long, value_enum, default_value_t = SeverityArg::Error)]
+    severity: SeverityArg,
+    /// Error code, e.g. V0102
+    #[arg(long)]
+    code: Option<String>,
+    /// Help footer
+    #[arg(long = "help-text")]
+    help_text: Option<String>,
+    /// Note footer (repeatable)
+    #[arg(long)]
+    note: Vec<String>,
+    /// Number of columns a tab advances to when rendering
+    #[arg(long, default_value_t = DEFAULT_TAB_WIDTH)]
+    tab_width: usize,
+    /// What --column, --length and record columns count
+    #[arg(long, value_enum, default_value_t = ColumnUnitArg::Grapheme)]
+    column_unit: ColumnUnitArg,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Human)]
+    format: Format,
+    /// How human output is laid out
+    #[arg(long, value_enum, default_value_t = RenderArg::Graphical)]
+    render: RenderArg,
+    /// When to color human output; auto respects NO_COLOR
+    #[arg(long, value_enum, default_value_t = ColorArg::Auto)]
+    color: ColorArg,
+    /// Render every JSON record (one per line) from this file, or from stdin with "-"
+    #[arg(long, conflicts_with_all = ["source", "message", "line", "column"])]
+    batch: Option<String>,
+    /// Print the extended description of an error code, e.g. V0102
+    #[arg(long, conflicts_with_all = ["source", "message", "line", "column", "batch"])]
+    explain: Option<String>,
+    /// Apply machine-applicable suggestions to the source files in place
+    #[arg(long)]
+    fix: bool,
+    /// How to print errors in the input, such as an invalid record or label
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+    #[command(flatten)]
+    log: LogArgs,
 }
 
-fn main() -> miette::Result<()> {
-    let args = Args::parse();
-    let src = fs::read_to_string(&args.source).map_err(|e| miette::miette!("Failed to read source: {}", e))?;
-    let offset = calculate_offset(&src, args.line, args.column);
-    let span = SourceSpan::new(offset.into(), args.length.into());
-    let err = ViraError {
-        message: args.message,
-        src,
-        span,
-    };
-    let mut handler = GraphicalReportHandler::new();
-    let mut out = String::new();
-    handler.render_report(&mut out, &err as &dyn Diagnostic)
-        .map_err(|e| miette::miette!("Failed to render report: {}", e))?;
-    println!("{}", out);
-    Ok(())
+/// Why the tool stopped, and the exit status that says so.
+struct Failure {
+    status: i32,
+    message: String,
 }
 
-fn calculate_offset(src: &str, line: usize, column: usize) -> usize {
-    let mut offset: usize = 0;
-    let mut current_line = 1;
+impl Failure {
+    /// A file or stdin could not be read or written.
+    fn io(message: String) -> Self {
+        Failure { status: exit::IO, message }
+    }
 
-    let mut chars = src.chars().peekable();
+    /// The diagnostic asked for is invalid, such as a malformed record or an unknown code.
+    fn compile(message: String) -> Self {
+        Failure { status: exit::COMPILE, message }
+    }
+}
 
-    while current_line < line {
-        if let Some(&ch) = chars.peek() {
-            if ch == '\n' {
-                current_line += 1;
-            }
-            offset += ch.len_utf8();
-            chars.next();
+fn main() {
+    let args = Args::parse();
+    let timings = log::init(&args.log);
+    if let Err(failure) = run(&args) {
+        if failure.status == exit::COMPILE {
+            args.error_format.eprint(&ViraDiagnostic::error(failure.message));
         } else {
-            return offset;
+            eprintln!("Error: {}", failure.message);
         }
+        // Exiting skips destructors
+        drop(timings);
+        process::exit(failure.status);
     }
+}
 
-    let mut current_column = 1;
-    while current_column < column {
-        if let Some(&ch) = chars.peek() {
-            if ch == '\n' {
-                return offset;
+fn run(args: &Args) -> Result<(), Failure> {
+    if let Some(code) = &args.explain {
+        let entry = codes::lookup(code).ok_or_else(|| Failure::compile(format!("Unknown error code '{}'", code)))?;
+        print!("{}", codes::explain(entry));
+        return Ok(());
+    }
+    let diags = match &args.batch {
+        Some(path) => info_span!("read").in_scope(|| read_batch(path, column_unit(args)))?,
+        None => info_span!("read").in_scope(|| single_diagnostic(args).map(|diag| vec![diag]))?,
+    };
+    let render = info_span!("render").entered();
+    match args.format {
+        Format::Human => {
+            let mode = match args.render {
+                RenderArg::Graphical => RenderMode::Graphical,
+                RenderArg::Narratable => RenderMode::Narratable,
+                RenderArg::Short => RenderMode::Short,
+            };
+            let color = match args.color {
+                ColorArg::Auto => ColorChoice::Auto,
+                ColorArg::Always => ColorChoice::Always,
+                ColorArg::Never => ColorChoice::Never,
+            };
+            let renderer = Renderer::new(mode, color).tab_width(args.tab_width);
+            for diag in &diags {
+                let out = renderer.render(diag).map_err(|e| Failure {
+                    status: exit::INTERNAL,
+                    message: format!("Failed to render report: {}", e),
+                })?;
+                println!("{}", out);
             }
-            offset += ch.len_utf8();
-            chars.next();
-            current_column += 1;
-        } else {
-            return offset;
         }
+        Format::Json => {
+            for diag in &diags {
+                println!("{}", format::to_json(diag));
+            }
+        }
+        Format::Sarif => println!("{:#}", format::to_sarif(&diags)),
+    }
+    drop(render);
+    if args.fix {
+        info_span!("fix").in_scope(|| apply_fixes(&diags))?;
     }
+    Ok(())
+}
 
-    offset
+fn apply_fixes(diags: &[ViraDiagnostic]) -> Result<(), Failure> {
+    let mut files: Vec<&str> = diags
+        .iter()
+        .filter_map(|diag| diag.source.as_ref().map(|src| src.name()))
+        .collect();
+    files.sort();
+    files.dedup();
+    for file in files {
+        // Nothing to write back to
+        if file == input::STDIN_NAME {
+            eprintln!("can't fix {} in place", file);
+            continue;
+        }
+        let in_file: Vec<&ViraDiagnostic> = diags
+            .iter()
+            .filter(|diag| diag.source.as_ref().is_some_and(|src| src.name() == file))
+            .collect();
+        let Some(src) = in_file[0].source.as_ref() else {
+            continue;
+        };
+        let (fixed, applied) = fix::apply_suggestions(src.inner(), in_file.iter().flat_map(|diag| &diag.suggestions));
+        if applied > 0 {
+            fs::write(file, fixed).map_err(|e| Failure::io(format!("Failed to write '{}': {}", file, e)))?;
+            eprintln!("fixed {} issue(s) in {}", applied, file);
+        }
+    }
+    Ok(())
+}
+
+fn single_diagnostic(args: &Args) -> Result<ViraDiagnostic, Failure> {
+    // clap guarantees these are present outside batch mode
+    let source = args.source.clone().unwrap_or_default();
+    let path = Path::new(&source);
+    let src = input::read(path).map_err(|e| Failure::io(format!("Failed to read {}: {}", input::name(path), e)))?;
+    let map = SourceMap::new(&src).with_unit(column_unit(args));
+    let span = map.span(args.line.unwrap_or(1), args.column.unwrap_or(1), args.length);
+    let mut diag = ViraDiagnostic::new(args.severity.into(), args.message.clone().unwrap_or_default())
+        .with_label(span, args.label.clone());
+    for secondary in &args.secondary {
+        let (line, column, length, text) = parse_secondary(secondary)?;
+        diag = diag.with_secondary_label(map.span(line, column, length), text);
+    }
+    if let Some(code) = &args.code {
+        diag = diag.with_code(code);
+    }
+    if let Some(help) = &args.help_text {
+        diag = diag.with_help(help);
+    }
+    for note in &args.note {
+        diag = diag.with_note(note);
+    }
+    Ok(diag.with_source(input::name(path), src))
+}
+
+fn column_unit(args: &Args) -> ColumnUnit {
+    match args.column_unit {
+        ColumnUnitArg::Grapheme => ColumnUnit::Grapheme,
+        ColumnUnitArg::Byte => ColumnUnit::Byte,
+        ColumnUnitArg::Display => ColumnUnit::Display { tab_width: args.tab_width },
+    }
+}
+
+fn read_batch(path: &str, unit: ColumnUnit) -> Result<Vec<ViraDiagnostic>, Failure> {
+    let batch_on_stdin = input::is_stdin(Path::new(path));
+    let reader: Box<dyn BufRead> = if batch_on_stdin {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        let file = fs::File::open(path).map_err(|e| Failure::io(format!("Failed to read batch file: {}", e)))?;
+        Box::new(BufReader::new(file))
+    };
+    let mut sources: HashMap<String, String> = HashMap::new();
+    let mut diags = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| Failure::io(format!("Failed to read batch input: {}", e)))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut record: DiagnosticInput = serde_json::from_str(&line)
+            .map_err(|e| Failure::compile(format!("Invalid diagnostic record on line {}: {}", index + 1, e)))?;
+        let file = Path::new(&record.file);
+        if batch_on_stdin && input::is_stdin(file) {
+            return Err(Failure::compile(format!("Record on line {} reads stdin, which holds the records", index + 1)));
+        }
+        let name = input::name(file);
+        if !sources.contains_key(&name) {
+            let src = input::read(file).map_err(|e| Failure::io(format!("Failed to read {}: {}", name, e)))?;
+            sources.insert(name.clone(), src);
+        }
+        let src = &sources[&name];
+        record.file = name;
+        diags.push(record.into_diagnostic(src, unit));
+    }
+    Ok(diags)
+}
+
+fn parse_secondary(spec: &str) -> Result<(usize, usize, usize, String), Failure> {
+    let mut parts = spec.splitn(4, ':');
+    let mut number = |what: &str| {
+        parts
+            .next()
+            .and_then(|part| part.parse::<usize>().ok())
+            .ok_or_else(|| Failure::compile(format!("Invalid {} in secondary label '{}'", what, spec)))
+    };
+    let line = number("line")?;
+    let column = number("column")?;
+    let length = number("length")?;
+    let text = parts.next().unwrap_or_default().to_string();
+    Ok((line, column, length, text))
 }
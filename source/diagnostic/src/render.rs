@@ -0,0 +1,99 @@
+use miette::{GraphicalReportHandler, GraphicalTheme, NarratableReportHandler};
+use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal};
+
+use crate::report::ViraDiagnostic;
+use crate::span::{SourceMap, DEFAULT_TAB_WIDTH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Source snippet with underlined labels.
+    Graphical,
+    /// Plain prose, one fact per line, for screen readers.
+    Narratable,
+    /// A single `file:line:col: error: message` line for editors.
+    Short,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// `Auto` colors only when stderr is a terminal and `NO_COLOR` is unset or empty.
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+                !no_color && io::stderr().is_terminal()
+            }
+        }
+    }
+}
+
+pub struct Renderer {
+    mode: RenderMode,
+    color: bool,
+    tab_width: usize,
+}
+
+impl Renderer {
+    pub fn new(mode: RenderMode, color: ColorChoice) -> Self {
+        Renderer {
+            mode,
+            color: color.enabled(),
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
+
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = tab_width;
+        self
+    }
+
+    pub fn render(&self, diag: &ViraDiagnostic) -> Result<String, fmt::Error> {
+        let mut out = String::new();
+        match self.mode {
+            RenderMode::Graphical => {
+                let theme = if self.color {
+                    GraphicalTheme::unicode()
+                } else {
+                    GraphicalTheme::unicode_nocolor()
+                };
+                GraphicalReportHandler::new_themed(theme)
+                    .tab_width(self.tab_width)
+                    .render_report(&mut out, diag)?;
+                out.push_str(&diag.footer());
+            }
+            RenderMode::Narratable => {
+                NarratableReportHandler::new().render_report(&mut out, diag)?;
+                out.push_str(&diag.footer());
+            }
+            RenderMode::Short => out.push_str(&short(diag)),
+        }
+        Ok(out)
+    }
+}
+
+fn short(diag: &ViraDiagnostic) -> String {
+    let file = diag.source.as_ref().map_or("<unknown>", |src| src.name());
+    let position = diag
+        .source
+        .as_ref()
+        .zip(diag.labels.iter().find(|label| label.primary).or(diag.labels.first()))
+        .map(|(src, label)| SourceMap::new(src.inner()).line_col(label.span.offset()));
+    let severity = match &diag.code {
+        Some(code) => format!("{}[{}]", diag.severity, code),
+        None => diag.severity.to_string(),
+    };
+    match position {
+        Some((line, column)) => format!("{}:{}:{}: {}: {}", file, line, column, severity, diag.message),
+        None => format!("{}: {}: {}", file, severity, diag.message),
+    }
+}
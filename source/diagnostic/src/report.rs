@@ -0,0 +1,221 @@
+use miette::{Diagnostic, LabeledSpan, NamedSource, SourceCode, SourceSpan};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+impl From<Severity> for miette::Severity {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Error => miette::Severity::Error,
+            Severity::Warning => miette::Severity::Warning,
+            Severity::Note => miette::Severity::Advice,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: SourceSpan,
+    pub text: Option<String>,
+    pub primary: bool,
+}
+
+/// How safely a suggestion can be applied without a human looking at it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Applicability {
+    MachineApplicable,
+    MaybeIncorrect,
+}
+
+/// Replace the text under `span` with `replacement`. An empty span inserts, an empty replacement deletes.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub span: SourceSpan,
+    pub replacement: String,
+    pub message: String,
+    pub applicability: Applicability,
+}
+
+/// A diagnostic with a severity, optional code, any number of labeled spans and
+/// `help`/`note` footers. Start from `error`, `warning` or `note` and chain `with_*` calls.
+#[derive(Debug, Clone)]
+pub struct ViraDiagnostic {
+    pub message: String,
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub source: Option<NamedSource<String>>,
+    pub labels: Vec<Label>,
+    pub help: Option<String>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl ViraDiagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        ViraDiagnostic {
+            message: message.into(),
+            severity,
+            code: None,
+            source: None,
+            labels: Vec::new(),
+            help: None,
+            notes: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    pub fn note(message: impl Into<String>) -> Self {
+        Self::new(Severity::Note, message)
+    }
+
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    pub fn with_source(mut self, name: impl AsRef<str>, src: impl Into<String>) -> Self {
+        self.source = Some(NamedSource::new(name, src.into()));
+        self
+    }
+
+    /// Adds the primary label; the first one added is where the report points.
+    pub fn with_label(mut self, span: impl Into<SourceSpan>, text: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span: span.into(),
+            text: Some(text.into()),
+            primary: true,
+        });
+        self
+    }
+
+    /// Adds a label for related context, such as the opening brace of an unclosed block.
+    pub fn with_secondary_label(mut self, span: impl Into<SourceSpan>, text: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span: span.into(),
+            text: Some(text.into()),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Adds a `note:` footer rendered after the main report.
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_suggestion(
+        mut self,
+        span: impl Into<SourceSpan>,
+        replacement: impl Into<String>,
+        message: impl Into<String>,
+        applicability: Applicability,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            span: span.into(),
+            replacement: replacement.into(),
+            message: message.into(),
+            applicability,
+        });
+        self
+    }
+
+    /// Suggestion and `note:` lines printed after the miette report, which has no slot for them.
+    pub fn footer(&self) -> String {
+        let mut out = String::new();
+        for suggestion in &self.suggestions {
+            out.push_str(&format!("  help: {}\n", self.describe(suggestion)));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("  note: {}\n", note));
+        }
+        out
+    }
+
+    fn describe(&self, suggestion: &Suggestion) -> String {
+        let original = self.source.as_ref().and_then(|src| {
+            let start = suggestion.span.offset();
+            src.inner().get(start..start + suggestion.span.len())
+        });
+        let action = match original {
+            Some("") | None => format!("insert `{}`", suggestion.replacement),
+            Some(original) if suggestion.replacement.is_empty() => format!("remove `{}`", original),
+            Some(original) => format!("replace `{}` with `{}`", original, suggestion.replacement),
+        };
+        if suggestion.message.is_empty() {
+            action
+        } else {
+            format!("{}: {}", suggestion.message, action)
+        }
+    }
+}
+
+impl fmt::Display for ViraDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ViraDiagnostic {}
+
+impl Diagnostic for ViraDiagnostic {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.code.as_ref().map(|code| Box::new(code) as Box<dyn fmt::Display>)
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(self.severity.into())
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.help.as_ref().map(|help| Box::new(help) as Box<dyn fmt::Display>)
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.source.as_ref().map(|src| src as &dyn SourceCode)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        if self.labels.is_empty() {
+            return None;
+        }
+        Some(Box::new(self.labels.iter().map(|label| {
+            if label.primary {
+                LabeledSpan::new_primary_with_span(label.text.clone(), label.span)
+            } else {
+                LabeledSpan::new_with_span(label.text.clone(), label.span)
+            }
+        })))
+    }
+}
@@ -0,0 +1,228 @@
+use miette::SourceSpan;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// What a column number counts. Tools disagree: plsa counts bytes, editors count characters
+/// as the user sees them, and terminals count cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnUnit {
+    /// User-perceived characters, so `é` written as `e` + combining accent or a flag emoji is one column.
+    Grapheme,
+    /// UTF-8 bytes.
+    Byte,
+    /// Terminal cells: tabs advance to the next tab stop and full-width characters take two cells.
+    Display { tab_width: usize },
+}
+
+/// Line index over a source file, used to move between byte offsets and 1-based line/column positions.
+/// Lines end at `\n`; a `\r` before it belongs to the terminator, not to the line.
+pub struct SourceMap<'a> {
+    src: &'a str,
+    line_starts: Vec<usize>,
+    unit: ColumnUnit,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(src: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(src.match_indices('\n').map(|(i, _)| i + 1));
+        SourceMap {
+            src,
+            line_starts,
+            unit: ColumnUnit::Grapheme,
+        }
+    }
+
+    /// Interprets columns passed to and returned from this map in `unit`.
+    pub fn with_unit(mut self, unit: ColumnUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Text of a 1-based line without its line terminator.
+    pub fn line_text(&self, line: usize) -> &'a str {
+        let Some(&start) = self.line_starts.get(line.saturating_sub(1)) else {
+            return "";
+        };
+        let end = self.line_starts.get(line).map_or(self.src.len(), |&next| next - 1);
+        let text = &self.src[start..end];
+        text.strip_suffix('\r').unwrap_or(text)
+    }
+
+    /// Byte offset of a 1-based line and column. Positions past the end of a line
+    /// clamp to the line end, and lines past the end of the file clamp to the end of the source.
+    pub fn offset(&self, line: usize, column: usize) -> usize {
+        let Some(&start) = self.line_starts.get(line.saturating_sub(1)) else {
+            return self.src.len();
+        };
+        let text = self.line_text(line);
+        let target = column.saturating_sub(1);
+        let within = match self.unit {
+            ColumnUnit::Grapheme => text.grapheme_indices(true).nth(target).map_or(text.len(), |(i, _)| i),
+            ColumnUnit::Byte => {
+                // A byte column inside a multi-byte character points at that character
+                let mut i = target.min(text.len());
+                while !text.is_char_boundary(i) {
+                    i -= 1;
+                }
+                i
+            }
+            ColumnUnit::Display { tab_width } => {
+                let mut width = 0;
+                let mut found = text.len();
+                for (i, grapheme) in text.grapheme_indices(true) {
+                    width = advance(width, grapheme, tab_width);
+                    if width > target {
+                        found = i;
+                        break;
+                    }
+                }
+                found
+            }
+        };
+        start + within
+    }
+
+    /// 1-based line and column of a byte offset.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        let offset = self.char_start(offset);
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let start = self.line_starts[line - 1];
+        let before = &self.src[start..offset];
+        let column = match self.unit {
+            ColumnUnit::Grapheme => before.graphemes(true).count(),
+            ColumnUnit::Byte => before.len(),
+            ColumnUnit::Display { tab_width } => display_width(before, tab_width),
+        };
+        (line, column + 1)
+    }
+
+    /// On-screen column (1-based) of a byte offset, expanding tabs to the next tab stop and
+    /// counting full-width characters as two cells.
+    pub fn display_column(&self, offset: usize, tab_width: usize) -> usize {
+        let offset = self.char_start(offset);
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let start = self.line_starts[line - 1];
+        display_width(&self.src[start..offset], tab_width) + 1
+    }
+
+    /// `offset` within the source, moved back to the start of the character it falls inside.
+    fn char_start(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.src.len());
+        while !self.src.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+
+    /// Span starting at a 1-based line/column and covering `length` columns, kept within the line.
+    pub fn span(&self, line: usize, column: usize, length: usize) -> SourceSpan {
+        let start = self.offset(line, column);
+        let end = self.offset(line, column + length).max(start);
+        SourceSpan::new(start.into(), end - start)
+    }
+}
+
+fn advance(width: usize, grapheme: &str, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = tab_width.max(1);
+        width + tab_width - width % tab_width
+    } else {
+        width + grapheme.width()
+    }
+}
+
+/// Width of `text` in terminal cells, with tabs advancing to the next multiple of `tab_width`.
+pub fn display_width(text: &str, tab_width: usize) -> usize {
+    text.graphemes(true).fold(0, |width, grapheme| advance(width, grapheme, tab_width))
+}
+
+/// Smallest span covering both `a` and `b`.
+pub fn merge_spans(a: SourceSpan, b: SourceSpan) -> SourceSpan {
+    let start = a.offset().min(b.offset());
+    let end = (a.offset() + a.len()).max(b.offset() + b.len());
+    SourceSpan::new(start.into(), end - start)
+}
+
+/// Whether two spans share at least one byte.
+pub fn spans_overlap(a: SourceSpan, b: SourceSpan) -> bool {
+    a.offset() < b.offset() + b.len() && b.offset() < a.offset() + a.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Line and column of the first `x` in `src`, in `unit`.
+    fn x_position(src: &str, unit: ColumnUnit) -> (usize, usize) {
+        SourceMap::new(src).with_unit(unit).line_col(src.find('x').unwrap())
+    }
+
+    const DISPLAY: ColumnUnit = ColumnUnit::Display { tab_width: DEFAULT_TAB_WIDTH };
+
+    #[test]
+    fn emoji_cluster_is_one_character_and_two_cells() {
+        // A family: three people joined by zero-width joiners, 18 bytes in all
+        let src = "\"\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}\" + x";
+        assert_eq!(x_position(src, ColumnUnit::Grapheme), (1, 7));
+        assert_eq!(x_position(src, ColumnUnit::Byte), (1, 24));
+        assert_eq!(x_position(src, DISPLAY), (1, 8));
+        assert_eq!(SourceMap::new(src).display_column(src.find('x').unwrap(), DEFAULT_TAB_WIDTH), 8);
+    }
+
+    #[test]
+    fn combining_accent_joins_the_letter_before_it() {
+        let src = "\"e\u{301}\" + x";
+        assert_eq!(x_position(src, ColumnUnit::Grapheme), (1, 7));
+        assert_eq!(x_position(src, ColumnUnit::Byte), (1, 9));
+        assert_eq!(x_position(src, DISPLAY), (1, 7));
+    }
+
+    #[test]
+    fn crlf_ends_lines_without_taking_a_column() {
+        let src = "let a = 1;\r\n\tx;\r\n";
+        let map = SourceMap::new(src);
+        assert_eq!(map.line_count(), 3);
+        assert_eq!(map.line_text(1), "let a = 1;");
+        assert_eq!(map.line_col(src.find('\r').unwrap()), (1, 11));
+        assert_eq!(x_position(src, ColumnUnit::Grapheme), (2, 2));
+        assert_eq!(x_position(src, DISPLAY), (2, 5));
+        assert_eq!(map.offset(2, 2), src.find('x').unwrap());
+        // Past the end of the line is the `\r`, not a column of its own
+        assert_eq!(map.offset(1, 40), src.find('\r').unwrap());
+    }
+
+    #[test]
+    fn tabs_advance_to_the_next_tab_stop() {
+        let src = "ab\tx";
+        let x = src.find('x').unwrap();
+        let map = SourceMap::new(src);
+        assert_eq!(map.display_column(x, 4), 5);
+        assert_eq!(map.display_column(x, 8), 9);
+        assert_eq!(map.display_column(x, 2), 5);
+        // A width of zero would never advance, so it counts as one
+        assert_eq!(map.display_column(x, 0), 4);
+        let display = SourceMap::new(src).with_unit(ColumnUnit::Display { tab_width: 8 });
+        assert_eq!(display.offset(1, 9), x);
+        assert_eq!(display.offset(1, 6), 2);
+    }
+
+    #[test]
+    fn offsets_inside_a_character_count_from_its_start() {
+        // Full-width characters, three bytes and two cells each
+        let src = "\u{65E5}\u{672C} x";
+        let map = SourceMap::new(src);
+        for offset in 3..6 {
+            assert_eq!(map.line_col(offset), (1, 2));
+            assert_eq!(map.display_column(offset, 4), 3);
+        }
+        assert_eq!(map.display_column(src.find('x').unwrap(), 4), 6);
+        assert_eq!(map.display_column(src.len() + 5, 4), 7);
+    }
+}
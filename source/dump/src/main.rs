@@ -0,0 +1,183 @@
+use clap::{ArgGroup, Parser, ValueEnum};
+use diagnostic::exit;
+use diagnostic::format::ErrorFormat;
+use diagnostic::input;
+use diagnostic::log::{self, LogArgs};
+use diagnostic::ViraDiagnostic;
+use serde::{Serialize, Serializer};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+use tracing::info_span;
+use vira_core::cache;
+use vira_core::{Lexer, Program};
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Emit {
+    /// Binary AST cache, readable with --from-ast
+    Ast,
+}
+
+/// JSON views of the front end for tools and tests. Spans are byte offsets into the file,
+/// `start` inclusive and `end` exclusive.
+#[derive(Parser, Debug)]
+#[command(version, about = "Vira Front End Dump")]
+#[command(group(ArgGroup::new("what").required(true).args(["tokens", "ast", "trivia", "emit"])))]
+struct Args {
+    /// Path to the source file, or - for standard input
+    #[arg(required_unless_present = "from_ast")]
+    source: Option<String>,
+    /// Print the token stream as it is lexed; after a lexing error the array ends before the bad token
+    #[arg(long)]
+    tokens: bool,
+    /// Print the parsed program
+    #[arg(long)]
+    ast: bool,
+    /// Print the lossless token stream, with whitespace and comments attached to tokens
+    #[arg(long)]
+    trivia: bool,
+    /// Print JSON on one line instead of indented
+    #[arg(long)]
+    compact: bool,
+    /// Write a build artifact instead of printing JSON
+    #[arg(long, value_enum)]
+    emit: Option<Emit>,
+    /// Where --emit writes; defaults to the source path with a .vast extension, or stdin.vast
+    #[arg(short, long, requires = "emit")]
+    output: Option<String>,
+    /// With --ast, read the program from an AST cache instead of parsing; with a source path, refuse a stale cache
+    #[arg(long, requires = "ast", conflicts_with_all = ["tokens", "trivia", "emit"])]
+    from_ast: Option<String>,
+    /// How to print errors in the source
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+fn main() {
+    let args = Args::parse();
+    let timings = log::init(&args.log);
+    let read = info_span!("read").entered();
+    let src = args.source.as_ref().map(|source| match input::read(Path::new(source)) {
+        Ok(src) => src,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", input::name(Path::new(source)), e);
+            process::exit(exit::IO);
+        }
+    });
+
+    drop(read);
+
+    if let Some(path) = &args.from_ast {
+        let program = info_span!("decode").in_scope(|| load_cache(path, src.as_deref()));
+        info_span!("print").in_scope(|| print_json(&program, args.compact));
+        return;
+    }
+    let path = args.source.unwrap_or_default();
+    let source = input::name(Path::new(&path));
+    let src = src.unwrap_or_default();
+
+    if let Some(Emit::Ast) = args.emit {
+        let program = match info_span!("parse").in_scope(|| vira_core::parse(&src)) {
+            Ok((program, _)) => program,
+            Err(err) => {
+                report(&source, &src, &err, args.error_format);
+                process::exit(exit::COMPILE);
+            }
+        };
+        let stem = if input::is_stdin(Path::new(&path)) { "stdin" } else { &path };
+        let output = args.output.unwrap_or_else(|| Path::new(stem).with_extension("vast").display().to_string());
+        let encoded = info_span!("encode").in_scope(|| cache::encode(&program, &src));
+        if let Err(e) = fs::write(&output, encoded) {
+            eprintln!("Error writing {}: {}", output, e);
+            process::exit(exit::IO);
+        }
+        return;
+    }
+
+    // Tokens are printed as they are lexed, so that phase includes printing
+    let result = if args.tokens {
+        info_span!("lex").in_scope(|| print_tokens(Lexer::new(&src), args.compact))
+    } else if args.ast {
+        let parsed = info_span!("parse").in_scope(|| vira_core::parse(&src));
+        parsed.map(|(program, _)| info_span!("print").in_scope(|| print_json(&program, args.compact)))
+    } else {
+        let tokens = info_span!("lex").in_scope(|| vira_core::lossless::tokenize(&src));
+        tokens.map(|tokens| info_span!("print").in_scope(|| print_json(&tokens, args.compact)))
+    };
+    if let Err(err) = result {
+        report(&source, &src, &err, args.error_format);
+        // Exiting skips destructors
+        drop(timings);
+        process::exit(exit::COMPILE);
+    }
+}
+
+fn load_cache(path: &str, src: Option<&str>) -> Program {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", path, e);
+            process::exit(exit::IO);
+        }
+    };
+    match cache::decode(&bytes) {
+        Ok(cached) if src.is_some_and(|src| !cached.is_fresh(src)) => {
+            eprintln!("Error: {} is stale; the source changed since it was written", path);
+            process::exit(exit::COMPILE);
+        }
+        Ok(cached) => cached.program,
+        Err(e) => {
+            eprintln!("Error: {}: {}", path, e);
+            process::exit(exit::COMPILE);
+        }
+    }
+}
+
+fn print_json(value: &impl Serialize, compact: bool) {
+    let json = if compact {
+        serde_json::to_string(value)
+    } else {
+        serde_json::to_string_pretty(value)
+    };
+    match json {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("Error serializing: {}", e);
+            process::exit(exit::INTERNAL);
+        }
+    }
+}
+
+/// Writes each token as soon as it is lexed instead of collecting the whole stream first.
+fn print_tokens(lexer: Lexer, compact: bool) -> Result<(), vira_core::Error> {
+    let mut error = None;
+    let tokens = lexer.map_while(|token| token.map_err(|err| error = Some(err)).ok());
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let written = if compact {
+        serde_json::Serializer::new(&mut out).collect_seq(tokens)
+    } else {
+        serde_json::Serializer::pretty(&mut out).collect_seq(tokens)
+    };
+    if let Err(e) = written.map_err(io::Error::from).and_then(|()| writeln!(out)).and_then(|()| out.flush()) {
+        eprintln!("Error writing tokens: {}", e);
+        process::exit(exit::IO);
+    }
+    error.map_or(Ok(()), Err)
+}
+
+fn report(name: &str, src: &str, err: &vira_core::Error, format: ErrorFormat) {
+    let mut diag = ViraDiagnostic::error(err.message.clone())
+        .with_code(err.code)
+        .with_label((err.span.start, err.span.len()), "here")
+        .with_source(name, src);
+    for (span, text) in &err.labels {
+        diag = diag.with_secondary_label((span.start, span.len()), text.clone());
+    }
+    if let Some(help) = &err.help {
+        diag = diag.with_help(help.clone());
+    }
+    format.eprint(&diag);
+}
@@ -0,0 +1,3 @@
+pub mod printer;
+
+pub use printer::format_source;
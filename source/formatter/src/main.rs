@@ -0,0 +1,122 @@
+use clap::Parser;
+use diagnostic::exit;
+use diagnostic::format::ErrorFormat;
+use diagnostic::input;
+use diagnostic::log::{self, LogArgs};
+use diagnostic::ViraDiagnostic;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use tracing::{debug, info, info_span};
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Vira Code Formatter")]
+struct Args {
+    /// Files or directories to format; - or no paths at all reads stdin and writes stdout
+    paths: Vec<PathBuf>,
+    /// List files that are not formatted and exit with 1 instead of rewriting them
+    #[arg(long)]
+    check: bool,
+    /// How to print errors in the source
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+fn main() {
+    let args = Args::parse();
+    let timings = log::init(&args.log);
+
+    let mut files = Vec::new();
+    for path in &args.paths {
+        collect_files(path, &mut files);
+    }
+    if args.paths.is_empty() {
+        files.push(PathBuf::from(input::STDIN));
+    }
+
+    // The exit status: UNFORMATTED for unformatted files, then the most serious failure
+    let mut status = exit::SUCCESS;
+    for file in &files {
+        let name = input::name(file);
+        let src = match info_span!("read").in_scope(|| input::read(file)) {
+            Ok(src) => src,
+            Err(e) => {
+                eprintln!("Error reading {}: {}", name, e);
+                status = status.max(exit::IO);
+                continue;
+            }
+        };
+        let formatted = match info_span!("format").in_scope(|| formatter::format_source(&src)) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                report(&name, &src, &err, args.error_format);
+                status = status.max(exit::COMPILE);
+                continue;
+            }
+        };
+        // Standard input is formatted to standard output, whether or not anything changed
+        if input::is_stdin(file) && !args.check {
+            print!("{}", formatted);
+            continue;
+        }
+        if formatted == src {
+            debug!(file = %name, "already formatted");
+            continue;
+        }
+        if args.check {
+            println!("{} is not formatted", name);
+            status = status.max(exit::UNFORMATTED);
+        } else if let Err(e) = info_span!("write").in_scope(|| fs::write(file, formatted)) {
+            eprintln!("Error writing {}: {}", file.display(), e);
+            status = status.max(exit::IO);
+        } else {
+            info!(file = %name, "formatted");
+        }
+    }
+
+    if status != exit::SUCCESS {
+        // Exiting skips destructors
+        drop(timings);
+        process::exit(status);
+    }
+}
+
+/// Expands directories to the `.vira` files below them. Paths named explicitly are kept whatever their extension.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return;
+    }
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Error reading {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+    paths.sort();
+    for entry in paths {
+        if entry.is_dir() {
+            collect_files(&entry, files);
+        } else if entry.extension().is_some_and(|ext| ext == "vira") {
+            files.push(entry);
+        }
+    }
+}
+
+fn report(name: &str, src: &str, err: &vira_core::Error, format: ErrorFormat) {
+    let mut diag = ViraDiagnostic::error(err.message.clone())
+        .with_code(err.code)
+        .with_label((err.span.start, err.span.len()), "here")
+        .with_source(name, src);
+    for (span, text) in &err.labels {
+        diag = diag.with_secondary_label((span.start, span.len()), text.clone());
+    }
+    if let Some(help) = &err.help {
+        diag = diag.with_help(help.clone());
+    }
+    format.eprint(&diag);
+}
@@ -0,0 +1,356 @@
+use vira_core::ast::{Block, Else, Expr, Pattern, Program, Stmt, UNARY_PRECEDENCE};
+use vira_core::{parse, Comment, Error};
+
+const INDENT: &str = "    ";
+
+/// Parses `src` and prints it back in canonical form: four-space indentation, one space
+/// around binary operators, only the parentheses precedence requires, a blank line around
+/// every function, and at most one blank line kept between other statements. Comments are
+/// kept; a comment inside a simple statement moves above it.
+pub fn format_source(src: &str) -> Result<String, Error> {
+    let (program, comments) = parse(src)?;
+    let mut printer = Printer {
+        src,
+        comments: &comments,
+        next_comment: 0,
+        out: String::new(),
+        last_end: None,
+        block_end: usize::MAX,
+    };
+    printer.program(&program);
+    Ok(printer.out)
+}
+
+struct Printer<'a> {
+    src: &'a str,
+    comments: &'a [Comment<'a>],
+    next_comment: usize,
+    out: String,
+    /// Source end of the last item written in the current block, `None` right after an opening brace.
+    last_end: Option<usize>,
+    /// End of the innermost block being printed; comments past it are not pulled inside.
+    block_end: usize,
+}
+
+impl Printer<'_> {
+    fn program(&mut self, program: &Program) {
+        let after_function = self.statements(&program.statements, 0, true);
+        self.comments_before(self.src.len(), 0, after_function);
+    }
+
+    /// Returns whether the last statement was a function, which owes a blank line to whatever follows.
+    fn statements(&mut self, statements: &[Stmt], depth: usize, top_level: bool) -> bool {
+        let mut after_function = false;
+        for stmt in statements {
+            let is_function = matches!(stmt, Stmt::FuncDef { .. });
+            self.statement(stmt, depth, top_level && (after_function || is_function));
+            after_function = is_function;
+        }
+        after_function
+    }
+
+    fn statement(&mut self, stmt: &Stmt, depth: usize, force_blank: bool) {
+        match stmt {
+            Stmt::FuncDef { name, params, body, .. } => {
+                let mut header = format!("def {}(", name);
+                for (index, param) in params.iter().enumerate() {
+                    if index > 0 {
+                        header.push_str(", ");
+                    }
+                    header.push_str(param.name.as_str());
+                    if let Some(default) = &param.default {
+                        header.push_str(" = ");
+                        write_expression(self.src, &mut header, default);
+                    }
+                }
+                header.push(')');
+                self.open(stmt, body, depth, force_blank, &header);
+                self.close(body, depth);
+            }
+            Stmt::If { .. } => {
+                self.if_chain(stmt, depth, force_blank);
+            }
+            Stmt::While { condition, body, .. } => {
+                self.open(stmt, body, depth, force_blank, &format!("while {}", expression(self.src, condition)));
+                self.close(body, depth);
+            }
+            Stmt::Match { .. } => {
+                self.match_arms(stmt, depth, force_blank);
+            }
+            _ => {
+                let span = stmt.span();
+                let force_blank = self.comments_before(span.end, depth, force_blank);
+                self.separate(span.start, force_blank);
+                let text = match stmt {
+                    Stmt::Let { name, value, .. } => format!("let {} = {};", name, expression(self.src, value)),
+                    Stmt::Assign { name, value, .. } => format!("{} = {};", name, expression(self.src, value)),
+                    Stmt::Write(value, _) => format!("write {};", expression(self.src, value)),
+                    Stmt::Return(Some(value), _) => format!("return {};", expression(self.src, value)),
+                    Stmt::Return(None, _) => "return;".to_string(),
+                    Stmt::Expr(value, _) => format!("{};", expression(self.src, value)),
+                    Stmt::Enum { name, variants, .. } if variants.is_empty() => format!("enum {} {{}}", name),
+                    Stmt::Enum { name, variants, .. } => {
+                        let variants: Vec<&str> = variants.iter().map(|variant| variant.name.as_str()).collect();
+                        format!("enum {} {{ {} }}", name, variants.join(", "))
+                    }
+                    _ => unreachable!("compound statements are handled above"),
+                };
+                self.line(depth, &text);
+            }
+        }
+        self.last_end = Some(stmt.span().end);
+        self.trailing_comment(stmt.span().end);
+    }
+
+    fn if_chain(&mut self, stmt: &Stmt, depth: usize, force_blank: bool) {
+        let Stmt::If {
+            condition,
+            then_block,
+            else_branch,
+            ..
+        } = stmt
+        else {
+            return;
+        };
+        self.open(stmt, then_block, depth, force_blank, &format!("if {}", expression(self.src, condition)));
+        let mut else_branch = else_branch;
+        let mut block = then_block;
+        loop {
+            match else_branch {
+                Some(Else::If(nested)) => {
+                    let Stmt::If {
+                        condition,
+                        then_block,
+                        else_branch: next,
+                        ..
+                    } = nested.as_ref()
+                    else {
+                        break;
+                    };
+                    self.body(block, depth);
+                    self.header(then_block, depth, &format!("}} else if {}", expression(self.src, condition)));
+                    block = then_block;
+                    else_branch = next;
+                }
+                Some(Else::Block(else_block)) => {
+                    self.body(block, depth);
+                    self.header(else_block, depth, "} else");
+                    block = else_block;
+                    else_branch = &None;
+                }
+                None => break,
+            }
+        }
+        self.close(block, depth);
+    }
+
+    /// Writes a match with one arm per line group, each arm's block indented under the match.
+    fn match_arms(&mut self, stmt: &Stmt, depth: usize, force_blank: bool) {
+        let Stmt::Match { value, arms, span } = stmt else {
+            return;
+        };
+        let force_blank = self.comments_before(value.span().end, depth, force_blank);
+        self.separate(span.start, force_blank);
+        self.line(depth, &format!("match {} {{", expression(self.src, value)));
+        let outer = std::mem::replace(&mut self.block_end, span.end);
+        self.last_end = None;
+        for arm in arms {
+            let patterns: Vec<String> = arm.patterns.iter().map(pattern).collect();
+            let start = arm.patterns[0].span().start;
+            let force_blank = self.comments_before(arm.body.span.start, depth + 1, false);
+            self.separate(start, force_blank);
+            self.header(&arm.body, depth + 1, &format!("{} =>", patterns.join(", ")));
+            self.close(&arm.body, depth + 1);
+            self.last_end = Some(arm.body.span.end);
+            self.trailing_comment(arm.body.span.end);
+        }
+        self.comments_before(span.end - 1, depth + 1, false);
+        self.block_end = outer;
+        self.line(depth, "}");
+    }
+
+    /// Writes the comments above a compound statement, then its header line.
+    fn open(&mut self, stmt: &Stmt, block: &Block, depth: usize, force_blank: bool, header: &str) {
+        let force_blank = self.comments_before(block.span.start, depth, force_blank);
+        self.separate(stmt.span().start, force_blank);
+        self.header(block, depth, header);
+    }
+
+    fn header(&mut self, block: &Block, depth: usize, header: &str) {
+        self.line(depth, &format!("{} {{", header));
+        let outer = std::mem::replace(&mut self.block_end, block.span.end);
+        self.trailing_comment(block.span.start + 1);
+        self.block_end = outer;
+        self.last_end = None;
+    }
+
+    /// Writes the statements of a block and any comments before its closing brace.
+    fn body(&mut self, block: &Block, depth: usize) {
+        let outer = std::mem::replace(&mut self.block_end, block.span.end);
+        self.statements(&block.statements, depth + 1, false);
+        self.comments_before(block.span.end - 1, depth + 1, false);
+        self.block_end = outer;
+    }
+
+    fn close(&mut self, block: &Block, depth: usize) {
+        self.body(block, depth);
+        self.line(depth, "}");
+    }
+
+    /// Writes every pending comment that starts before `offset` on its own line.
+    /// Returns whether a forced blank line is still owed to the next item.
+    fn comments_before(&mut self, offset: usize, depth: usize, mut force_blank: bool) -> bool {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.span.start >= offset {
+                break;
+            }
+            self.separate(comment.span.start, force_blank);
+            force_blank = false;
+            self.line(depth, comment.text);
+            self.last_end = Some(comment.span.end);
+            self.next_comment += 1;
+        }
+        force_blank
+    }
+
+    /// Appends the next comment to the line just written if it started on the same source line as `end`.
+    fn trailing_comment(&mut self, end: usize) {
+        let Some(comment) = self.comments.get(self.next_comment) else {
+            return;
+        };
+        if comment.span.start < end || comment.span.start >= self.block_end || self.src[end..comment.span.start].contains('\n') {
+            return;
+        }
+        self.out.pop();
+        self.out.push(' ');
+        self.out.push_str(comment.text);
+        self.out.push('\n');
+        self.last_end = Some(comment.span.end);
+        self.next_comment += 1;
+    }
+
+    /// Emits a blank line before an item starting at `start` when forced, or when the source had one.
+    fn separate(&mut self, start: usize, force_blank: bool) {
+        if let Some(prev) = self.last_end {
+            if force_blank || self.src[prev..start].matches('\n').count() >= 2 {
+                self.out.push('\n');
+            }
+        }
+    }
+
+    fn line(&mut self, depth: usize, text: &str) {
+        for _ in 0..depth {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+}
+
+fn expression(src: &str, expr: &Expr) -> String {
+    let mut out = String::new();
+    write_expression(src, &mut out, expr);
+    out
+}
+
+/// Appends to one buffer rather than returning a string per operand, which would copy the text of
+/// a long chain like `1 + 1 + ...` once for every term.
+fn write_expression(src: &str, out: &mut String, expr: &Expr) {
+    vira_core::ensure_stack(|| match expr {
+        Expr::Number(value, _) => out.push_str(&value.to_string()),
+        // Raw and `"""` strings are kept as written, since escaping would undo the reason for them
+        Expr::String(_, span) if src[span.start..].starts_with('r') || src[span.start..].starts_with("\"\"\"") => {
+            out.push_str(&src[span.start..span.end].replace("\r\n", "\n"));
+        }
+        Expr::String(value, _) => {
+            out.push('"');
+            out.push_str(&escape(value));
+            out.push('"');
+        }
+        Expr::Identifier(name, _) => out.push_str(name.as_str()),
+        Expr::Variant(enum_name, variant, _) => {
+            out.push_str(enum_name.as_str());
+            out.push('.');
+            out.push_str(variant.as_str());
+        }
+        Expr::Call(name, args, named, _) => {
+            out.push_str(name.as_str());
+            out.push('(');
+            let named = named.iter().map(|arg| (Some(arg.name), &arg.value));
+            for (index, (name, arg)) in args.iter().map(|arg| (None, arg)).chain(named).enumerate() {
+                if index > 0 {
+                    out.push_str(", ");
+                }
+                if let Some(name) = name {
+                    out.push_str(name.as_str());
+                    out.push_str(" = ");
+                }
+                write_expression(src, out, arg);
+            }
+            out.push(')');
+        }
+        Expr::Unary(op, operand, _) => {
+            out.push_str(op.symbol());
+            write_operand(src, out, operand, UNARY_PRECEDENCE);
+        }
+        // Operators are left-associative, so a right operand of equal precedence needs parentheses
+        Expr::Binary(op, left, right, _) => {
+            write_operand(src, out, left, op.precedence());
+            out.push(' ');
+            out.push_str(op.symbol());
+            out.push(' ');
+            write_operand(src, out, right, op.precedence() + 1);
+        }
+        Expr::Index(value, index, _) => {
+            write_operand(src, out, value, UNARY_PRECEDENCE + 1);
+            out.push('[');
+            write_expression(src, out, index);
+            out.push(']');
+        }
+        Expr::Slice(value, start, end, _) => {
+            write_operand(src, out, value, UNARY_PRECEDENCE + 1);
+            out.push('[');
+            if let Some(start) = start {
+                write_expression(src, out, start);
+            }
+            out.push_str("..");
+            if let Some(end) = end {
+                write_expression(src, out, end);
+            }
+            out.push(']');
+        }
+    })
+}
+
+fn pattern(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Variant(enum_name, variant, _) => format!("{}.{}", enum_name, variant),
+        Pattern::Wildcard(_) => "_".to_string(),
+    }
+}
+
+fn write_operand(src: &str, out: &mut String, expr: &Expr, min_precedence: u8) {
+    if expr.precedence() < min_precedence {
+        out.push('(');
+        write_expression(src, out, expr);
+        out.push(')');
+    } else {
+        write_expression(src, out, expr);
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\0' => out.push_str("\\0"),
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
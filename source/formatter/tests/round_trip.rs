@@ -0,0 +1,322 @@
+//! Random valid programs, written out with every operator parenthesized and no spacing, through
+//! the formatter. Its output must parse to the same program, and formatting it again must not
+//! change it.
+
+use std::fmt::Write;
+
+use formatter::format_source;
+use proptest::prelude::*;
+use vira_core::ast::{BinOp, Block, Else, Expr, MatchArm, NamedArg, Param, Pattern, Program, Stmt, UnOp, Variant};
+use vira_core::{parse, Span, Symbol};
+
+/// Names that are never keywords, with some overlap so uses can find declarations.
+const NAMES: &[&str] = &["a", "b", "x", "total", "f", "g_2"];
+const OPERATORS: &[BinOp] = &[
+    BinOp::Add,
+    BinOp::Sub,
+    BinOp::Mul,
+    BinOp::Div,
+    BinOp::Mod,
+    BinOp::Eq,
+    BinOp::Ne,
+    BinOp::Lt,
+    BinOp::Le,
+    BinOp::Gt,
+    BinOp::Ge,
+    BinOp::And,
+    BinOp::Or,
+];
+
+const NO_SPAN: Span = Span { start: 0, end: 0 };
+
+fn name() -> impl Strategy<Value = Symbol> {
+    prop::sample::select(NAMES).prop_map(Symbol::intern)
+}
+
+/// Literals never start with `-`, which parses as an operator.
+fn number() -> impl Strategy<Value = f64> {
+    // Thousandths, so fractions and their printing get exercised
+    prop_oneof![any::<u32>().prop_map(f64::from), any::<u32>().prop_map(|value| f64::from(value) / 1000.0)]
+}
+
+fn expr() -> BoxedStrategy<Expr> {
+    let leaf = prop_oneof![
+        number().prop_map(|value| Expr::Number(value, NO_SPAN)),
+        any::<String>().prop_map(|text| Expr::String(text, NO_SPAN)),
+        name().prop_map(|name| Expr::Identifier(name, NO_SPAN)),
+        (name(), name()).prop_map(|(enum_name, variant)| Expr::Variant(enum_name, variant, NO_SPAN)),
+    ];
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            call(inner.clone()),
+            (prop::sample::select(&[UnOp::Neg, UnOp::Not][..]), inner.clone())
+                .prop_map(|(op, operand)| Expr::Unary(op, Box::new(operand), NO_SPAN)),
+            (prop::sample::select(OPERATORS), inner.clone(), inner.clone())
+                .prop_map(|(op, left, right)| Expr::Binary(op, Box::new(left), Box::new(right), NO_SPAN)),
+            (inner.clone(), inner.clone()).prop_map(|(value, index)| Expr::Index(Box::new(value), Box::new(index), NO_SPAN)),
+            (inner.clone(), prop::option::of(inner.clone()), prop::option::of(inner))
+                .prop_map(|(value, start, end)| Expr::Slice(Box::new(value), start.map(Box::new), end.map(Box::new), NO_SPAN)),
+        ]
+    })
+    .boxed()
+}
+
+fn call(arg: BoxedStrategy<Expr>) -> impl Strategy<Value = Expr> {
+    let named = (name(), arg.clone()).prop_map(|(name, value)| NamedArg { name, name_span: NO_SPAN, value });
+    (name(), prop::collection::vec(arg, 0..3), prop::collection::vec(named, 0..2))
+        .prop_map(|(name, args, named)| Expr::Call(name, args, named, NO_SPAN))
+}
+
+fn block(stmt: BoxedStrategy<Stmt>) -> impl Strategy<Value = Block> {
+    prop::collection::vec(stmt, 0..3).prop_map(|statements| Block { statements, span: NO_SPAN })
+}
+
+fn pattern() -> impl Strategy<Value = Pattern> {
+    prop_oneof![
+        (name(), name()).prop_map(|(enum_name, variant)| Pattern::Variant(enum_name, variant, NO_SPAN)),
+        Just(Pattern::Wildcard(NO_SPAN)),
+    ]
+}
+
+/// Statements allowed in any block.
+fn stmt() -> BoxedStrategy<Stmt> {
+    let simple = prop_oneof![
+        (name(), expr()).prop_map(|(name, value)| Stmt::Let { name, name_span: NO_SPAN, value, span: NO_SPAN }),
+        (name(), expr()).prop_map(|(name, value)| Stmt::Assign { name, name_span: NO_SPAN, value, span: NO_SPAN }),
+        expr().prop_map(|value| Stmt::Write(value, NO_SPAN)),
+        prop::option::of(expr()).prop_map(|value| Stmt::Return(value, NO_SPAN)),
+        call(expr()).prop_map(|value| Stmt::Expr(value, NO_SPAN)),
+    ];
+    simple.prop_recursive(3, 24, 3, |inner| {
+        let arm = (prop::collection::vec(pattern(), 1..3), block(inner.clone())).prop_map(|(patterns, body)| MatchArm { patterns, body });
+        prop_oneof![
+            (expr(), block(inner.clone()), prop::option::of(block(inner.clone()))).prop_map(|(condition, then_block, otherwise)| {
+                Stmt::If { condition, then_block, else_branch: otherwise.map(Else::Block), span: NO_SPAN }
+            }),
+            (expr(), block(inner.clone()), expr(), block(inner.clone())).prop_map(|(condition, then_block, nested, body)| {
+                let nested = Stmt::If { condition: nested, then_block: body, else_branch: None, span: NO_SPAN };
+                Stmt::If { condition, then_block, else_branch: Some(Else::If(Box::new(nested))), span: NO_SPAN }
+            }),
+            (expr(), block(inner)).prop_map(|(condition, body)| Stmt::While { condition, body, span: NO_SPAN }),
+            (expr(), prop::collection::vec(arm, 0..3)).prop_map(|(value, arms)| Stmt::Match { value, arms, span: NO_SPAN }),
+        ]
+    })
+    .boxed()
+}
+
+/// Statements, plus the definitions only allowed at the top level.
+fn program() -> impl Strategy<Value = Program> {
+    let default = prop_oneof![
+        number().prop_map(|value| Expr::Number(value, NO_SPAN)),
+        any::<String>().prop_map(|text| Expr::String(text, NO_SPAN)),
+    ];
+    let param = (name(), prop::option::of(default)).prop_map(|(name, default)| Param { name, span: NO_SPAN, default });
+    let function = (name(), prop::collection::vec(param, 0..3), block(stmt()))
+        .prop_map(|(name, params, body)| Stmt::FuncDef { name, name_span: NO_SPAN, params, body, span: NO_SPAN });
+    let variant = name().prop_map(|name| Variant { name, span: NO_SPAN });
+    let enumeration = (name(), prop::collection::vec(variant, 0..3))
+        .prop_map(|(name, variants)| Stmt::Enum { name, name_span: NO_SPAN, variants, span: NO_SPAN });
+    prop::collection::vec(prop_oneof![3 => stmt(), 1 => function, 1 => enumeration], 0..6).prop_map(|statements| Program { statements })
+}
+
+/// The program as source without spacing or comments. Spans are ignored, so this also tells
+/// whether two programs are the same once parsed.
+fn write_program(program: &Program) -> String {
+    let mut out = String::new();
+    write_statements(&mut out, &program.statements);
+    out
+}
+
+fn write_statements(out: &mut String, statements: &[Stmt]) {
+    for stmt in statements {
+        write_stmt(out, stmt);
+    }
+}
+
+fn write_block(out: &mut String, block: &Block) {
+    out.push('{');
+    write_statements(out, &block.statements);
+    out.push('}');
+}
+
+fn write_stmt(out: &mut String, stmt: &Stmt) {
+    match stmt {
+        Stmt::Let { name, value, .. } => {
+            write!(out, "let {}=", name).unwrap();
+            write_expr(out, value);
+        }
+        Stmt::Assign { name, value, .. } => {
+            write!(out, "{}=", name).unwrap();
+            write_expr(out, value);
+        }
+        Stmt::Write(value, _) => {
+            out.push_str("write ");
+            write_expr(out, value);
+        }
+        Stmt::Return(value, _) => {
+            out.push_str("return");
+            if let Some(value) = value {
+                out.push(' ');
+                write_expr(out, value);
+            }
+        }
+        Stmt::Expr(value, _) => write_expr(out, value),
+        Stmt::FuncDef { name, params, body, .. } => {
+            write!(out, "def {}(", name).unwrap();
+            for (index, param) in params.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(param.name.as_str());
+                if let Some(default) = &param.default {
+                    out.push('=');
+                    write_expr(out, default);
+                }
+            }
+            out.push(')');
+            write_block(out, body);
+            return;
+        }
+        Stmt::Enum { name, variants, .. } => {
+            write!(out, "enum {}{{", name).unwrap();
+            for variant in variants {
+                write!(out, "{},", variant.name).unwrap();
+            }
+            out.push('}');
+            return;
+        }
+        Stmt::If {
+            condition,
+            then_block,
+            else_branch,
+            ..
+        } => {
+            out.push_str("if ");
+            write_expr(out, condition);
+            write_block(out, then_block);
+            match else_branch {
+                Some(Else::If(nested)) => {
+                    out.push_str("else ");
+                    write_stmt(out, nested);
+                }
+                Some(Else::Block(block)) => {
+                    out.push_str("else");
+                    write_block(out, block);
+                }
+                None => {}
+            }
+            return;
+        }
+        Stmt::While { condition, body, .. } => {
+            out.push_str("while ");
+            write_expr(out, condition);
+            write_block(out, body);
+            return;
+        }
+        Stmt::Match { value, arms, .. } => {
+            out.push_str("match ");
+            write_expr(out, value);
+            out.push('{');
+            for arm in arms {
+                for (index, pattern) in arm.patterns.iter().enumerate() {
+                    if index > 0 {
+                        out.push(',');
+                    }
+                    match pattern {
+                        Pattern::Variant(enum_name, variant, _) => write!(out, "{}.{}", enum_name, variant).unwrap(),
+                        Pattern::Wildcard(_) => out.push('_'),
+                    }
+                }
+                out.push_str("=>");
+                write_block(out, &arm.body);
+            }
+            out.push('}');
+            return;
+        }
+    }
+    out.push(';');
+}
+
+fn write_expr(out: &mut String, expr: &Expr) {
+    match expr {
+        Expr::Number(value, _) => write!(out, "{}", value).unwrap(),
+        Expr::String(text, _) => {
+            out.push('"');
+            for ch in text.chars() {
+                match ch {
+                    '\n' => out.push_str("\\n"),
+                    '\t' => out.push_str("\\t"),
+                    '\r' => out.push_str("\\r"),
+                    '\0' => out.push_str("\\0"),
+                    '"' => out.push_str("\\\""),
+                    '\\' => out.push_str("\\\\"),
+                    _ => out.push(ch),
+                }
+            }
+            out.push('"');
+        }
+        Expr::Identifier(name, _) => out.push_str(name.as_str()),
+        Expr::Variant(enum_name, variant, _) => write!(out, "{}.{}", enum_name, variant).unwrap(),
+        Expr::Call(name, args, named, _) => {
+            write!(out, "{}(", name).unwrap();
+            for (index, arg) in args.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_expr(out, arg);
+            }
+            for (index, arg) in named.iter().enumerate() {
+                if index > 0 || !args.is_empty() {
+                    out.push(',');
+                }
+                write!(out, "{}=", arg.name).unwrap();
+                write_expr(out, &arg.value);
+            }
+            out.push(')');
+        }
+        Expr::Unary(op, operand, _) => {
+            write!(out, "{}(", op.symbol()).unwrap();
+            write_expr(out, operand);
+            out.push(')');
+        }
+        Expr::Binary(op, left, right, _) => {
+            out.push('(');
+            write_expr(out, left);
+            out.push_str(op.symbol());
+            write_expr(out, right);
+            out.push(')');
+        }
+        Expr::Index(value, index, _) => {
+            out.push('(');
+            write_expr(out, value);
+            out.push_str(")[");
+            write_expr(out, index);
+            out.push(']');
+        }
+        Expr::Slice(value, start, end, _) => {
+            out.push('(');
+            write_expr(out, value);
+            out.push_str(")[");
+            if let Some(start) = start {
+                write_expr(out, start);
+            }
+            out.push_str("..");
+            if let Some(end) = end {
+                write_expr(out, end);
+            }
+            out.push(']');
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn formatting_keeps_the_program_and_is_idempotent(program in program()) {
+        let src = write_program(&program);
+        let formatted = format_source(&src).map_err(|err| TestCaseError::fail(format!("{}\n{}", err, src)))?;
+        let (reparsed, _) = parse(&formatted).map_err(|err| TestCaseError::fail(format!("{}\n{}", err, formatted)))?;
+        prop_assert_eq!(write_program(&reparsed), src, "formatting changed the program:\n{}", formatted);
+        let again = format_source(&formatted).map_err(|err| TestCaseError::fail(err.to_string()))?;
+        prop_assert_eq!(again, formatted);
+    }
+}
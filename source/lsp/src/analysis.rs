@@ -0,0 +1,439 @@
+use std::collections::HashMap;
+
+use vira_core::ast::{BinOp, Block, Else, Expr, Param, Pattern, Program, Stmt, Variant};
+use vira_core::{Error, Span};
+
+/// An open file and everything the front end knows about it, recomputed on each change.
+pub struct Document {
+    pub text: String,
+    /// As much as parsed; statements with syntax errors are left out.
+    pub program: Program,
+    pub errors: Vec<Error>,
+}
+
+/// A name under the cursor, with where it was declared and what it holds if that is known.
+pub struct Symbol {
+    pub span: Span,
+    pub definition: Option<Span>,
+    pub detail: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Variable,
+    Enum,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        // A file with syntax errors keeps what parsed around them, for the outline and hovers, but is
+        // only checked once it parses, as the checker would trip over the statements left out
+        let (program, _, mut errors) = vira_core::parse_all(&text);
+        if errors.is_empty() {
+            errors = vira_core::check(&program);
+        }
+        Document {
+            text,
+            program,
+            errors,
+        }
+    }
+
+    /// Top-level functions, variables and enums as (name, kind, name span, whole statement span).
+    pub fn outline(&self) -> Vec<(&str, SymbolKind, Span, Span)> {
+        let mut outline = Vec::new();
+        for stmt in &self.program.statements {
+            match stmt {
+                Stmt::FuncDef { name, name_span, span, .. } => outline.push((name.as_str(), SymbolKind::Function, *name_span, *span)),
+                Stmt::Let { name, name_span, span, .. } => outline.push((name.as_str(), SymbolKind::Variable, *name_span, *span)),
+                Stmt::Enum { name, name_span, span, .. } => outline.push((name.as_str(), SymbolKind::Enum, *name_span, *span)),
+                _ => {}
+            }
+        }
+        outline
+    }
+
+    /// The function, variable, enum or variant named at `offset`.
+    pub fn symbol_at(&self, offset: usize) -> Option<Symbol> {
+        let program = &self.program;
+        let mut finder = Finder {
+            offset,
+            functions: HashMap::new(),
+            enums: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            found: None,
+        };
+        for stmt in &program.statements {
+            match stmt {
+                Stmt::FuncDef { name, name_span, params, .. } => {
+                    finder.functions.entry(name.as_str()).or_insert((*name_span, params.as_slice()));
+                }
+                Stmt::Enum { name, name_span, variants, .. } => {
+                    finder.enums.entry(name.as_str()).or_insert((*name_span, variants.as_slice()));
+                }
+                _ => {}
+            }
+        }
+        for stmt in &program.statements {
+            finder.stmt(stmt);
+            if finder.found.is_some() {
+                break;
+            }
+        }
+        finder.found
+    }
+}
+
+/// Type of a variable as far as a quick look at its initializer can tell.
+type Binding = (Span, Option<&'static str>);
+
+struct Finder<'a> {
+    offset: usize,
+    functions: HashMap<&'a str, (Span, &'a [Param])>,
+    enums: HashMap<&'a str, (Span, &'a [Variant])>,
+    scopes: Vec<HashMap<&'a str, Binding>>,
+    found: Option<Symbol>,
+}
+
+impl<'a> Finder<'a> {
+    fn contains(&self, span: Span) -> bool {
+        span.start <= self.offset && self.offset <= span.end
+    }
+
+    fn stmt(&mut self, stmt: &'a Stmt) {
+        if self.found.is_some() {
+            return;
+        }
+        if !self.contains(stmt.span()) {
+            // The cursor is elsewhere, but declarations stay visible to the statements after them
+            if let Stmt::Let { name, name_span, value, .. } = stmt {
+                let ty = self.infer(value);
+                self.declare(name.as_str(), *name_span, ty);
+            }
+            return;
+        }
+        match stmt {
+            Stmt::Let { name, name_span, value, .. } => {
+                self.expr(value);
+                let ty = self.infer(value);
+                if self.contains(*name_span) {
+                    self.variable(name.as_str(), *name_span, Some((*name_span, ty)));
+                }
+                self.declare(name.as_str(), *name_span, ty);
+            }
+            Stmt::Assign { name, name_span, value, .. } => {
+                if self.contains(*name_span) {
+                    let binding = self.lookup(name.as_str());
+                    self.variable(name.as_str(), *name_span, binding);
+                }
+                self.expr(value);
+            }
+            Stmt::FuncDef {
+                name,
+                name_span,
+                params,
+                body,
+                ..
+            } => {
+                if self.contains(*name_span) {
+                    self.function(name.as_str(), *name_span);
+                    return;
+                }
+                let scope = params.iter().map(|p| (p.name.as_str(), (p.span, None))).collect();
+                let outer = std::mem::replace(&mut self.scopes, vec![scope]);
+                if let Some(param) = params.iter().find(|p| self.contains(p.span)) {
+                    self.variable(param.name.as_str(), param.span, Some((param.span, None)));
+                }
+                self.block(body);
+                self.scopes = outer;
+            }
+            Stmt::Write(expr, _) | Stmt::Expr(expr, _) | Stmt::Return(Some(expr), _) => self.expr(expr),
+            Stmt::Return(None, _) => {}
+            Stmt::Enum { name, name_span, variants, .. } => {
+                if self.contains(*name_span) {
+                    self.enumeration(name.as_str(), *name_span);
+                } else if let Some(variant) = variants.iter().find(|v| self.contains(v.span)) {
+                    self.variant(name.as_str(), variant.name.as_str(), variant.span);
+                }
+            }
+            Stmt::Match { value, arms, .. } => {
+                self.expr(value);
+                for arm in arms {
+                    for pattern in &arm.patterns {
+                        if let Pattern::Variant(enum_name, variant, span) = pattern {
+                            if self.contains(*span) {
+                                self.variant(enum_name.as_str(), variant.as_str(), *span);
+                            }
+                        }
+                    }
+                    self.block(&arm.body);
+                }
+            }
+            Stmt::If {
+                condition,
+                then_block,
+                else_branch,
+                ..
+            } => {
+                self.expr(condition);
+                self.block(then_block);
+                match else_branch {
+                    Some(Else::If(stmt)) => self.stmt(stmt),
+                    Some(Else::Block(block)) => self.block(block),
+                    None => {}
+                }
+            }
+            Stmt::While { condition, body, .. } => {
+                self.expr(condition);
+                self.block(body);
+            }
+        }
+    }
+
+    fn block(&mut self, block: &'a Block) {
+        self.scopes.push(HashMap::new());
+        for stmt in &block.statements {
+            self.stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn expr(&mut self, expr: &'a Expr) {
+        if self.found.is_some() || !self.contains(expr.span()) {
+            return;
+        }
+        vira_core::ensure_stack(|| match expr {
+            Expr::Identifier(name, span) => {
+                let binding = self.lookup(name.as_str());
+                self.variable(name.as_str(), *span, binding);
+            }
+            Expr::Call(name, args, named, span) => {
+                // Only the name, not the parentheses
+                let name_span = Span::new(span.start, span.start + name.as_str().len());
+                if self.contains(name_span) {
+                    self.function(name.as_str(), name_span);
+                }
+                // A named argument leads to the parameter it is for
+                if let Some(arg) = named.iter().find(|arg| self.contains(arg.name_span)) {
+                    let param = self.functions.get(name.as_str()).and_then(|(_, params)| params.iter().find(|p| p.name == arg.name));
+                    self.variable(arg.name.as_str(), arg.name_span, param.map(|param| (param.span, None)));
+                }
+                for arg in args.iter().chain(named.iter().map(|arg| &arg.value)) {
+                    self.expr(arg);
+                }
+            }
+            Expr::Unary(_, operand, _) => self.expr(operand),
+            Expr::Binary(_, left, right, _) | Expr::Index(left, right, _) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Slice(value, start, end, _) => {
+                self.expr(value);
+                for bound in [start, end].into_iter().flatten() {
+                    self.expr(bound);
+                }
+            }
+            Expr::Variant(enum_name, variant, span) => self.variant(enum_name.as_str(), variant.as_str(), *span),
+            Expr::Number(..) | Expr::String(..) => {}
+        })
+    }
+
+    fn declare(&mut self, name: &'a str, span: Span, ty: Option<&'static str>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, (span, ty));
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name).copied())
+    }
+
+    fn variable(&mut self, name: &str, span: Span, binding: Option<Binding>) {
+        let ty = binding.and_then(|(_, ty)| ty).unwrap_or("unknown");
+        self.found = Some(Symbol {
+            span,
+            definition: binding.map(|(span, _)| span),
+            detail: format!("{}: {}", name, ty),
+        });
+    }
+
+    fn function(&mut self, name: &str, span: Span) {
+        let definition = self.functions.get(name).copied();
+        let detail = match definition {
+            Some((_, params)) => {
+                let params: Vec<String> = params
+                    .iter()
+                    .map(|p| match &p.default {
+                        Some(default) => format!("{} = {}", p.name, literal(default)),
+                        None => p.name.to_string(),
+                    })
+                    .collect();
+                format!("def {}({})", name, params.join(", "))
+            }
+            None => format!("{}: undefined function", name),
+        };
+        self.found = Some(Symbol {
+            span,
+            definition: definition.map(|(span, _)| span),
+            detail,
+        });
+    }
+
+    fn enumeration(&mut self, name: &str, span: Span) {
+        let definition = self.enums.get(name).copied();
+        let detail = match definition {
+            Some((_, variants)) => {
+                let variants: Vec<&str> = variants.iter().map(|v| v.name.as_str()).collect();
+                format!("enum {} {{ {} }}", name, variants.join(", "))
+            }
+            None => format!("{}: undefined enum", name),
+        };
+        self.found = Some(Symbol {
+            span,
+            definition: definition.map(|(span, _)| span),
+            detail,
+        });
+    }
+
+    /// `Enum.Variant` spanning `span`, which leads to the enum from its name and to the variant from
+    /// the rest.
+    fn variant(&mut self, enum_name: &str, variant: &str, span: Span) {
+        let name_span = Span::new(span.start, span.start + enum_name.len());
+        if self.contains(name_span) {
+            self.enumeration(enum_name, name_span);
+            return;
+        }
+        let found = self
+            .enums
+            .get(enum_name)
+            .and_then(|(_, variants)| variants.iter().enumerate().find(|(_, v)| v.name == variant));
+        self.found = Some(Symbol {
+            span,
+            definition: found.map(|(_, v)| v.span),
+            detail: match found {
+                Some((index, _)) => format!("{}.{} = {}", enum_name, variant, index),
+                None => format!("{}.{}: undefined variant", enum_name, variant),
+            },
+        });
+    }
+
+    /// `num` or `str` when it follows from literals and known variables, `None` otherwise.
+    fn infer(&self, expr: &Expr) -> Option<&'static str> {
+        vira_core::ensure_stack(|| match expr {
+            Expr::Number(..) | Expr::Unary(..) | Expr::Variant(..) => Some("num"),
+            Expr::String(..) | Expr::Index(..) | Expr::Slice(..) => Some("str"),
+            Expr::Identifier(name, _) => self.lookup(name.as_str()).and_then(|(_, ty)| ty),
+            Expr::Call(..) => None,
+            Expr::Binary(BinOp::Add, left, right, _) => match (self.infer(left), self.infer(right)) {
+                (Some("str"), _) | (_, Some("str")) => Some("str"),
+                (Some("num"), Some("num")) => Some("num"),
+                _ => None,
+            },
+            Expr::Binary(..) => Some("num"),
+        })
+    }
+}
+
+/// A parameter's default as written: a number, possibly negated, or a string.
+fn literal(expr: &Expr) -> String {
+    match expr {
+        Expr::Number(value, _) => value.to_string(),
+        Expr::String(value, _) => format!("{:?}", value),
+        Expr::Unary(op, operand, _) => format!("{}{}", op.symbol(), literal(operand)),
+        _ => "...".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+enum Color { Red, Green }
+def paint(c, times = 2) {
+    let label = \"paint\";
+    return c + times;
+}
+let color = Color.Green;
+let count = paint(color, times = 3);
+if count {
+    let count = \"inner\";
+    write count;
+}
+write count;
+";
+
+    /// Offset of the `nth` occurrence of `needle` in `SOURCE`, counting from 0.
+    fn offset(needle: &str, nth: usize) -> usize {
+        SOURCE.match_indices(needle).nth(nth).unwrap().0
+    }
+
+    /// Hover text of the symbol at the `nth` `needle`, and the text of its definition.
+    fn lookup(needle: &str, nth: usize) -> (String, Option<&'static str>) {
+        let symbol = Document::new(SOURCE.to_string()).symbol_at(offset(needle, nth)).unwrap();
+        (symbol.detail, symbol.definition.map(|span| &SOURCE[span.start..span.end]))
+    }
+
+    /// Offset where the definition of the symbol at the `nth` `needle` starts.
+    fn definition(needle: &str, nth: usize) -> usize {
+        Document::new(SOURCE.to_string()).symbol_at(offset(needle, nth)).unwrap().definition.unwrap().start
+    }
+
+    #[test]
+    fn a_valid_document_has_no_errors() {
+        let document = Document::new(SOURCE.to_string());
+        assert!(document.errors.is_empty(), "{:?}", document.errors.iter().map(|e| &e.message).collect::<Vec<_>>());
+        let names: Vec<_> = document.outline().into_iter().map(|(name, ..)| name).collect();
+        assert_eq!(names, ["Color", "paint", "color", "count"]);
+    }
+
+    #[test]
+    fn syntax_errors_keep_the_rest_of_the_document() {
+        let document = Document::new("let a = 1;\nlet b = ;\ndef f() { return a; }\nwrite missing;\n".to_string());
+        let codes: Vec<_> = document.errors.iter().map(|error| error.code).collect();
+        assert_eq!(codes, ["V0010"]);
+        // Only checked once it parses, so `missing` is not reported yet
+        let names: Vec<_> = document.outline().into_iter().map(|(name, ..)| name).collect();
+        assert_eq!(names, ["a", "f"]);
+    }
+
+    #[test]
+    fn a_document_that_parses_is_checked() {
+        let text = "let a = 1;\nwrite missing;\n";
+        let document = Document::new(text.to_string());
+        let errors: Vec<_> = document.errors.iter().map(|error| (error.code, &text[error.span.start..error.span.end])).collect();
+        assert_eq!(errors, [("V0102", "missing")]);
+    }
+
+    #[test]
+    fn hovers_describe_what_a_name_holds() {
+        assert_eq!(lookup("paint", 2), ("def paint(c, times = 2)".to_string(), Some("paint")));
+        assert_eq!(lookup("color", 1), ("color: num".to_string(), Some("color")));
+        assert_eq!(lookup("label", 0), ("label: str".to_string(), Some("label")));
+        assert_eq!(lookup("Color", 1), ("enum Color { Red, Green }".to_string(), Some("Color")));
+        assert_eq!(lookup("Green", 1), ("Color.Green = 1".to_string(), Some("Green")));
+    }
+
+    #[test]
+    fn definitions_follow_scopes() {
+        // A parameter, from inside the function
+        assert_eq!(definition("c +", 0), offset("c, times", 0));
+        // A named argument leads to its parameter
+        assert_eq!(definition("times = 3", 0), offset("times = 2", 0));
+        // The inner `count` shadows the outer one only inside its block
+        assert_eq!(definition("count;", 0), offset("count = \"inner\"", 0));
+        assert_eq!(definition("count;", 1), offset("count = paint", 0));
+        assert_eq!(definition("count {", 0), offset("count = paint", 0));
+    }
+
+    #[test]
+    fn unknown_names_have_no_definition() {
+        let document = Document::new("write nothing + Shade.Dark;\n".to_string());
+        let symbol = document.symbol_at(7).unwrap();
+        assert_eq!((symbol.detail.as_str(), symbol.definition), ("nothing: unknown", None));
+        let symbol = document.symbol_at(18).unwrap();
+        assert_eq!((symbol.detail.as_str(), symbol.definition), ("Shade: undefined enum", None));
+        assert!(document.symbol_at(14).is_none());
+    }
+}
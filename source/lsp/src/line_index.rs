@@ -0,0 +1,43 @@
+use tower_lsp::lsp_types::{Position, Range};
+use vira_core::Span;
+
+/// Converts between byte offsets and LSP positions, whose columns count UTF-16 code units.
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { line_starts }
+    }
+
+    pub fn position(&self, text: &str, offset: usize) -> Position {
+        let offset = offset.min(text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let character = text[start..offset].encode_utf16().count();
+        Position::new(line as u32, character as u32)
+    }
+
+    /// Byte offset of `position`, clamped to the end of its line.
+    pub fn offset(&self, text: &str, position: Position) -> usize {
+        let Some(&start) = self.line_starts.get(position.line as usize) else {
+            return text.len();
+        };
+        let end = self.line_starts.get(position.line as usize + 1).map_or(text.len(), |&next| next - 1);
+        let mut units = 0;
+        for (i, ch) in text[start..end].char_indices() {
+            if units >= position.character as usize {
+                return start + i;
+            }
+            units += ch.len_utf16();
+        }
+        end
+    }
+
+    pub fn range(&self, text: &str, span: Span) -> Range {
+        Range::new(self.position(text, span.start), self.position(text, span.end))
+    }
+}
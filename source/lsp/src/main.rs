@@ -0,0 +1,188 @@
+mod analysis;
+mod line_index;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types::*;
+use tower_lsp::{Client, LanguageServer, LspService, Server};
+
+use analysis::Document;
+use line_index::LineIndex;
+
+struct Backend {
+    client: Client,
+    documents: Mutex<HashMap<Url, Document>>,
+}
+
+impl Backend {
+    /// Re-analyzes `uri` and publishes its diagnostics.
+    async fn update(&self, uri: Url, text: String, version: Option<i32>) {
+        let document = Document::new(text);
+        let diagnostics = diagnostics(&uri, &document);
+        self.documents.lock().unwrap().insert(uri.clone(), document);
+        self.client.publish_diagnostics(uri, diagnostics, version).await;
+    }
+
+    /// Runs `f` on the document at `position`, passing the byte offset of the position.
+    fn with_document<T>(&self, position: &TextDocumentPositionParams, f: impl FnOnce(&Document, &LineIndex, usize) -> Option<T>) -> Option<T> {
+        let documents = self.documents.lock().unwrap();
+        let document = documents.get(&position.text_document.uri)?;
+        let index = LineIndex::new(&document.text);
+        let offset = index.offset(&document.text, position.position);
+        f(document, &index, offset)
+    }
+}
+
+fn diagnostics(uri: &Url, document: &Document) -> Vec<Diagnostic> {
+    let index = LineIndex::new(&document.text);
+    document
+        .errors
+        .iter()
+        .map(|err| {
+            let mut message = err.message.clone();
+            if let Some(help) = &err.help {
+                message.push_str("\nhelp: ");
+                message.push_str(help);
+            }
+            let related = err
+                .labels
+                .iter()
+                .map(|(span, text)| DiagnosticRelatedInformation {
+                    location: Location::new(uri.clone(), index.range(&document.text, *span)),
+                    message: text.clone(),
+                })
+                .collect::<Vec<_>>();
+            Diagnostic {
+                range: index.range(&document.text, err.span),
+                severity: Some(DiagnosticSeverity::ERROR),
+                code: Some(NumberOrString::String(err.code.to_string())),
+                source: Some("vira".to_string()),
+                message,
+                related_information: (!related.is_empty()).then_some(related),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+#[tower_lsp::async_trait]
+impl LanguageServer for Backend {
+    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+        Ok(InitializeResult {
+            capabilities: ServerCapabilities {
+                text_document_sync: Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
+                    open_close: Some(true),
+                    change: Some(TextDocumentSyncKind::FULL),
+                    save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
+                        include_text: Some(true),
+                    })),
+                    ..Default::default()
+                })),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                definition_provider: Some(OneOf::Left(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                ..Default::default()
+            },
+            server_info: Some(ServerInfo {
+                name: "vira-lsp".to_string(),
+                version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            }),
+        })
+    }
+
+    async fn initialized(&self, _: InitializedParams) {
+        self.client.log_message(MessageType::INFO, "vira-lsp initialized").await;
+    }
+
+    async fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn did_open(&self, params: DidOpenTextDocumentParams) {
+        let doc = params.text_document;
+        self.update(doc.uri, doc.text, Some(doc.version)).await;
+    }
+
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
+        // Full sync: the last change holds the whole text
+        if let Some(change) = params.content_changes.into_iter().last() {
+            let doc = params.text_document;
+            self.update(doc.uri, change.text, Some(doc.version)).await;
+        }
+    }
+
+    async fn did_save(&self, params: DidSaveTextDocumentParams) {
+        if let Some(text) = params.text {
+            self.update(params.text_document.uri, text, None).await;
+        }
+    }
+
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.documents.lock().unwrap().remove(&uri);
+        self.client.publish_diagnostics(uri, Vec::new(), None).await;
+    }
+
+    async fn goto_definition(&self, params: GotoDefinitionParams) -> Result<Option<GotoDefinitionResponse>> {
+        let position = params.text_document_position_params;
+        Ok(self.with_document(&position, |document, index, offset| {
+            let span = document.symbol_at(offset)?.definition?;
+            let location = Location::new(position.text_document.uri.clone(), index.range(&document.text, span));
+            Some(GotoDefinitionResponse::Scalar(location))
+        }))
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        Ok(self.with_document(&params.text_document_position_params, |document, index, offset| {
+            let symbol = document.symbol_at(offset)?;
+            Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: format!("```vira\n{}\n```", symbol.detail),
+                }),
+                range: Some(index.range(&document.text, symbol.span)),
+            })
+        }))
+    }
+
+    async fn document_symbol(&self, params: DocumentSymbolParams) -> Result<Option<DocumentSymbolResponse>> {
+        let documents = self.documents.lock().unwrap();
+        let Some(document) = documents.get(&params.text_document.uri) else {
+            return Ok(None);
+        };
+        let index = LineIndex::new(&document.text);
+        #[allow(deprecated)] // `deprecated` is a required field even though LSP replaced it with tags
+        let symbols = document
+            .outline()
+            .into_iter()
+            .map(|(name, kind, name_span, span)| DocumentSymbol {
+                name: name.to_string(),
+                detail: None,
+                kind: match kind {
+                    analysis::SymbolKind::Function => SymbolKind::FUNCTION,
+                    analysis::SymbolKind::Variable => SymbolKind::VARIABLE,
+                    analysis::SymbolKind::Enum => SymbolKind::ENUM,
+                },
+                tags: None,
+                deprecated: None,
+                range: index.range(&document.text, span),
+                selection_range: index.range(&document.text, name_span),
+                children: None,
+            })
+            .collect();
+        Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let stdin = tokio::io::stdin();
+    let stdout = tokio::io::stdout();
+    let (service, socket) = LspService::new(|client| Backend {
+        client,
+        documents: Mutex::new(HashMap::new()),
+    });
+    Server::new(stdin, stdout, socket).serve(service).await;
+}
@@ -5,6 +5,12 @@
 #include <map>
 #include <cctype>
 #include <stdexcept>
+#include <algorithm>
+
+// Messages carry the stable code documented by `diagnostic --explain`
+std::runtime_error codedError(const std::string& code, const std::string& message) {
+    return std::runtime_error("error[" + code + "]: " + message);
+}
 
 enum class TokenType {
     Identifier,
@@ -49,7 +55,7 @@ public:
             advance();
             return {TokenType::Punctuator, std::string(1, ch), line, column - 1};
         } else {
-            throw std::runtime_error("Unexpected character: " + std::string(1, ch));
+            throw codedError("V0001", "Unexpected character: " + std::string(1, ch));
         }
     }
 
@@ -104,6 +110,9 @@ private:
             s += currentChar();
             advance();
         }
+        if (position >= input.size()) {
+            throw codedError("V0002", "Unterminated string");
+        }
         advance(); // skip closing "
         return {TokenType::StringLiteral, s, line, start_col};
     }
@@ -133,39 +142,137 @@ class Parser {
 private:
     Lexer lexer;
     Token currentToken;
+    // Everything that would have been accepted at the current token, reset on every advance
+    std::vector<std::string> expected;
+    // Unclosed '{' tokens, innermost last, so errors can point back at the block they are in
+    std::vector<Token> openBraces;
+    std::vector<std::string> errors;
+
+    static std::string describe(TokenType type, const std::string& value) {
+        if (!value.empty()) {
+            return "'" + value + "'";
+        }
+        switch (type) {
+            case TokenType::Identifier: return "identifier";
+            case TokenType::Keyword: return "keyword";
+            case TokenType::Number: return "number";
+            case TokenType::StringLiteral: return "string";
+            case TokenType::Punctuator: return "punctuator";
+            case TokenType::EOFToken: return "end of file";
+        }
+        return "token";
+    }
+
+    static std::string describe(const Token& token) {
+        if (token.type == TokenType::EOFToken) {
+            return "end of file";
+        }
+        if (token.type == TokenType::StringLiteral) {
+            return "string \"" + token.value + "\"";
+        }
+        return "'" + token.value + "'";
+    }
+
+    static std::string oneOf(const std::vector<std::string>& options) {
+        std::string out;
+        for (size_t i = 0; i < options.size(); i++) {
+            if (i > 0) {
+                out += i + 1 == options.size() ? " or " : ", ";
+            }
+            out += options[i];
+        }
+        return out;
+    }
 
-    void eat(TokenType expectedType, const std::string& expectedValue = "") {
-        if (currentToken.type == expectedType &&
-            (expectedValue.empty() || currentToken.value == expectedValue)) {
-            currentToken = lexer.nextToken();
+    void advance() {
+        if (currentToken.type == TokenType::Punctuator) {
+            if (currentToken.value == "{") {
+                openBraces.push_back(currentToken);
+            } else if (currentToken.value == "}" && !openBraces.empty()) {
+                openBraces.pop_back();
+            }
+        }
+        expected.clear();
+        currentToken = lexer.nextToken();
+    }
+
+    // Records the token as acceptable here and reports whether the current token is it
+    bool check(TokenType type, const std::string& value = "") {
+        std::string description = describe(type, value);
+        if (std::find(expected.begin(), expected.end(), description) == expected.end()) {
+            expected.push_back(description);
+        }
+        return currentToken.type == type && (value.empty() || currentToken.value == value);
+    }
+
+    std::runtime_error syntaxError(const std::string& context) {
+        std::string message = "expected " + oneOf(expected);
+        if (!context.empty()) {
+            message += " " + context;
+        }
+        message += ", found " + describe(currentToken) + " at line " + std::to_string(currentToken.line) +
+                   ", column " + std::to_string(currentToken.column);
+        if (!openBraces.empty()) {
+            const Token& brace = openBraces.back();
+            message += "\nnote: inside the block opened by '{' at line " + std::to_string(brace.line) +
+                       ", column " + std::to_string(brace.column);
+        }
+        return codedError("V0010", message);
+    }
+
+    void eat(TokenType expectedType, const std::string& expectedValue = "", const std::string& context = "") {
+        if (check(expectedType, expectedValue)) {
+            advance();
         } else {
-            throw std::runtime_error("Syntax error at line " + std::to_string(currentToken.line) +
-                                     ", column " + std::to_string(currentToken.column));
+            throw syntaxError(context);
+        }
+    }
+
+    // Skips to just past the next ';', or up to the '}' closing the current block, so one bad
+    // statement does not hide errors in the ones after it
+    void synchronize(size_t depth) {
+        while (currentToken.type != TokenType::EOFToken) {
+            if (currentToken.type == TokenType::Punctuator) {
+                if (currentToken.value == ";" && openBraces.size() == depth) {
+                    advance();
+                    return;
+                }
+                if (currentToken.value == "}" && openBraces.size() == depth) {
+                    return;
+                }
+            }
+            advance();
         }
     }
 
-    ASTNode* parsePrimary() {
-        if (currentToken.type == TokenType::Number) {
+    ASTNode* parsePrimary(const std::string& context) {
+        if (check(TokenType::Number)) {
             ASTNode* node = new ASTNode{ASTType::NumberLiteral, currentToken.value};
-            eat(TokenType::Number);
+            advance();
             return node;
-        } else if (currentToken.type == TokenType::Identifier) {
+        } else if (check(TokenType::Identifier)) {
             ASTNode* node = new ASTNode{ASTType::Identifier, currentToken.value};
-            eat(TokenType::Identifier);
+            advance();
             return node;
         } else {
-            throw std::runtime_error("Unexpected token in primary");
+            throw syntaxError(context);
         }
     }
 
-    ASTNode* parseExpr() {
-        ASTNode* node = parsePrimary();
-        while (currentToken.type == TokenType::Punctuator &&
-               (currentToken.value == "+" || currentToken.value == "-" ||
-                currentToken.value == "*" || currentToken.value == "/")) {
+    bool checkOperator() {
+        bool found = false;
+        for (const char* op : {"+", "-", "*", "/"}) {
+            found = check(TokenType::Punctuator, op) || found;
+        }
+        return found;
+    }
+
+    ASTNode* parseExpr(const std::string& context) {
+        ASTNode* node = parsePrimary(context);
+        while (checkOperator()) {
             std::string op = currentToken.value;
-            eat(TokenType::Punctuator, op);
-            ASTNode* right = parsePrimary();
+            advance();
+            ASTNode* right = parsePrimary("after '" + op + "'");
             ASTNode* newNode = new ASTNode{ASTType::BinaryOp, op};
             newNode->children.push_back(node);
             newNode->children.push_back(right);
@@ -175,30 +282,43 @@ private:
     }
 
     ASTNode* parseStatement() {
-        if (currentToken.type == TokenType::Keyword && currentToken.value == "return") {
-            eat(TokenType::Keyword, "return");
-            ASTNode* expr = parseExpr();
-            eat(TokenType::Punctuator, ";");
+        if (check(TokenType::Keyword, "return")) {
+            advance();
+            ASTNode* expr = parseExpr("after 'return'");
+            eat(TokenType::Punctuator, ";", "after return value");
             ASTNode* node = new ASTNode{ASTType::ReturnStmt, ""};
             node->children.push_back(expr);
             return node;
         } else {
-            throw std::runtime_error("Unsupported statement");
+            check(TokenType::Punctuator, "}");
+            throw codedError("V0011", "Unsupported statement: " + syntaxErrorText("at the start of a statement"));
         }
     }
 
+    std::string syntaxErrorText(const std::string& context) {
+        std::string text = syntaxError(context).what();
+        return text.substr(text.find(": ") + 2);
+    }
+
     ASTNode* parseFunction() {
-        eat(TokenType::Keyword, "int");
+        eat(TokenType::Keyword, "int", "at the start of a function");
         std::string name = currentToken.value;
-        eat(TokenType::Identifier);
-        eat(TokenType::Punctuator, "(");
-        eat(TokenType::Punctuator, ")");
-        eat(TokenType::Punctuator, "{");
+        eat(TokenType::Identifier, "", "after 'int'");
+        eat(TokenType::Punctuator, "(", "after function name");
+        eat(TokenType::Punctuator, ")", "to close the parameter list");
+        eat(TokenType::Punctuator, "{", "before function body");
+        size_t depth = openBraces.size();
         ASTNode* node = new ASTNode{ASTType::Function, name};
-        while (currentToken.type != TokenType::Punctuator || currentToken.value != "}") {
-            node->children.push_back(parseStatement());
+        while (currentToken.type != TokenType::EOFToken &&
+               (currentToken.type != TokenType::Punctuator || currentToken.value != "}")) {
+            try {
+                node->children.push_back(parseStatement());
+            } catch (const std::runtime_error& e) {
+                errors.push_back(e.what());
+                synchronize(depth);
+            }
         }
-        eat(TokenType::Punctuator, "}");
+        eat(TokenType::Punctuator, "}", "to close the body of '" + name + "'");
         return node;
     }
 
@@ -208,22 +328,79 @@ public:
     ASTNode* parse() {
         ASTNode* program = new ASTNode{ASTType::Program, ""};
         while (currentToken.type != TokenType::EOFToken) {
-            program->children.push_back(parseFunction());
+            try {
+                program->children.push_back(parseFunction());
+            } catch (const std::runtime_error& e) {
+                errors.push_back(e.what());
+                // Resume at the next function definition outside any block
+                while (currentToken.type != TokenType::EOFToken &&
+                       !(openBraces.empty() && currentToken.type == TokenType::Keyword && currentToken.value == "int")) {
+                    advance();
+                }
+            }
+        }
+        if (!errors.empty()) {
+            std::string all;
+            for (const auto& error : errors) {
+                all += (all.empty() ? "" : "\n") + error;
+            }
+            delete program;
+            throw std::runtime_error(all);
         }
         return program;
     }
 };
 
+// Edit distance counting an adjacent transposition as a single edit, so "mian" is close to "main"
+size_t editDistance(const std::string& a, const std::string& b) {
+    std::vector<std::vector<size_t>> d(a.size() + 1, std::vector<size_t>(b.size() + 1));
+    for (size_t i = 0; i <= a.size(); i++) {
+        d[i][0] = i;
+    }
+    for (size_t j = 0; j <= b.size(); j++) {
+        d[0][j] = j;
+    }
+    for (size_t i = 1; i <= a.size(); i++) {
+        for (size_t j = 1; j <= b.size(); j++) {
+            size_t cost = a[i - 1] == b[j - 1] ? 0 : 1;
+            d[i][j] = std::min({d[i - 1][j] + 1, d[i][j - 1] + 1, d[i - 1][j - 1] + cost});
+            if (i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1]) {
+                d[i][j] = std::min(d[i][j], d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    return d[a.size()][b.size()];
+}
+
 class SemanticChecker {
 private:
     std::map<std::string, std::string> symbolTable; // Simple type table
 
+    // Closest name in scope, or empty if nothing is near enough to be a likely typo
+    std::string suggestName(const std::string& name) const {
+        std::string best;
+        size_t bestDistance = std::max<size_t>(1, name.size() / 3) + 1;
+        for (const auto& entry : symbolTable) {
+            size_t distance = editDistance(name, entry.first);
+            if (distance < bestDistance) {
+                bestDistance = distance;
+                best = entry.first;
+            }
+        }
+        return best;
+    }
+
     void checkExpr(ASTNode* node) {
         if (node->type == ASTType::NumberLiteral) {
             // OK
         } else if (node->type == ASTType::Identifier) {
             if (symbolTable.find(node->value) == symbolTable.end()) {
-                throw std::runtime_error("Undefined identifier: " + node->value);
+                std::string message = "Undefined identifier: " + node->value;
+                std::string suggestion = suggestName(node->value);
+                if (!suggestion.empty()) {
+                    message += "\nhelp: did you mean '" + suggestion + "'?";
+                }
+                throw codedError("V0102", message);
             }
         } else if (node->type == ASTType::BinaryOp) {
             if (node->children.size() != 2) {
@@ -263,6 +440,9 @@ public:
         if (program->type != ASTType::Program) {
             throw std::runtime_error("Expected program");
         }
+        for (auto func : program->children) {
+            symbolTable[func->value] = "function";
+        }
         for (auto func : program->children) {
             checkFunction(func);
         }
@@ -296,7 +476,7 @@ int main(int argc, char* argv[]) {
 
         delete ast;
     } catch (const std::exception& e) {
-        std::cerr << "Error: " << e.what() << std::endl;
+        std::cerr << e.what() << std::endl;
         return 1;
     }
 
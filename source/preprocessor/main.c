@@ -45,7 +45,7 @@ char *find_define(const char *name) {
 
 void add_define(const char *name, const char *value) {
     if (num_defines >= MAX_DEFINES) {
-        fprintf(stderr, "Too many defines\n");
+        fprintf(stderr, "error[V0504]: Too many defines\n");
         exit(1);
     }
     defines[num_defines].name = strdup(name);
@@ -90,17 +90,17 @@ void process_directive(char *line, FILE *output) {
         char *filename = directive + 1;
         char *end = strchr(filename, system ? '>' : '"');
         if (!end) {
-            fprintf(stderr, "Invalid include\n");
+            fprintf(stderr, "error[V0501]: Invalid include\n");
             exit(1);
         }
         *end = '\0';
         FILE *fp = open_include(filename, system);
         if (!fp) {
-            fprintf(stderr, "Cannot open include: %s\n", filename);
+            fprintf(stderr, "error[V0502]: Cannot open include: %s\n", filename);
             exit(1);
         }
         if (include_depth >= MAX_INCLUDE_DEPTH) {
-            fprintf(stderr, "Include depth exceeded\n");
+            fprintf(stderr, "error[V0503]: Include depth exceeded\n");
             exit(1);
         }
         include_stack[include_depth] = fp;
@@ -165,7 +165,8 @@ void preprocess(FILE *input, FILE *output, const char *filename) {
             if (include_depth > 0) {
                 fclose(include_stack[--include_depth]);
                 free(include_filenames[include_depth]);
-                input = include_depth > 0 ? include_stack[include_depth - 1] : stdin; // Assuming main input is stdin for simplicity
+                if (include_depth == 0) break;
+                input = include_stack[include_depth - 1];
                 continue;
             }
             break;
@@ -186,13 +187,15 @@ int main(int argc, char *argv[]) {
         return 1;
     }
 
-    FILE *input = fopen(argv[1], "r");
+    // "-" is standard input or output, so vira can pipe a program through without temporary files
+    int from_stdin = strcmp(argv[1], "-") == 0;
+    FILE *input = from_stdin ? stdin : fopen(argv[1], "r");
     if (!input) {
         fprintf(stderr, "Cannot open input: %s\n", argv[1]);
         return 1;
     }
 
-    FILE *output = fopen(argv[2], "w");
+    FILE *output = strcmp(argv[2], "-") == 0 ? stdout : fopen(argv[2], "w");
     if (!output) {
         fprintf(stderr, "Cannot open output: %s\n", argv[2]);
         fclose(input);
@@ -200,7 +203,7 @@ int main(int argc, char *argv[]) {
     }
 
     include_stack[0] = input;
-    include_filenames[0] = strdup(argv[1]);
+    include_filenames[0] = strdup(from_stdin ? "<stdin>" : argv[1]);
     include_depth = 1;
 
     preprocess(input, output, argv[1]);
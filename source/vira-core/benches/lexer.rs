@@ -0,0 +1,52 @@
+//! Lexing and parsing throughput on generated sources of a few megabytes.
+//!
+//! Run with `cargo bench`; criterion compares each run against the previous one.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use vira_core::{parse, Lexer};
+
+/// A function, a loop and some strings, so every kind of token shows up.
+const CHUNK: &str = r#"// Sums the first n numbers
+def sum_to(n) {
+    let total = 0;
+    let i = 1;
+    while i <= n {
+        total = total + i;
+        i = i + 1;
+    }
+    return total;
+}
+let label = "sum: ";
+let escaped = "tab\there, quote \" there";
+if sum_to(10) == 55 && label != "" {
+    write label + "ok";
+} else {
+    write escaped;
+}
+"#;
+
+/// About `megabytes` of source. Function names get a suffix so the program stays valid.
+fn source(megabytes: usize) -> String {
+    let mut source = String::new();
+    let mut index = 0;
+    while source.len() < megabytes << 20 {
+        source.push_str(&CHUNK.replace("sum_to", &format!("sum_to_{}", index)));
+        index += 1;
+    }
+    source
+}
+
+fn lexer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lexer");
+    group.sample_size(20);
+    for megabytes in [1, 4] {
+        let input = source(megabytes);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::new("tokenize", megabytes), &input, |b, input| b.iter(|| Lexer::tokenize(input).unwrap()));
+        group.bench_with_input(BenchmarkId::new("parse", megabytes), &input, |b, input| b.iter(|| parse(input).unwrap()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lexer);
+criterion_main!(benches);
@@ -0,0 +1,387 @@
+//! Random valid programs, written out with every operator parenthesized and no spacing, through
+//! the formatter. Its output must parse to the same program, and formatting it again must not
+//! change it.
+#![no_main]
+
+use std::fmt::Write;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use vira_core::ast::{Block, Else, Expr, Pattern, Stmt};
+
+/// Names that are never keywords, with some overlap so uses can find declarations.
+const NAMES: &[&str] = &["a", "b", "x", "total", "f", "g_2"];
+const OPERATORS: &[&str] = &["+", "-", "*", "/", "%", "==", "!=", "<", "<=", ">", ">=", "&&", "||"];
+
+#[derive(Arbitrary, Debug)]
+struct Program {
+    enums: Vec<(u8, Vec<u8>)>,
+    functions: Vec<Function>,
+    statements: Vec<Statement>,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Function {
+    name: u8,
+    params: Vec<(u8, Option<Default>)>,
+    body: Vec<Statement>,
+}
+
+#[derive(Arbitrary, Debug)]
+enum Default {
+    Integer(i32),
+    String(String),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Statement {
+    Let(u8, Expression),
+    Assign(u8, Expression),
+    Write(Expression),
+    Return(Option<Expression>),
+    Call(u8, Vec<Expression>, Vec<(u8, Expression)>),
+    If {
+        condition: Expression,
+        then: Vec<Statement>,
+        else_ifs: Vec<(Expression, Vec<Statement>)>,
+        otherwise: Option<Vec<Statement>>,
+    },
+    While(Expression, Vec<Statement>),
+    /// Each arm has at least one pattern; `None` is `_`.
+    Match(Expression, Vec<((u8, u8), Vec<Option<(u8, u8)>>, Vec<Statement>)>),
+}
+
+#[derive(Arbitrary, Debug)]
+enum Expression {
+    Integer(u32),
+    /// Thousandths, so fractions and their printing get exercised.
+    Fraction(u32),
+    String(String),
+    /// Written as `r"..."`, without its quotes.
+    RawString(String),
+    /// Written as a `"""` string with its line breaks as they are.
+    LongString(String),
+    Variable(u8),
+    Variant(u8, u8),
+    Call(u8, Vec<Expression>, Vec<(u8, Expression)>),
+    Negate(Box<Expression>),
+    Not(Box<Expression>),
+    Binary(u8, Box<Expression>, Box<Expression>),
+    Index(Box<Expression>, Box<Expression>),
+    Slice(Box<Expression>, Option<Box<Expression>>, Option<Box<Expression>>),
+}
+
+fn name(index: u8) -> &'static str {
+    NAMES[index as usize % NAMES.len()]
+}
+
+fn write_program(out: &mut String, program: &Program) {
+    for (enum_name, variants) in &program.enums {
+        write!(out, "enum {}{{", name(*enum_name)).unwrap();
+        for variant in variants {
+            write!(out, "{},", name(*variant)).unwrap();
+        }
+        out.push('}');
+    }
+    for function in &program.functions {
+        write!(out, "def {}(", name(function.name)).unwrap();
+        for (index, (param, default)) in function.params.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            out.push_str(name(*param));
+            match default {
+                Some(Default::Integer(value)) => write!(out, "={}", value).unwrap(),
+                Some(Default::String(text)) => {
+                    out.push('=');
+                    write_string(out, text);
+                }
+                None => {}
+            }
+        }
+        out.push_str("){");
+        write_statements(out, &function.body);
+        out.push('}');
+    }
+    write_statements(out, &program.statements);
+}
+
+fn write_statements(out: &mut String, statements: &[Statement]) {
+    for statement in statements {
+        write_statement(out, statement);
+    }
+}
+
+fn write_block(out: &mut String, statements: &[Statement]) {
+    out.push('{');
+    write_statements(out, statements);
+    out.push('}');
+}
+
+fn write_statement(out: &mut String, statement: &Statement) {
+    match statement {
+        Statement::Let(variable, value) => {
+            write!(out, "let {}=", name(*variable)).unwrap();
+            write_expression(out, value);
+        }
+        Statement::Assign(variable, value) => {
+            write!(out, "{}=", name(*variable)).unwrap();
+            write_expression(out, value);
+        }
+        Statement::Write(value) => {
+            out.push_str("write ");
+            write_expression(out, value);
+        }
+        Statement::Return(value) => {
+            out.push_str("return");
+            if let Some(value) = value {
+                out.push(' ');
+                write_expression(out, value);
+            }
+        }
+        Statement::Call(function, args, named) => write_call(out, *function, args, named),
+        Statement::If {
+            condition,
+            then,
+            else_ifs,
+            otherwise,
+        } => {
+            out.push_str("if ");
+            write_expression(out, condition);
+            write_block(out, then);
+            for (condition, body) in else_ifs {
+                out.push_str("else if ");
+                write_expression(out, condition);
+                write_block(out, body);
+            }
+            if let Some(body) = otherwise {
+                out.push_str("else");
+                write_block(out, body);
+            }
+            return;
+        }
+        Statement::While(condition, body) => {
+            out.push_str("while ");
+            write_expression(out, condition);
+            write_block(out, body);
+            return;
+        }
+        Statement::Match(value, arms) => {
+            out.push_str("match ");
+            write_expression(out, value);
+            out.push('{');
+            for (first, rest, body) in arms {
+                let patterns = std::iter::once(Some(*first)).chain(rest.iter().copied());
+                for (index, pattern) in patterns.enumerate() {
+                    if index > 0 {
+                        out.push(',');
+                    }
+                    match pattern {
+                        Some((enum_name, variant)) => write!(out, "{}.{}", name(enum_name), name(variant)).unwrap(),
+                        None => out.push('_'),
+                    }
+                }
+                out.push_str("=>");
+                write_block(out, body);
+            }
+            out.push('}');
+            return;
+        }
+    }
+    out.push(';');
+}
+
+fn write_call(out: &mut String, function: u8, args: &[Expression], named: &[(u8, Expression)]) {
+    write!(out, "{}(", name(function)).unwrap();
+    let named = named.iter().map(|(param, arg)| (Some(*param), arg));
+    for (index, (param, arg)) in args.iter().map(|arg| (None, arg)).chain(named).enumerate() {
+        if index > 0 {
+            out.push(',');
+        }
+        if let Some(param) = param {
+            write!(out, "{}=", name(param)).unwrap();
+        }
+        write_expression(out, arg);
+    }
+    out.push(')');
+}
+
+fn write_string(out: &mut String, text: &str) {
+    out.push('"');
+    write_escaped(out, text, false);
+    out.push('"');
+}
+
+fn write_escaped(out: &mut String, text: &str, long: bool) {
+    for ch in text.chars() {
+        match ch {
+            '\n' if long => out.push('\n'),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\0' => out.push_str("\\0"),
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+fn write_expression(out: &mut String, expression: &Expression) {
+    match expression {
+        Expression::Integer(value) => write!(out, "{}", value).unwrap(),
+        Expression::Fraction(value) => write!(out, "{}", f64::from(*value) / 1000.0).unwrap(),
+        Expression::String(text) => write_string(out, text),
+        Expression::RawString(text) => write!(out, "r\"{}\"", text.replace('"', "")).unwrap(),
+        Expression::LongString(text) => {
+            out.push_str("\"\"\"");
+            write_escaped(out, text, true);
+            out.push_str("\"\"\"");
+        }
+        Expression::Variable(variable) => out.push_str(name(*variable)),
+        Expression::Variant(enum_name, variant) => write!(out, "{}.{}", name(*enum_name), name(*variant)).unwrap(),
+        Expression::Call(function, args, named) => write_call(out, *function, args, named),
+        Expression::Negate(operand) | Expression::Not(operand) => {
+            out.push_str(if matches!(expression, Expression::Negate(_)) { "-(" } else { "!(" });
+            write_expression(out, operand);
+            out.push(')');
+        }
+        Expression::Binary(op, left, right) => {
+            out.push('(');
+            write_expression(out, left);
+            out.push_str(OPERATORS[*op as usize % OPERATORS.len()]);
+            write_expression(out, right);
+            out.push(')');
+        }
+        Expression::Index(value, index) => {
+            out.push('(');
+            write_expression(out, value);
+            out.push_str(")[");
+            write_expression(out, index);
+            out.push(']');
+        }
+        Expression::Slice(value, start, end) => {
+            out.push('(');
+            write_expression(out, value);
+            out.push_str(")[");
+            if let Some(start) = start {
+                write_expression(out, start);
+            }
+            out.push_str("..");
+            if let Some(end) = end {
+                write_expression(out, end);
+            }
+            out.push(']');
+        }
+    }
+}
+
+/// The program without spans, which formatting is free to change.
+fn shape(statements: &[Stmt]) -> String {
+    let mut out = String::new();
+    for stmt in statements {
+        shape_stmt(&mut out, stmt);
+    }
+    out
+}
+
+fn shape_block(out: &mut String, block: &Block) {
+    out.push('{');
+    out.push_str(&shape(&block.statements));
+    out.push('}');
+}
+
+fn shape_stmt(out: &mut String, stmt: &Stmt) {
+    match stmt {
+        Stmt::Let { name, value, .. } => write!(out, "(let {} {})", name, shape_expr(value)).unwrap(),
+        Stmt::Assign { name, value, .. } => write!(out, "(set {} {})", name, shape_expr(value)).unwrap(),
+        Stmt::FuncDef { name, params, body, .. } => {
+            let params: Vec<String> = params
+                .iter()
+                .map(|param| match &param.default {
+                    Some(default) => format!("(= {} {})", param.name, shape_expr(default)),
+                    None => param.name.to_string(),
+                })
+                .collect();
+            write!(out, "(def {} {}", name, params.join(" ")).unwrap();
+            shape_block(out, body);
+            out.push(')');
+        }
+        Stmt::Write(value, _) => write!(out, "(write {})", shape_expr(value)).unwrap(),
+        Stmt::Return(value, _) => write!(out, "(return {})", value.as_ref().map(shape_expr).unwrap_or_default()).unwrap(),
+        Stmt::If {
+            condition,
+            then_block,
+            else_branch,
+            ..
+        } => {
+            write!(out, "(if {}", shape_expr(condition)).unwrap();
+            shape_block(out, then_block);
+            match else_branch {
+                Some(Else::If(nested)) => shape_stmt(out, nested),
+                Some(Else::Block(block)) => shape_block(out, block),
+                None => {}
+            }
+            out.push(')');
+        }
+        Stmt::While { condition, body, .. } => {
+            write!(out, "(while {}", shape_expr(condition)).unwrap();
+            shape_block(out, body);
+            out.push(')');
+        }
+        Stmt::Expr(value, _) => write!(out, "({})", shape_expr(value)).unwrap(),
+        Stmt::Enum { name, variants, .. } => {
+            let variants: Vec<&str> = variants.iter().map(|variant| variant.name.as_str()).collect();
+            write!(out, "(enum {} {})", name, variants.join(" ")).unwrap();
+        }
+        Stmt::Match { value, arms, .. } => {
+            write!(out, "(match {}", shape_expr(value)).unwrap();
+            for arm in arms {
+                let patterns: Vec<String> = arm
+                    .patterns
+                    .iter()
+                    .map(|pattern| match pattern {
+                        Pattern::Variant(enum_name, variant, _) => format!("{}.{}", enum_name, variant),
+                        Pattern::Wildcard(_) => "_".to_string(),
+                    })
+                    .collect();
+                write!(out, "({}", patterns.join(" ")).unwrap();
+                shape_block(out, &arm.body);
+                out.push(')');
+            }
+            out.push(')');
+        }
+    }
+}
+
+fn shape_expr(expr: &Expr) -> String {
+    match expr {
+        Expr::Number(value, _) => value.to_string(),
+        Expr::String(text, _) => format!("{:?}", text),
+        Expr::Identifier(name, _) => name.to_string(),
+        Expr::Variant(enum_name, variant, _) => format!("{}.{}", enum_name, variant),
+        Expr::Call(name, args, named, _) => {
+            let named = named.iter().map(|arg| format!("(= {} {})", arg.name, shape_expr(&arg.value)));
+            let args: Vec<String> = args.iter().map(shape_expr).chain(named).collect();
+            format!("(call {} {})", name, args.join(" "))
+        }
+        Expr::Unary(op, operand, _) => format!("({} {})", op.symbol(), shape_expr(operand)),
+        Expr::Binary(op, left, right, _) => format!("({} {} {})", op.symbol(), shape_expr(left), shape_expr(right)),
+        Expr::Index(value, index, _) => format!("(index {} {})", shape_expr(value), shape_expr(index)),
+        Expr::Slice(value, start, end, _) => {
+            let bound = |bound: &Option<Box<Expr>>| bound.as_deref().map(shape_expr).unwrap_or_default();
+            format!("(slice {} {} {})", shape_expr(value), bound(start), bound(end))
+        }
+    }
+}
+
+fuzz_target!(|program: Program| {
+    let mut src = String::new();
+    write_program(&mut src, &program);
+    let (original, _) = vira_core::parse(&src).unwrap_or_else(|err| panic!("generated an invalid program: {}\n{}", err, src));
+    let formatted = formatter::format_source(&src).expect("the formatter rejected a valid program");
+    let (reparsed, _) = vira_core::parse(&formatted).unwrap_or_else(|err| panic!("formatted output doesn't parse: {}\n{}", err, formatted));
+    assert_eq!(shape(&original.statements), shape(&reparsed.statements), "formatting changed the program:\n{}", formatted);
+    let again = formatter::format_source(&formatted).expect("the formatter rejected its own output");
+    assert_eq!(formatted, again, "formatting is not idempotent");
+});
@@ -0,0 +1,29 @@
+//! Arbitrary text through the streaming and the lossless lexer. Lexing may fail but must not
+//! panic, tokens must come in order within the input, and lossless tokens must print back to it.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use vira_core::{lossless, Lexer};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(src) = std::str::from_utf8(data) else {
+        return;
+    };
+    let mut end = 0;
+    for token in Lexer::new(src) {
+        let Ok(token) = token else {
+            break;
+        };
+        assert!(end <= token.span.start && token.span.start <= token.span.end && token.span.end <= src.len());
+        end = token.span.end;
+    }
+    if let Ok(tokens) = lossless::tokenize(src) {
+        let mut printed = String::new();
+        for token in &tokens {
+            token.leading.iter().for_each(|trivia| printed.push_str(trivia.text));
+            printed.push_str(token.text);
+            token.trailing.iter().for_each(|trivia| printed.push_str(trivia.text));
+        }
+        assert_eq!(printed, src);
+    }
+});
@@ -0,0 +1,25 @@
+//! Arbitrary text through the whole front end into optimized IR. A program the checker accepts
+//! must lower without an internal error, and nothing may panic.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(src) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok((program, _)) = vira_core::parse(src) else {
+        return;
+    };
+    if !vira_core::check(&program).is_empty() {
+        return;
+    }
+    let resolution = vira_core::resolve(&program);
+    match vira_ir::lower([(&program, &resolution)]) {
+        Ok(mut module) => {
+            vira_ir::optimize(&mut module);
+            module.to_string();
+        }
+        Err(err) => assert!(!err.internal, "a checked program failed to lower: {}", err.what),
+    }
+});
@@ -0,0 +1,43 @@
+//! Arbitrary token streams, including ones cut short by a lexing error or ending without `Eof`,
+//! through the parser and then the checker and resolver for whatever parses.
+#![no_main]
+
+use std::borrow::Cow;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use vira_core::lexer::{KEYWORDS, PUNCTUATORS};
+use vira_core::{Error, Parser, Span, Token, TokenKind};
+
+/// A few names, so that declarations and uses sometimes match, plus the builtin.
+const NAMES: &[&str] = &["x", "y", "f", "main", "assert"];
+
+#[derive(Arbitrary, Debug)]
+enum Piece {
+    Identifier(u8),
+    Keyword(u8),
+    Number(f64),
+    String(String),
+    Punctuator(u8),
+    LexError,
+}
+
+fuzz_target!(|pieces: Vec<Piece>| {
+    let tokens = pieces.iter().enumerate().map(|(index, piece)| {
+        let span = Span::new(index, index + 1);
+        let pick = |names: &[&'static str], index: u8| names[index as usize % names.len()];
+        let kind = match piece {
+            Piece::Identifier(index) => TokenKind::Identifier(pick(NAMES, *index)),
+            Piece::Keyword(index) => TokenKind::Keyword(pick(KEYWORDS, *index)),
+            Piece::Number(value) => TokenKind::Number(*value),
+            Piece::String(text) => TokenKind::StringLiteral(Cow::Borrowed(text)),
+            Piece::Punctuator(index) => TokenKind::Punctuator(pick(PUNCTUATORS, *index)),
+            Piece::LexError => return Err(Error::new("V0001", "Unexpected character", span)),
+        };
+        Ok(Token { kind, span })
+    });
+    if let Ok(program) = Parser::new(tokens).parse_program() {
+        vira_core::check(&program);
+        vira_core::resolve(&program);
+    }
+});
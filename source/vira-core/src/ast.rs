@@ -0,0 +1,320 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Span, Symbol};
+
+/// A whole file. Top-level statements other than `def` run in order as the program's `main`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    pub statements: Vec<Stmt>,
+}
+
+/// Statements between braces. The span covers the braces.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Block {
+    pub statements: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Param {
+    pub name: Symbol,
+    pub span: Span,
+    /// The literal after `=`, which a call that leaves the parameter out passes instead. Strings
+    /// parse but the checker rejects them, as parameters hold numbers.
+    pub default: Option<Expr>,
+}
+
+/// A name an `enum` gives one of its values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: Symbol,
+    pub span: Span,
+}
+
+/// `patterns => { body }` in a `match`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub patterns: Vec<Pattern>,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Pattern {
+    /// `Enum.Variant`, which matches that variant.
+    Variant(Symbol, Symbol, Span),
+    /// `_`, which matches any value.
+    Wildcard(Span),
+}
+
+/// `name = value` in a call, which passes `value` for the parameter called `name`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedArg {
+    pub name: Symbol,
+    pub name_span: Span,
+    pub value: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Stmt {
+    /// `let name = value;`
+    Let {
+        name: Symbol,
+        name_span: Span,
+        value: Expr,
+        span: Span,
+    },
+    /// `name = value;`
+    Assign {
+        name: Symbol,
+        name_span: Span,
+        value: Expr,
+        span: Span,
+    },
+    /// `def name(params) { body }`, only allowed at the top level.
+    FuncDef {
+        name: Symbol,
+        name_span: Span,
+        params: Vec<Param>,
+        body: Block,
+        span: Span,
+    },
+    /// `enum name { variants }`, only allowed at the top level. The variants are numbers, counting
+    /// from 0 in the order they are listed.
+    Enum {
+        name: Symbol,
+        name_span: Span,
+        variants: Vec<Variant>,
+        span: Span,
+    },
+    /// `match value { arms }` runs the first arm with a pattern that matches `value`, if any.
+    Match {
+        value: Expr,
+        arms: Vec<MatchArm>,
+        span: Span,
+    },
+    /// `write value;` prints the value followed by a newline.
+    Write(Expr, Span),
+    Return(Option<Expr>, Span),
+    If {
+        condition: Expr,
+        then_block: Block,
+        else_branch: Option<Else>,
+        span: Span,
+    },
+    While {
+        condition: Expr,
+        body: Block,
+        span: Span,
+    },
+    /// An expression evaluated for its side effects, such as a call.
+    Expr(Expr, Span),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Else {
+    /// `else if ...`, holding a `Stmt::If`.
+    If(Box<Stmt>),
+    Block(Block),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Expr {
+    Number(f64, Span),
+    String(String, Span),
+    Identifier(Symbol, Span),
+    /// `Enum.Variant`, the number the enum gives that variant.
+    Variant(Symbol, Symbol, Span),
+    /// `name(args)`: the arguments passed by position, then those passed by name. The span covers
+    /// the name through the closing parenthesis.
+    Call(Symbol, #[serde(with = "nested")] Vec<Expr>, #[serde(with = "nested")] Vec<NamedArg>, Span),
+    Unary(UnOp, #[serde(with = "nested")] Box<Expr>, Span),
+    Binary(BinOp, #[serde(with = "nested")] Box<Expr>, #[serde(with = "nested")] Box<Expr>, Span),
+    /// `value[index]`, the character of a string at `index`.
+    Index(#[serde(with = "nested")] Box<Expr>, #[serde(with = "nested")] Box<Expr>, Span),
+    /// `value[start..end]`, the characters from `start` up to but not including `end`. Without
+    /// `start` the slice begins at the first character, without `end` it runs to the last.
+    Slice(
+        #[serde(with = "nested")] Box<Expr>,
+        #[serde(with = "nested")] Option<Box<Expr>>,
+        #[serde(with = "nested")] Option<Box<Expr>>,
+        Span,
+    ),
+}
+
+/// Dropping is done with a work list instead of by recursion, which would overflow the stack on a
+/// long chain like `1 + 1 + ...`.
+impl Drop for Expr {
+    fn drop(&mut self) {
+        let mut pending = Vec::new();
+        let take_operands = |expr: &mut Expr, pending: &mut Vec<Expr>| match expr {
+            Expr::Call(_, args, named, _) => {
+                pending.append(args);
+                pending.extend(named.drain(..).map(|arg| arg.value));
+            }
+            Expr::Unary(_, operand, _) => pending.push(std::mem::replace(operand.as_mut(), Expr::Number(0.0, Span::default()))),
+            Expr::Binary(_, left, right, _) | Expr::Index(left, right, _) => {
+                pending.push(std::mem::replace(left.as_mut(), Expr::Number(0.0, Span::default())));
+                pending.push(std::mem::replace(right.as_mut(), Expr::Number(0.0, Span::default())));
+            }
+            Expr::Slice(value, start, end, _) => {
+                pending.push(std::mem::replace(value.as_mut(), Expr::Number(0.0, Span::default())));
+                pending.extend([start.take(), end.take()].into_iter().flatten().map(|bound| *bound));
+            }
+            Expr::Number(..) | Expr::String(..) | Expr::Identifier(..) | Expr::Variant(..) => {}
+        };
+        take_operands(self, &mut pending);
+        while let Some(mut expr) = pending.pop() {
+            take_operands(&mut expr, &mut pending);
+        }
+    }
+}
+
+/// Serde adapter for the fields expressions nest through, so encoding or decoding a deep
+/// expression grows the stack instead of overflowing it.
+mod nested {
+    use super::*;
+
+    pub fn serialize<T: Serialize, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::ensure_stack(|| value.serialize(serializer))
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        crate::ensure_stack(|| T::deserialize(deserializer))
+    }
+}
+
+impl BinOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Mod => "%",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+        }
+    }
+
+    /// Binding strength, higher binds tighter. All binary operators are left-associative.
+    pub fn precedence(self) -> u8 {
+        match self {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::Eq | BinOp::Ne => 3,
+            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => 4,
+            BinOp::Add | BinOp::Sub => 5,
+            BinOp::Mul | BinOp::Div | BinOp::Mod => 6,
+        }
+    }
+
+    pub fn from_symbol(symbol: &str) -> Option<BinOp> {
+        Some(match symbol {
+            "+" => BinOp::Add,
+            "-" => BinOp::Sub,
+            "*" => BinOp::Mul,
+            "/" => BinOp::Div,
+            "%" => BinOp::Mod,
+            "==" => BinOp::Eq,
+            "!=" => BinOp::Ne,
+            "<" => BinOp::Lt,
+            "<=" => BinOp::Le,
+            ">" => BinOp::Gt,
+            ">=" => BinOp::Ge,
+            "&&" => BinOp::And,
+            "||" => BinOp::Or,
+            _ => return None,
+        })
+    }
+}
+
+impl UnOp {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnOp::Neg => "-",
+            UnOp::Not => "!",
+        }
+    }
+}
+
+/// Tighter than any binary operator.
+pub const UNARY_PRECEDENCE: u8 = 7;
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Let { span, .. }
+            | Stmt::Assign { span, .. }
+            | Stmt::FuncDef { span, .. }
+            | Stmt::Enum { span, .. }
+            | Stmt::Match { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::While { span, .. }
+            | Stmt::Write(_, span)
+            | Stmt::Return(_, span)
+            | Stmt::Expr(_, span) => *span,
+        }
+    }
+}
+
+impl Pattern {
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Variant(_, _, span) | Pattern::Wildcard(span) => *span,
+        }
+    }
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Number(_, span)
+            | Expr::String(_, span)
+            | Expr::Identifier(_, span)
+            | Expr::Variant(_, _, span)
+            | Expr::Call(_, _, _, span)
+            | Expr::Unary(_, _, span)
+            | Expr::Binary(_, _, _, span)
+            | Expr::Index(_, _, span)
+            | Expr::Slice(_, _, _, span) => *span,
+        }
+    }
+
+    /// How tightly this expression binds when printed without parentheses.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary(op, ..) => op.precedence(),
+            Expr::Unary(..) => UNARY_PRECEDENCE,
+            _ => UNARY_PRECEDENCE + 1,
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! On-disk cache of parsed programs, so tools can reuse a parse instead of lexing a large file again.
+//!
+//! The layout is a 4-byte magic, a little-endian `u32` format version, then the bincode encoding of
+//! the source hash and the `Program`. Files written by a build with a different AST are rejected
+//! by the version check rather than decoded into garbage.
+
+use std::fmt;
+
+use crate::ast::Program;
+
+const MAGIC: &[u8; 4] = b"VAST";
+/// Bump whenever `Program` or anything it contains changes shape.
+const FORMAT_VERSION: u32 = 5;
+
+pub struct CachedProgram {
+    /// `source_hash` of the text the program was parsed from.
+    pub source_hash: u64,
+    pub program: Program,
+}
+
+impl CachedProgram {
+    /// Whether the cache was built from exactly `source`.
+    pub fn is_fresh(&self, source: &str) -> bool {
+        self.source_hash == source_hash(source)
+    }
+}
+
+#[derive(Debug)]
+pub enum CacheError {
+    NotACache,
+    Version(u32),
+    Corrupt(String),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::NotACache => write!(f, "not a Vira AST cache"),
+            CacheError::Version(found) => write!(
+                f,
+                "AST cache format {} does not match this build (format {}); regenerate it",
+                found, FORMAT_VERSION
+            ),
+            CacheError::Corrupt(reason) => write!(f, "corrupt AST cache: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+pub fn encode(program: &Program, source: &str) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    // Serializing plain data into a Vec cannot fail
+    let body = bincode::serialize(&(source_hash(source), program)).expect("AST is serializable");
+    bytes.extend_from_slice(&body);
+    bytes
+}
+
+pub fn decode(bytes: &[u8]) -> Result<CachedProgram, CacheError> {
+    let rest = bytes.strip_prefix(MAGIC.as_slice()).ok_or(CacheError::NotACache)?;
+    let (version, body) = rest.split_first_chunk::<4>().ok_or(CacheError::NotACache)?;
+    let version = u32::from_le_bytes(*version);
+    if version != FORMAT_VERSION {
+        return Err(CacheError::Version(version));
+    }
+    let (source_hash, program) = bincode::deserialize(body).map_err(|e| CacheError::Corrupt(e.to_string()))?;
+    Ok(CachedProgram { source_hash, program })
+}
+
+/// 64-bit FNV-1a of the source text. Unlike `DefaultHasher` it is the same on every build and platform.
+pub fn source_hash(source: &str) -> u64 {
+    source.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3))
+}
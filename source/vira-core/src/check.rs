@@ -0,0 +1,391 @@
+use std::collections::HashMap;
+
+use crate::ast::{BinOp, Block, Else, Expr, NamedArg, Param, Pattern, Program, Stmt, Variant};
+use crate::{ensure_stack, resolve, Error, Span, Symbol};
+
+/// Name resolution over a parsed program. Unlike parsing, this reports every problem it finds
+/// instead of stopping at the first, in source order.
+///
+/// Functions are visible everywhere, including before their definition. Variables are resolved
+/// by `resolve`, whose errors are included here.
+pub fn check(program: &Program) -> Vec<Error> {
+    check_with(program, &[], &[])
+}
+
+/// Functions every program can call without defining them, with the fewest and most arguments they
+/// take. A program that defines a function of the same name calls its own instead.
+pub const BUILTINS: &[(&str, usize, usize)] = &[
+    ("assert", 1, 2),
+    ("assert_eq", 2, 2),
+    ("panic", 1, 1),
+    ("len", 1, 1),
+    ("substr", 3, 3),
+    ("find", 2, 2),
+    ("replace", 3, 3),
+    ("trim", 1, 1),
+    ("num", 1, 1),
+    ("str", 1, 1),
+    ("int", 1, 1),
+    ("typeof", 1, 1),
+    ("pi", 0, 0),
+    ("e", 0, 0),
+    ("sin", 1, 1),
+    ("cos", 1, 1),
+    ("tan", 1, 1),
+    ("log", 1, 1),
+    ("exp", 1, 1),
+    ("pow", 2, 2),
+    ("sqrt", 1, 1),
+    ("random", 0, 0),
+    ("rand_range", 2, 2),
+    ("seed", 1, 1),
+    ("now", 0, 0),
+    ("clock", 0, 0),
+    ("sleep", 1, 1),
+    ("format_time", 2, 2),
+    ("matches", 2, 2),
+    ("regex_find", 2, 2),
+    ("regex_replace", 3, 3),
+    ("getenv", 1, 1),
+    ("setenv", 2, 2),
+    ("exec", 1, 1),
+    ("exec_output", 1, 1),
+    ("exit", 1, 1),
+    ("http_get", 1, 1),
+    ("http_post", 2, 2),
+    ("http_status", 0, 0),
+];
+
+/// Like `check`, for one file of a program made of several. `external` gives the name and
+/// parameters of every function the other files define, and `enums` the name and variants of their
+/// enums; uses of them resolve like uses of local ones. Clashes between files are the caller's to
+/// report, so both should leave out local names.
+pub fn check_with<'a>(
+    program: &'a Program,
+    external: &[(Symbol, &'a [Param])],
+    enums: &[(Symbol, &'a [Variant])],
+) -> Vec<Error> {
+    let mut checker = Checker {
+        functions: BUILTINS
+            .iter()
+            .map(|&(name, min, max)| (Symbol::intern(name), Callee::Builtin(min, max)))
+            .chain(external.iter().map(|&(name, params)| (name, Callee::Function(params))))
+            .collect(),
+        enums: enums.iter().copied().collect(),
+        errors: Vec::new(),
+    };
+    for stmt in &program.statements {
+        if let Stmt::FuncDef { name, name_span, params, .. } = stmt {
+            if let Some(Callee::Function(_)) = checker.functions.get(name) {
+                checker.errors.push(
+                    Error::new("V0103", format!("Function '{}' is defined more than once", name), *name_span)
+                        .with_help("rename or remove one of the definitions"),
+                );
+            } else {
+                checker.functions.insert(*name, Callee::Function(params));
+            }
+        }
+        if let Stmt::Enum { name, name_span, variants, .. } = stmt {
+            if checker.enums.contains_key(name) {
+                checker.errors.push(
+                    Error::new("V0109", format!("Enum '{}' is defined more than once", name), *name_span)
+                        .with_help("rename or remove one of the definitions"),
+                );
+                continue;
+            }
+            for (index, variant) in variants.iter().enumerate() {
+                if variants[..index].iter().any(|earlier| earlier.name == variant.name) {
+                    checker.errors.push(
+                        Error::new("V0109", format!("Variant '{}' is listed more than once in '{}'", variant.name, name), variant.span)
+                            .with_help("remove or rename one of them"),
+                    );
+                }
+            }
+            checker.enums.insert(*name, variants);
+        }
+    }
+    for stmt in &program.statements {
+        checker.stmt(stmt);
+    }
+    let mut errors = checker.errors;
+    errors.extend(resolve(program).errors);
+    errors.sort_by_key(|error| error.span.start);
+    errors
+}
+
+struct Checker<'a> {
+    functions: HashMap<Symbol, Callee<'a>>,
+    enums: HashMap<Symbol, &'a [Variant]>,
+    errors: Vec<Error>,
+}
+
+/// What a call is checked against.
+#[derive(Clone, Copy)]
+enum Callee<'a> {
+    /// A builtin, with the fewest and most arguments it takes. Its parameters have no names to pass
+    /// arguments by.
+    Builtin(usize, usize),
+    Function(&'a [Param]),
+}
+
+impl Checker<'_> {
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::FuncDef { params, body, .. } => {
+                for param in params {
+                    if let Some(default) = &param.default {
+                        self.number(default, || format!("Parameter '{}' can't default to a string", param.name));
+                    }
+                }
+                self.block(body);
+            }
+            Stmt::Let { value: expr, .. }
+            | Stmt::Assign { value: expr, .. }
+            | Stmt::Write(expr, _)
+            | Stmt::Expr(expr, _)
+            | Stmt::Return(Some(expr), _) => self.expr(expr),
+            Stmt::Return(None, _) | Stmt::Enum { .. } => {}
+            Stmt::Match { value, arms, .. } => {
+                self.expr(value);
+                // The enum of the first variant named, which every other one must belong to
+                let mut matched: Option<Symbol> = None;
+                for pattern in arms.iter().flat_map(|arm| &arm.patterns) {
+                    let Pattern::Variant(enum_name, variant, span) = pattern else {
+                        continue;
+                    };
+                    if !self.variant(*enum_name, *variant, *span) {
+                        continue;
+                    }
+                    match matched {
+                        Some(first) if first != *enum_name => self.errors.push(
+                            Error::new("V0111", format!("'{}.{}' is not a variant of '{}'", enum_name, variant, first), *span)
+                                .with_help(format!("match on variants of '{}' only, or split the match", first)),
+                        ),
+                        _ => matched = Some(*enum_name),
+                    }
+                }
+                for arm in arms {
+                    self.block(&arm.body);
+                }
+            }
+            Stmt::If {
+                condition,
+                then_block,
+                else_branch,
+                ..
+            } => {
+                self.expr(condition);
+                self.block(then_block);
+                match else_branch {
+                    Some(Else::If(stmt)) => self.stmt(stmt),
+                    Some(Else::Block(block)) => self.block(block),
+                    None => {}
+                }
+            }
+            Stmt::While { condition, body, .. } => {
+                self.expr(condition);
+                self.block(body);
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        for stmt in &block.statements {
+            self.stmt(stmt);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        ensure_stack(|| match expr {
+            Expr::Number(..) | Expr::String(..) | Expr::Identifier(..) => {}
+            Expr::Variant(enum_name, variant, span) => {
+                self.variant(*enum_name, *variant, *span);
+            }
+            Expr::Call(name, args, named, span) => {
+                for arg in args.iter().chain(named.iter().map(|arg| &arg.value)) {
+                    self.expr(arg);
+                }
+                match self.functions.get(name).copied() {
+                    Some(Callee::Builtin(..)) if !named.is_empty() => self.errors.push(Error::new(
+                        "V0107",
+                        format!("Function '{}' is built in and takes no named arguments", name),
+                        named[0].name_span,
+                    )),
+                    Some(Callee::Builtin(min, max)) if !(min..=max).contains(&args.len()) => {
+                        self.errors.push(wrong_count(*name, min, max, args.len(), *span))
+                    }
+                    Some(Callee::Builtin(..)) => {}
+                    Some(Callee::Function(params)) => self.call(*name, params, args, named, *span),
+                    None => {
+                        let names: Vec<&str> = self.functions.keys().map(|name| name.as_str()).collect();
+                        let error = Error::new("V0101", format!("Undefined function: {}", name), *span);
+                        self.errors.push(match suggest(name.as_str(), &names) {
+                            Some(best) => error.with_help(format!("did you mean '{}'?", best)),
+                            None => error,
+                        });
+                    }
+                }
+            }
+            Expr::Unary(_, operand, _) => self.expr(operand),
+            Expr::Binary(_, left, right, _) | Expr::Index(left, right, _) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Slice(value, start, end, _) => {
+                self.expr(value);
+                for bound in [start, end].into_iter().flatten() {
+                    self.expr(bound);
+                }
+            }
+        })
+    }
+
+    /// Checks that `enum_name.variant` at `span` names a variant, and tells whether it does.
+    fn variant(&mut self, enum_name: Symbol, variant: Symbol, span: Span) -> bool {
+        let Some(variants) = self.enums.get(&enum_name) else {
+            let names: Vec<&str> = self.enums.keys().map(|name| name.as_str()).collect();
+            let error = Error::new("V0110", format!("Undefined enum: {}", enum_name), span);
+            self.errors.push(match suggest(enum_name.as_str(), &names) {
+                Some(best) => error.with_help(format!("did you mean '{}'?", best)),
+                None => error,
+            });
+            return false;
+        };
+        if variants.iter().any(|known| known.name == variant) {
+            return true;
+        }
+        let names: Vec<&str> = variants.iter().map(|known| known.name.as_str()).collect();
+        let error = Error::new("V0110", format!("Enum '{}' has no variant '{}'", enum_name, variant), span);
+        self.errors.push(match suggest(variant.as_str(), &names) {
+            Some(best) => error.with_help(format!("did you mean '{}.{}'?", enum_name, best)),
+            None => error.with_help(format!("its variants are {}", names.join(", "))),
+        });
+        false
+    }
+
+    /// Reports `expr` if it is plainly a string: parameters only hold numbers.
+    fn number(&mut self, expr: &Expr, message: impl FnOnce() -> String) {
+        if is_string(expr) {
+            self.errors.push(Error::new("V0112", message(), expr.span()).with_help("function parameters only hold numbers"));
+        }
+    }
+
+    /// Checks a call to a function with `params` that passes `args` by position, then `named` by
+    /// name. Positional arguments fill the parameters from the first; every parameter left without
+    /// an argument needs a default.
+    fn call(&mut self, name: Symbol, params: &[Param], args: &[Expr], named: &[NamedArg], span: Span) {
+        for arg in args.iter().chain(named.iter().map(|arg| &arg.value)) {
+            self.number(arg, || format!("Function '{}' takes numbers, not strings", name));
+        }
+        let positional = args.len();
+        let required = params.iter().filter(|param| param.default.is_none()).count();
+        if positional > params.len() {
+            self.errors.push(wrong_count(name, required, params.len(), positional + named.len(), span));
+            return;
+        }
+        let mut passed = vec![false; params.len()];
+        passed[..positional].fill(true);
+        for arg in named {
+            let Some(index) = params.iter().position(|param| param.name == arg.name) else {
+                let names: Vec<&str> = params.iter().map(|param| param.name.as_str()).collect();
+                let error = Error::new("V0107", format!("Function '{}' has no parameter named '{}'", name, arg.name), arg.name_span);
+                self.errors.push(match suggest(arg.name.as_str(), &names) {
+                    Some(best) => error.with_help(format!("did you mean '{}'?", best)),
+                    None => error,
+                });
+                continue;
+            };
+            if passed[index] {
+                self.errors.push(
+                    Error::new("V0108", format!("Parameter '{}' is given more than one argument", arg.name), arg.name_span)
+                        .with_help("remove one of the arguments"),
+                );
+            }
+            passed[index] = true;
+        }
+        let missing: Vec<String> = params
+            .iter()
+            .zip(&passed)
+            .filter(|(param, &passed)| !passed && param.default.is_none())
+            .map(|(param, _)| format!("'{}'", param.name))
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        // Without names or defaults in play, the count says it all
+        if named.is_empty() && required == params.len() {
+            self.errors.push(wrong_count(name, required, params.len(), positional, span));
+        } else {
+            self.errors.push(Error::new(
+                "V0104",
+                format!(
+                    "Function '{}' is missing {} for {}",
+                    name,
+                    if missing.len() == 1 { "an argument" } else { "arguments" },
+                    missing.join(", ")
+                ),
+                span,
+            ));
+        }
+    }
+}
+
+/// Whether `expr` is a string literal, or strings joined with `+`, whatever the variables in it hold.
+fn is_string(expr: &Expr) -> bool {
+    ensure_stack(|| match expr {
+        Expr::String(..) => true,
+        Expr::Binary(BinOp::Add, left, right, _) => is_string(left) || is_string(right),
+        _ => false,
+    })
+}
+
+/// The error for a call that passes `given` arguments to a function that takes from `min` to `max`.
+fn wrong_count(name: Symbol, min: usize, max: usize, given: usize, span: Span) -> Error {
+    let takes = if min == max { min.to_string() } else { format!("{} to {}", min, max) };
+    Error::new(
+        "V0104",
+        format!(
+            "Function '{}' takes {} argument{} but {} {} given",
+            name,
+            takes,
+            if max == 1 { "" } else { "s" },
+            given,
+            if given == 1 { "was" } else { "were" }
+        ),
+        span,
+    )
+}
+
+/// Closest candidate to `name`, or `None` if nothing is near enough to be a likely typo.
+pub fn suggest<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1) + 1;
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance < limit)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// Optimal string alignment distance: Levenshtein plus adjacent transpositions, so `mian` is one edit from `main`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
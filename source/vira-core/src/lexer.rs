@@ -0,0 +1,299 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use crate::lossless::{Trivia, TriviaKind};
+use crate::{Error, Span};
+
+pub const KEYWORDS: &[&str] = &["let", "def", "write", "return", "if", "else", "while", "enum", "match"];
+/// Longest first, so `<=` is never read as `<` followed by `=`.
+pub const PUNCTUATORS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "=>", "..", ".", "+", "-", "*", "/", "%", "=", "<", ">", "!", "(", ")", "{", "}", "[", "]", ",", ";",
+];
+
+/// Tokens borrow their text from the source, so lexing allocates nothing but the token list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TokenKind<'src> {
+    Identifier(&'src str),
+    Keyword(&'src str),
+    Number(f64),
+    /// Contents with escape sequences already resolved. Only a string with escapes needs a copy.
+    StringLiteral(#[serde(borrow)] Cow<'src, str>),
+    Punctuator(&'src str),
+    Eof,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Token<'src> {
+    #[serde(borrow)]
+    pub kind: TokenKind<'src>,
+    pub span: Span,
+}
+
+/// A `//` line comment. The text includes the leading slashes but not the newline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Comment<'src> {
+    pub text: &'src str,
+    pub span: Span,
+}
+
+/// Lexes on demand: as an iterator it yields each token, ending with `Eof`, or stops after the
+/// first error. Comments are collected on the side as the tokens after them are lexed.
+pub struct Lexer<'a> {
+    input: &'a str,
+    position: usize,
+    comments: Vec<Comment<'a>>,
+    done: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Lexer {
+            input,
+            position: 0,
+            comments: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Lexes the whole input, returning the tokens (ending with `Eof`) and the comments skipped between them.
+    pub fn tokenize(input: &'a str) -> Result<(Vec<Token<'a>>, Vec<Comment<'a>>), Error> {
+        Lexer::new(input).collect_tokens()
+    }
+
+    /// Lexes the rest of the input, for callers that need every token at once.
+    pub fn collect_tokens(mut self) -> Result<(Vec<Token<'a>>, Vec<Comment<'a>>), Error> {
+        let tokens = self.by_ref().collect::<Result<_, _>>()?;
+        Ok((tokens, self.comments))
+    }
+
+    pub fn next_token(&mut self) -> Result<Token<'a>, Error> {
+        self.skip_trivia();
+        let start = self.position;
+        let Some(ch) = self.current_char() else {
+            return Ok(Token {
+                kind: TokenKind::Eof,
+                span: Span::new(start, start),
+            });
+        };
+        let kind = if ch == 'r' && self.input[start + 1..].starts_with('"') {
+            self.lex_string(true)?
+        } else if ch.is_alphabetic() || ch == '_' {
+            self.lex_identifier_or_keyword()
+        } else if ch.is_ascii_digit() {
+            self.lex_number()?
+        } else if ch == '"' {
+            self.lex_string(false)?
+        } else if let Some(punct) = PUNCTUATORS.iter().find(|p| self.input[start..].starts_with(*p)) {
+            self.position += punct.len();
+            TokenKind::Punctuator(punct)
+        } else {
+            self.advance();
+            return Err(Error::new(
+                "V0001",
+                format!("Unexpected character: {}", ch),
+                Span::new(start, self.position),
+            ));
+        };
+        Ok(Token {
+            kind,
+            span: Span::new(start, self.position),
+        })
+    }
+
+    pub fn comments(&self) -> &[Comment<'a>] {
+        &self.comments
+    }
+
+    pub fn into_comments(self) -> Vec<Comment<'a>> {
+        self.comments
+    }
+
+    fn current_char(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn advance(&mut self) {
+        if let Some(ch) = self.current_char() {
+            self.position += ch.len_utf8();
+        }
+    }
+
+    /// Consumes whitespace, line breaks and comments before the next token, returning them in source order.
+    pub(crate) fn lex_trivia(&mut self) -> Vec<Trivia<'a>> {
+        let mut trivia = Vec::new();
+        let mut start = self.position;
+        while let Some(kind) = self.trivium() {
+            trivia.push(Trivia {
+                kind,
+                text: &self.input[start..self.position],
+                span: Span::new(start, self.position),
+            });
+            start = self.position;
+        }
+        trivia
+    }
+
+    /// Like `lex_trivia`, but without keeping what it skips, apart from comments.
+    fn skip_trivia(&mut self) {
+        while self.trivium().is_some() {}
+    }
+
+    /// Consumes one piece of whitespace, line break or comment, if the next token is not yet due.
+    fn trivium(&mut self) -> Option<TriviaKind> {
+        let start = self.position;
+        if let Some(len) = self.newline_len() {
+            self.position += len;
+            Some(TriviaKind::Newline)
+        } else if self.current_char().is_some_and(char::is_whitespace) {
+            while self.newline_len().is_none() && self.current_char().is_some_and(char::is_whitespace) {
+                self.advance();
+            }
+            Some(TriviaKind::Whitespace)
+        } else if self.input[start..].starts_with("//") {
+            while self.newline_len().is_none() && self.current_char().is_some() {
+                self.advance();
+            }
+            self.comments.push(Comment {
+                text: self.input[start..self.position].trim_end(),
+                span: Span::new(start, self.position),
+            });
+            Some(TriviaKind::Comment)
+        } else {
+            None
+        }
+    }
+
+    /// Length of the line break at the current position, counting `\r\n` as one.
+    fn newline_len(&self) -> Option<usize> {
+        let rest = &self.input[self.position..];
+        if rest.starts_with("\r\n") {
+            Some(2)
+        } else if rest.starts_with('\n') {
+            Some(1)
+        } else {
+            None
+        }
+    }
+
+    fn lex_identifier_or_keyword(&mut self) -> TokenKind<'a> {
+        let start = self.position;
+        while self.current_char().is_some_and(|ch| ch.is_alphanumeric() || ch == '_') {
+            self.advance();
+        }
+        let id = &self.input[start..self.position];
+        if KEYWORDS.contains(&id) {
+            TokenKind::Keyword(id)
+        } else {
+            TokenKind::Identifier(id)
+        }
+    }
+
+    fn lex_number(&mut self) -> Result<TokenKind<'a>, Error> {
+        let start = self.position;
+        while self.current_char().is_some_and(|ch| ch.is_ascii_digit()) {
+            self.advance();
+        }
+        // A fraction needs a digit after the dot
+        let rest = &self.input[self.position..];
+        if rest.starts_with('.') && rest[1..].starts_with(|ch: char| ch.is_ascii_digit()) {
+            self.advance();
+            while self.current_char().is_some_and(|ch| ch.is_ascii_digit()) {
+                self.advance();
+            }
+        }
+        let text = &self.input[start..self.position];
+        match text.parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(TokenKind::Number(value)),
+            _ => Err(Error::new(
+                "V0003",
+                format!("Number literal {} is too large", text),
+                Span::new(start, self.position),
+            )),
+        }
+    }
+
+    /// Lexes a `"` or `"""` string, or either one after an `r` when `raw`. A raw string keeps
+    /// backslashes as written, and a `"""` string drops the line break right after its opening quotes.
+    /// Both of those read `\r\n` as `\n`, so a file's line endings don't change its strings.
+    fn lex_string(&mut self, raw: bool) -> Result<TokenKind<'a>, Error> {
+        let start = self.position;
+        if raw {
+            self.advance(); // skip r
+        }
+        let delimiter = if self.input[self.position..].starts_with("\"\"\"") { "\"\"\"" } else { "\"" };
+        let normalize = raw || delimiter.len() > 1;
+        self.position += delimiter.len();
+        if delimiter.len() > 1 {
+            if let Some(len) = self.newline_len() {
+                self.position += len;
+            }
+        }
+        let contents = self.position;
+        // Filled from the first escape or `\r\n` on; until then the contents are a slice of the input
+        let mut resolved: Option<String> = None;
+        loop {
+            match self.current_char() {
+                None => return Err(Error::new("V0002", "Unterminated string", Span::new(start, self.position))),
+                Some('"') if self.input[self.position..].starts_with(delimiter) => break,
+                Some('\r') if normalize && self.newline_len() == Some(2) => {
+                    resolved
+                        .get_or_insert_with(|| self.input[contents..self.position].to_string())
+                        .push('\n');
+                    self.position += 2;
+                }
+                Some('\\') if !raw => {
+                    let escape_start = self.position;
+                    self.advance();
+                    let ch = match self.current_char() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('0') => '\0',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        None => continue,
+                        Some(other) => {
+                            self.advance();
+                            return Err(Error::new(
+                                "V0004",
+                                format!("Unknown escape sequence \\{}", other),
+                                Span::new(escape_start, self.position),
+                            )
+                            .with_help("supported escapes are \\n, \\t, \\r, \\0, \\\" and \\\\; a raw string r\"...\" keeps backslashes as written"));
+                        }
+                    };
+                    resolved
+                        .get_or_insert_with(|| self.input[contents..escape_start].to_string())
+                        .push(ch);
+                    self.advance();
+                }
+                Some(ch) => {
+                    if let Some(resolved) = &mut resolved {
+                        resolved.push(ch);
+                    }
+                    self.advance();
+                }
+            }
+        }
+        let text = match resolved {
+            Some(resolved) => Cow::Owned(resolved),
+            None => Cow::Borrowed(&self.input[contents..self.position]),
+        };
+        self.position += delimiter.len();
+        Ok(TokenKind::StringLiteral(text))
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let token = self.next_token();
+        self.done = token.as_ref().map_or(true, |token| token.kind == TokenKind::Eof);
+        Some(token)
+    }
+}
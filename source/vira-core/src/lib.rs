@@ -0,0 +1,103 @@
+pub mod ast;
+pub mod cache;
+pub mod check;
+pub mod lexer;
+pub mod lossless;
+pub mod parser;
+pub mod resolve;
+pub mod symbol;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Byte range into the source text, `start` inclusive and `end` exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// A lexing or parsing failure, carrying the stable code documented by `diagnostic --explain`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub help: Option<String>,
+    /// Other places the error relates to, such as the `{` that a missing `}` would close.
+    pub labels: Vec<(Span, String)>,
+    /// An edit certain to fix the error: the text to put in place of the span, which is empty for
+    /// an insertion. `compiler check --fix` applies it. Boxed, as every token the lexer returns
+    /// carries room for an `Error`.
+    pub fix: Option<Box<(Span, String)>>,
+}
+
+impl Error {
+    pub fn new(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Error {
+            code,
+            message: message.into(),
+            span,
+            help: None,
+            labels: Vec::new(),
+            fix: None,
+        }
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, text: impl Into<String>) -> Self {
+        self.labels.push((span, text.into()));
+        self
+    }
+
+    pub fn with_fix(mut self, span: Span, replacement: impl Into<String>) -> Self {
+        self.fix = Some(Box::new((span, replacement.into())));
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error[{}]: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Runs `f`, first switching to a new stack segment when little of the current one is left.
+/// Code that recurses over expressions calls this at each level: `1 + 1 + ...` nests one level
+/// per term, and generated files can hold hundreds of thousands of them.
+pub fn ensure_stack<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(64 * 1024, 1024 * 1024, f)
+}
+
+pub use ast::{Expr, Program, Stmt};
+pub use check::{check, check_with};
+pub use lexer::{Comment, Lexer, Token, TokenKind};
+pub use lossless::SyntaxTree;
+pub use parser::{parse, parse_all, Parser};
+pub use resolve::{resolve, Resolution, SymbolId};
+pub use symbol::Symbol;
@@ -0,0 +1,123 @@
+//! Lossless token stream. Every byte of the source belongs either to a token or to the trivia
+//! attached to one, so printing the tokens back reproduces the file exactly.
+//!
+//! Trivia after a token up to and including the end of its line is trailing trivia of that token;
+//! everything from the start of the next line up to the next token is leading trivia of that token.
+//! The final `Eof` token carries whatever follows the last real token's line.
+
+use serde::Serialize;
+use std::fmt;
+
+use crate::ast::Program;
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::parser::Parser;
+use crate::{Error, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TriviaKind {
+    /// Spaces and tabs, never containing a line break.
+    Whitespace,
+    /// A single `\n` or `\r\n`.
+    Newline,
+    /// A `//` comment, without the line break that ends it.
+    Comment,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Trivia<'src> {
+    pub kind: TriviaKind,
+    pub text: &'src str,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LosslessToken<'src> {
+    pub token: Token<'src>,
+    pub text: &'src str,
+    pub leading: Vec<Trivia<'src>>,
+    pub trailing: Vec<Trivia<'src>>,
+}
+
+impl<'src> LosslessToken<'src> {
+    pub fn comments(&self) -> impl Iterator<Item = &Trivia<'src>> {
+        self.leading.iter().chain(&self.trailing).filter(|t| t.kind == TriviaKind::Comment)
+    }
+
+    /// Number of empty lines in the leading trivia, i.e. blank lines directly above this token.
+    pub fn blank_lines_before(&self) -> usize {
+        let mut blank = 0;
+        let mut line_empty = true;
+        for trivia in &self.leading {
+            match trivia.kind {
+                TriviaKind::Newline if line_empty => blank += 1,
+                TriviaKind::Newline => line_empty = true,
+                TriviaKind::Comment => line_empty = false,
+                TriviaKind::Whitespace => {}
+            }
+        }
+        blank
+    }
+}
+
+/// Tokens with trivia alongside the AST parsed from them. AST spans index the same source the tokens came from.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyntaxTree<'src> {
+    pub tokens: Vec<LosslessToken<'src>>,
+    pub program: Program,
+}
+
+impl<'src> SyntaxTree<'src> {
+    pub fn parse(input: &'src str) -> Result<Self, Error> {
+        let tokens = tokenize(input)?;
+        let program = Parser::new(tokens.iter().map(|t| Ok(t.token.clone()))).parse_program()?;
+        Ok(SyntaxTree { tokens, program })
+    }
+
+    /// Index of the token starting at byte `offset`, for mapping AST spans back to their trivia.
+    pub fn token_at(&self, offset: usize) -> Option<&LosslessToken<'src>> {
+        let index = self.tokens.partition_point(|t| t.token.span.start < offset);
+        self.tokens.get(index).filter(|t| t.token.span.start == offset)
+    }
+}
+
+impl fmt::Display for SyntaxTree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            for trivia in &token.leading {
+                f.write_str(trivia.text)?;
+            }
+            f.write_str(token.text)?;
+            for trivia in &token.trailing {
+                f.write_str(trivia.text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lexes `input` keeping all trivia. The last token is always `Eof`.
+pub fn tokenize(input: &str) -> Result<Vec<LosslessToken<'_>>, Error> {
+    let mut lexer = Lexer::new(input);
+    let mut tokens: Vec<LosslessToken> = Vec::new();
+    loop {
+        let mut leading = lexer.lex_trivia();
+        if let Some(prev) = tokens.last_mut() {
+            let split = leading
+                .iter()
+                .position(|t| t.kind == TriviaKind::Newline)
+                .map_or(leading.len(), |i| i + 1);
+            prev.trailing = leading.drain(..split).collect();
+        }
+        let token = lexer.next_token()?;
+        let done = token.kind == TokenKind::Eof;
+        tokens.push(LosslessToken {
+            text: &input[token.span.start..token.span.end],
+            token,
+            leading,
+            trailing: Vec::new(),
+        });
+        if done {
+            return Ok(tokens);
+        }
+    }
+}
@@ -0,0 +1,715 @@
+use crate::ast::{BinOp, Block, Else, Expr, MatchArm, NamedArg, Param, Pattern, Program, Stmt, UnOp, Variant};
+use crate::lexer::{Comment, Lexer, Token, TokenKind};
+use crate::check::suggest;
+use crate::lexer::KEYWORDS;
+use crate::{ensure_stack, Error, Span, Symbol};
+
+/// Pulls tokens from `tokens` as it goes, looking at most two ahead, so a file is lexed and
+/// parsed in one pass without a token list in between.
+pub struct Parser<'src, I = Lexer<'src>> {
+    tokens: I,
+    current: Token<'src>,
+    next: Token<'src>,
+    /// Where the last token pulled ends, for the `Eof` of a stream that stops without one.
+    end: usize,
+    /// The lexing error that cut the token stream short.
+    error: Option<Error>,
+    /// What would have been accepted at the current token, for the message if nothing is.
+    expected: Vec<Expected>,
+    /// Brackets opened and not yet closed, innermost last, as where each opened and its opening
+    /// and closing text.
+    open: Vec<(Span, &'static str, &'static str)>,
+    /// Syntax errors so far. After each, parsing goes on at the next statement.
+    errors: Vec<Error>,
+    /// Where the last token taken ended, which is where a missing `;` goes.
+    last_end: usize,
+}
+
+/// Something the parser would have accepted where it found something else.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expected {
+    Punct(&'static str),
+    /// A kind of token or construct, such as "an identifier".
+    Other(&'static str),
+}
+
+/// Parses a whole file, returning the program and the comments the lexer skipped, or the first
+/// error.
+pub fn parse(input: &str) -> Result<(Program, Vec<Comment<'_>>), Error> {
+    let mut parser = Parser::new(Lexer::new(input));
+    let program = parser.parse_program()?;
+    Ok((program, parser.into_tokens().into_comments()))
+}
+
+/// Like `parse`, but reports every syntax error in the file, in source order, along with what
+/// could be parsed around them. The program is only complete when there are no errors.
+pub fn parse_all(input: &str) -> (Program, Vec<Comment<'_>>, Vec<Error>) {
+    let mut parser = Parser::new(Lexer::new(input));
+    let (program, errors) = parser.parse_recovering();
+    (program, parser.into_tokens().into_comments(), errors)
+}
+
+impl<'src, I: Iterator<Item = Result<Token<'src>, Error>>> Parser<'src, I> {
+    /// `tokens` should end with an `Eof` token, as a `Lexer` does; the first error ends the stream.
+    pub fn new(mut tokens: I) -> Self {
+        let mut end = 0;
+        let mut error = None;
+        let current = pull(&mut tokens, &mut end, &mut error);
+        let next = if current.kind == TokenKind::Eof {
+            current.clone()
+        } else {
+            pull(&mut tokens, &mut end, &mut error)
+        };
+        Parser {
+            tokens,
+            current,
+            next,
+            end,
+            error,
+            expected: Vec::new(),
+            open: Vec::new(),
+            errors: Vec::new(),
+            last_end: 0,
+        }
+    }
+
+    /// Gives back the token source, such as the lexer with the comments it collected.
+    pub fn into_tokens(self) -> I {
+        self.tokens
+    }
+
+    /// Parses up to `Eof`, failing with the first error.
+    pub fn parse_program(&mut self) -> Result<Program, Error> {
+        let (program, errors) = self.parse_recovering();
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(program),
+        }
+    }
+
+    /// Parses up to `Eof`, skipping each statement that fails to parse and returning every error.
+    /// A lexing error replaces the parse errors at or after it, which are usually the parser
+    /// tripping over where the tokens stopped.
+    pub fn parse_recovering(&mut self) -> (Program, Vec<Error>) {
+        let mut statements = Vec::new();
+        while self.peek().kind != TokenKind::Eof {
+            let result = if self.at_keyword("def") {
+                self.parse_function()
+            } else if self.at_keyword("enum") {
+                self.parse_enum()
+            } else {
+                self.parse_statement()
+            };
+            match result {
+                Ok(stmt) => statements.push(stmt),
+                Err(error) => {
+                    self.report(error);
+                    self.synchronize(0);
+                }
+            }
+        }
+        let mut errors = std::mem::take(&mut self.errors);
+        if let Some(error) = self.error.take() {
+            errors.retain(|parse_error| parse_error.span.start < error.span.start);
+            errors.push(error);
+        }
+        (Program { statements }, errors)
+    }
+
+    /// Keeps `error` unless one was already reported at the same place, which happens when a
+    /// missing `}` at the end of the file fails every block still open.
+    fn report(&mut self, error: Error) {
+        if self.errors.last().is_none_or(|last| last.span.start != error.span.start) {
+            self.errors.push(error);
+        }
+    }
+
+    /// Skips the rest of a statement that failed to parse, `depth` being the number of brackets
+    /// open where it started. It ends after the next `;` or after the `}` of a block the statement
+    /// opened, such as a function body, or before the `}` closing the block around it. At the top
+    /// level, it also stops before the next `def` or `enum`, and skips a stray `}`.
+    fn synchronize(&mut self, depth: usize) {
+        loop {
+            let braces = self.open[depth.min(self.open.len())..].iter().filter(|(_, opening, _)| *opening == "{").count();
+            match self.peek().kind {
+                TokenKind::Eof => break,
+                TokenKind::Punctuator(";") if braces == 0 => {
+                    self.advance();
+                    break;
+                }
+                TokenKind::Punctuator("}") if braces == 0 => {
+                    if depth == 0 {
+                        self.advance();
+                    }
+                    break;
+                }
+                TokenKind::Punctuator("}") if braces == 1 => {
+                    self.advance();
+                    break;
+                }
+                TokenKind::Keyword("def" | "enum") if depth == 0 && braces == 0 => break,
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+        // Brackets left open by the skipped statement are given up on
+        self.open.truncate(depth);
+    }
+
+    fn peek(&self) -> &Token<'src> {
+        &self.current
+    }
+
+    fn peek_next(&self) -> &Token<'src> {
+        &self.next
+    }
+
+    fn advance(&mut self) -> Token<'src> {
+        if self.current.kind == TokenKind::Eof {
+            return self.current.clone();
+        }
+        self.expected.clear();
+        self.last_end = self.current.span.end;
+        match self.current.kind {
+            TokenKind::Punctuator("{") => self.open.push((self.current.span, "{", "}")),
+            TokenKind::Punctuator("(") => self.open.push((self.current.span, "(", ")")),
+            TokenKind::Punctuator("[") => self.open.push((self.current.span, "[", "]")),
+            // A `}` also closes any bracket left open inside its block
+            TokenKind::Punctuator("}") => {
+                if let Some(index) = self.open.iter().rposition(|(_, opening, _)| *opening == "{") {
+                    self.open.truncate(index);
+                }
+            }
+            TokenKind::Punctuator(closing @ (")" | "]"))
+                if self.open.last().is_some_and(|(_, _, expected)| *expected == closing) =>
+            {
+                self.open.pop();
+            }
+            _ => {}
+        }
+        let following = if self.next.kind == TokenKind::Eof {
+            self.next.clone()
+        } else {
+            pull(&mut self.tokens, &mut self.end, &mut self.error)
+        };
+        let next = std::mem::replace(&mut self.next, following);
+        std::mem::replace(&mut self.current, next)
+    }
+
+    fn at_punct(&self, punct: &str) -> bool {
+        matches!(self.peek().kind, TokenKind::Punctuator(p) if p == punct)
+    }
+
+    /// Whether `name =` comes next, starting an assignment or a named argument.
+    fn at_name_and_equals(&self) -> bool {
+        matches!(self.peek().kind, TokenKind::Identifier(_)) && matches!(self.peek_next().kind, TokenKind::Punctuator("="))
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek().kind, TokenKind::Keyword(k) if k == keyword)
+    }
+
+    /// Notes that `expected` would be accepted at the current token.
+    fn expect(&mut self, expected: Expected) {
+        if !self.expected.contains(&expected) {
+            self.expected.push(expected);
+        }
+    }
+
+    /// Like `at_punct`, at a point where `punct` is one of the choices.
+    fn check_punct(&mut self, punct: &'static str) -> bool {
+        self.expect(Expected::Punct(punct));
+        self.at_punct(punct)
+    }
+
+    /// `context` says where in the construct the token is missing, as in "after the variable name".
+    fn expect_punct(&mut self, punct: &'static str, context: &str) -> Result<Span, Error> {
+        if self.check_punct(punct) {
+            Ok(self.advance().span)
+        } else if punct == ";" {
+            // The statement is complete up to here, or the parser would have gone on with it
+            let end = Span::new(self.last_end, self.last_end);
+            let error = self.unexpected(context).with_fix(end, ";");
+            // When a new statement or the end of the block follows, parsing carries on as if the `;`
+            // were there, so that one missing `;` doesn't cost the rest of the block
+            if matches!(self.peek().kind, TokenKind::Keyword(_) | TokenKind::Punctuator("}")) {
+                self.report(error);
+                Ok(end)
+            } else {
+                Err(error)
+            }
+        } else {
+            Err(self.unexpected(context))
+        }
+    }
+
+    fn expect_identifier(&mut self, context: &str) -> Result<(Symbol, Span), Error> {
+        match self.peek().kind {
+            TokenKind::Identifier(name) => {
+                let name = Symbol::intern(name);
+                Ok((name, self.advance().span))
+            }
+            _ => Err(self.expected(Expected::Other("an identifier"), context)),
+        }
+    }
+
+    /// `unexpected`, after noting one more thing that would have been accepted.
+    fn expected(&mut self, expected: Expected, context: &str) -> Error {
+        self.expect(expected);
+        self.unexpected(context)
+    }
+
+    /// An error at the current token listing everything that would have been accepted there. When
+    /// the closing bracket of the innermost open one is among them, or the file ended, a second
+    /// label points at where that bracket opened.
+    fn unexpected(&self, context: &str) -> Error {
+        let token = self.peek();
+        let expected: Vec<String> = self
+            .expected
+            .iter()
+            .map(|expected| match expected {
+                Expected::Punct(punct) => format!("'{}'", punct),
+                Expected::Other(what) => what.to_string(),
+            })
+            .collect();
+        let mut message = format!("Expected {}", one_of(&expected));
+        if !context.is_empty() {
+            message.push(' ');
+            message.push_str(context);
+        }
+        message.push_str(&format!(", found {}", describe(&token.kind)));
+        let mut error = Error::new("V0010", message, token.span);
+        if let Some(&(span, opening, closing)) = self.open.last() {
+            if token.kind == TokenKind::Eof {
+                error = error.with_label(span, format!("this '{}' is never closed", opening));
+            } else if self.expected.contains(&Expected::Punct(closing)) {
+                error = error.with_label(span, format!("'{}' opened here", opening));
+            }
+        }
+        error
+    }
+
+    fn parse_function(&mut self) -> Result<Stmt, Error> {
+        let start = self.advance().span; // def
+        let (name, name_span) = self.expect_identifier("after 'def'")?;
+        self.expect_punct("(", "after the function name")?;
+        let mut params = Vec::new();
+        if !self.check_punct(")") {
+            loop {
+                let (name, span) = self.expect_identifier("in the parameter list")?;
+                let default = if self.check_punct("=") {
+                    self.advance();
+                    Some(self.parse_default()?)
+                } else {
+                    None
+                };
+                params.push(Param { name, span, default });
+                if !self.check_punct(",") {
+                    break;
+                }
+                self.advance();
+            }
+        }
+        self.expect_punct(")", "in the parameter list")?;
+        let body = self.parse_block("before the function body")?;
+        Ok(Stmt::FuncDef {
+            name,
+            name_span,
+            params,
+            span: start.to(body.span),
+            body,
+        })
+    }
+
+    fn parse_enum(&mut self) -> Result<Stmt, Error> {
+        let start = self.advance().span; // enum
+        let (name, name_span) = self.expect_identifier("after 'enum'")?;
+        self.expect_punct("{", "after the enum name")?;
+        let mut variants = Vec::new();
+        // A trailing comma is allowed, since variants are often listed one per line
+        while !self.check_punct("}") {
+            let (name, span) = self.expect_identifier("in the variant list")?;
+            variants.push(Variant { name, span });
+            if !self.check_punct(",") {
+                break;
+            }
+            self.advance();
+        }
+        let end = self.expect_punct("}", "in the variant list")?;
+        Ok(Stmt::Enum {
+            name,
+            name_span,
+            variants,
+            span: start.to(end),
+        })
+    }
+
+    /// A parameter's default, which is a number, possibly negated, or a string.
+    fn parse_default(&mut self) -> Result<Expr, Error> {
+        match self.peek().kind {
+            TokenKind::Number(_) | TokenKind::StringLiteral(_) => self.parse_primary(),
+            TokenKind::Punctuator("-") if matches!(self.peek_next().kind, TokenKind::Number(_)) => {
+                let start = self.advance().span;
+                let operand = self.parse_primary()?;
+                let span = start.to(operand.span());
+                Ok(Expr::Unary(UnOp::Neg, Box::new(operand), span))
+            }
+            _ => Err(self.expected(Expected::Other("a number or a string"), "as the default value")),
+        }
+    }
+
+    /// A block whose statements are parsed even after one of them fails; `context` says where the
+    /// `{` is missing.
+    fn parse_block(&mut self, context: &str) -> Result<Block, Error> {
+        let start = self.expect_punct("{", context)?;
+        let depth = self.open.len();
+        let mut statements = Vec::new();
+        while !self.check_punct("}") {
+            if self.peek().kind == TokenKind::Eof {
+                return Err(self.expected(Expected::Other("a statement"), ""));
+            }
+            match self.parse_statement() {
+                Ok(stmt) => statements.push(stmt),
+                Err(error) => {
+                    self.report(error);
+                    self.synchronize(depth);
+                }
+            }
+        }
+        let end = self.advance().span;
+        Ok(Block {
+            statements,
+            span: start.to(end),
+        })
+    }
+
+    fn parse_statement(&mut self) -> Result<Stmt, Error> {
+        let start = self.peek().span;
+        if let TokenKind::Keyword(keyword) = self.peek().kind {
+            match keyword {
+                "let" => {
+                    self.advance();
+                    let (name, name_span) = self.expect_identifier("after 'let'")?;
+                    self.expect_punct("=", "after the variable name")?;
+                    let value = self.parse_expression()?;
+                    let end = self.expect_punct(";", "after the value")?;
+                    return Ok(Stmt::Let {
+                        name,
+                        name_span,
+                        value,
+                        span: start.to(end),
+                    });
+                }
+                "write" => {
+                    self.advance();
+                    let value = self.parse_expression()?;
+                    let end = self.expect_punct(";", "after the value")?;
+                    return Ok(Stmt::Write(value, start.to(end)));
+                }
+                "return" => {
+                    self.advance();
+                    let value = if self.check_punct(";") { None } else { Some(self.parse_expression()?) };
+                    let end = self.expect_punct(";", "after the return value")?;
+                    return Ok(Stmt::Return(value, start.to(end)));
+                }
+                "if" => return self.parse_if(),
+                "while" => {
+                    self.advance();
+                    let condition = self.parse_expression()?;
+                    let body = self.parse_block("after the loop condition")?;
+                    return Ok(Stmt::While {
+                        condition,
+                        span: start.to(body.span),
+                        body,
+                    });
+                }
+                "match" => return self.parse_match(),
+                "def" => {
+                    return Err(Error::new(
+                        "V0011",
+                        "Functions can only be defined at the top level",
+                        start,
+                    ))
+                }
+                "enum" => return Err(Error::new("V0011", "Enums can only be defined at the top level", start)),
+                _ => return Err(self.expected(Expected::Other("a statement"), "")),
+            }
+        }
+        if self.at_name_and_equals() {
+            let (name, name_span) = self.expect_identifier("")?;
+            self.advance(); // =
+            let value = self.parse_expression()?;
+            let end = self.expect_punct(";", "after the value")?;
+            return Ok(Stmt::Assign {
+                name,
+                name_span,
+                value,
+                span: start.to(end),
+            });
+        }
+        if !starts_expression(&self.peek().kind) {
+            return Err(self.expected(Expected::Other("a statement"), ""));
+        }
+        let expr = self.parse_expression()?;
+        let end = match self.expect_punct(";", "after the expression") {
+            Ok(end) => end,
+            Err(error) => return Err(misspelled_keyword(&expr, error)),
+        };
+        Ok(Stmt::Expr(expr, start.to(end)))
+    }
+
+    fn parse_match(&mut self) -> Result<Stmt, Error> {
+        let start = self.advance().span; // match
+        let value = self.parse_expression()?;
+        self.expect_punct("{", "after the value to match")?;
+        let mut arms = Vec::new();
+        while !self.check_punct("}") {
+            let mut patterns = vec![self.parse_pattern()?];
+            while self.check_punct(",") {
+                self.advance();
+                patterns.push(self.parse_pattern()?);
+            }
+            self.expect_punct("=>", "after the pattern")?;
+            let body = self.parse_block("after '=>'")?;
+            arms.push(MatchArm { patterns, body });
+        }
+        let end = self.advance().span;
+        Ok(Stmt::Match {
+            value,
+            arms,
+            span: start.to(end),
+        })
+    }
+
+    /// `Enum.Variant` or `_`.
+    fn parse_pattern(&mut self) -> Result<Pattern, Error> {
+        if !matches!(self.peek().kind, TokenKind::Identifier(_)) {
+            return Err(self.expected(Expected::Other("a variant like `Enum.Variant`, or `_`"), ""));
+        }
+        let (name, span) = self.expect_identifier("")?;
+        if name == "_" && !self.at_punct(".") {
+            return Ok(Pattern::Wildcard(span));
+        }
+        self.expect_punct(".", "after the enum name")?;
+        let (variant, end) = self.expect_identifier("after '.'")?;
+        Ok(Pattern::Variant(name, variant, span.to(end)))
+    }
+
+    fn parse_if(&mut self) -> Result<Stmt, Error> {
+        let start = self.advance().span; // if
+        let condition = self.parse_expression()?;
+        let then_block = self.parse_block("after the condition")?;
+        let mut end = then_block.span;
+        let else_branch = if self.at_keyword("else") {
+            self.advance();
+            if self.at_keyword("if") {
+                let nested = self.parse_if()?;
+                end = nested.span();
+                Some(Else::If(Box::new(nested)))
+            } else {
+                let block = self.parse_block("after 'else'")?;
+                end = block.span;
+                Some(Else::Block(block))
+            }
+        } else {
+            None
+        };
+        Ok(Stmt::If {
+            condition,
+            then_block,
+            else_branch,
+            span: start.to(end),
+        })
+    }
+
+    pub fn parse_expression(&mut self) -> Result<Expr, Error> {
+        self.parse_binary(1)
+    }
+
+    /// Precedence climbing. Chains of one operator are built in a loop rather than by recursion,
+    /// so long sums do not grow the stack.
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr, Error> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Punctuator(p) => BinOp::from_symbol(p),
+                _ => None,
+            };
+            if op.is_none() {
+                self.expect(Expected::Other("an operator"));
+            }
+            let Some(op) = op.filter(|op| op.precedence() >= min_precedence) else {
+                return Ok(left);
+            };
+            self.advance();
+            let right = self.parse_binary(op.precedence() + 1)?;
+            let span = left.span().to(right.span());
+            left = Expr::Binary(op, Box::new(left), Box::new(right), span);
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, Error> {
+        ensure_stack(|| {
+            let op = if self.at_punct("-") {
+                UnOp::Neg
+            } else if self.at_punct("!") {
+                UnOp::Not
+            } else {
+                return self.parse_postfix();
+            };
+            let start = self.advance().span;
+            let operand = self.parse_unary()?;
+            let span = start.to(operand.span());
+            Ok(Expr::Unary(op, Box::new(operand), span))
+        })
+    }
+
+    /// Indexing and slicing, which bind tighter than any operator and chain left to right, as in
+    /// `s[1..][0]`.
+    fn parse_postfix(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.parse_primary()?;
+        while self.at_punct("[") {
+            self.advance();
+            let start = if self.at_punct("..") { None } else { Some(self.parse_expression()?) };
+            expr = match start {
+                Some(index) if !self.check_punct("..") => {
+                    let end = self.expect_punct("]", "to close the index")?;
+                    let span = expr.span().to(end);
+                    Expr::Index(Box::new(expr), Box::new(index), span)
+                }
+                start => {
+                    self.advance(); // ..
+                    let end = if self.at_punct("]") { None } else { Some(self.parse_expression()?) };
+                    let close = self.expect_punct("]", "to close the slice")?;
+                    let span = expr.span().to(close);
+                    Expr::Slice(Box::new(expr), start.map(Box::new), end.map(Box::new), span)
+                }
+            };
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, Error> {
+        let token = self.peek().clone();
+        match token.kind {
+            TokenKind::Number(value) => {
+                self.advance();
+                Ok(Expr::Number(value, token.span))
+            }
+            TokenKind::StringLiteral(value) => {
+                self.advance();
+                Ok(Expr::String(value.into_owned(), token.span))
+            }
+            TokenKind::Identifier(name) => {
+                self.advance();
+                let name = Symbol::intern(name);
+                if self.at_punct(".") {
+                    self.advance();
+                    let (variant, end) = self.expect_identifier("after '.'")?;
+                    return Ok(Expr::Variant(name, variant, token.span.to(end)));
+                }
+                if !self.at_punct("(") {
+                    return Ok(Expr::Identifier(name, token.span));
+                }
+                self.advance();
+                let mut args = Vec::new();
+                let mut named = Vec::new();
+                if !self.check_punct(")") {
+                    loop {
+                        if self.at_name_and_equals() {
+                            let (name, name_span) = self.expect_identifier("")?;
+                            self.advance(); // =
+                            let value = self.parse_expression()?;
+                            named.push(NamedArg { name, name_span, value });
+                        } else if named.is_empty() {
+                            args.push(self.parse_expression()?);
+                        } else {
+                            return Err(self
+                                .expected(Expected::Other("a named argument"), "")
+                                .with_help("pass arguments by position before any by name"));
+                        }
+                        if !self.check_punct(",") {
+                            break;
+                        }
+                        self.advance();
+                    }
+                }
+                let end = self.expect_punct(")", "in the argument list")?;
+                Ok(Expr::Call(name, args, named, token.span.to(end)))
+            }
+            TokenKind::Punctuator("(") => {
+                self.advance();
+                let expr = self.parse_expression()?;
+                self.expect_punct(")", "to close the parenthesis")?;
+                Ok(expr)
+            }
+            _ => Err(self.expected(Expected::Other("an expression"), "")),
+        }
+    }
+}
+
+/// Whether a token can start an expression, and so an expression statement.
+fn starts_expression(kind: &TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Identifier(_) | TokenKind::Number(_) | TokenKind::StringLiteral(_) | TokenKind::Punctuator("(" | "-" | "!")
+    )
+}
+
+/// Adds a hint to `error` when the statement it ends is a lone name close to a keyword, as
+/// `retrun x;` reads as the name `retrun` followed by a stray `x`.
+fn misspelled_keyword(expr: &Expr, error: Error) -> Error {
+    let Expr::Identifier(name, _) = expr else {
+        return error;
+    };
+    let keywords: Vec<&str> = KEYWORDS.iter().copied().filter(|keyword| *keyword != "else").collect();
+    match suggest(name.as_str(), &keywords) {
+        Some(keyword) if error.help.is_none() => {
+            // A `;` after the name would parse, but not mean what was written
+            Error { fix: None, ..error }.with_help(format!("did you mean '{}'?", keyword))
+        }
+        _ => error,
+    }
+}
+
+/// `a`, `a or b`, or `a, b or c`.
+fn one_of(options: &[String]) -> String {
+    match options {
+        [] => "something else".to_string(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} or {}", rest.join(", "), last),
+    }
+}
+
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Identifier(name) => format!("identifier '{}'", name),
+        TokenKind::Keyword(word) => format!("'{}'", word),
+        TokenKind::Number(value) => format!("number {}", value),
+        TokenKind::StringLiteral(_) => "string literal".to_string(),
+        TokenKind::Punctuator(p) => format!("'{}'", p),
+        TokenKind::Eof => "end of file".to_string(),
+    }
+}
+
+/// The next token of the stream, or an `Eof` where it stops, keeping the error that stopped it.
+fn pull<'src>(tokens: &mut impl Iterator<Item = Result<Token<'src>, Error>>, end: &mut usize, error: &mut Option<Error>) -> Token<'src> {
+    let token = match tokens.next() {
+        Some(Ok(token)) => token,
+        Some(Err(err)) => {
+            let token = Token {
+                kind: TokenKind::Eof,
+                span: Span::new(err.span.start, err.span.start),
+            };
+            error.get_or_insert(err);
+            token
+        }
+        None => Token {
+            kind: TokenKind::Eof,
+            span: Span::new(*end, *end),
+        },
+    };
+    *end = token.span.end;
+    token
+}
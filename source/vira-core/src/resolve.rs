@@ -0,0 +1,241 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{Block, Else, Expr, Program, Stmt};
+use crate::check::suggest;
+use crate::{ensure_stack, Error, Span, Symbol};
+
+/// Identifies one declared variable or parameter within a file, an index into `Resolution::declarations`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Parameter,
+    Variable,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub name: Symbol,
+    pub kind: SymbolKind,
+    /// The name in the declaration.
+    pub span: Span,
+    /// Blocks between the declaration and the function body or top level, which are depth 0.
+    pub depth: usize,
+    /// How many times the variable is read; assigning to it doesn't count.
+    pub reads: usize,
+}
+
+/// A declaration that hides another variable of the same name that would otherwise be visible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shadowing {
+    pub symbol: SymbolId,
+    pub shadowed: SymbolId,
+}
+
+/// What every variable name in a file refers to.
+#[derive(Debug, Clone, Default)]
+pub struct Resolution {
+    /// Every declaration in source order.
+    pub declarations: Vec<Declaration>,
+    /// The symbol each name stands for, keyed by the span of the name: uses, assignment targets and
+    /// the declarations themselves.
+    references: HashMap<Span, SymbolId>,
+    pub shadowings: Vec<Shadowing>,
+    /// Uses of undeclared variables, uses before the declaration and repeated parameter names.
+    pub errors: Vec<Error>,
+}
+
+impl Resolution {
+    pub fn declaration(&self, id: SymbolId) -> &Declaration {
+        &self.declarations[id.0 as usize]
+    }
+
+    /// The symbol the name at `span` refers to or declares, if it resolved.
+    pub fn lookup(&self, span: Span) -> Option<SymbolId> {
+        self.references.get(&span).copied()
+    }
+}
+
+/// Resolves every variable name in a program to its declaration. Variables are visible from their
+/// `let` to the end of the enclosing block, and a function body sees only its own parameters and
+/// locals, not the variables of the top-level program. Functions are left to `check`.
+pub fn resolve(program: &Program) -> Resolution {
+    let mut resolver = Resolver {
+        resolution: Resolution::default(),
+        scopes: Vec::new(),
+    };
+    resolver.scoped(&program.statements, Vec::new());
+    resolver.resolution
+}
+
+struct Scope {
+    /// Names declared so far, most recent last, so a redeclaration wins.
+    declared: Vec<(Symbol, SymbolId)>,
+    /// The `let`s further down the block, to tell a use before the declaration from a typo.
+    later: HashSet<Symbol>,
+}
+
+struct Resolver {
+    resolution: Resolution,
+    /// Innermost last; a function body starts over with just its parameters.
+    scopes: Vec<Scope>,
+}
+
+impl Resolver {
+    /// Resolves `statements` in a new scope that starts out with `declared`.
+    fn scoped(&mut self, statements: &[Stmt], declared: Vec<(Symbol, SymbolId)>) {
+        let later = statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Let { name, .. } => Some(*name),
+                _ => None,
+            })
+            .collect();
+        self.scopes.push(Scope { declared, later });
+        for stmt in statements {
+            self.stmt(stmt);
+        }
+        self.scopes.pop();
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.scoped(&block.statements, Vec::new());
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Let { name, name_span, value, .. } => {
+                // The value is resolved first, so `let x = x + 1;` reads an outer `x`
+                self.expr(value);
+                self.declare(*name, *name_span, SymbolKind::Variable);
+            }
+            Stmt::Assign { name, name_span, value, .. } => {
+                self.expr(value);
+                self.use_variable(*name, *name_span, true);
+            }
+            Stmt::FuncDef { params, body, .. } => {
+                let outer = std::mem::take(&mut self.scopes);
+                self.scopes.push(Scope {
+                    declared: Vec::new(),
+                    later: HashSet::new(),
+                });
+                for param in params {
+                    if self.scopes[0].declared.iter().any(|(name, _)| *name == param.name) {
+                        self.resolution.errors.push(
+                            Error::new("V0105", format!("Parameter '{}' is declared more than once", param.name), param.span)
+                                .with_help("rename one of the parameters"),
+                        );
+                        continue;
+                    }
+                    self.declare(param.name, param.span, SymbolKind::Parameter);
+                }
+                let params = self.scopes.pop().map(|scope| scope.declared).unwrap_or_default();
+                self.scoped(&body.statements, params);
+                self.scopes = outer;
+            }
+            Stmt::Write(expr, _) | Stmt::Expr(expr, _) | Stmt::Return(Some(expr), _) => self.expr(expr),
+            Stmt::Return(None, _) | Stmt::Enum { .. } => {}
+            Stmt::Match { value, arms, .. } => {
+                self.expr(value);
+                for arm in arms {
+                    self.block(&arm.body);
+                }
+            }
+            Stmt::If {
+                condition,
+                then_block,
+                else_branch,
+                ..
+            } => {
+                self.expr(condition);
+                self.block(then_block);
+                match else_branch {
+                    Some(Else::If(stmt)) => self.stmt(stmt),
+                    Some(Else::Block(block)) => self.block(block),
+                    None => {}
+                }
+            }
+            Stmt::While { condition, body, .. } => {
+                self.expr(condition);
+                self.block(body);
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        ensure_stack(|| match expr {
+            Expr::Number(..) | Expr::String(..) | Expr::Variant(..) => {}
+            Expr::Identifier(name, span) => self.use_variable(*name, *span, false),
+            Expr::Call(_, args, named, _) => {
+                for arg in args.iter().chain(named.iter().map(|arg| &arg.value)) {
+                    self.expr(arg);
+                }
+            }
+            Expr::Unary(_, operand, _) => self.expr(operand),
+            Expr::Binary(_, left, right, _) | Expr::Index(left, right, _) => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Slice(value, start, end, _) => {
+                self.expr(value);
+                for bound in [start, end].into_iter().flatten() {
+                    self.expr(bound);
+                }
+            }
+        })
+    }
+
+    fn declare(&mut self, name: Symbol, span: Span, kind: SymbolKind) {
+        let id = SymbolId(self.resolution.declarations.len() as u32);
+        if let Some(shadowed) = self.visible(name) {
+            self.resolution.shadowings.push(Shadowing { symbol: id, shadowed });
+        }
+        let depth = self.scopes.len().saturating_sub(1);
+        self.resolution.declarations.push(Declaration {
+            name,
+            kind,
+            span,
+            depth,
+            reads: 0,
+        });
+        self.resolution.references.insert(span, id);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.declared.push((name, id));
+            scope.later.remove(&name);
+        }
+    }
+
+    fn visible(&self, name: Symbol) -> Option<SymbolId> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.declared.iter().rev().find(|(declared, _)| *declared == name))
+            .map(|(_, id)| *id)
+    }
+
+    /// Resolves a read, or with `assign`, the target of an assignment.
+    fn use_variable(&mut self, name: Symbol, span: Span, assign: bool) {
+        if let Some(id) = self.visible(name) {
+            self.resolution.references.insert(span, id);
+            if !assign {
+                self.resolution.declarations[id.0 as usize].reads += 1;
+            }
+            return;
+        }
+        if self.scopes.iter().any(|scope| scope.later.contains(&name)) {
+            self.resolution.errors.push(
+                Error::new("V0106", format!("Variable '{}' is used before its declaration", name), span)
+                    .with_help(format!("move `let {} = ...;` before this line", name)),
+            );
+            return;
+        }
+        let error = Error::new("V0102", format!("Undefined variable: {}", name), span);
+        let visible: Vec<&str> = self.scopes.iter().flat_map(|scope| &scope.declared).map(|(name, _)| name.as_str()).collect();
+        self.resolution.errors.push(match suggest(name.as_str(), &visible) {
+            Some(best) => error.with_help(format!("did you mean '{}'?", best)),
+            None if assign => error.with_help(format!("declare it first with `let {} = ...;`", name)),
+            None => error,
+        });
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{LazyLock, RwLock};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An interned identifier. Equal names get the same symbol, so comparing and hashing names is
+/// comparing and hashing a `u32`, and copying one allocates nothing.
+///
+/// The interner is global, so a symbol means the same name on every thread, and never frees a
+/// name; a program only has so many identifiers. Symbols are numbered in the order names are
+/// first seen, which differs between runs, so they have no order of their own: sort by `as_str`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+/// Bytes set aside at a time for names.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+#[derive(Default)]
+struct Interner {
+    symbols: HashMap<&'static str, Symbol>,
+    names: Vec<&'static str>,
+    /// Where the names are kept, packed together. A chunk is never filled past its capacity, so it
+    /// never reallocates and the names in it stay put.
+    chunks: Vec<String>,
+}
+
+impl Interner {
+    fn store(&mut self, name: &str) -> &'static str {
+        let fits = self.chunks.last().is_some_and(|chunk| chunk.capacity() - chunk.len() >= name.len());
+        if !fits {
+            self.chunks.push(String::with_capacity(name.len().max(CHUNK_SIZE)));
+        }
+        let chunk = self.chunks.last_mut().expect("a chunk was just added");
+        let start = chunk.len();
+        chunk.push_str(name);
+        // SAFETY: the chunk's buffer never moves, as nothing is pushed past its capacity, and is
+        // never freed, as the interner lives in a static
+        unsafe { &*(&chunk[start..] as *const str) }
+    }
+}
+
+static INTERNER: LazyLock<RwLock<Interner>> = LazyLock::new(Default::default);
+
+impl Symbol {
+    pub fn intern(name: &str) -> Symbol {
+        if let Some(&symbol) = INTERNER.read().unwrap_or_else(|e| e.into_inner()).symbols.get(name) {
+            return symbol;
+        }
+        let mut interner = INTERNER.write().unwrap_or_else(|e| e.into_inner());
+        // Another thread may have added it between the two locks
+        if let Some(&symbol) = interner.symbols.get(name) {
+            return symbol;
+        }
+        let name = interner.store(name);
+        let symbol = Symbol(interner.names.len() as u32);
+        interner.names.push(name);
+        interner.symbols.insert(name, symbol);
+        symbol
+    }
+
+    pub fn as_str(self) -> &'static str {
+        INTERNER.read().unwrap_or_else(|e| e.into_inner()).names[self.0 as usize]
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// Serialized as the name itself, since the numbers differ from one run to the next.
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|name| Symbol::intern(&name))
+    }
+}
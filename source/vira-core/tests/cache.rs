@@ -0,0 +1,48 @@
+use vira_core::cache::{self, CacheError};
+use vira_core::{parse, Program};
+
+const SOURCE: &str = "def add(a, b) {\n    return a + b;\n}\nlet s = \"text\";\nif add(1, 2) > 2 { write s; } else { write 0; }\n";
+
+fn program() -> Program {
+    parse(SOURCE).unwrap().0
+}
+
+#[test]
+fn decoding_gives_back_the_encoded_program() {
+    let program = program();
+    let cached = cache::decode(&cache::encode(&program, SOURCE)).unwrap();
+    assert_eq!(cached.program, program);
+    assert!(cached.is_fresh(SOURCE));
+}
+
+#[test]
+fn an_entry_for_other_source_is_stale() {
+    let program = program();
+    let cached = cache::decode(&cache::encode(&program, SOURCE)).unwrap();
+    assert!(!cached.is_fresh(&SOURCE.replace("1, 2", "1, 3")));
+    assert!(!cached.is_fresh(""));
+}
+
+#[test]
+fn an_entry_from_another_format_version_is_rejected() {
+    let mut bytes = cache::encode(&program(), SOURCE);
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    bytes[4..8].copy_from_slice(&(version + 1).to_le_bytes());
+    assert!(matches!(cache::decode(&bytes), Err(CacheError::Version(found)) if found == version + 1));
+}
+
+#[test]
+fn bytes_without_the_magic_are_not_a_cache() {
+    let bytes = cache::encode(&program(), SOURCE);
+    assert!(matches!(cache::decode(b""), Err(CacheError::NotACache)));
+    assert!(matches!(cache::decode(&bytes[..6]), Err(CacheError::NotACache)));
+    assert!(matches!(cache::decode(SOURCE.as_bytes()), Err(CacheError::NotACache)));
+}
+
+#[test]
+fn a_truncated_entry_is_corrupt() {
+    let bytes = cache::encode(&program(), SOURCE);
+    for len in [8, 12, bytes.len() / 2, bytes.len() - 1] {
+        assert!(matches!(cache::decode(&bytes[..len]), Err(CacheError::Corrupt(_))), "length {}", len);
+    }
+}
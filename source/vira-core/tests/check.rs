@@ -0,0 +1,80 @@
+use vira_core::check;
+use vira_core::parser::parse;
+
+/// Codes and source text of the errors `check` finds in `source`.
+fn errors(source: &str) -> Vec<(&'static str, &str)> {
+    let (program, _) = parse(source).unwrap();
+    check(&program).into_iter().map(|error| (error.code, &source[error.span.start..error.span.end])).collect()
+}
+
+#[test]
+fn rejects_string_defaults_and_arguments() {
+    let source = "def greet(name, greeting = \"hi\") { return name; }\ngreet(name = \"bob\");\n";
+    assert_eq!(errors(source), [("V0112", "\"hi\""), ("V0112", "\"bob\"")]);
+}
+
+#[test]
+fn accepts_number_defaults_and_named_arguments() {
+    let source = "def area(width, height = 2) { return width * height; }\nwrite area(height = 3, width = 4);\nwrite area(5);\n";
+    assert_eq!(errors(source), []);
+}
+
+#[test]
+fn user_functions_replace_builtins() {
+    let source = "def panic(message, code = 1) { write message; return code; }\nwrite panic(0, code = 2);\n";
+    assert_eq!(errors(source), []);
+    assert_eq!(errors("def len(s) { return 0; }\ndef len(s) { return 1; }\n"), [("V0103", "len")]);
+}
+
+#[test]
+fn accepts_enums_used_in_expressions_and_matches() {
+    let source = "\
+enum Color { Red, Green, Blue }
+let c = Color.Green;
+match c {
+    Color.Red, Color.Blue => { write 1; }
+    _ => { write c == Color.Green; }
+}
+";
+    assert_eq!(errors(source), []);
+}
+
+#[test]
+fn enums_and_variants_are_defined_once() {
+    let source = "enum Color { Red, Green }\nenum Color { Blue }\nenum Shape { Circle, Square, Circle }\n";
+    assert_eq!(errors(source), [("V0109", "Color"), ("V0109", "Circle")]);
+}
+
+#[test]
+fn rejects_unknown_enums_and_variants() {
+    let source = "\
+enum Color { Red, Green }
+write Colour.Red;
+write Color.Purple;
+match Color.Red {
+    Color.Gren => { write 1; }
+    _ => { write 2; }
+}
+";
+    assert_eq!(errors(source), [("V0110", "Colour.Red"), ("V0110", "Color.Purple"), ("V0110", "Color.Gren")]);
+}
+
+#[test]
+fn unknown_variants_suggest_a_close_one() {
+    let (program, _) = parse("enum Color { Red, Green }\nwrite Color.Gren;\nwrite Colour.Red;\n").unwrap();
+    let helps: Vec<_> = check(&program).into_iter().map(|error| error.help).collect();
+    assert_eq!(helps, [Some("did you mean 'Color.Green'?".to_string()), Some("did you mean 'Color'?".to_string())]);
+}
+
+#[test]
+fn a_match_uses_the_variants_of_one_enum() {
+    let source = "\
+enum Color { Red, Green }
+enum Shape { Circle, Square }
+match Color.Red {
+    Color.Red => { write 1; }
+    Shape.Circle, Color.Green => { write 2; }
+}
+";
+    assert_eq!(errors(source), [("V0111", "Shape.Circle")]);
+}
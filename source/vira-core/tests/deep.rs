@@ -0,0 +1,31 @@
+//! Expressions nested far deeper than the stack could hold one frame per level for.
+
+use vira_core::ast::{BinOp, Expr, Stmt};
+use vira_core::parser::parse;
+use vira_core::{check, resolve::resolve};
+
+const TERMS: usize = 100_000;
+
+fn long_sum() -> String {
+    format!("write {};\n", vec!["1"; TERMS].join("+"))
+}
+
+#[test]
+fn parses_and_checks_a_sum_of_100k_terms() {
+    let source = long_sum();
+    let (program, _) = parse(&source).unwrap();
+    assert!(check(&program).is_empty());
+    resolve(&program);
+
+    let Stmt::Write(expr, _) = &program.statements[0] else { panic!("expected a write") };
+    let mut expr = expr;
+    // `+` is left-associative, so the chain runs down the left operands
+    let mut operators = 0;
+    while let Expr::Binary(BinOp::Add, left, right, _) = expr {
+        assert!(matches!(**right, Expr::Number(value, _) if value == 1.0));
+        operators += 1;
+        expr = left;
+    }
+    assert!(matches!(expr, Expr::Number(value, _) if *value == 1.0));
+    assert_eq!(operators, TERMS - 1);
+}
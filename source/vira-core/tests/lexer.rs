@@ -0,0 +1,51 @@
+use vira_core::{Lexer, TokenKind};
+
+/// The contents of the string literal `source` starts with, or the code and source text of the
+/// error lexing it.
+fn string(source: &str) -> Result<String, (&'static str, &str)> {
+    match Lexer::new(source).next_token() {
+        Ok(token) => match token.kind {
+            TokenKind::StringLiteral(text) => Ok(text.into_owned()),
+            other => panic!("expected a string literal, found {:?}", other),
+        },
+        Err(error) => Err((error.code, &source[error.span.start..error.span.end])),
+    }
+}
+
+#[test]
+fn raw_strings_keep_backslashes() {
+    assert_eq!(string(r#"r"C:\new\table""#), Ok(r"C:\new\table".to_string()));
+    assert_eq!(string(r#"r"\q""#), Ok(r"\q".to_string()));
+    // Not an escape, so it doesn't keep the string open
+    assert_eq!(string(r#"r"ends\" x"#), Ok("ends\\".to_string()));
+}
+
+#[test]
+fn triple_quoted_strings_hold_quotes_and_line_breaks() {
+    assert_eq!(string("\"\"\"\nsay \"hi\"\nand \"\"bye\"\"\n\"\"\""), Ok("say \"hi\"\nand \"\"bye\"\"\n".to_string()));
+    // Only the line break right after the opening quotes is dropped
+    assert_eq!(string("\"\"\"\n\nx\"\"\""), Ok("\nx".to_string()));
+    assert_eq!(string("\"\"\"a\r\nb\"\"\""), Ok("a\nb".to_string()));
+}
+
+#[test]
+fn escapes_apply_in_triple_quoted_strings_but_not_raw_ones() {
+    assert_eq!(string("\"\"\"a\\tb\\\"\"\"\""), Ok("a\tb\"".to_string()));
+    assert_eq!(string("r\"\"\"a\\tb \"q\" \"\"\""), Ok("a\\tb \"q\" ".to_string()));
+    assert_eq!(string("r\"\"\"\r\nline\r\n\"\"\""), Ok("line\n".to_string()));
+}
+
+#[test]
+fn unknown_escapes_are_errors_outside_raw_strings() {
+    assert_eq!(string(r#""a\qb""#), Err(("V0004", r"\q")));
+    assert_eq!(string("\"\"\"a\\qb\"\"\""), Err(("V0004", r"\q")));
+    assert!(string(r#"r"a\qb""#).is_ok());
+}
+
+#[test]
+fn unterminated_strings_span_to_the_end() {
+    assert_eq!(string("\"open"), Err(("V0002", "\"open")));
+    assert_eq!(string("r\"open\\"), Err(("V0002", "r\"open\\")));
+    assert_eq!(string("\"\"\"one \"\" two\n"), Err(("V0002", "\"\"\"one \"\" two\n")));
+    assert_eq!(string("r\"\"\"\nline"), Err(("V0002", "r\"\"\"\nline")));
+}
@@ -0,0 +1,68 @@
+use vira_core::lossless::{tokenize, TriviaKind};
+use vira_core::SyntaxTree;
+
+const SOURCES: &[&str] = &[
+    "",
+    "\n\n",
+    "// only a comment",
+    "write 1;",
+    "// Leading comment\n\n\nlet x = 1;   // trailing\n\n\n// before f\ndef f(a) {\n\n    return a; // done\n}\n\n\n",
+    "let s = \"a // not a comment\";\r\n\r\n// crlf comment\r\nwrite s;\r\n",
+    "  \t let   y=2 ;\t\n\twrite y;  \n// after the last token, with no line break",
+    "let mixed = 1;\r\n\nwrite mixed;\n\r\n",
+    "let t = \"\"\"\r\nline\r\n\"\"\";\r\nwrite r\"raw\\\";\r\n",
+];
+
+#[test]
+fn printing_the_tree_gives_back_the_source() {
+    for source in SOURCES {
+        let tree = SyntaxTree::parse(source).unwrap();
+        assert_eq!(tree.to_string(), *source);
+    }
+}
+
+#[test]
+fn every_byte_belongs_to_a_token_or_its_trivia() {
+    for source in SOURCES {
+        let tokens = tokenize(source).unwrap();
+        let mut offset = 0;
+        for token in &tokens {
+            for trivia in &token.leading {
+                assert_eq!(trivia.span.start, offset, "{:?}", source);
+                offset = trivia.span.end;
+            }
+            assert_eq!(token.token.span.start, offset, "{:?}", source);
+            offset = token.token.span.end;
+            for trivia in &token.trailing {
+                assert_eq!(trivia.span.start, offset, "{:?}", source);
+                offset = trivia.span.end;
+            }
+        }
+        assert_eq!(offset, source.len(), "{:?}", source);
+    }
+}
+
+#[test]
+fn crlf_is_one_newline() {
+    let tokens = tokenize("write 1;\r\n\r\nwrite 2;\r\n").unwrap();
+    let newlines: Vec<&str> = tokens
+        .iter()
+        .flat_map(|token| token.leading.iter().chain(&token.trailing))
+        .filter(|trivia| trivia.kind == TriviaKind::Newline)
+        .map(|trivia| trivia.text)
+        .collect();
+    assert_eq!(newlines, ["\r\n", "\r\n", "\r\n"]);
+    // The line break after `;` stays with it, and the blank line goes to the next `write`
+    assert_eq!(tokens[3].blank_lines_before(), 1);
+}
+
+#[test]
+fn comments_and_blank_lines_attach_where_they_are() {
+    let source = "let x = 1; // one\n\n// about y\nlet y = 2;\n";
+    let tokens = tokenize(source).unwrap();
+    let comments: Vec<_> = tokens.iter().map(|token| token.comments().map(|c| c.text).collect::<Vec<_>>()).collect();
+    // `;` keeps its trailing comment, and the second `let` gets the one above it
+    assert_eq!(comments[4], ["// one"]);
+    assert_eq!(comments[5], ["// about y"]);
+    assert_eq!(tokens[5].blank_lines_before(), 1);
+}
@@ -0,0 +1,104 @@
+use vira_core::parser::parse_all;
+use vira_core::{Span, Stmt};
+
+/// Messages and source text of the syntax errors in `source`.
+fn errors(source: &str) -> Vec<(String, &str)> {
+    let (_, _, errors) = parse_all(source);
+    errors.into_iter().map(|error| (error.message, &source[error.span.start..error.span.end])).collect()
+}
+
+#[test]
+fn lists_what_was_expected() {
+    assert_eq!(errors("let x }"), [("Expected '=' after the variable name, found '}'".to_string(), "}")]);
+    assert_eq!(
+        errors("def f(a b) { return a; }"),
+        [("Expected '=', ',' or ')' in the parameter list, found identifier 'b'".to_string(), "b")]
+    );
+    assert_eq!(errors("write 1 +;"), [("Expected an expression, found ';'".to_string(), ";")]);
+    assert_eq!(errors("write (1;"), [("Expected an operator or ')' to close the parenthesis, found ';'".to_string(), ";")]);
+}
+
+#[test]
+fn points_at_the_unclosed_brace() {
+    let source = "def f() {\n    return 1;\n";
+    let (_, _, errors) = parse_all(source);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Expected '}' or a statement, found end of file");
+    let labels: Vec<_> = errors[0].labels.iter().map(|(span, text)| (&source[span.start..span.end], text.as_str())).collect();
+    assert_eq!(labels, [("{", "this '{' is never closed")]);
+
+    let source = "write f(1, 2;";
+    let (_, _, errors) = parse_all(source);
+    let labels: Vec<_> = errors[0].labels.iter().map(|(span, text)| (&source[span.start..span.end], text.as_str())).collect();
+    assert_eq!(labels, [("(", "'(' opened here")]);
+}
+
+#[test]
+fn reports_every_statement_that_fails() {
+    let source = "\
+def f(x) {
+    let a = ;
+    write x;
+    let b 2;
+}
+write 1
+def g() { return 2; }
+write g();
+";
+    assert_eq!(
+        errors(source),
+        [
+            ("Expected an expression, found ';'".to_string(), ";"),
+            ("Expected '=' after the variable name, found number 2".to_string(), "2"),
+            ("Expected an operator or ';' after the value, found 'def'".to_string(), "def"),
+        ]
+    );
+    // What parsed around the errors is kept, and a statement missing only its `;` counts as parsed
+    let (program, _, _) = parse_all(source);
+    assert_eq!(program.statements.len(), 4);
+    let Stmt::FuncDef { body, .. } = &program.statements[0] else {
+        panic!("expected f, found {:?}", program.statements[0]);
+    };
+    assert_eq!(body.statements.len(), 1);
+}
+
+#[test]
+fn skips_a_stray_closing_brace() {
+    assert_eq!(errors("write 1; }\nwrite 2 3;\n").len(), 2);
+    let (program, _, _) = parse_all("}\nwrite 2;\n");
+    assert_eq!(program.statements.len(), 1);
+}
+
+#[test]
+fn suggests_a_misspelled_keyword() {
+    let (_, _, errors) = parse_all("def f(x) { retrun x; }");
+    assert_eq!(errors[0].message, "Expected an operator or ';' after the expression, found identifier 'x'");
+    assert_eq!(errors[0].help.as_deref(), Some("did you mean 'return'?"));
+    let (_, _, errors) = parse_all("count x;");
+    assert_eq!(errors[0].help, None);
+}
+
+#[test]
+fn reports_the_lexing_error_in_place_of_what_follows() {
+    let (_, _, errors) = parse_all("let x 1;\nwrite \"open");
+    let codes: Vec<_> = errors.iter().map(|error| error.code).collect();
+    assert_eq!(codes, ["V0010", "V0002"]);
+}
+
+#[test]
+fn inserts_a_missing_semicolon() {
+    let source = "def f(x) {\n    let a = x + 1\n    return a\n}\nwrite f(1)";
+    let (program, _, errors) = parse_all(source);
+    let fixes: Vec<_> = errors.iter().map(|error| error.fix.as_deref().cloned()).collect();
+    let at = |offset: usize| Some((Span::new(offset, offset), ";".to_string()));
+    assert_eq!(fixes, [at(source.find(" + 1").unwrap() + 4), at(source.find("a\n}").unwrap() + 1), at(source.len())]);
+    // Parsing went on after each, so the body is whole
+    let Stmt::FuncDef { body, .. } = &program.statements[0] else {
+        panic!("expected f, found {:?}", program.statements[0]);
+    };
+    assert_eq!(body.statements.len(), 2);
+
+    // A `;` after a misspelled keyword would parse, but not as meant
+    let (_, _, errors) = parse_all("def f(x) { retrun x; }");
+    assert_eq!(errors[0].fix, None);
+}
@@ -0,0 +1,91 @@
+use vira_core::parser::parse;
+use vira_core::resolve;
+use vira_core::resolve::Resolution;
+
+fn resolution(source: &str) -> Resolution {
+    let (program, _) = parse(source).unwrap();
+    resolve(&program)
+}
+
+/// Codes and source text of the errors resolving `source` finds.
+fn errors(source: &str) -> Vec<(&'static str, &str)> {
+    resolution(source).errors.into_iter().map(|error| (error.code, &source[error.span.start..error.span.end])).collect()
+}
+
+/// Each shadowing in `source`, as the line of the new declaration and of the one it hides.
+fn shadowings(source: &str) -> Vec<(usize, usize)> {
+    let resolution = resolution(source);
+    let line = |id| source[..resolution.declaration(id).span.start].lines().count().max(1);
+    resolution.shadowings.iter().map(|shadowing| (line(shadowing.symbol), line(shadowing.shadowed))).collect()
+}
+
+#[test]
+fn reports_use_before_declaration() {
+    assert_eq!(errors("write total;\nlet total = 1;\n"), [("V0106", "total")]);
+    // In a loop body too, where the `let` runs on the next time around but still comes later
+    assert_eq!(errors("while 1 {\n    write n;\n    let n = 2;\n}\n"), [("V0106", "n")]);
+}
+
+#[test]
+fn accepts_uses_after_declaration() {
+    assert_eq!(errors("let total = 1;\nwrite total;\ntotal = total + 1;\n"), []);
+    // The value is resolved before the name, so it reads the outer variable
+    assert_eq!(errors("let x = 1;\nif x { let x = x + 1; write x; }\n"), []);
+}
+
+#[test]
+fn a_later_declaration_in_another_block_is_not_visible() {
+    // The `let` is in a sibling block, so this is an undefined name rather than an early use
+    assert_eq!(errors("if 1 { let a = 1; }\nwrite a;\n"), [("V0102", "a")]);
+    assert_eq!(errors("let b = 1;\ndef f() { return b; }\n"), [("V0102", "b")]);
+}
+
+#[test]
+fn reports_repeated_parameters() {
+    assert_eq!(errors("def f(a, b, a) { return a + b; }\n"), [("V0105", "a")]);
+    assert_eq!(errors("def f(a, b) { return a + b; }\ndef g(a) { return a; }\n"), []);
+}
+
+#[test]
+fn records_shadowing() {
+    let source = "\
+let x = 1;
+if x {
+    let x = 2;
+    write x;
+}
+def f(y) {
+    let y = y + 1;
+    return y;
+}
+";
+    assert_eq!(shadowings(source), [(3, 1), (7, 6)]);
+}
+
+#[test]
+fn separate_scopes_do_not_shadow() {
+    let source = "\
+if 1 { let x = 1; write x; }
+if 1 { let x = 2; write x; }
+let y = 1;
+def f(y) { return y; }
+";
+    // Function bodies don't see the top level, so the parameter hides nothing
+    assert_eq!(shadowings(source), []);
+}
+
+#[test]
+fn names_resolve_to_their_own_declarations() {
+    let source = "let x = 1;\nif 1 { let x = 2; write x; }\nwrite x;\n";
+    let resolution = resolution(source);
+    let at = |index: usize| {
+        let offset = source.match_indices('x').nth(index).unwrap().0;
+        resolution.lookup(vira_core::Span::new(offset, offset + 1)).unwrap()
+    };
+    // The declarations, then the inner and outer reads
+    assert_ne!(at(0), at(1));
+    assert_eq!(at(2), at(1));
+    assert_eq!(at(3), at(0));
+    assert_eq!(resolution.declaration(at(0)).reads, 1);
+    assert_eq!(resolution.declaration(at(1)).depth, 1);
+}
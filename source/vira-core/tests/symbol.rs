@@ -0,0 +1,14 @@
+use vira_core::Symbol;
+
+#[test]
+fn names_survive_filling_many_chunks() {
+    let names: Vec<String> = (0..20_000).map(|i| format!("name_{}", i)).collect();
+    // Longer than a chunk, so it gets one of its own
+    let long = "x".repeat(40_000);
+    let mut symbols: Vec<Symbol> = names.iter().map(|name| Symbol::intern(name)).collect();
+    symbols.push(Symbol::intern(&long));
+    for (symbol, name) in symbols.iter().zip(names.iter().chain([&long])) {
+        assert_eq!(symbol.as_str(), name);
+        assert_eq!(Symbol::intern(name), *symbol);
+    }
+}
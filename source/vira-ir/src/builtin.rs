@@ -0,0 +1,312 @@
+//! Functions Vira provides itself, which the runtime or the C math library implement. Each one is a
+//! plain C function, so backends only need its symbol and signature.
+
+use std::fmt;
+
+use crate::Type;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    /// `len(s)`, the number of characters in `s`.
+    Len,
+    /// `substr(s, start, count)`, up to `count` characters from `start`, cut short at the end of `s`.
+    Substr,
+    /// `find(s, needle)`, where `needle` first occurs in `s` in characters, or -1.
+    Find,
+    /// `replace(s, from, to)`, `s` with every `from` replaced by `to`.
+    Replace,
+    /// `trim(s)`, `s` without whitespace at either end.
+    Trim,
+    /// `num(s)`, the number `s` spells out, surrounding whitespace allowed.
+    Num,
+    /// `str(n)`, `n` written the way `write` prints it.
+    Str,
+    /// `int(n)`, `n` without its fraction, rounded toward zero.
+    Int,
+    /// `sin(x)`, `x` in radians.
+    Sin,
+    /// `cos(x)`, `x` in radians.
+    Cos,
+    /// `tan(x)`, `x` in radians.
+    Tan,
+    /// `log(x)`, the natural logarithm.
+    Log,
+    /// `exp(x)`, e to the power of `x`.
+    Exp,
+    /// `pow(x, y)`, `x` to the power of `y`.
+    Pow,
+    /// `sqrt(x)`.
+    Sqrt,
+    /// `random()`, a number from 0 up to but not including 1, different on every run unless seeded.
+    Random,
+    /// `rand_range(low, high)`, `low` plus a random whole number, below `high`.
+    RandRange,
+    /// `seed(n)`, which makes the random numbers that follow the same on every run.
+    Seed,
+    /// `now()`, seconds since 1970 on the wall clock.
+    Now,
+    /// `clock()`, seconds on a clock that never goes back, for timing.
+    Clock,
+    /// `sleep(seconds)`.
+    Sleep,
+    /// `format_time(format, time)`, a time from `now()` in local time, formatted by `strftime`.
+    FormatTime,
+    /// `matches(pattern, s)`, 1 when the regular expression matches somewhere in `s`.
+    Matches,
+    /// `regex_find(pattern, s)`, where the first match starts in characters, or -1.
+    RegexFind,
+    /// `regex_replace(pattern, s, replacement)`, `s` with every match replaced.
+    RegexReplace,
+    /// `getenv(name)`, the environment variable's value, or `""` when it isn't set.
+    Getenv,
+    /// `setenv(name, value)`, for the rest of the run and the commands it starts.
+    Setenv,
+    /// `exec(command)`, which runs a shell command and gives its exit status.
+    Exec,
+    /// `exec_output(command)`, which runs a shell command and gives what it printed.
+    ExecOutput,
+    /// `exit(code)`, which ends the program with that status.
+    Exit,
+    /// `http_get(url)`, the body of the response to a GET request.
+    HttpGet,
+    /// `http_post(url, body)`, the body of the response to a POST request.
+    HttpPost,
+    /// `http_status()`, the status code of the last response.
+    HttpStatus,
+    /// `panic(message)`, which stops the program with `message`.
+    Panic,
+    /// `s[i]`.
+    Index,
+    /// `s[start..end]`.
+    Slice,
+    /// `a == b` on strings: 1 when they hold the same characters, 0 otherwise.
+    Equal,
+}
+
+/// The builtins called by name; the rest have syntax of their own.
+const CALLABLE: &[Builtin] = &[
+    Builtin::Len,
+    Builtin::Substr,
+    Builtin::Find,
+    Builtin::Replace,
+    Builtin::Trim,
+    Builtin::Num,
+    Builtin::Str,
+    Builtin::Int,
+    Builtin::Sin,
+    Builtin::Cos,
+    Builtin::Tan,
+    Builtin::Log,
+    Builtin::Exp,
+    Builtin::Pow,
+    Builtin::Sqrt,
+    Builtin::Random,
+    Builtin::RandRange,
+    Builtin::Seed,
+    Builtin::Now,
+    Builtin::Clock,
+    Builtin::Sleep,
+    Builtin::FormatTime,
+    Builtin::Matches,
+    Builtin::RegexFind,
+    Builtin::RegexReplace,
+    Builtin::Getenv,
+    Builtin::Setenv,
+    Builtin::Exec,
+    Builtin::ExecOutput,
+    Builtin::Exit,
+    Builtin::HttpGet,
+    Builtin::HttpPost,
+    Builtin::HttpStatus,
+    Builtin::Panic,
+];
+
+impl Builtin {
+    /// The builtin called `name`, if any.
+    pub fn from_name(name: &str) -> Option<Builtin> {
+        CALLABLE.iter().copied().find(|builtin| builtin.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Len => "len",
+            Builtin::Substr => "substr",
+            Builtin::Find => "find",
+            Builtin::Replace => "replace",
+            Builtin::Trim => "trim",
+            Builtin::Num => "num",
+            Builtin::Str => "str",
+            Builtin::Int => "int",
+            Builtin::Sin => "sin",
+            Builtin::Cos => "cos",
+            Builtin::Tan => "tan",
+            Builtin::Log => "log",
+            Builtin::Exp => "exp",
+            Builtin::Pow => "pow",
+            Builtin::Sqrt => "sqrt",
+            Builtin::Random => "random",
+            Builtin::RandRange => "rand_range",
+            Builtin::Seed => "seed",
+            Builtin::Now => "now",
+            Builtin::Clock => "clock",
+            Builtin::Sleep => "sleep",
+            Builtin::FormatTime => "format_time",
+            Builtin::Matches => "matches",
+            Builtin::RegexFind => "regex_find",
+            Builtin::RegexReplace => "regex_replace",
+            Builtin::Getenv => "getenv",
+            Builtin::Setenv => "setenv",
+            Builtin::Exec => "exec",
+            Builtin::ExecOutput => "exec_output",
+            Builtin::Exit => "exit",
+            Builtin::HttpGet => "http_get",
+            Builtin::HttpPost => "http_post",
+            Builtin::HttpStatus => "http_status",
+            Builtin::Panic => "panic",
+            Builtin::Index => "index",
+            Builtin::Slice => "slice",
+            Builtin::Equal => "equal",
+        }
+    }
+
+    /// The function that implements it: one of the runtime's, named `vira_...`, or else one of the
+    /// C math library's.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Builtin::Len => "vira_str_len",
+            Builtin::Substr => "vira_str_substr",
+            Builtin::Find => "vira_str_find",
+            Builtin::Replace => "vira_str_replace",
+            Builtin::Trim => "vira_str_trim",
+            Builtin::Num => "vira_str_to_num",
+            Builtin::Str => "vira_num_to_str",
+            Builtin::Int => "trunc",
+            Builtin::Sin => "sin",
+            Builtin::Cos => "cos",
+            Builtin::Tan => "tan",
+            Builtin::Log => "log",
+            Builtin::Exp => "exp",
+            Builtin::Pow => "pow",
+            Builtin::Sqrt => "sqrt",
+            Builtin::Random => "vira_random",
+            Builtin::RandRange => "vira_rand_range",
+            Builtin::Seed => "vira_seed",
+            Builtin::Now => "vira_now",
+            Builtin::Clock => "vira_clock",
+            Builtin::Sleep => "vira_sleep",
+            Builtin::FormatTime => "vira_format_time",
+            Builtin::Matches => "vira_regex_matches",
+            Builtin::RegexFind => "vira_regex_find",
+            Builtin::RegexReplace => "vira_regex_replace",
+            Builtin::Getenv => "vira_getenv",
+            Builtin::Setenv => "vira_setenv",
+            Builtin::Exec => "vira_exec",
+            Builtin::ExecOutput => "vira_exec_output",
+            Builtin::Exit => "vira_exit",
+            Builtin::HttpGet => "vira_http_get",
+            Builtin::HttpPost => "vira_http_post",
+            Builtin::HttpStatus => "vira_http_status",
+            Builtin::Panic => "vira_panic",
+            Builtin::Index => "vira_str_index",
+            Builtin::Slice => "vira_str_slice",
+            Builtin::Equal => "vira_str_eq",
+        }
+    }
+
+    pub fn params(self) -> &'static [Type] {
+        match self {
+            Builtin::Len
+            | Builtin::Trim
+            | Builtin::Num
+            | Builtin::Getenv
+            | Builtin::Exec
+            | Builtin::ExecOutput
+            | Builtin::HttpGet
+            | Builtin::Panic => &[Type::Str],
+            Builtin::Str
+            | Builtin::Int
+            | Builtin::Sin
+            | Builtin::Cos
+            | Builtin::Tan
+            | Builtin::Log
+            | Builtin::Exp
+            | Builtin::Sqrt
+            | Builtin::Seed
+            | Builtin::Sleep
+            | Builtin::Exit => &[Type::Number],
+            Builtin::Pow | Builtin::RandRange => &[Type::Number, Type::Number],
+            Builtin::FormatTime => &[Type::Str, Type::Number],
+            Builtin::Random | Builtin::Now | Builtin::Clock | Builtin::HttpStatus => &[],
+            Builtin::Substr | Builtin::Slice => &[Type::Str, Type::Number, Type::Number],
+            Builtin::Find | Builtin::Equal | Builtin::Matches | Builtin::RegexFind | Builtin::Setenv | Builtin::HttpPost => &[Type::Str, Type::Str],
+            Builtin::Replace | Builtin::RegexReplace => &[Type::Str, Type::Str, Type::Str],
+            Builtin::Index => &[Type::Str, Type::Number],
+        }
+    }
+
+    pub fn returns(self) -> Type {
+        match self {
+            Builtin::Substr
+            | Builtin::Replace
+            | Builtin::Trim
+            | Builtin::Str
+            | Builtin::FormatTime
+            | Builtin::RegexReplace
+            | Builtin::Getenv
+            | Builtin::ExecOutput
+            | Builtin::HttpGet
+            | Builtin::HttpPost
+            | Builtin::Index
+            | Builtin::Slice => Type::Str,
+            _ => Type::Number,
+        }
+    }
+
+    /// Whether the builtin can stop the program with an error. The runtime function then takes the
+    /// location of the call, as a string, after the other arguments.
+    pub fn can_fail(self) -> bool {
+        matches!(
+            self,
+            Builtin::Num
+                | Builtin::Matches
+                | Builtin::RegexFind
+                | Builtin::RegexReplace
+                | Builtin::Setenv
+                | Builtin::HttpGet
+                | Builtin::HttpPost
+                | Builtin::Panic
+                | Builtin::Index
+                | Builtin::Slice
+        )
+    }
+
+    /// Whether the builtin only computes its result from its arguments, so a call whose result
+    /// nothing reads can go.
+    pub fn is_pure(self) -> bool {
+        !self.can_fail()
+            && !self.is_system()
+            && !matches!(self, Builtin::Random | Builtin::RandRange | Builtin::Seed | Builtin::Sleep)
+    }
+
+    /// Whether the builtin reaches outside the program, to the environment, other programs, the
+    /// network or its exit status; a sandboxed build rejects these.
+    pub fn is_system(self) -> bool {
+        matches!(
+            self,
+            Builtin::Getenv
+                | Builtin::Setenv
+                | Builtin::Exec
+                | Builtin::ExecOutput
+                | Builtin::Exit
+                | Builtin::HttpGet
+                | Builtin::HttpPost
+                | Builtin::HttpStatus
+        )
+    }
+}
+
+impl fmt::Display for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
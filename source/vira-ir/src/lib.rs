@@ -0,0 +1,287 @@
+//! Mid-level IR between the AST and code generation. Every function is a list of basic blocks of
+//! three-address instructions over typed locals, so a pass sees plain data flow instead of nested
+//! expressions and scopes, and passes written here serve every backend.
+//!
+//! Locals are mutable slots rather than SSA values: a Vira variable is one local however often it
+//! is assigned, and backends with an SSA builder, like Cranelift's, turn them into values.
+
+pub mod builtin;
+pub mod lower;
+pub mod optimize;
+
+use std::fmt;
+
+pub use builtin::Builtin;
+pub use lower::{lower, LowerError};
+pub use optimize::{called_functions, optimize, Removed};
+pub use vira_core::ast::{BinOp, UnOp};
+use vira_core::{Span, Symbol};
+
+/// A whole program: the functions of every file and the top-level code of the entry file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub functions: Vec<Function>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    /// Name in Vira. The top-level program is called `main` but is told apart by `is_main`, since a
+    /// user function may be called `main` too.
+    pub name: Symbol,
+    pub is_main: bool,
+    /// Index of the file the function comes from, in the order the files were lowered.
+    pub file: usize,
+    /// The definition, or for `main` the first top-level statement.
+    pub span: Span,
+    /// The first `params` locals hold the arguments.
+    pub params: usize,
+    pub locals: Vec<Local>,
+    /// `blocks[0]` is the entry block.
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Local {
+    pub ty: Type,
+    /// The Vira variable this local holds, if any; temporaries have none.
+    pub name: Option<Symbol>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    Number,
+    /// A pointer to a NUL-terminated string.
+    Str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LocalId(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub u32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub insts: Vec<Inst>,
+    pub terminator: Terminator,
+    /// The statement the terminator comes from, such as a `return` or the `while` a loop jumps
+    /// back to; empty for the return the lowering adds at the end of a function.
+    pub terminator_span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inst {
+    pub dest: Option<LocalId>,
+    pub op: Op,
+    /// The statement this instruction comes from; empty for ones the lowering adds on its own,
+    /// such as the `0` an implicit return gives back.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Number(f64),
+    String(String),
+    Copy(LocalId),
+    Unary(UnOp, LocalId),
+    /// An operator on two numbers. `&&` and `||` never appear: they short-circuit, so lowering
+    /// turns them into branches.
+    Binary(BinOp, LocalId, LocalId),
+    /// `+` on two strings.
+    Concat(LocalId, LocalId),
+    /// `+` on two strings whose result goes back into the left operand's local, which is the only
+    /// place its string is held, so the runtime may grow that string in place. Lowering makes it out
+    /// of `s = s + x;`.
+    Append(LocalId, LocalId),
+    Call(Symbol, Vec<LocalId>),
+    /// A call to a function of the runtime; `at` is the call, for the errors it can report.
+    Builtin {
+        builtin: Builtin,
+        args: Vec<LocalId>,
+        at: Span,
+    },
+    /// `write`, which prints a number or a string on a line of its own.
+    Write(LocalId),
+    /// Stops the program because the `assert` or `assert_eq` call at `at` failed. The report gives
+    /// `message`, or the call as written when there is none, and then `values`, what the call
+    /// compared: left first, then right.
+    AssertFailed {
+        message: Option<LocalId>,
+        values: Vec<LocalId>,
+        at: Span,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    /// Goes to `then` when `condition` is not zero.
+    Branch {
+        condition: LocalId,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    Return(LocalId),
+}
+
+impl Op {
+    /// Whether the instruction only computes its result, so it can go when nothing reads it.
+    pub fn is_pure(&self) -> bool {
+        match self {
+            Op::Call(..) | Op::Write(_) | Op::AssertFailed { .. } => false,
+            Op::Builtin { builtin, .. } => builtin.is_pure(),
+            _ => true,
+        }
+    }
+
+    /// The locals the instruction reads.
+    pub fn operands(&self) -> Vec<LocalId> {
+        match self {
+            Op::Number(_) | Op::String(_) => Vec::new(),
+            Op::Copy(local) | Op::Unary(_, local) | Op::Write(local) => vec![*local],
+            Op::Binary(_, left, right) | Op::Concat(left, right) | Op::Append(left, right) => vec![*left, *right],
+            Op::Call(_, args) | Op::Builtin { args, .. } => args.clone(),
+            Op::AssertFailed { message, values, .. } => message.iter().chain(values).copied().collect(),
+        }
+    }
+
+    /// Applies `f` to every local the instruction reads.
+    pub fn map_operands(&mut self, mut f: impl FnMut(LocalId) -> LocalId) {
+        match self {
+            Op::Number(_) | Op::String(_) => {}
+            Op::Copy(local) | Op::Unary(_, local) | Op::Write(local) => *local = f(*local),
+            Op::Binary(_, left, right) | Op::Concat(left, right) | Op::Append(left, right) => {
+                *left = f(*left);
+                *right = f(*right);
+            }
+            Op::Call(_, args) | Op::Builtin { args, .. } => {
+                for arg in args {
+                    *arg = f(*arg);
+                }
+            }
+            Op::AssertFailed { message, values, .. } => {
+                for local in message.iter_mut().chain(values) {
+                    *local = f(*local);
+                }
+            }
+        }
+    }
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch { then, otherwise, .. } => vec![*then, *otherwise],
+            Terminator::Return(_) => Vec::new(),
+        }
+    }
+
+    /// The local the terminator reads, if any.
+    pub fn operand(&self) -> Option<LocalId> {
+        match self {
+            Terminator::Jump(_) => None,
+            Terminator::Branch { condition, .. } => Some(*condition),
+            Terminator::Return(value) => Some(*value),
+        }
+    }
+
+    fn map_operand(&mut self, f: impl FnOnce(LocalId) -> LocalId) {
+        match self {
+            Terminator::Jump(_) => {}
+            Terminator::Branch { condition: local, .. } | Terminator::Return(local) => *local = f(*local),
+        }
+    }
+}
+
+impl Function {
+    pub fn local(&self, id: LocalId) -> &Local {
+        &self.locals[id.0 as usize]
+    }
+
+    pub fn block(&self, id: BlockId) -> &Block {
+        &self.blocks[id.0 as usize]
+    }
+
+    /// Blocks control can reach from the entry, in the order they were created.
+    pub fn reachable_blocks(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut worklist = vec![BlockId(0)];
+        while let Some(block) = worklist.pop() {
+            if !std::mem::replace(&mut reachable[block.0 as usize], true) {
+                worklist.extend(self.block(block).terminator.successors());
+            }
+        }
+        reachable
+    }
+
+    /// `%3` for a temporary, `x.3` for a local holding the variable `x`.
+    fn display_local(&self, id: LocalId) -> String {
+        match &self.local(id).name {
+            Some(name) => format!("{}.{}", name, id.0),
+            None => format!("%{}", id.0),
+        }
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params: Vec<String> = (0..self.params).map(|index| self.display_local(LocalId(index as u32))).collect();
+        let name = if self.is_main { "<main>" } else { self.name.as_str() };
+        writeln!(f, "fn {}({}):", name, params.join(", "))?;
+        for (index, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "b{}:", index)?;
+            for inst in &block.insts {
+                write!(f, "    ")?;
+                if let Some(dest) = inst.dest {
+                    write!(f, "{} = ", self.display_local(dest))?;
+                }
+                let local = |id: &LocalId| self.display_local(*id);
+                match &inst.op {
+                    Op::Number(value) => write!(f, "{}", value)?,
+                    Op::String(text) => write!(f, "{:?}", text)?,
+                    Op::Copy(source) => write!(f, "{}", local(source))?,
+                    Op::Unary(op, operand) => write!(f, "{}{}", op.symbol(), local(operand))?,
+                    Op::Binary(op, left, right) => write!(f, "{} {} {}", local(left), op.symbol(), local(right))?,
+                    Op::Concat(left, right) => write!(f, "concat {}, {}", local(left), local(right))?,
+                    Op::Append(left, right) => write!(f, "append {}, {}", local(left), local(right))?,
+                    Op::Call(name, args) => write!(f, "call {}({})", name, args.iter().map(local).collect::<Vec<_>>().join(", "))?,
+                    Op::Builtin { builtin, args, .. } => {
+                        write!(f, "builtin {}({})", builtin, args.iter().map(local).collect::<Vec<_>>().join(", "))?
+                    }
+                    Op::Write(value) => write!(f, "write {}", local(value))?,
+                    Op::AssertFailed { message, values, .. } => {
+                        write!(f, "assert_failed")?;
+                        if let Some(message) = message {
+                            write!(f, " {}", local(message))?;
+                        }
+                        if !values.is_empty() {
+                            write!(f, " ({})", values.iter().map(local).collect::<Vec<_>>().join(", "))?;
+                        }
+                    }
+                }
+                writeln!(f)?;
+            }
+            match &block.terminator {
+                Terminator::Jump(target) => writeln!(f, "    jump b{}", target.0)?,
+                Terminator::Branch { condition, then, otherwise } => {
+                    writeln!(f, "    branch {}, b{}, b{}", self.display_local(*condition), then.0, otherwise.0)?
+                }
+                Terminator::Return(value) => writeln!(f, "    return {}", self.display_local(*value))?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,686 @@
+use std::collections::HashMap;
+
+use vira_core::ast::{BinOp, Else, Expr, NamedArg, Param, Pattern, Program, Stmt, UnOp, Variant};
+use vira_core::{ensure_stack, Resolution, Span, Symbol, SymbolId};
+
+use crate::{Block, BlockId, Builtin, Function, Inst, Local, LocalId, Module, Op, Terminator, Type};
+
+/// Valid Vira that the IR can't express, found while lowering.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LowerError {
+    /// The construct, as in "adding a number to a string".
+    pub what: String,
+    pub span: Span,
+    /// Index of the file the span points into.
+    pub file: usize,
+    /// Set when the program should have been rejected by the checker, which means a bug.
+    pub internal: bool,
+}
+
+/// Lowers checked programs, one per file with the entry file first and each with its resolution,
+/// into one module. Every function is lowered, called or not; `optimize` removes the ones nothing
+/// calls. The entry file's top-level statements become `main`, which comes last.
+pub fn lower<'a>(files: impl IntoIterator<Item = (&'a Program, &'a Resolution)>) -> Result<Module, LowerError> {
+    let files: Vec<_> = files.into_iter().collect();
+    // Calls may pass arguments by name or leave out ones with defaults, so lowering one needs the
+    // parameters of the function it calls, wherever that is defined. Likewise a variant is numbered
+    // by its place in an enum that may be defined in another file
+    let mut signatures = HashMap::new();
+    let mut enums = HashMap::new();
+    for stmt in files.iter().flat_map(|(program, _)| &program.statements) {
+        match stmt {
+            Stmt::FuncDef { name, params, .. } => {
+                signatures.entry(*name).or_insert(params.as_slice());
+            }
+            Stmt::Enum { name, variants, .. } => {
+                enums.entry(*name).or_insert(variants.as_slice());
+            }
+            _ => {}
+        }
+    }
+    let tables = Tables {
+        signatures: &signatures,
+        enums: &enums,
+    };
+    let mut functions = Vec::new();
+    let mut top_level = Vec::new();
+    let mut entry = None;
+    for (file, (program, resolution)) in files.into_iter().enumerate() {
+        entry = entry.or(Some(resolution));
+        for stmt in &program.statements {
+            match stmt {
+                Stmt::FuncDef {
+                    name, params, body, span, ..
+                } => {
+                    let mut lowerer = Lowerer::new(file, resolution, tables, params)?;
+                    lowerer.stmts(&body.statements)?;
+                    functions.push(lowerer.finish(*name, false, *span, params.len()));
+                }
+                Stmt::Enum { .. } => {}
+                // Only the entry file may have top-level code; the checker reports it elsewhere
+                _ if file == 0 => top_level.push(stmt),
+                _ => {}
+            }
+        }
+    }
+    let Some(resolution) = entry else {
+        return Ok(Module { functions });
+    };
+    let mut lowerer = Lowerer::new(0, resolution, tables, &[])?;
+    lowerer.stmts(top_level.iter().copied())?;
+    let span = top_level.first().map_or(Span::default(), |stmt| stmt.span());
+    functions.push(lowerer.finish(Symbol::intern("main"), true, span, 0));
+    Ok(Module { functions })
+}
+
+/// What every function may refer to, across all files.
+#[derive(Clone, Copy)]
+struct Tables<'a> {
+    /// The parameters of every function in the program.
+    signatures: &'a HashMap<Symbol, &'a [Param]>,
+    /// The variants of every enum in the program.
+    enums: &'a HashMap<Symbol, &'a [Variant]>,
+}
+
+/// A block under construction: its instructions, and its terminator with the span it comes from
+/// once it has one.
+type OpenBlock = (Vec<Inst>, Option<(Terminator, Span)>);
+
+/// Builds one function.
+struct Lowerer<'a> {
+    file: usize,
+    resolution: &'a Resolution,
+    tables: Tables<'a>,
+    locals: Vec<Local>,
+    /// Blocks under construction; a block is finished once it has a terminator.
+    blocks: Vec<OpenBlock>,
+    current: BlockId,
+    /// The local of every variable declared so far. A `let` always makes a new symbol and so a new
+    /// local, even when it shadows another variable.
+    variables: HashMap<SymbolId, LocalId>,
+    /// The statement being lowered.
+    span: Span,
+}
+
+impl<'a> Lowerer<'a> {
+    fn new(
+        file: usize,
+        resolution: &'a Resolution,
+        tables: Tables<'a>,
+        params: &[Param],
+    ) -> Result<Self, LowerError> {
+        let mut lowerer = Lowerer {
+            file,
+            resolution,
+            tables,
+            locals: Vec::new(),
+            blocks: vec![(Vec::new(), None)],
+            current: BlockId(0),
+            variables: HashMap::new(),
+            span: Span::default(),
+        };
+        for param in params {
+            let local = lowerer.local(Type::Number, Some(param.name));
+            let symbol = lowerer.symbol(param.name, param.span)?;
+            lowerer.variables.insert(symbol, local);
+        }
+        Ok(lowerer)
+    }
+
+    /// Returns 0 from every block that doesn't end on its own yet.
+    fn finish(mut self, name: Symbol, is_main: bool, span: Span, params: usize) -> Function {
+        self.span = Span::default();
+        for index in 0..self.blocks.len() {
+            if self.blocks[index].1.is_none() {
+                self.current = BlockId(index as u32);
+                let zero = self.value(Type::Number, Op::Number(0.0));
+                self.blocks[index].1 = Some((Terminator::Return(zero), Span::default()));
+            }
+        }
+        self.mark_appends(params);
+        Function {
+            name,
+            is_main,
+            file: self.file,
+            span,
+            params,
+            locals: self.locals,
+            blocks: self
+                .blocks
+                .into_iter()
+                .map(|(insts, terminator)| {
+                    let (terminator, terminator_span) = terminator.expect("every block was terminated above");
+                    Block {
+                        insts,
+                        terminator,
+                        terminator_span,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Turns `s = concat s, x` into an append wherever no other local can hold the string in `s`:
+    /// `s` is not a parameter, whose string the caller holds too, is never copied into another
+    /// local, and is never assigned another variable's string. Functions only return numbers, so a
+    /// string passed to a call is let go of when it returns.
+    fn mark_appends(&mut self, params: usize) {
+        let mut shared = vec![false; self.locals.len()];
+        shared[..params].fill(true);
+        for inst in self.blocks.iter().flat_map(|(insts, _)| insts) {
+            if let (Some(dest), Op::Copy(source)) = (inst.dest, &inst.op) {
+                shared[source.0 as usize] = true;
+                // Temporaries only ever hold new strings
+                if self.locals[source.0 as usize].name.is_some() {
+                    shared[dest.0 as usize] = true;
+                }
+            }
+        }
+        for inst in self.blocks.iter_mut().flat_map(|(insts, _)| insts) {
+            if let (Some(dest), Op::Concat(left, right)) = (inst.dest, &inst.op) {
+                if dest == *left && !shared[dest.0 as usize] {
+                    inst.op = Op::Append(*left, *right);
+                }
+            }
+        }
+    }
+
+    fn stmts<'s>(&mut self, statements: impl IntoIterator<Item = &'s Stmt>) -> Result<(), LowerError> {
+        for stmt in statements {
+            self.stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), LowerError> {
+        self.span = stmt.span();
+        match stmt {
+            Stmt::Let { name, name_span, value, .. } => {
+                let value = self.expr(value)?;
+                let local = self.local(self.ty(value), Some(*name));
+                self.emit(Some(local), Op::Copy(value));
+                let symbol = self.symbol(*name, *name_span)?;
+                self.variables.insert(symbol, local);
+            }
+            Stmt::Assign { name, name_span, value, .. } => {
+                let local = self.variable(*name, *name_span)?;
+                if let Some(pieces) = self.appended(local, value)? {
+                    // `s + a + b` reads `s` before anything else, so the pieces are all worked out
+                    // before the first is added to it
+                    let mut values = Vec::with_capacity(pieces.len());
+                    for (piece, span) in pieces {
+                        let value = self.expr(piece)?;
+                        if self.ty(value) != Type::Str {
+                            return Err(self.unsupported("adding a number to a string", span));
+                        }
+                        values.push(value);
+                    }
+                    for value in values {
+                        self.emit(Some(local), Op::Concat(local, value));
+                    }
+                    return Ok(());
+                }
+                let value_span = value.span();
+                let value = self.expr(value)?;
+                if self.ty(value) != self.ty(local) {
+                    return Err(self.unsupported("changing a variable between a number and a string", value_span));
+                }
+                self.emit(Some(local), Op::Copy(value));
+            }
+            Stmt::Write(value, _) => {
+                let value = self.expr(value)?;
+                self.emit(None, Op::Write(value));
+            }
+            Stmt::Expr(expr, _) => {
+                self.expr(expr)?;
+            }
+            Stmt::Return(value, _) => {
+                let value = match value {
+                    Some(expr) => self.number(expr, "returning a string")?,
+                    None => self.value(Type::Number, Op::Number(0.0)),
+                };
+                self.terminate(Terminator::Return(value));
+            }
+            Stmt::If {
+                condition,
+                then_block,
+                else_branch,
+                ..
+            } => {
+                let condition = self.number(condition, "a string condition")?;
+                let then = self.new_block();
+                let otherwise = self.new_block();
+                let merge = self.new_block();
+                self.terminate(Terminator::Branch { condition, then, otherwise });
+
+                self.current = then;
+                self.stmts(&then_block.statements)?;
+                self.terminate(Terminator::Jump(merge));
+
+                self.current = otherwise;
+                match else_branch {
+                    Some(Else::If(nested)) => self.stmt(nested)?,
+                    Some(Else::Block(block)) => self.stmts(&block.statements)?,
+                    None => {}
+                }
+                self.terminate(Terminator::Jump(merge));
+                // When both branches return, nothing jumps here and the block stays unreachable
+                self.current = merge;
+            }
+            Stmt::While { condition, body, .. } => {
+                let header = self.new_block();
+                let body_block = self.new_block();
+                let exit = self.new_block();
+                self.terminate(Terminator::Jump(header));
+
+                self.current = header;
+                let condition = self.number(condition, "a string condition")?;
+                self.terminate(Terminator::Branch {
+                    condition,
+                    then: body_block,
+                    otherwise: exit,
+                });
+
+                self.current = body_block;
+                self.stmts(&body.statements)?;
+                self.terminate(Terminator::Jump(header));
+                self.current = exit;
+            }
+            Stmt::Match { value, arms, .. } => {
+                // Each pattern compares the value once, in order, and the first that matches runs its arm
+                let value = self.number(value, "matching a string")?;
+                let end = self.new_block();
+                for arm in arms {
+                    let body = self.new_block();
+                    for pattern in &arm.patterns {
+                        match pattern {
+                            Pattern::Wildcard(_) => self.terminate(Terminator::Jump(body)),
+                            Pattern::Variant(enum_name, variant, span) => {
+                                let index = self.variant(*enum_name, *variant, *span)?;
+                                let index = self.value(Type::Number, Op::Number(index));
+                                let condition = self.value(Type::Number, Op::Binary(BinOp::Eq, value, index));
+                                let next = self.new_block();
+                                self.terminate(Terminator::Branch {
+                                    condition,
+                                    then: body,
+                                    otherwise: next,
+                                });
+                                self.current = next;
+                            }
+                        }
+                    }
+                    // After a wildcard the next arm's tests land in a block nothing jumps to
+                    let next = self.current;
+                    self.current = body;
+                    self.stmts(&arm.body.statements)?;
+                    self.terminate(Terminator::Jump(end));
+                    self.current = next;
+                }
+                self.terminate(Terminator::Jump(end));
+                self.current = end;
+            }
+            Stmt::Enum { span, .. } => return Err(self.unsupported("a nested enum", *span)),
+            Stmt::FuncDef { span, .. } => return Err(self.unsupported("a nested function", *span)),
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<LocalId, LowerError> {
+        ensure_stack(|| Ok(match expr {
+            Expr::Number(value, _) => self.value(Type::Number, Op::Number(*value)),
+            Expr::String(text, _) => self.value(Type::Str, Op::String(text.clone())),
+            Expr::Identifier(name, span) => self.variable(*name, *span)?,
+            Expr::Variant(enum_name, variant, span) => {
+                let index = self.variant(*enum_name, *variant, *span)?;
+                self.value(Type::Number, Op::Number(index))
+            }
+            Expr::Unary(op, operand, _) => {
+                let operand = self.number(operand, "an operator on a string")?;
+                self.value(Type::Number, Op::Unary(*op, operand))
+            }
+            Expr::Binary(op @ (BinOp::And | BinOp::Or), left, right, _) => {
+                // Short-circuit: the right operand only runs when the left one doesn't decide the result
+                let result = self.local(Type::Number, None);
+                let left = self.number(left, "an operator on a string")?;
+                self.truthy(result, left);
+                let right_block = self.new_block();
+                let merge = self.new_block();
+                self.terminate(if *op == BinOp::And {
+                    Terminator::Branch {
+                        condition: result,
+                        then: right_block,
+                        otherwise: merge,
+                    }
+                } else {
+                    Terminator::Branch {
+                        condition: result,
+                        then: merge,
+                        otherwise: right_block,
+                    }
+                });
+
+                self.current = right_block;
+                let right = self.number(right, "an operator on a string")?;
+                self.truthy(result, right);
+                self.terminate(Terminator::Jump(merge));
+                self.current = merge;
+                result
+            }
+            Expr::Binary(BinOp::Add, left, right, span) => {
+                let left = self.expr(left)?;
+                let right = self.expr(right)?;
+                match (self.ty(left), self.ty(right)) {
+                    (Type::Number, Type::Number) => self.value(Type::Number, Op::Binary(BinOp::Add, left, right)),
+                    (Type::Str, Type::Str) => self.value(Type::Str, Op::Concat(left, right)),
+                    _ => return Err(self.unsupported("adding a number to a string", *span)),
+                }
+            }
+            Expr::Binary(op @ (BinOp::Eq | BinOp::Ne), left, right, span) => {
+                let left = self.expr(left)?;
+                let right = self.expr(right)?;
+                self.equality(*op, left, right, *span)?
+            }
+            Expr::Binary(op, left, right, _) => {
+                let left = self.number(left, "an operator on a string")?;
+                let right = self.number(right, "an operator on a string")?;
+                self.value(Type::Number, Op::Binary(*op, left, right))
+            }
+            Expr::Call(name, args, named, span) if self.tables.signatures.contains_key(name) => self.call(*name, args, named, *span)?,
+            Expr::Call(name, args, _, span) if *name == "assert" || *name == "assert_eq" => self.assert(*name, args, *span)?,
+            Expr::Index(value, index, span) => {
+                let value = self.string(value, "indexing a number")?;
+                let index = self.number(index, "a string index")?;
+                self.builtin(Builtin::Index, vec![value, index], *span)
+            }
+            Expr::Slice(value, start, end, span) => {
+                let value = self.string(value, "slicing a number")?;
+                let start = match start {
+                    Some(start) => self.number(start, "a string slice bound")?,
+                    None => self.value(Type::Number, Op::Number(0.0)),
+                };
+                let end = match end {
+                    Some(end) => self.number(end, "a string slice bound")?,
+                    None => self.builtin(Builtin::Len, vec![value], *span),
+                };
+                self.builtin(Builtin::Slice, vec![value, start, end], *span)
+            }
+            Expr::Call(name, ..) if *name == "pi" => self.value(Type::Number, Op::Number(std::f64::consts::PI)),
+            Expr::Call(name, ..) if *name == "e" => self.value(Type::Number, Op::Number(std::f64::consts::E)),
+            Expr::Call(name, args, _, span) if *name == "typeof" => {
+                let [value] = args.as_slice() else {
+                    return Err(self.internal("typeof takes one argument", *span));
+                };
+                // Every value's type is known here, so only the argument's side effects are left to run
+                let value = self.expr(value)?;
+                let name = match self.ty(value) {
+                    Type::Number => "num",
+                    Type::Str => "str",
+                };
+                self.value(Type::Str, Op::String(name.to_string()))
+            }
+            Expr::Call(name, args, named, span) => match Builtin::from_name(name.as_str()) {
+                Some(builtin) if named.is_empty() => self.call_builtin(builtin, args, *span)?,
+                Some(_) => return Err(self.internal("builtins take no named arguments", *span)),
+                None => return Err(self.internal(&format!("no function '{}'", name), *span)),
+            },
+        }))
+    }
+
+    /// Lowers an expression that must be a number; `what` names the unsupported use of a string.
+    fn number(&mut self, expr: &Expr, what: &str) -> Result<LocalId, LowerError> {
+        let value = self.expr(expr)?;
+        if self.ty(value) == Type::Str {
+            return Err(self.unsupported(what, expr.span()));
+        }
+        Ok(value)
+    }
+
+    /// Lowers an expression that must be a string; `what` names the unsupported use of a number.
+    fn string(&mut self, expr: &Expr, what: &str) -> Result<LocalId, LowerError> {
+        let value = self.expr(expr)?;
+        if self.ty(value) == Type::Number {
+            return Err(self.unsupported(what, expr.span()));
+        }
+        Ok(value)
+    }
+
+    /// Lowers `assert(condition)`, `assert(condition, message)` or `assert_eq(left, right)`. When the
+    /// condition compares two values, as `assert_eq` always does, a failure reports both.
+    fn assert(&mut self, name: Symbol, args: &[Expr], at: Span) -> Result<LocalId, LowerError> {
+        let (condition, values, message) = match args {
+            [left, right] if name == "assert_eq" => {
+                let left = self.expr(left)?;
+                let right = self.expr(right)?;
+                (self.equality(BinOp::Eq, left, right, at)?, vec![left, right], None)
+            }
+            [condition, message @ ..] if name == "assert" && message.len() <= 1 => {
+                let (condition, values) = match condition {
+                    Expr::Binary(op @ (BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge), left, right, span) => {
+                        let operands = [(self.expr(left)?, left.span()), (self.expr(right)?, right.span())];
+                        let [(left, _), (right, _)] = operands;
+                        let condition = match op {
+                            BinOp::Eq | BinOp::Ne => self.equality(*op, left, right, *span)?,
+                            _ => {
+                                if let Some((_, span)) = operands.iter().find(|(value, _)| self.ty(*value) == Type::Str) {
+                                    return Err(self.unsupported("an operator on a string", *span));
+                                }
+                                self.value(Type::Number, Op::Binary(*op, left, right))
+                            }
+                        };
+                        (condition, vec![left, right])
+                    }
+                    _ => (self.number(condition, "a string condition")?, Vec::new()),
+                };
+                (condition, values, message.first())
+            }
+            _ => return Err(self.internal(&format!("{} with the wrong number of arguments", name), at)),
+        };
+        let pass = self.new_block();
+        let fail = self.new_block();
+        self.terminate(Terminator::Branch {
+            condition,
+            then: pass,
+            otherwise: fail,
+        });
+
+        // The message is only built when the assertion fails
+        self.current = fail;
+        let message = match message {
+            Some(message) => Some(self.string(message, "an assertion message that is not a string")?),
+            None => None,
+        };
+        self.emit(None, Op::AssertFailed { message, values, at });
+        self.terminate(Terminator::Jump(pass));
+        self.current = pass;
+        Ok(self.value(Type::Number, Op::Number(0.0)))
+    }
+
+    /// `left == right` or `left != right`, on two numbers or two strings.
+    fn equality(&mut self, op: BinOp, left: LocalId, right: LocalId, span: Span) -> Result<LocalId, LowerError> {
+        Ok(match (self.ty(left), self.ty(right)) {
+            (Type::Number, Type::Number) => self.value(Type::Number, Op::Binary(op, left, right)),
+            (Type::Str, Type::Str) => {
+                let equal = self.builtin(Builtin::Equal, vec![left, right], span);
+                match op {
+                    BinOp::Ne => self.value(Type::Number, Op::Unary(UnOp::Not, equal)),
+                    _ => equal,
+                }
+            }
+            _ => return Err(self.unsupported("comparing a number to a string", span)),
+        })
+    }
+
+    /// Lowers a call to a Vira function. The arguments run in the order they are written, then go to
+    /// the parameters they are for, and parameters the call leaves out get their defaults.
+    fn call(&mut self, name: Symbol, args: &[Expr], named: &[NamedArg], at: Span) -> Result<LocalId, LowerError> {
+        let params = self.tables.signatures[&name];
+        let mut slots = vec![None; params.len()];
+        for (index, arg) in args.iter().enumerate() {
+            let value = self.number(arg, "passing a string to a function")?;
+            match slots.get_mut(index) {
+                Some(slot) => *slot = Some(value),
+                None => return Err(self.internal(&format!("{} takes {} arguments", name, params.len()), at)),
+            }
+        }
+        for arg in named {
+            let value = self.number(&arg.value, "passing a string to a function")?;
+            match params.iter().position(|param| param.name == arg.name) {
+                Some(index) => slots[index] = Some(value),
+                None => return Err(self.internal(&format!("{} has no parameter '{}'", name, arg.name), arg.name_span)),
+            }
+        }
+        let mut locals = Vec::with_capacity(params.len());
+        for (slot, param) in slots.into_iter().zip(params) {
+            let value = match (slot, &param.default) {
+                (Some(value), _) => value,
+                // The default's span is in the file of the definition, so errors point at the call
+                (None, Some(default)) => {
+                    let value = self.expr(default)?;
+                    if self.ty(value) == Type::Str {
+                        return Err(self.internal(&format!("the string default of '{}'", param.name), at));
+                    }
+                    value
+                }
+                (None, None) => return Err(self.internal(&format!("no argument for '{}'", param.name), at)),
+            };
+            locals.push(value);
+        }
+        Ok(self.value(Type::Number, Op::Call(name, locals)))
+    }
+
+    /// Lowers a call by name to a builtin, checking each argument against the type it takes.
+    fn call_builtin(&mut self, builtin: Builtin, args: &[Expr], at: Span) -> Result<LocalId, LowerError> {
+        let params = builtin.params();
+        if args.len() != params.len() {
+            return Err(self.internal(&format!("{} takes {} arguments", builtin, params.len()), at));
+        }
+        let mut locals = Vec::with_capacity(args.len());
+        for (arg, &ty) in args.iter().zip(params) {
+            let local = self.expr(arg)?;
+            if self.ty(local) != ty {
+                let what = match ty {
+                    Type::Number => format!("passing a string to '{}'", builtin),
+                    Type::Str => format!("passing a number to '{}'", builtin),
+                };
+                return Err(self.unsupported(&what, arg.span()));
+            }
+            locals.push(local);
+        }
+        Ok(self.builtin(builtin, locals, at))
+    }
+
+    /// Calls `builtin` at `at` with arguments of the types it takes.
+    fn builtin(&mut self, builtin: Builtin, args: Vec<LocalId>, at: Span) -> LocalId {
+        self.value(builtin.returns(), Op::Builtin { builtin, args, at })
+    }
+
+    /// Sets `result` to 1 when `value` is not zero and to 0 otherwise.
+    fn truthy(&mut self, result: LocalId, value: LocalId) {
+        let zero = self.value(Type::Number, Op::Number(0.0));
+        self.emit(Some(result), Op::Binary(BinOp::Ne, value, zero));
+    }
+
+    /// The symbol the resolver gave the name at `span`.
+    fn symbol(&self, name: Symbol, span: Span) -> Result<SymbolId, LowerError> {
+        self.resolution
+            .lookup(span)
+            .ok_or_else(|| self.internal(&format!("'{}' was not resolved", name), span))
+    }
+
+    fn variable(&self, name: Symbol, span: Span) -> Result<LocalId, LowerError> {
+        match self.variables.get(&self.symbol(name, span)?) {
+            Some(local) => Ok(*local),
+            None => Err(self.internal(&format!("no variable for '{}'", name), span)),
+        }
+    }
+
+    /// For `value` of the form `s + a + b ...` where `s` is the string variable in `local`, the
+    /// pieces added to it in order, each with the span of its `+`; `None` for anything else.
+    fn appended<'e>(&self, local: LocalId, mut value: &'e Expr) -> Result<Option<Vec<(&'e Expr, Span)>>, LowerError> {
+        if self.ty(local) != Type::Str {
+            return Ok(None);
+        }
+        let mut pieces = Vec::new();
+        while let Expr::Binary(BinOp::Add, left, right, span) = value {
+            pieces.push((right.as_ref(), *span));
+            value = left;
+        }
+        if !matches!(value, Expr::Identifier(name, span) if self.variable(*name, *span)? == local) || pieces.is_empty() {
+            return Ok(None);
+        }
+        pieces.reverse();
+        // A later piece that is `s` itself would read it after the earlier ones went in
+        for (piece, _) in &pieces[1..] {
+            if matches!(piece, Expr::Identifier(name, span) if self.variable(*name, *span)? == local) {
+                return Ok(None);
+            }
+        }
+        Ok(Some(pieces))
+    }
+
+    fn ty(&self, local: LocalId) -> Type {
+        self.locals[local.0 as usize].ty
+    }
+
+    fn local(&mut self, ty: Type, name: Option<Symbol>) -> LocalId {
+        self.locals.push(Local { ty, name });
+        LocalId(self.locals.len() as u32 - 1)
+    }
+
+    /// A new temporary holding the result of `op`.
+    fn value(&mut self, ty: Type, op: Op) -> LocalId {
+        let dest = self.local(ty, None);
+        self.emit(Some(dest), op);
+        dest
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push((Vec::new(), None));
+        BlockId(self.blocks.len() as u32 - 1)
+    }
+
+    /// Code after a terminator, such as statements following a `return`, goes into a new block that
+    /// nothing jumps to.
+    fn open_block(&mut self) -> usize {
+        if self.blocks[self.current.0 as usize].1.is_some() {
+            self.current = self.new_block();
+        }
+        self.current.0 as usize
+    }
+
+    fn emit(&mut self, dest: Option<LocalId>, op: Op) {
+        let block = self.open_block();
+        self.blocks[block].0.push(Inst { dest, op, span: self.span });
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = self.open_block();
+        self.blocks[block].1 = Some((terminator, self.span));
+    }
+
+    /// The number `enum_name.variant` stands for: its place in the enum, counting from 0.
+    fn variant(&self, enum_name: Symbol, variant: Symbol, span: Span) -> Result<f64, LowerError> {
+        self.tables
+            .enums
+            .get(&enum_name)
+            .and_then(|variants| variants.iter().position(|known| known.name == variant))
+            .map(|index| index as f64)
+            .ok_or_else(|| self.internal("an undefined variant", span))
+    }
+
+    fn unsupported(&self, what: &str, span: Span) -> LowerError {
+        LowerError {
+            what: what.to_string(),
+            span,
+            file: self.file,
+            internal: false,
+        }
+    }
+
+    fn internal(&self, what: &str, span: Span) -> LowerError {
+        LowerError {
+            internal: true,
+            ..self.unsupported(what, span)
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::{HashMap, HashSet};
+
+use vira_core::{Span, Symbol};
+
+use crate::{BinOp, BlockId, Builtin, Function, LocalId, Module, Op, Terminator, UnOp};
+
+/// Something dead code elimination took out of a module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Removed {
+    /// What it was, such as "function 'helper'".
+    pub what: String,
+    pub span: Span,
+    /// Index of the file the span points into.
+    pub file: usize,
+}
+
+/// Rewrites a module into a simpler one that behaves the same: folds constants, propagates copies
+/// and removes dead code until nothing changes. Functions that `main` never calls go first, so
+/// what is reported about the rest only concerns code that can run.
+pub fn optimize(module: &mut Module) -> Vec<Removed> {
+    let mut removed = remove_uncalled(module);
+    for function in &mut module.functions {
+        let read = read_locals(function);
+        loop {
+            let mut changed = fold_constants(function);
+            changed |= propagate_copies(function);
+            changed |= remove_unreachable(function, &mut removed);
+            changed |= remove_unused(function, &read, &mut removed);
+            if !changed {
+                break;
+            }
+        }
+    }
+    // Folding can drop the only call to a function, from a branch that never runs
+    removed.extend(remove_uncalled(module));
+    removed
+}
+
+/// Names of the functions `main` can reach through calls, directly or not.
+pub fn called_functions(module: &Module) -> HashSet<Symbol> {
+    let by_name: HashMap<Symbol, &Function> = module
+        .functions
+        .iter()
+        .filter(|function| !function.is_main)
+        .map(|function| (function.name, function))
+        .collect();
+    let mut called = HashSet::new();
+    let mut worklist: Vec<&Function> = module.functions.iter().filter(|function| function.is_main).collect();
+    while let Some(function) = worklist.pop() {
+        for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+            if let Op::Call(name, _) = &inst.op {
+                if called.insert(*name) {
+                    worklist.extend(by_name.get(name));
+                }
+            }
+        }
+    }
+    called
+}
+
+/// Removes the functions `main` can't reach through calls.
+fn remove_uncalled(module: &mut Module) -> Vec<Removed> {
+    let called = called_functions(module);
+    let mut removed = Vec::new();
+    module.functions.retain(|function| {
+        let keep = function.is_main || called.contains(&function.name);
+        if !keep {
+            removed.push(Removed {
+                what: format!("function '{}'", function.name),
+                span: function.span,
+                file: function.file,
+            });
+        }
+        keep
+    });
+    removed
+}
+
+/// How many times each local is assigned; parameters count their argument as one assignment.
+fn definitions(function: &Function) -> Vec<usize> {
+    let mut count = vec![0; function.locals.len()];
+    count[..function.params].fill(1);
+    for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+        if let Some(dest) = inst.dest {
+            count[dest.0 as usize] += 1;
+        }
+    }
+    count
+}
+
+/// The named locals the function reads, so that removing a variable is only reported when the
+/// program never used it, not when propagation made it unnecessary.
+fn read_locals(function: &Function) -> HashSet<LocalId> {
+    let mut read = HashSet::new();
+    for block in &function.blocks {
+        for inst in &block.insts {
+            read.extend(inst.op.operands());
+        }
+        read.extend(block.terminator.operand());
+    }
+    read
+}
+
+/// Evaluates operators whose operands are all constants, so `2 + 3 * 4` becomes `14`, `"a" + "b"`
+/// becomes `"ab"` and `"a" == "b"` becomes `0`; drops operations that leave a number unchanged,
+/// `x * 1`, `x / 1`, `x + -0` and `x - 0`; and turns a branch on a constant into a jump.
+///
+/// A local counts as constant when it is assigned once, from a constant. Lowering only reads a
+/// variable after its `let`, so that assignment always runs first.
+fn fold_constants(function: &mut Function) -> bool {
+    let definitions = definitions(function);
+    let mut constants: HashMap<LocalId, Op> = HashMap::new();
+    for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+        if let (Some(dest), Op::Number(_) | Op::String(_)) = (inst.dest, &inst.op) {
+            if definitions[dest.0 as usize] == 1 {
+                constants.insert(dest, inst.op.clone());
+            }
+        }
+    }
+    let number = |constants: &HashMap<LocalId, Op>, local: &LocalId| match constants.get(local) {
+        Some(Op::Number(value)) => Some(*value),
+        _ => None,
+    };
+    let mut changed = false;
+    for block in &mut function.blocks {
+        for inst in &mut block.insts {
+            let simpler = match &inst.op {
+                Op::Copy(source) => constants.get(source).cloned(),
+                Op::Unary(op, operand) => number(&constants, operand).map(|value| Op::Number(unary(*op, value))),
+                Op::Binary(op, left, right) => match (number(&constants, left), number(&constants, right)) {
+                    (Some(left), Some(right)) => Some(Op::Number(binary(*op, left, right))),
+                    (None, Some(value)) if is_identity(*op, value, false) => Some(Op::Copy(*left)),
+                    (Some(value), None) if is_identity(*op, value, true) => Some(Op::Copy(*right)),
+                    _ => None,
+                },
+                Op::Concat(left, right) => match (constants.get(left), constants.get(right)) {
+                    (Some(Op::String(left)), Some(Op::String(right))) => Some(Op::String(format!("{}{}", left, right))),
+                    _ => None,
+                },
+                Op::Builtin {
+                    builtin: Builtin::Equal,
+                    args,
+                    ..
+                } => match (constants.get(&args[0]), constants.get(&args[1])) {
+                    (Some(Op::String(left)), Some(Op::String(right))) => Some(Op::Number(f64::from(u8::from(left == right)))),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(simpler) = simpler {
+                // Later instructions can use the result right away, so a chain like `1 + 1 + ...`
+                // folds in one pass instead of one pass per operator
+                if let (Some(dest), Op::Number(_) | Op::String(_)) = (inst.dest, &simpler) {
+                    if definitions[dest.0 as usize] == 1 {
+                        constants.insert(dest, simpler.clone());
+                    }
+                }
+                inst.op = simpler;
+                changed = true;
+            }
+        }
+        if let Terminator::Branch { condition, then, otherwise } = block.terminator {
+            if let Some(value) = number(&constants, &condition) {
+                block.terminator = Terminator::Jump(if value != 0.0 { then } else { otherwise });
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// Replaces reads of a local that was copied from another with reads of the original, when each
+/// is assigned exactly once and so both hold the same value wherever the copy is visible.
+fn propagate_copies(function: &mut Function) -> bool {
+    let definitions = definitions(function);
+    let mut sources: HashMap<LocalId, LocalId> = HashMap::new();
+    for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+        if let (Some(dest), Op::Copy(source)) = (inst.dest, &inst.op) {
+            if definitions[dest.0 as usize] == 1 && definitions[source.0 as usize] == 1 {
+                sources.insert(dest, *source);
+            }
+        }
+    }
+    if sources.is_empty() {
+        return false;
+    }
+    let resolve = |mut local: LocalId| {
+        while let Some(source) = sources.get(&local) {
+            local = *source;
+        }
+        local
+    };
+    let mut changed = false;
+    let mut replace = |local: LocalId| {
+        let source = resolve(local);
+        changed |= source != local;
+        source
+    };
+    for block in &mut function.blocks {
+        for inst in &mut block.insts {
+            // Rewriting the copies themselves would only make them copy a local to itself
+            if !matches!(inst.op, Op::Copy(_)) {
+                inst.op.map_operands(&mut replace);
+            }
+        }
+        block.terminator.map_operand(&mut replace);
+    }
+    changed
+}
+
+/// Removes the blocks control can't reach, such as code after a `return` or the branch of an `if`
+/// whose condition folded to a constant.
+fn remove_unreachable(function: &mut Function, removed: &mut Vec<Removed>) -> bool {
+    let reachable = function.reachable_blocks();
+    if reachable.iter().all(|&reachable| reachable) {
+        return false;
+    }
+    let mut spans: Vec<Span> = Vec::new();
+    for (block, _) in function.blocks.iter().zip(&reachable).filter(|(_, &reachable)| !reachable) {
+        // Instructions the lowering added itself have no span and nothing to show
+        spans.extend(block.insts.iter().map(|inst| inst.span).filter(|span| *span != Span::default()));
+    }
+    // One report per statement, not per instruction or per nested statement
+    spans.sort_by_key(|span| (span.start, std::cmp::Reverse(span.end)));
+    let mut outer: Option<Span> = None;
+    for span in spans {
+        if outer.is_some_and(|outer| outer.start <= span.start && span.end <= outer.end) {
+            continue;
+        }
+        outer = Some(span);
+        removed.push(Removed {
+            what: "unreachable code".to_string(),
+            span,
+            file: function.file,
+        });
+    }
+
+    let mut renumbered = Vec::with_capacity(reachable.len());
+    let mut next = 0;
+    for &reachable in &reachable {
+        renumbered.push(BlockId(next));
+        next += u32::from(reachable);
+    }
+    let mut index = 0;
+    function.blocks.retain(|_| {
+        index += 1;
+        reachable[index - 1]
+    });
+    for block in &mut function.blocks {
+        match &mut block.terminator {
+            Terminator::Jump(target) => *target = renumbered[target.0 as usize],
+            Terminator::Branch { then, otherwise, .. } => {
+                *then = renumbered[then.0 as usize];
+                *otherwise = renumbered[otherwise.0 as usize];
+            }
+            Terminator::Return(_) => {}
+        }
+    }
+    true
+}
+
+/// Removes instructions that only compute a value nothing reads. `read` holds the locals the
+/// function read before optimizing; a variable outside it is reported the first time one of its
+/// assignments goes.
+fn remove_unused(function: &mut Function, read: &HashSet<LocalId>, removed: &mut Vec<Removed>) -> bool {
+    let used = read_locals(function);
+    let mut changed = false;
+    let mut reported = HashSet::new();
+    for block in &mut function.blocks {
+        block.insts.retain(|inst| {
+            let Some(dest) = inst.dest else {
+                return true;
+            };
+            if used.contains(&dest) || !inst.op.is_pure() {
+                return true;
+            }
+            if let Some(name) = &function.locals[dest.0 as usize].name {
+                if !read.contains(&dest) && reported.insert(dest) {
+                    removed.push(Removed {
+                        what: format!("unused variable '{}'", name),
+                        span: inst.span,
+                        file: function.file,
+                    });
+                }
+            }
+            changed = true;
+            false
+        });
+    }
+    changed
+}
+
+/// Whether `value` on one side of `op` leaves the other operand unchanged; `left` says which side.
+/// Only a zero of the right sign does: `-0 + 0` and `-0 - -0` are `0`, not `-0`.
+fn is_identity(op: BinOp, value: f64, left: bool) -> bool {
+    match op {
+        BinOp::Add => value == 0.0 && value.is_sign_negative(),
+        BinOp::Sub => value == 0.0 && value.is_sign_positive() && !left,
+        BinOp::Mul => value == 1.0,
+        BinOp::Div => value == 1.0 && !left,
+        _ => false,
+    }
+}
+
+/// `op value` as the generated code computes it.
+pub fn unary(op: UnOp, value: f64) -> f64 {
+    match op {
+        UnOp::Neg => -value,
+        UnOp::Not => f64::from(u8::from(value == 0.0)),
+    }
+}
+
+/// `left op right` as the generated code computes it: `%` is C's `fmod`, comparisons give 1 or 0.
+pub fn binary(op: BinOp, left: f64, right: f64) -> f64 {
+    let flag = |value: bool| f64::from(u8::from(value));
+    match op {
+        BinOp::Add => left + right,
+        BinOp::Sub => left - right,
+        BinOp::Mul => left * right,
+        BinOp::Div => left / right,
+        BinOp::Mod => left % right,
+        BinOp::Eq => flag(left == right),
+        BinOp::Ne => flag(left != right),
+        BinOp::Lt => flag(left < right),
+        BinOp::Le => flag(left <= right),
+        BinOp::Gt => flag(left > right),
+        BinOp::Ge => flag(left >= right),
+        BinOp::And => flag(left != 0.0 && right != 0.0),
+        BinOp::Or => flag(left != 0.0 || right != 0.0),
+    }
+}
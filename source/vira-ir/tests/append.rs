@@ -0,0 +1,67 @@
+//! Which `s = s + x` assignments lower to an append, which may change the string in place, rather
+//! than a concatenation into a new one.
+
+use vira_core::parser::parse;
+use vira_core::{check, resolve::resolve};
+use vira_ir::{lower, Op};
+
+/// How many times `variable` is appended to and how many times it is concatenated into, anywhere
+/// in `source`.
+fn appends(source: &str, variable: &str) -> (usize, usize) {
+    let (program, _) = parse(source).unwrap();
+    assert!(check(&program).is_empty());
+    let resolution = resolve(&program);
+    let module = lower([(&program, &resolution)]).unwrap();
+
+    let (mut appended, mut concatenated) = (0, 0);
+    for function in &module.functions {
+        for inst in function.blocks.iter().flat_map(|block| &block.insts) {
+            let Some(dest) = inst.dest else { continue };
+            if function.locals[dest.0 as usize].name.is_none_or(|name| name != variable) {
+                continue;
+            }
+            match inst.op {
+                Op::Append(..) => appended += 1,
+                Op::Concat(..) => concatenated += 1,
+                _ => {}
+            }
+        }
+    }
+    (appended, concatenated)
+}
+
+#[test]
+fn a_string_only_its_variable_holds_is_appended_to() {
+    let source = "let s = \"\";\nlet i = 0;\nwhile i < 3 {\n    s = s + \"x\";\n    i = i + 1;\n}\nwrite s;\n";
+    assert_eq!(appends(source, "s"), (1, 0));
+}
+
+#[test]
+fn each_piece_of_a_longer_sum_is_appended() {
+    let source = "let s = \"a\";\nlet t = \"b\";\ns = s + t + \"c\";\nwrite s;\n";
+    assert_eq!(appends(source, "s"), (2, 0));
+}
+
+#[test]
+fn appending_the_string_to_itself_is_still_an_append() {
+    assert_eq!(appends("let s = \"ab\";\ns = s + s;\nwrite s;\n", "s"), (1, 0));
+}
+
+#[test]
+fn a_string_copied_into_another_variable_is_not_appended_to() {
+    let source = "let s = \"a\";\nlet t = s;\ns = s + \"x\";\nwrite s;\nwrite t;\n";
+    assert_eq!(appends(source, "s"), (0, 1));
+}
+
+#[test]
+fn a_string_assigned_from_another_variable_is_not_appended_to() {
+    let source = "let t = \"a\";\nlet s = \"\";\ns = t;\ns = s + \"x\";\nwrite s;\nwrite t;\n";
+    assert_eq!(appends(source, "s"), (0, 1));
+}
+
+#[test]
+fn concatenating_into_a_different_variable_leaves_the_string_alone() {
+    let source = "let s = \"a\";\nlet t = \"\";\nt = s + \"x\";\nwrite s;\nwrite t;\n";
+    assert_eq!(appends(source, "s"), (0, 0));
+    assert_eq!(appends(source, "t"), (0, 0));
+}
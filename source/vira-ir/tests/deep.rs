@@ -0,0 +1,19 @@
+//! Lowering and folding an expression far deeper than the stack could hold one frame per level for.
+
+use vira_core::parser::parse;
+use vira_core::{check, resolve::resolve};
+use vira_ir::{lower, optimize, Op};
+
+#[test]
+fn folds_a_sum_of_100k_terms() {
+    let source = format!("write {};\n", vec!["1"; 100_000].join("+"));
+    let (program, _) = parse(&source).unwrap();
+    assert!(check(&program).is_empty());
+    let resolution = resolve(&program);
+    let mut module = lower([(&program, &resolution)]).unwrap();
+    optimize(&mut module);
+
+    let main = module.functions.iter().find(|function| function.is_main).unwrap();
+    let folded = main.blocks.iter().flat_map(|block| &block.insts).any(|inst| inst.op == Op::Number(100_000.0));
+    assert!(folded, "the sum should fold to one constant");
+}
@@ -0,0 +1,70 @@
+//! The Vira call stack, so a failure can show how the program got there. Programs compiled with
+//! `-g` record every call here; in others it stays empty and a failure only gives its own location.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::{c_char, CStr};
+use core::fmt::Write;
+use core::ptr;
+
+/// Innermost calls a failure shows; deeper recursion is summed up in one line.
+const SHOWN: usize = 32;
+
+struct Frame {
+    /// The function called.
+    function: *const c_char,
+    /// Where it was called from.
+    call: *const c_char,
+}
+
+// Vira programs are single-threaded, so nothing else ever touches the stack
+static mut FRAMES: Vec<Frame> = Vec::new();
+
+/// Records a call to `function` from `call`, just before it is made.
+///
+/// # Safety
+///
+/// Both arguments must point to NUL-terminated strings that live until the program exits.
+#[no_mangle]
+pub unsafe extern "C" fn vira_frame_push(function: *const c_char, call: *const c_char) {
+    (*ptr::addr_of_mut!(FRAMES)).push(Frame { function, call });
+}
+
+/// Records that the innermost call returned.
+#[no_mangle]
+pub extern "C" fn vira_frame_pop() {
+    unsafe {
+        (*ptr::addr_of_mut!(FRAMES)).pop();
+    }
+}
+
+/// The call stack under a failure at `location`, innermost first, one `  in f at file:line:column`
+/// line per function; empty when no calls were recorded.
+///
+/// # Safety
+///
+/// `location` must point to a NUL-terminated string.
+pub(crate) unsafe fn describe(location: *const c_char) -> Vec<u8> {
+    let frames = &*ptr::addr_of!(FRAMES);
+    let mut out = Vec::new();
+    if frames.is_empty() {
+        return out;
+    }
+    out.extend_from_slice(b"backtrace:\n");
+    // Each function is where the call to the next one inward was made, and the innermost one is
+    // where the failure was
+    let functions = frames.iter().rev().map(|frame| frame.function).chain([c"main".as_ptr()]);
+    let locations = [location].into_iter().chain(frames.iter().rev().map(|frame| frame.call));
+    for (index, (function, location)) in functions.zip(locations).enumerate() {
+        if index == SHOWN {
+            let mut line = String::new();
+            let _ = writeln!(line, "  ... and {} more", frames.len() + 1 - SHOWN);
+            out.extend_from_slice(line.as_bytes());
+            break;
+        }
+        for part in [b"  in ".as_slice(), CStr::from_ptr(function).to_bytes(), b" at ", CStr::from_ptr(location).to_bytes(), b"\n"] {
+            out.extend_from_slice(part);
+        }
+    }
+    out
+}
@@ -0,0 +1,34 @@
+//! Conversions between numbers and strings. `int` needs no runtime; it is the C library's `trunc`.
+
+use alloc::string::String;
+use core::ffi::{c_char, CStr};
+use core::fmt::Write;
+
+use crate::string::new_string;
+use crate::{runtime_error, write_number, Buffer};
+
+/// `num(text)`: the number `text` spells out, such as `42`, `-3.5` or `1e6`, with any whitespace
+/// around it. Anything else stops the program with an error reported at `location`.
+///
+/// # Safety
+///
+/// Both arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_str_to_num(text: *const c_char, location: *const c_char) -> f64 {
+    let text = CStr::from_ptr(text).to_str().unwrap_or_default();
+    if let Ok(value) = text.trim().parse::<f64>() {
+        return value;
+    }
+    let mut message = String::new();
+    let _ = write!(message, "{:?} is not a number", text);
+    runtime_error(location, &message)
+}
+
+/// `str(value)`: the number as `write` prints it.
+#[no_mangle]
+pub extern "C" fn vira_num_to_str(value: f64) -> *mut c_char {
+    let mut buffer = Buffer::new();
+    // Six significant digits always fit the buffer
+    let _ = write_number(&mut buffer, value);
+    new_string(buffer.as_str().as_bytes())
+}
@@ -0,0 +1,102 @@
+//! HTTP builtins: `http_get`, `http_post` and `http_status`. Requests block until the whole
+//! response is in, and follow redirects.
+//!
+//! They need the standard library and an HTTP client, so they only work in a runtime built with
+//! the `http` feature; without it the runtime stays free of both and every request stops the
+//! program with an error. The compiler's `--sandbox` rejects programs that call them.
+
+use core::ffi::c_char;
+#[cfg(feature = "http")]
+use core::ffi::CStr;
+#[cfg(feature = "http")]
+use core::ptr;
+
+use crate::runtime_error;
+#[cfg(feature = "http")]
+use crate::string::new_string;
+
+/// The status of the last response, 0 before the first. Vira programs are single-threaded, so
+/// nothing else ever touches it.
+#[cfg(feature = "http")]
+static mut STATUS: u16 = 0;
+
+/// `http_get(url)`: the body of the response to a GET request for `url`, whatever its status. A
+/// request that gets no response, or a body that isn't text, stops the program with an error
+/// reported at `location`.
+///
+/// # Safety
+///
+/// Both arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_http_get(url: *const c_char, location: *const c_char) -> *mut c_char {
+    #[cfg(feature = "http")]
+    {
+        let url = CStr::from_ptr(url).to_str().unwrap_or_default();
+        respond(agent().get(url).call(), location)
+    }
+    #[cfg(not(feature = "http"))]
+    {
+        let _ = url;
+        unavailable(location)
+    }
+}
+
+/// `http_post(url, body)`: like `http_get`, for a POST request that sends `body`.
+///
+/// # Safety
+///
+/// All arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_http_post(url: *const c_char, body: *const c_char, location: *const c_char) -> *mut c_char {
+    #[cfg(feature = "http")]
+    {
+        let url = CStr::from_ptr(url).to_str().unwrap_or_default();
+        respond(agent().post(url).send(CStr::from_ptr(body).to_bytes()), location)
+    }
+    #[cfg(not(feature = "http"))]
+    {
+        let _ = (url, body);
+        unavailable(location)
+    }
+}
+
+/// `http_status()`: the status code of the last response, such as 200 or 404; 0 before the first.
+#[no_mangle]
+pub extern "C" fn vira_http_status() -> f64 {
+    #[cfg(feature = "http")]
+    {
+        f64::from(unsafe { *ptr::addr_of!(STATUS) })
+    }
+    #[cfg(not(feature = "http"))]
+    {
+        0.0
+    }
+}
+
+/// An agent that hands back error statuses as responses, since a Vira program reads the status
+/// with `http_status()` rather than failing on a 404.
+#[cfg(feature = "http")]
+fn agent() -> ureq::Agent {
+    ureq::Agent::config_builder().http_status_as_error(false).build().into()
+}
+
+/// The body of `response`, after noting its status.
+#[cfg(feature = "http")]
+unsafe fn respond(response: Result<ureq::http::Response<ureq::Body>, ureq::Error>, location: *const c_char) -> *mut c_char {
+    use alloc::string::ToString;
+
+    let mut response = match response {
+        Ok(response) => response,
+        Err(err) => runtime_error(location, &err.to_string()),
+    };
+    *ptr::addr_of_mut!(STATUS) = response.status().as_u16();
+    match response.body_mut().read_to_string() {
+        Ok(body) => new_string(body.as_bytes()),
+        Err(err) => runtime_error(location, &err.to_string()),
+    }
+}
+
+#[cfg(not(feature = "http"))]
+unsafe fn unavailable(location: *const c_char) -> ! {
+    runtime_error(location, "HTTP needs a Vira runtime built with `cargo build --release --features http`")
+}
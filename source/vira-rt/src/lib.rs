@@ -0,0 +1,340 @@
+//! Runtime linked into every program the Vira compiler builds. Generated code calls these functions
+//! for printing and strings instead of reaching into the C library itself.
+//!
+//! The crate doesn't use the Rust standard library, so the archive only needs the C library that
+//! compiled programs link anyway. The `http` feature is the exception: its client brings the
+//! standard library in.
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+mod backtrace;
+mod convert;
+mod http;
+mod profile;
+mod os;
+mod random;
+mod regex;
+mod string;
+mod time;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_int, c_uint, c_void, CStr};
+use core::fmt::{self, Write};
+use core::ptr;
+
+extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn realloc(ptr: *mut c_void, size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+    fn abort() -> !;
+    fn exit(status: c_int) -> !;
+    fn puts(text: *const c_char) -> c_int;
+    fn fflush(stream: *mut c_void) -> c_int;
+    // `stderr` is a macro with a different definition in every C library, but descriptor 2 is the
+    // same everywhere
+    #[cfg_attr(windows, link_name = "_write")]
+    fn write(fd: c_int, bytes: *const c_void, count: c_uint) -> c_int;
+}
+
+/// Prints a number on a line of its own, formatted like C's `%g`: six significant digits, without
+/// trailing zeros, in scientific notation for very large and very small magnitudes.
+#[no_mangle]
+pub extern "C" fn vira_print_num(value: f64) {
+    let mut buffer = Buffer::new();
+    // Six significant digits always fit the buffer
+    let _ = write_number(&mut buffer, value);
+    unsafe {
+        puts(buffer.as_c_str());
+    }
+}
+
+/// Prints a string on a line of its own.
+///
+/// # Safety
+///
+/// `text` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vira_print_str(text: *const c_char) {
+    puts(text);
+}
+
+/// Joins two strings into a newly allocated one. Vira has no way to free a string, so the result
+/// lives until the program exits.
+///
+/// # Safety
+///
+/// Both arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_concat(left: *const c_char, right: *const c_char) -> *mut c_char {
+    let left = CStr::from_ptr(left).to_bytes();
+    let right = CStr::from_ptr(right).to_bytes();
+    let joined = vira_alloc(left.len() + right.len() + 1).cast::<u8>();
+    ptr::copy_nonoverlapping(left.as_ptr(), joined, left.len());
+    ptr::copy_nonoverlapping(right.as_ptr(), joined.add(left.len()), right.len());
+    *joined.add(left.len() + right.len()) = 0;
+    joined.cast()
+}
+
+/// A string `vira_append` made, with room after it to grow.
+#[derive(Clone, Copy)]
+struct Growable {
+    text: *mut u8,
+    len: usize,
+    capacity: usize,
+}
+
+/// The strings `vira_append` made most recently, so appending to one of them again can grow it in
+/// place. A program building more strings at once than this still works, but copies them.
+static mut GROWABLE: [Growable; 16] = [Growable {
+    text: ptr::null_mut(),
+    len: 0,
+    capacity: 0,
+}; 16];
+/// The slot the next string that isn't growable yet goes into, taking turns.
+static mut NEXT_GROWABLE: usize = 0;
+
+/// `left + right` for `s = s + x;`, where the compiler has made sure nothing but the variable
+/// holds `left` and that the variable gets the result. So the string can change in place: one that
+/// came from here before grows into the room left after it, which doubles whenever it runs out,
+/// and building a string piece by piece in a loop takes time in proportion to its length rather
+/// than to its square.
+///
+/// # Safety
+///
+/// Both arguments must point to NUL-terminated strings, and `left` must not be used again except
+/// through the result.
+#[no_mangle]
+pub unsafe extern "C" fn vira_append(left: *mut c_char, right: *const c_char) -> *mut c_char {
+    let growable = &mut *ptr::addr_of_mut!(GROWABLE);
+    let right_len = CStr::from_ptr(right).to_bytes().len();
+    let slot = match growable.iter().position(|string| string.text == left.cast()) {
+        Some(slot) => slot,
+        None => {
+            // Strings made elsewhere, including literals, have no room, so the first append copies
+            let len = CStr::from_ptr(left).to_bytes().len();
+            let capacity = (len + right_len + 1).max(32);
+            let text = vira_alloc(capacity).cast::<u8>();
+            ptr::copy_nonoverlapping(left.cast::<u8>(), text, len);
+            let slot = *ptr::addr_of!(NEXT_GROWABLE);
+            *ptr::addr_of_mut!(NEXT_GROWABLE) = (slot + 1) % growable.len();
+            growable[slot] = Growable { text, len, capacity };
+            slot
+        }
+    };
+    let Growable { mut text, len, mut capacity } = growable[slot];
+    let needed = len + right_len + 1;
+    if needed > capacity {
+        capacity = needed.max(capacity * 2);
+        text = realloc(text.cast(), capacity).cast();
+        if text.is_null() {
+            abort()
+        }
+    }
+    // For `s = s + s` the right string is the left one, which may have just moved
+    let source = if ptr::eq(right, left) { text.cast_const() } else { right.cast() };
+    ptr::copy_nonoverlapping(source, text.add(len), right_len);
+    *text.add(len + right_len) = 0;
+    growable[slot] = Growable {
+        text,
+        len: len + right_len,
+        capacity,
+    };
+    text.cast()
+}
+
+/// The values the assertion about to fail compared, as its report shows them.
+static mut ASSERT_VALUES: Vec<String> = Vec::new();
+
+/// Notes a number the assertion about to fail compared, left before right.
+#[no_mangle]
+pub extern "C" fn vira_assert_value_num(value: f64) {
+    let mut shown = String::new();
+    let _ = write_number(&mut shown, value);
+    unsafe { (*ptr::addr_of_mut!(ASSERT_VALUES)).push(shown) }
+}
+
+/// Notes a string the assertion about to fail compared, left before right. It is shown quoted, so
+/// that `"1"` and `1` or an empty string and a missing one can be told apart.
+///
+/// # Safety
+///
+/// `value` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vira_assert_value_str(value: *const c_char) {
+    let mut shown = String::new();
+    let _ = write!(shown, "{:?}", String::from_utf8_lossy(CStr::from_ptr(value).to_bytes()));
+    (*ptr::addr_of_mut!(ASSERT_VALUES)).push(shown)
+}
+
+/// Reports a failed `assert` or `assert_eq` on standard error, with the values noted for it, and
+/// exits with status 1. Output the program has printed so far comes first.
+///
+/// # Safety
+///
+/// Both arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_assert_failed(message: *const c_char, location: *const c_char) -> ! {
+    let mut values = String::new();
+    for (label, value) in ["left", "right"].iter().zip(&*ptr::addr_of!(ASSERT_VALUES)) {
+        let _ = write!(values, "\n  {}: {}", label, value);
+    }
+    fail(
+        &[b"assertion failed at ", CStr::from_ptr(location).to_bytes(), b": ", CStr::from_ptr(message).to_bytes(), values.as_bytes()],
+        location,
+    )
+}
+
+/// `panic(message)`: stops the program with `message`, reported at `location`, and exit status 1.
+///
+/// # Safety
+///
+/// Both arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_panic(message: *const c_char, location: *const c_char) -> f64 {
+    fail(&[b"panicked at ", CStr::from_ptr(location).to_bytes(), b": ", CStr::from_ptr(message).to_bytes()], location)
+}
+
+/// Reports an error a builtin ran into at `location`, like an index past the end of a string, and
+/// exits with status 1.
+///
+/// # Safety
+///
+/// `location` must point to a NUL-terminated string.
+pub(crate) unsafe fn runtime_error(location: *const c_char, message: &str) -> ! {
+    fail(&[b"error at ", CStr::from_ptr(location).to_bytes(), b": ", message.as_bytes()], location)
+}
+
+/// Writes `parts` and a line break to standard error, after the output the program has printed so
+/// far, then the call stack for a failure at `location` if the program recorded one, and exits
+/// with status 1.
+unsafe fn fail(parts: &[&[u8]], location: *const c_char) -> ! {
+    fflush(ptr::null_mut());
+    let backtrace = backtrace::describe(location);
+    for bytes in parts.iter().chain([&b"\n".as_slice(), &backtrace.as_slice()]) {
+        write(2, bytes.as_ptr().cast(), bytes.len() as c_uint);
+    }
+    exit(1)
+}
+
+/// Allocates `size` bytes. Running out of memory aborts the program, so callers never see null.
+#[no_mangle]
+pub extern "C" fn vira_alloc(size: usize) -> *mut c_void {
+    let allocation = unsafe { malloc(size.max(1)) };
+    if allocation.is_null() {
+        unsafe { abort() }
+    }
+    allocation
+}
+
+/// Releases memory from [`vira_alloc`].
+///
+/// # Safety
+///
+/// `allocation` must come from `vira_alloc` and not have been freed yet, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn vira_free(allocation: *mut c_void) {
+    free(allocation);
+}
+
+fn write_number(out: &mut impl Write, value: f64) -> fmt::Result {
+    if value.is_nan() {
+        return out.write_str(if value.is_sign_negative() { "-nan" } else { "nan" });
+    }
+    if value.is_infinite() {
+        return out.write_str(if value < 0.0 { "-inf" } else { "inf" });
+    }
+    // The exponent after rounding to six digits picks the notation, as in `%g`
+    let mut scientific = Buffer::new();
+    write!(scientific, "{:.5e}", value)?;
+    let (mantissa, exponent) = scientific.as_str().split_once('e').unwrap_or((scientific.as_str(), "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    if (-4..6).contains(&exponent) {
+        let mut fixed = Buffer::new();
+        write!(fixed, "{:.*}", (5 - exponent) as usize, value)?;
+        out.write_str(trim_zeros(fixed.as_str()))
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        write!(out, "{}e{}{:02}", trim_zeros(mantissa), sign, exponent.abs())
+    }
+}
+
+/// Drops zeros at the end of the fraction, and the decimal point if no digits are left after it.
+fn trim_zeros(text: &str) -> &str {
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        text
+    }
+}
+
+/// Formatting target on the stack, with room for the NUL that `puts` needs.
+struct Buffer {
+    bytes: [u8; 32],
+    len: usize,
+}
+
+impl Buffer {
+    fn new() -> Self {
+        Buffer { bytes: [0; 32], len: 0 }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+
+    fn as_c_str(&mut self) -> *const c_char {
+        self.bytes[self.len] = 0;
+        self.bytes.as_ptr().cast()
+    }
+}
+
+impl Write for Buffer {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        let end = self.len + text.len();
+        if end >= self.bytes.len() {
+            return Err(fmt::Error);
+        }
+        self.bytes[self.len..end].copy_from_slice(text.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Lets the runtime's own bookkeeping use `alloc` collections on top of the C library's `malloc`.
+#[cfg(not(test))]
+struct Malloc;
+
+#[cfg(not(test))]
+unsafe impl core::alloc::GlobalAlloc for Malloc {
+    unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
+        // `malloc` aligns for every C type, which covers everything the runtime allocates
+        if layout.align() > 16 {
+            return ptr::null_mut();
+        }
+        malloc(layout.size()).cast()
+    }
+
+    unsafe fn dealloc(&self, allocation: *mut u8, _: core::alloc::Layout) {
+        free(allocation.cast());
+    }
+}
+
+#[cfg(not(test))]
+#[global_allocator]
+static ALLOCATOR: Malloc = Malloc;
+
+// The standard library, which the `http` feature brings in, has its own
+#[cfg(not(any(test, feature = "http")))]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    unsafe { abort() }
+}
+
+/// The archive includes `compiler_builtins`, whose unwind tables name this routine. A linker can
+/// pick its `fmod` over libm's, so the symbol has to exist, but with `panic = "abort"` nothing
+/// unwinds and it is never called.
+#[cfg(not(any(test, feature = "http")))]
+#[no_mangle]
+extern "C" fn rust_eh_personality() {}
@@ -0,0 +1,136 @@
+//! Builtins that reach outside the program: environment variables, other programs and the exit
+//! status. The compiler's `--sandbox` rejects programs that call any of them.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_int, c_void, CStr};
+use core::fmt::Write;
+use core::ptr;
+
+use crate::runtime_error;
+use crate::string::new_string;
+
+extern "C" {
+    fn getenv(name: *const c_char) -> *const c_char;
+    fn exit(status: c_int) -> !;
+    fn fflush(stream: *mut c_void) -> c_int;
+    fn system(command: *const c_char) -> c_int;
+    #[cfg_attr(windows, link_name = "_popen")]
+    fn popen(command: *const c_char, mode: *const c_char) -> *mut c_void;
+    #[cfg_attr(windows, link_name = "_pclose")]
+    fn pclose(stream: *mut c_void) -> c_int;
+    fn fread(buffer: *mut c_void, size: usize, count: usize, stream: *mut c_void) -> usize;
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn setenv(name: *const c_char, value: *const c_char, overwrite: c_int) -> c_int;
+}
+
+#[cfg(windows)]
+extern "C" {
+    fn _putenv_s(name: *const c_char, value: *const c_char) -> c_int;
+}
+
+/// `getenv(name)`: the environment variable's value, or the empty string when it isn't set.
+///
+/// # Safety
+///
+/// `name` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vira_getenv(name: *const c_char) -> *mut c_char {
+    let value = getenv(name);
+    if value.is_null() {
+        return new_string(b"");
+    }
+    new_string(CStr::from_ptr(value).to_bytes())
+}
+
+/// `setenv(name, value)`: sets the environment variable for the rest of the run and for the
+/// programs `exec` starts. Gives 0. A name that is empty or holds `=` stops the program with an
+/// error reported at `location`.
+///
+/// # Safety
+///
+/// All arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_setenv(name: *const c_char, value: *const c_char, location: *const c_char) -> f64 {
+    let bytes = CStr::from_ptr(name).to_bytes();
+    if bytes.is_empty() || bytes.contains(&b'=') {
+        let mut message = String::new();
+        let _ = write!(message, "{:?} is not a valid environment variable name", String::from_utf8_lossy(bytes));
+        runtime_error(location, &message)
+    }
+    #[cfg(unix)]
+    let status = setenv(name, value, 1);
+    #[cfg(windows)]
+    let status = _putenv_s(name, value);
+    if status != 0 {
+        runtime_error(location, "could not set the environment variable")
+    }
+    0.0
+}
+
+/// `exec(command)`: runs `command` with the system shell, `sh` or `cmd.exe`, and gives its exit
+/// status once it finishes. Its output goes where the program's does. A command the shell couldn't
+/// start gives -1, and one killed by a signal 128 plus the signal, as shells report it.
+///
+/// # Safety
+///
+/// `command` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vira_exec(command: *const c_char) -> f64 {
+    // Otherwise what the program printed before could come out after the command's output
+    fflush(ptr::null_mut());
+    exit_status(system(command))
+}
+
+/// `exec_output(command)`: runs `command` like `exec` and gives what it wrote to standard output.
+/// A command that couldn't be started gives the empty string.
+///
+/// # Safety
+///
+/// `command` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vira_exec_output(command: *const c_char) -> *mut c_char {
+    fflush(ptr::null_mut());
+    let stream = popen(command, c"r".as_ptr());
+    if stream.is_null() {
+        return new_string(b"");
+    }
+    let mut output = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        let read = fread(chunk.as_mut_ptr().cast(), 1, chunk.len(), stream);
+        if read == 0 {
+            break;
+        }
+        output.extend_from_slice(&chunk[..read]);
+    }
+    pclose(stream);
+    new_string(&output)
+}
+
+/// `exit(code)`: ends the program at once with `code` as its exit status, after writing out what
+/// it has printed. Never gives anything back.
+#[no_mangle]
+pub extern "C" fn vira_exit(code: f64) -> f64 {
+    // Float to integer casts saturate, and NaN becomes 0
+    unsafe { exit(code as c_int) }
+}
+
+/// What `system` gives back as the status a shell would show.
+#[cfg(unix)]
+fn exit_status(status: c_int) -> f64 {
+    match (status, status & 0x7F) {
+        (-1, _) => -1.0,
+        (_, 0) => f64::from((status >> 8) & 0xFF),
+        (_, signal) => f64::from(128 + signal),
+    }
+}
+
+/// On Windows `system` gives the command's exit code as it is.
+#[cfg(windows)]
+fn exit_status(status: c_int) -> f64 {
+    f64::from(status)
+}
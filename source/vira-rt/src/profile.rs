@@ -0,0 +1,180 @@
+//! Exact profiler for programs compiled with `--profile`. Every Vira function reports when it is
+//! entered and left, and the time between is added to a call tree. When the program exits, the tree
+//! is written in the collapsed-stack format of flamegraph.pl and inferno: one line per call stack,
+//! `main;outer;inner 1234`, with the microseconds spent in `inner` itself on that stack.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::{c_char, c_int, c_void, CStr};
+use core::fmt::Write;
+use core::ptr;
+
+use crate::time::now;
+
+extern "C" {
+    fn atexit(callback: extern "C" fn()) -> c_int;
+    fn getenv(name: *const c_char) -> *const c_char;
+    fn fopen(path: *const c_char, mode: *const c_char) -> *mut c_void;
+    fn fwrite(bytes: *const c_void, size: usize, count: usize, file: *mut c_void) -> usize;
+    fn fclose(file: *mut c_void) -> c_int;
+}
+
+/// Where the profile goes unless `VIRA_PROFILE` names another file.
+const DEFAULT_PATH: &CStr = c"profile.folded";
+
+struct Node {
+    name: *const c_char,
+    parent: usize,
+    children: Vec<usize>,
+    /// Nanoseconds spent in this function on this call stack, not counting its callees.
+    self_time: u64,
+}
+
+struct Frame {
+    node: usize,
+    entered: u64,
+    /// Nanoseconds spent in callees so far.
+    callees: u64,
+}
+
+struct Profile {
+    /// The call tree; the root is `main`.
+    nodes: Vec<Node>,
+    stack: Vec<Frame>,
+}
+
+// Vira programs are single-threaded, so nothing else ever touches the profile
+static mut PROFILE: Option<Profile> = None;
+
+fn profile() -> Option<&'static mut Profile> {
+    unsafe { (*ptr::addr_of_mut!(PROFILE)).as_mut() }
+}
+
+/// Starts profiling; `main` calls this first. The profile is written when the program exits.
+#[no_mangle]
+pub extern "C" fn vira_profile_start() {
+    let root = Node {
+        name: c"main".as_ptr(),
+        parent: 0,
+        children: Vec::new(),
+        self_time: 0,
+    };
+    let frame = Frame {
+        node: 0,
+        entered: now(),
+        callees: 0,
+    };
+    unsafe {
+        *ptr::addr_of_mut!(PROFILE) = Some(Profile {
+            nodes: alloc::vec![root],
+            stack: alloc::vec![frame],
+        });
+        atexit(write_profile);
+    }
+}
+
+/// Records a call to the function called `name`.
+///
+/// # Safety
+///
+/// `name` must point to a NUL-terminated string that lives until the program exits.
+#[no_mangle]
+pub unsafe extern "C" fn vira_profile_enter(name: *const c_char) {
+    let Some(profile) = profile() else {
+        return;
+    };
+    let parent = profile.stack.last().map_or(0, |frame| frame.node);
+    // Each file's object has its own copy of the name, so compare the text too
+    let existing = profile.nodes[parent]
+        .children
+        .iter()
+        .copied()
+        .find(|&child| ptr::eq(profile.nodes[child].name, name) || CStr::from_ptr(profile.nodes[child].name) == CStr::from_ptr(name));
+    let node = existing.unwrap_or_else(|| {
+        profile.nodes.push(Node {
+            name,
+            parent,
+            children: Vec::new(),
+            self_time: 0,
+        });
+        let node = profile.nodes.len() - 1;
+        profile.nodes[parent].children.push(node);
+        node
+    });
+    profile.stack.push(Frame {
+        node,
+        entered: now(),
+        callees: 0,
+    });
+}
+
+/// Records that the function entered last returns.
+#[no_mangle]
+pub extern "C" fn vira_profile_exit() {
+    if let Some(profile) = profile() {
+        profile.leave(now());
+    }
+}
+
+impl Profile {
+    fn leave(&mut self, now: u64) {
+        let Some(frame) = self.stack.pop() else {
+            return;
+        };
+        let elapsed = now.saturating_sub(frame.entered);
+        self.nodes[frame.node].self_time += elapsed.saturating_sub(frame.callees);
+        if let Some(caller) = self.stack.last_mut() {
+            caller.callees += elapsed;
+        }
+    }
+
+    /// The collapsed stacks, skipping those that took less than a microsecond.
+    fn folded(&self) -> String {
+        let mut out = String::new();
+        let mut path = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            let micros = node.self_time / 1000;
+            if micros == 0 {
+                continue;
+            }
+            path.clear();
+            let mut current = index;
+            loop {
+                path.push(self.nodes[current].name);
+                if current == 0 {
+                    break;
+                }
+                current = self.nodes[current].parent;
+            }
+            for (depth, name) in path.iter().rev().enumerate() {
+                if depth > 0 {
+                    out.push(';');
+                }
+                out.push_str(unsafe { CStr::from_ptr(*name) }.to_str().unwrap_or("?"));
+            }
+            let _ = writeln!(out, " {}", micros);
+        }
+        out
+    }
+}
+
+/// Runs at exit: closes the calls still in progress, `main` included, and writes the profile.
+extern "C" fn write_profile() {
+    let Some(profile) = profile() else {
+        return;
+    };
+    let now = now();
+    while !profile.stack.is_empty() {
+        profile.leave(now);
+    }
+    let folded = profile.folded();
+    unsafe {
+        let path = getenv(c"VIRA_PROFILE".as_ptr());
+        let path = if path.is_null() { DEFAULT_PATH.as_ptr() } else { path };
+        let file = fopen(path, c"w".as_ptr());
+        if !file.is_null() {
+            fwrite(folded.as_ptr().cast(), 1, folded.len(), file);
+            fclose(file);
+        }
+    }
+}
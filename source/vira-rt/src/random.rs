@@ -0,0 +1,68 @@
+//! Random numbers for `random()` and `rand_range()`. The generator is SplitMix64: fast, tiny and
+//! good enough for scripts, though not for anything secret.
+//!
+//! Each run starts from a different seed unless the program calls `seed(n)` or the environment
+//! variable `VIRA_SEED` holds a number, which makes runs repeatable: the same seed always gives
+//! the same numbers.
+
+use core::ffi::{c_char, CStr};
+use core::ptr;
+
+use crate::time::{now, vira_now};
+
+extern "C" {
+    fn getenv(name: *const c_char) -> *const c_char;
+}
+
+/// The generator's state, set on first use. Vira programs are single-threaded, so nothing else
+/// ever touches it.
+static mut STATE: Option<u64> = None;
+
+/// `random()`: a number from 0 up to but not including 1.
+#[no_mangle]
+pub extern "C" fn vira_random() -> f64 {
+    // The top 53 bits fill a double's mantissa exactly
+    (next() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// `rand_range(low, high)`: `low` plus a whole number, below `high`, so `rand_range(1, 7)` rolls a
+/// die. Gives `low` when the range is empty.
+#[no_mangle]
+pub extern "C" fn vira_rand_range(low: f64, high: f64) -> f64 {
+    if high <= low {
+        return low;
+    }
+    // Truncating a non-negative number rounds it down
+    let steps = (vira_random() * (high - low)) as u64 as f64;
+    low + steps
+}
+
+/// `seed(n)`: restarts the numbers from `n`. Gives 0, like other calls with nothing to return.
+#[no_mangle]
+pub extern "C" fn vira_seed(seed: f64) -> f64 {
+    unsafe { *ptr::addr_of_mut!(STATE) = Some(seed.to_bits()) };
+    0.0
+}
+
+fn next() -> u64 {
+    let state = unsafe { (*ptr::addr_of_mut!(STATE)).get_or_insert_with(initial_seed) };
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// `VIRA_SEED` as `seed` would take it, or else a seed that differs from run to run: the wall
+/// clock, the monotonic clock, and where the stack is, which address space randomization moves.
+fn initial_seed() -> u64 {
+    let variable = unsafe { getenv(c"VIRA_SEED".as_ptr()) };
+    if !variable.is_null() {
+        let text = unsafe { CStr::from_ptr(variable) }.to_str().unwrap_or_default();
+        if let Ok(seed) = text.trim().parse::<f64>() {
+            return seed.to_bits();
+        }
+    }
+    let stack = 0u8;
+    vira_now().to_bits() ^ now() ^ ptr::addr_of!(stack) as u64
+}
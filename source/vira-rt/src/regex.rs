@@ -0,0 +1,105 @@
+//! Regular expression builtins, on regex-automata's meta engine: the syntax of Rust's `regex`
+//! crate, matching in time linear in the text, with Unicode classes. Positions count characters,
+//! as in the string builtins.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::{c_char, CStr};
+use core::fmt::Write;
+use core::ptr;
+
+use regex_automata::meta::Regex;
+
+use crate::runtime_error;
+use crate::string::{char_count, new_string};
+
+/// Patterns compiled so far. A program usually has a handful of them, written as literals, so
+/// this keeps each one instead of compiling it again on every call.
+static mut COMPILED: Vec<(String, Regex)> = Vec::new();
+
+/// `matches(pattern, text)`: 1 when `pattern` matches anywhere in `text`, 0 otherwise; anchor it
+/// with `^` and `$` to match all of it.
+///
+/// # Safety
+///
+/// All arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_regex_matches(pattern: *const c_char, text: *const c_char, location: *const c_char) -> f64 {
+    let regex = compile(pattern, location);
+    f64::from(u8::from(regex.is_match(str(text))))
+}
+
+/// `regex_find(pattern, text)`: where the first match starts, or -1 when there is none.
+///
+/// # Safety
+///
+/// All arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_regex_find(pattern: *const c_char, text: *const c_char, location: *const c_char) -> f64 {
+    let regex = compile(pattern, location);
+    let text = str(text);
+    match regex.find(text) {
+        Some(found) => char_count(&text.as_bytes()[..found.start()]) as f64,
+        None => -1.0,
+    }
+}
+
+/// `regex_replace(pattern, text, replacement)`: `text` with every match replaced. In the
+/// replacement `$1` or `${name}` stands for what a group matched, and `$$` for a dollar sign.
+///
+/// # Safety
+///
+/// All arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_regex_replace(
+    pattern: *const c_char,
+    text: *const c_char,
+    replacement: *const c_char,
+    location: *const c_char,
+) -> *mut c_char {
+    let regex = compile(pattern, location);
+    let text = str(text);
+    let replacement = str(replacement);
+    let mut replaced = String::with_capacity(text.len());
+    let mut last = 0;
+    for captures in regex.captures_iter(text) {
+        let Some(found) = captures.get_match() else {
+            continue;
+        };
+        replaced.push_str(&text[last..found.start()]);
+        captures.interpolate_string_into(text, replacement, &mut replaced);
+        last = found.end();
+    }
+    replaced.push_str(&text[last..]);
+    new_string(replaced.as_bytes())
+}
+
+/// The compiled `pattern`; a pattern that isn't valid stops the program with an error reported at
+/// `location`.
+unsafe fn compile(pattern: *const c_char, location: *const c_char) -> Regex {
+    let pattern = str(pattern);
+    let compiled = &mut *ptr::addr_of_mut!(COMPILED);
+    if let Some((_, regex)) = compiled.iter().find(|(known, _)| known == pattern) {
+        return regex.clone();
+    }
+    match Regex::new(pattern) {
+        Ok(regex) => {
+            compiled.push((pattern.into(), regex.clone()));
+            regex
+        }
+        Err(err) => {
+            let mut message = String::new();
+            let _ = write!(message, "invalid regular expression {:?}", pattern);
+            // Without the standard library the build error only says which pattern failed
+            if let Some(syntax) = err.syntax_error() {
+                let _ = write!(message, ":\n{}", syntax);
+            }
+            runtime_error(location, &message)
+        }
+    }
+}
+
+/// Vira strings are always UTF-8.
+unsafe fn str<'a>(text: *const c_char) -> &'a str {
+    CStr::from_ptr(text).to_str().unwrap_or_default()
+}
@@ -0,0 +1,200 @@
+//! String builtins. Strings are UTF-8 and every position or length is in characters, not bytes;
+//! finding a character's bytes means walking the string from the start.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ffi::{c_char, CStr};
+use core::fmt::Write;
+use core::ptr;
+
+use crate::{runtime_error, vira_alloc, write_number};
+
+/// `len(text)`.
+///
+/// # Safety
+///
+/// `text` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vira_str_len(text: *const c_char) -> f64 {
+    char_count(CStr::from_ptr(text).to_bytes()) as f64
+}
+
+/// `text[index]`, the character at `index` as a string of its own. An index that isn't a whole
+/// number inside the string stops the program with an error reported at `location`.
+///
+/// # Safety
+///
+/// All arguments but `index` must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_str_index(text: *const c_char, index: f64, location: *const c_char) -> *mut c_char {
+    let bytes = CStr::from_ptr(text).to_bytes();
+    let len = char_count(bytes);
+    let Some(index) = position(index, len).filter(|&index| index < len) else {
+        out_of_range(location, "index", index, len)
+    };
+    let start = byte_offset(bytes, index);
+    new_string(&bytes[start..byte_offset(bytes, index + 1)])
+}
+
+/// `text[start..end]`, the characters from `start` up to but not including `end`. Bounds that
+/// aren't whole numbers with `0 <= start <= end <= len(text)` stop the program with an error
+/// reported at `location`.
+///
+/// # Safety
+///
+/// All arguments but the bounds must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_str_slice(text: *const c_char, start: f64, end: f64, location: *const c_char) -> *mut c_char {
+    let bytes = CStr::from_ptr(text).to_bytes();
+    let len = char_count(bytes);
+    let (Some(start_index), Some(end_index)) = (position(start, len), position(end, len)) else {
+        match position(start, len) {
+            None => out_of_range(location, "slice start", start, len),
+            Some(_) => out_of_range(location, "slice end", end, len),
+        }
+    };
+    if start_index > end_index {
+        let mut message = String::new();
+        let _ = write!(message, "slice start ");
+        let _ = write_number(&mut message, start);
+        let _ = write!(message, " is past its end ");
+        let _ = write_number(&mut message, end);
+        runtime_error(location, &message)
+    }
+    new_string(&bytes[byte_offset(bytes, start_index)..byte_offset(bytes, end_index)])
+}
+
+/// `substr(text, start, count)`: up to `count` characters from `start`. Unlike slicing it never
+/// fails; bounds are truncated to whole numbers and kept inside the string, so asking for more
+/// than is there gives what there is.
+///
+/// # Safety
+///
+/// `text` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vira_str_substr(text: *const c_char, start: f64, count: f64) -> *mut c_char {
+    let bytes = CStr::from_ptr(text).to_bytes();
+    let len = char_count(bytes);
+    // Float to integer casts saturate, and NaN becomes 0
+    let start = (start.max(0.0) as usize).min(len);
+    let end = start.saturating_add(count.max(0.0) as usize).min(len);
+    new_string(&bytes[byte_offset(bytes, start)..byte_offset(bytes, end)])
+}
+
+/// `find(text, needle)`: the position of the first `needle` in `text`, or -1 when there is none.
+/// An empty needle is found at 0.
+///
+/// # Safety
+///
+/// Both arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_str_find(text: *const c_char, needle: *const c_char) -> f64 {
+    let bytes = CStr::from_ptr(text).to_bytes();
+    match find(bytes, CStr::from_ptr(needle).to_bytes()) {
+        Some(offset) => char_count(&bytes[..offset]) as f64,
+        None => -1.0,
+    }
+}
+
+/// `replace(text, from, to)`: `text` with every `from`, left to right and not overlapping,
+/// replaced by `to`. An empty `from` leaves `text` as it is.
+///
+/// # Safety
+///
+/// All arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_str_replace(text: *const c_char, from: *const c_char, to: *const c_char) -> *mut c_char {
+    let mut rest = CStr::from_ptr(text).to_bytes();
+    let from = CStr::from_ptr(from).to_bytes();
+    let to = CStr::from_ptr(to).to_bytes();
+    if from.is_empty() {
+        return new_string(rest);
+    }
+    let mut replaced = Vec::with_capacity(rest.len());
+    while let Some(offset) = find(rest, from) {
+        replaced.extend_from_slice(&rest[..offset]);
+        replaced.extend_from_slice(to);
+        rest = &rest[offset + from.len()..];
+    }
+    replaced.extend_from_slice(rest);
+    new_string(&replaced)
+}
+
+/// `left == right` on strings: 1 when both hold the same bytes, 0 otherwise.
+///
+/// # Safety
+///
+/// Both arguments must point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn vira_str_eq(left: *const c_char, right: *const c_char) -> f64 {
+    f64::from(u8::from(CStr::from_ptr(left) == CStr::from_ptr(right)))
+}
+
+/// `trim(text)`: `text` without the whitespace at its start and end.
+///
+/// # Safety
+///
+/// `text` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vira_str_trim(text: *const c_char) -> *mut c_char {
+    let bytes = CStr::from_ptr(text).to_bytes();
+    match core::str::from_utf8(bytes) {
+        Ok(text) => new_string(text.trim().as_bytes()),
+        Err(_) => new_string(bytes.trim_ascii()),
+    }
+}
+
+/// Stops the program because `value`, used as `what`, doesn't fit a string of `len` characters.
+unsafe fn out_of_range(location: *const c_char, what: &str, value: f64, len: usize) -> ! {
+    let mut message = String::new();
+    let _ = write!(message, "{} ", what);
+    let _ = write_number(&mut message, value);
+    if !is_whole(value) {
+        let _ = write!(message, " is not a whole number");
+    } else {
+        let _ = write!(message, " is out of range for a string of {} character{}", len, if len == 1 { "" } else { "s" });
+    }
+    runtime_error(location, &message)
+}
+
+/// `value` as a position from 0 to `max`, if it is a whole number in that range.
+fn position(value: f64, max: usize) -> Option<usize> {
+    (is_whole(value) && value >= 0.0 && value <= max as f64).then_some(value as usize)
+}
+
+/// `core` has no `f64::fract`; the remainder is NaN for infinities and NaN, so those aren't whole.
+fn is_whole(value: f64) -> bool {
+    value % 1.0 == 0.0
+}
+
+/// Every character starts with a byte that isn't a UTF-8 continuation byte.
+pub(crate) fn char_count(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&byte| byte & 0xC0 != 0x80).count()
+}
+
+/// Where the character at `index` starts, or the length of `bytes` for the index past the last.
+fn byte_offset(bytes: &[u8], index: usize) -> usize {
+    bytes
+        .iter()
+        .enumerate()
+        .filter(|(_, &byte)| byte & 0xC0 != 0x80)
+        .nth(index)
+        .map_or(bytes.len(), |(offset, _)| offset)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// A NUL-terminated copy of `bytes` that lives until the program exits, like every Vira string.
+pub(crate) fn new_string(bytes: &[u8]) -> *mut c_char {
+    let copy = vira_alloc(bytes.len() + 1).cast::<u8>();
+    unsafe {
+        ptr::copy_nonoverlapping(bytes.as_ptr(), copy, bytes.len());
+        *copy.add(bytes.len()) = 0;
+    }
+    copy.cast()
+}
@@ -0,0 +1,177 @@
+//! Clocks and sleeping, for the time builtins and the profiler.
+
+use core::ffi::{c_char, c_int, CStr};
+
+use crate::string::new_string;
+
+/// `now()`: seconds since 1970-01-01 00:00 UTC, with a fraction.
+#[no_mangle]
+pub extern "C" fn vira_now() -> f64 {
+    wall_clock()
+}
+
+/// `clock()`: seconds on a clock that only moves forward, from some arbitrary start. Only the
+/// difference between two readings means anything, which makes it the clock for timing code.
+#[no_mangle]
+pub extern "C" fn vira_clock() -> f64 {
+    now() as f64 / 1e9
+}
+
+/// `sleep(seconds)`: waits that long, to the precision the system offers. Gives 0.
+#[no_mangle]
+pub extern "C" fn vira_sleep(seconds: f64) -> f64 {
+    if seconds > 0.0 {
+        // Saturates for absurdly long waits
+        sleep_nanos((seconds * 1e9) as u64);
+    }
+    0.0
+}
+
+/// `format_time(format, time)`: `time`, in seconds as `now()` gives them, written in local time
+/// by C's `strftime`, so `format_time("%Y-%m-%d %H:%M:%S", now())` gives the date and time.
+///
+/// # Safety
+///
+/// `format` must point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vira_format_time(format: *const c_char, time: f64) -> *mut c_char {
+    // Larger than any C library's `struct tm`, which has nine ints and on some systems a
+    // time zone offset and name after them
+    let mut fields = Tm([0; 8]);
+    let seconds = floor(time) as TimeT;
+    if !local_time(seconds, &mut fields) {
+        return new_string(b"");
+    }
+    let mut buffer = alloc::vec![0u8; 256];
+    // `strftime` gives 0 when the result doesn't fit, and for an empty result too, so the buffer
+    // only grows so far
+    while buffer.len() <= 64 * 1024 {
+        let written = strftime(buffer.as_mut_ptr().cast(), buffer.len(), format, &fields);
+        if written > 0 || CStr::from_ptr(format).is_empty() {
+            return new_string(&buffer[..written]);
+        }
+        buffer.resize(buffer.len() * 4, 0);
+    }
+    new_string(b"")
+}
+
+#[repr(C, align(8))]
+struct Tm([u64; 8]);
+
+/// C's `time_t`, which is 64 bits on Windows even where `long` is 32.
+#[cfg(windows)]
+type TimeT = i64;
+#[cfg(not(windows))]
+type TimeT = core::ffi::c_long;
+
+extern "C" {
+    fn strftime(out: *mut c_char, size: usize, format: *const c_char, time: *const Tm) -> usize;
+}
+
+/// `core` has no `f64::floor`; rounding toward negative infinity keeps times before 1970 right.
+fn floor(value: f64) -> f64 {
+    let truncated = value as i64 as f64;
+    if truncated > value {
+        truncated - 1.0
+    } else {
+        truncated
+    }
+}
+
+#[cfg(unix)]
+#[repr(C)]
+struct Timespec {
+    seconds: core::ffi::c_long,
+    nanoseconds: core::ffi::c_long,
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn clock_gettime(clock: c_int, time: *mut Timespec) -> c_int;
+    fn nanosleep(duration: *const Timespec, remaining: *mut Timespec) -> c_int;
+    fn localtime_r(time: *const TimeT, out: *mut Tm) -> *mut Tm;
+}
+
+/// Monotonic time in nanoseconds.
+#[cfg(unix)]
+pub(crate) fn now() -> u64 {
+    const CLOCK_MONOTONIC: c_int = if cfg!(any(target_os = "macos", target_os = "ios")) {
+        6
+    } else if cfg!(target_os = "freebsd") {
+        4
+    } else if cfg!(any(target_os = "openbsd", target_os = "netbsd")) {
+        3
+    } else {
+        1
+    };
+    let mut time = Timespec { seconds: 0, nanoseconds: 0 };
+    unsafe { clock_gettime(CLOCK_MONOTONIC, &mut time) };
+    time.seconds as u64 * 1_000_000_000 + time.nanoseconds as u64
+}
+
+#[cfg(unix)]
+fn wall_clock() -> f64 {
+    // `CLOCK_REALTIME` is 0 everywhere
+    let mut time = Timespec { seconds: 0, nanoseconds: 0 };
+    unsafe { clock_gettime(0, &mut time) };
+    time.seconds as f64 + time.nanoseconds as f64 / 1e9
+}
+
+#[cfg(unix)]
+fn sleep_nanos(nanos: u64) {
+    let mut duration = Timespec {
+        seconds: (nanos / 1_000_000_000) as core::ffi::c_long,
+        nanoseconds: (nanos % 1_000_000_000) as core::ffi::c_long,
+    };
+    // A signal cuts the sleep short and leaves what is left in `duration`
+    while unsafe { nanosleep(&duration, &mut duration) } != 0 {}
+}
+
+#[cfg(unix)]
+fn local_time(seconds: TimeT, out: &mut Tm) -> bool {
+    !unsafe { localtime_r(&seconds, out) }.is_null()
+}
+
+#[cfg(windows)]
+extern "system" {
+    fn QueryPerformanceCounter(count: *mut i64) -> c_int;
+    fn QueryPerformanceFrequency(frequency: *mut i64) -> c_int;
+    fn GetSystemTimePreciseAsFileTime(time: *mut u64);
+    fn Sleep(milliseconds: u32);
+}
+
+#[cfg(windows)]
+extern "C" {
+    #[link_name = "_localtime64_s"]
+    fn localtime_s(out: *mut Tm, time: *const TimeT) -> c_int;
+}
+
+/// Monotonic time in nanoseconds.
+#[cfg(windows)]
+pub(crate) fn now() -> u64 {
+    let (mut count, mut frequency) = (0, 1);
+    unsafe {
+        QueryPerformanceCounter(&mut count);
+        QueryPerformanceFrequency(&mut frequency);
+    }
+    (count as u128 * 1_000_000_000 / frequency.max(1) as u128) as u64
+}
+
+#[cfg(windows)]
+fn wall_clock() -> f64 {
+    // Hundreds of nanoseconds since 1601
+    const UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+    let mut time = 0;
+    unsafe { GetSystemTimePreciseAsFileTime(&mut time) };
+    time.wrapping_sub(UNIX_EPOCH) as i64 as f64 / 1e7
+}
+
+#[cfg(windows)]
+fn sleep_nanos(nanos: u64) {
+    unsafe { Sleep((nanos / 1_000_000).min(u64::from(u32::MAX - 1)) as u32) };
+}
+
+#[cfg(windows)]
+fn local_time(seconds: TimeT, out: &mut Tm) -> bool {
+    unsafe { localtime_s(out, &seconds) == 0 }
+}